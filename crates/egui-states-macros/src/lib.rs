@@ -3,12 +3,21 @@ use proc_macro::TokenStream;
 mod objects;
 mod states;
 
+/// Implements `Transportable`, the object type is built from the same fields in the same order as
+/// serde serializes them, the build scripts generate the python types from it. Serde attributes
+/// which change the serialized layout (skip, flatten, with, tag, untagged, ...) are a compile error,
+/// because the data would not match the object type.
 #[proc_macro_derive(Transportable)]
 pub fn transportable(input: TokenStream) -> TokenStream {
     objects::impl_transportable(input)
 }
 
-#[proc_macro_derive(State)]
+#[proc_macro_derive(EnumConvert)]
+pub fn enum_convert(input: TokenStream) -> TokenStream {
+    objects::impl_enum_convert(input)
+}
+
+#[proc_macro_derive(State, attributes(id))]
 pub fn state(input: TokenStream) -> TokenStream {
     states::impl_state(input)
 }
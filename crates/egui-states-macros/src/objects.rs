@@ -2,10 +2,71 @@ use proc_macro::TokenStream;
 use quote::{format_ident, quote};
 use syn::{self, Lit, parse_macro_input};
 
+// serde attributes changing the serialized layout, the object type would not match the data
+const LAYOUT_ATTRIBUTES: &[&str] = &[
+    "skip",
+    "skip_serializing",
+    "skip_deserializing",
+    "skip_serializing_if",
+    "flatten",
+    "with",
+    "serialize_with",
+    "deserialize_with",
+    "tag",
+    "content",
+    "untagged",
+    "transparent",
+    "from",
+    "try_from",
+    "into",
+    "remote",
+    "getter",
+];
+
+fn check_serde_attributes<'a>(
+    attrs: impl IntoIterator<Item = &'a syn::Attribute>,
+) -> Result<(), syn::Error> {
+    for attr in attrs {
+        if !attr.path().is_ident("serde") {
+            continue;
+        }
+        let syn::Meta::List(list) = &attr.meta else {
+            continue;
+        };
+        for token in list.tokens.clone() {
+            if let proc_macro2::TokenTree::Ident(ident) = token
+                && LAYOUT_ATTRIBUTES.contains(&ident.to_string().as_str())
+            {
+                return Err(syn::Error::new_spanned(
+                    attr,
+                    format!(
+                        "serde attribute `{}` changes the serialized data, so it does not match the Transportable type",
+                        ident
+                    ),
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
 pub(crate) fn impl_transportable(input: TokenStream) -> TokenStream {
     let input_clone = input.clone();
     let input = parse_macro_input!(input as syn::DeriveInput);
 
+    let inner_attrs: Vec<&syn::Attribute> = match &input.data {
+        syn::Data::Struct(data) => data.fields.iter().flat_map(|f| &f.attrs).collect(),
+        syn::Data::Enum(data) => data
+            .variants
+            .iter()
+            .flat_map(|v| v.attrs.iter().chain(v.fields.iter().flat_map(|f| &f.attrs)))
+            .collect(),
+        syn::Data::Union(_) => Vec::new(),
+    };
+    if let Err(e) = check_serde_attributes(input.attrs.iter().chain(inner_attrs)) {
+        return e.to_compile_error().into();
+    }
+
     match input.data {
         syn::Data::Struct(_) => impl_struct(input_clone),
         syn::Data::Enum(_) => impl_enum(input_clone),
@@ -27,7 +88,7 @@ fn impl_struct(input: TokenStream) -> TokenStream {
         panic!("Structs with generics are not supported");
     }
 
-    let fields_iter = fields.clone().into_iter().map(|f| f);
+    let fields_iter = fields.clone().into_iter();
     let mut names = Vec::new();
     let mut types = Vec::new();
     for field in fields_iter {
@@ -66,25 +127,14 @@ fn impl_struct(input: TokenStream) -> TokenStream {
     out.into()
 }
 
-fn impl_enum(input: TokenStream) -> TokenStream {
-    let input = parse_macro_input!(input as syn::ItemEnum);
-
-    let syn::ItemEnum {
-        ident,
-        generics,
-        variants,
-        ..
-    } = input;
-
-    if generics.lt_token.is_some() {
-        panic!("Enums with generics are not supported");
-    }
-
-    let variants = variants.clone().into_iter().map(|v| v);
+// names and discriminants of the unit variants
+fn unit_variants(
+    variants: &syn::punctuated::Punctuated<syn::Variant, syn::token::Comma>,
+) -> (Vec<syn::Ident>, Vec<i32>) {
     let mut names = Vec::new();
     let mut values = Vec::new();
     let mut actual = 0i32;
-    for variant in variants.clone() {
+    for variant in variants {
         if variant.fields != syn::Fields::Unit {
             panic!("Enum variants must be unit variants");
         }
@@ -108,7 +158,71 @@ fn impl_enum(input: TokenStream) -> TokenStream {
         values.push(actual);
         actual += 1;
     }
+    (names, values)
+}
+
+pub(crate) fn impl_enum_convert(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as syn::ItemEnum);
+    if input.generics.lt_token.is_some() {
+        panic!("Enums with generics are not supported");
+    }
+
+    let ident = input.ident;
+    let (names, values) = unit_variants(&input.variants);
+
+    let out = quote!(
+        impl TryFrom<i32> for #ident {
+            type Error = egui_states::InvalidEnumValue;
+
+            fn try_from(value: i32) -> Result<Self, Self::Error> {
+                match value {
+                    #(#values => Ok(Self::#names),)*
+                    value => Err(egui_states::InvalidEnumValue {
+                        enum_name: stringify!(#ident),
+                        value,
+                    }),
+                }
+            }
+        }
+
+        impl std::fmt::Display for #ident {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.write_str(match self {
+                    #(Self::#names => stringify!(#names)),*
+                })
+            }
+        }
+    );
+
+    out.into()
+}
+
+fn impl_enum(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as syn::ItemEnum);
+
+    let syn::ItemEnum {
+        ident,
+        generics,
+        variants,
+        ..
+    } = input;
+
+    if generics.lt_token.is_some() {
+        panic!("Enums with generics are not supported");
+    }
+
+    if variants
+        .iter()
+        .any(|variant| variant.fields != syn::Fields::Unit)
+    {
+        return impl_union(ident, variants);
+    }
+
+    let (names, values) = unit_variants(&variants);
     let values2 = values.clone();
+    let values3 = values.clone();
+    // negative discriminants do not fit into the bitset either
+    let max_bit = values.iter().map(|value| *value as u32).max().unwrap_or(0);
     let private_ident = format_ident!("__Private{}", ident);
     let private_mod = format_ident!("__private_{}", ident);
 
@@ -170,6 +284,74 @@ fn impl_enum(input: TokenStream) -> TokenStream {
         unsafe impl egui_states::Atomic for #ident {
             type Lock = egui_states::UpdateLock<#private_mod::#private_ident>;
         }
+
+        impl egui_states::BitFlag for #ident {
+            const MAX_BIT: u32 = #max_bit;
+
+            #[inline]
+            fn bit(&self) -> u32 {
+                match self {
+                    #(Self::#names => #values3 as u32),*
+                }
+            }
+        }
+    );
+
+    out.into()
+}
+
+// enum with data, serialized as the variant index followed by the variant fields
+fn impl_union(
+    ident: syn::Ident,
+    variants: syn::punctuated::Punctuated<syn::Variant, syn::token::Comma>,
+) -> TokenStream {
+    let mut variant_names = Vec::new();
+    let mut patterns = Vec::new();
+    let mut inits = Vec::new();
+    let mut types = Vec::new();
+    for variant in variants {
+        let name = variant.ident;
+        variant_names.push(name.to_string());
+        match variant.fields {
+            syn::Fields::Unit => {
+                patterns.push(quote!(Self::#name));
+                inits.push(Vec::new());
+                types.push(Vec::new());
+            }
+            syn::Fields::Unnamed(fields) => {
+                let bindings: Vec<_> = (0..fields.unnamed.len())
+                    .map(|i| format_ident!("field_{}", i))
+                    .collect();
+                patterns.push(quote!(Self::#name(#(#bindings),*)));
+                inits.push(bindings);
+                types.push(fields.unnamed.into_iter().map(|field| field.ty).collect());
+            }
+            syn::Fields::Named(_) => panic!("Enum variants with named fields are not supported"),
+        }
+    }
+
+    let out = quote!(
+        unsafe impl egui_states::Transportable for #ident {
+            #[inline]
+            fn init_value(&self) -> egui_states::InitValue {
+                match self {
+                    #(#patterns => egui_states::InitValue::Union(
+                        #variant_names.to_string(),
+                        vec![#(#inits.init_value()),*]
+                    )),*
+                }
+            }
+
+            #[inline]
+            fn get_type() -> egui_states::ObjectType {
+                egui_states::ObjectType::Union(
+                    stringify!(#ident).to_string(),
+                    vec![
+                        #((#variant_names.to_string(), vec![#(<#types as egui_states::Transportable>::get_type()),*])),*
+                    ]
+                )
+            }
+        }
     );
 
     out.into()
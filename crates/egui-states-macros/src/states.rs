@@ -42,6 +42,16 @@ fn expand_state(input: syn::ItemStruct) -> syn::Result<proc_macro2::TokenStream>
         }
     };
 
+    let mut ids = Vec::new();
+    for field in fields.iter() {
+        if let Some((id, lit)) = explicit_id(field)? {
+            if ids.contains(&id) {
+                return Err(syn::Error::new_spanned(lit, "duplicate state id"));
+            }
+            ids.push(id);
+        }
+    }
+
     let initializers = fields
         .iter()
         .map(field_initializer)
@@ -70,10 +80,13 @@ fn field_initializer(field: &syn::Field) -> syn::Result<proc_macro2::TokenStream
     let type_name = segment.ident.to_string();
 
     let value_default = quote!(::core::default::Default::default());
+    let id = explicit_id(field)?;
 
     let initializer = match type_name.as_str() {
         "Value" => quote!(c.value(#field_name, #value_default)),
         "ValueAtomic" => quote!(c.atomic(#field_name, #value_default)),
+        "ValueBitset" => quote!(c.bitset(#field_name, #value_default)),
+        "ValueArray" => quote!(c.array(#field_name, #value_default)),
         "Static" => quote!(c.add_static(#field_name, #value_default)),
         "StaticAtomic" => quote!(c.static_atomic(#field_name, #value_default)),
         "Signal" => quote!(c.signal(#field_name)),
@@ -85,10 +98,33 @@ fn field_initializer(field: &syn::Field) -> syn::Result<proc_macro2::TokenStream
         "DataTake" => quote!(c.data_take(#field_name)),
         "DataMulti" => quote!(c.data_multi(#field_name)),
         "DataMultiTake" => quote!(c.data_multi_take(#field_name)),
+        _ if id.is_some() => {
+            return Err(syn::Error::new_spanned(
+                field,
+                "id attribute is not supported on substates",
+            ));
+        }
         _ => quote!(c.substate(#field_name)),
     };
 
-    Ok(quote!(#name: #initializer))
+    match id {
+        Some((id, _)) => Ok(quote!(#name: { c.set_next_id(#id); #initializer })),
+        None => Ok(quote!(#name: #initializer)),
+    }
+}
+
+fn explicit_id(field: &syn::Field) -> syn::Result<Option<(u64, syn::LitInt)>> {
+    let Some(attr) = field.attrs.iter().find(|attr| attr.path().is_ident("id")) else {
+        return Ok(None);
+    };
+
+    let lit: syn::LitInt = attr.parse_args()?;
+    let id = lit.base10_parse::<u64>()?;
+    // ids 0-9 are reserved for the internal signals
+    if id < 10 {
+        return Err(syn::Error::new_spanned(lit, "state ids 0-9 are reserved"));
+    }
+    Ok(Some((id, lit)))
 }
 
 fn last_path_segment(ty: &syn::Type) -> syn::Result<&syn::PathSegment> {
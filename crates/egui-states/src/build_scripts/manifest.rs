@@ -0,0 +1,93 @@
+use std::collections::BTreeMap;
+use std::fs;
+
+use crate::State;
+use crate::build_scripts::scripts;
+use crate::build_scripts::states_creator_build::StateType;
+use crate::data_transport::DataType;
+use crate::transport::ObjectType;
+
+struct RustNames;
+
+// the data types are lowercase like the rust item types
+impl scripts::TypeNames for RustNames {
+    fn primitive(&self, info: &ObjectType) -> &'static str {
+        match info {
+            ObjectType::U8 => "u8",
+            ObjectType::U16 => "u16",
+            ObjectType::U32 => "u32",
+            ObjectType::U64 => "u64",
+            ObjectType::I8 => "i8",
+            ObjectType::I16 => "i16",
+            ObjectType::I32 => "i32",
+            ObjectType::I64 => "i64",
+            ObjectType::F32 => "f32",
+            ObjectType::F64 => "f64",
+            ObjectType::Bool => "bool",
+            ObjectType::String => "String",
+            ObjectType::Empty => "()",
+            _ => unreachable!("not a primitive type"),
+        }
+    }
+
+    fn tuple(&self, elements: Vec<String>) -> String {
+        format!("({})", elements.join(", "))
+    }
+
+    fn list(&self, element: String, size: u32) -> String {
+        format!("[{}; {}]", element, size)
+    }
+
+    fn vec(&self, element: String) -> String {
+        format!("Vec<{}>", element)
+    }
+
+    fn map(&self, key: String, value: String) -> String {
+        format!("HashMap<{}, {}>", key, value)
+    }
+
+    fn option(&self, element: String) -> String {
+        format!("Option<{}>", element)
+    }
+
+    fn data(&self, data_type: &DataType) -> String {
+        format!("{:?}", data_type).to_lowercase()
+    }
+}
+
+fn json_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Write the JSON description of all states: name, id, kind and type, sorted by the name, for
+/// tools outside of rust and python, for example to compare the states of two versions.
+pub fn generate_manifest<S: State>(path: impl ToString) -> Result<(), String> {
+    let (states, version_hash, explicit_ids) = scripts::parse_states::<S>();
+
+    let mut values_list = Vec::new();
+    scripts::states_into_values_list(&states, &mut values_list);
+    scripts::check_ids(&values_list, &explicit_ids)?;
+    let values: BTreeMap<&str, &StateType> = values_list.iter().map(|s| (s.name(), s)).collect();
+
+    let mut lines = Vec::new();
+    for (name, state) in values {
+        let id = scripts::state_id(name, &explicit_ids);
+        let kind = scripts::state_kind(state);
+        let type_name = scripts::state_type_name(state, &RustNames);
+        let type_name = type_name.map_or("null".to_string(), |t| json_string(&t));
+        lines.push(format!(
+            "    {{\"name\": {}, \"id\": {}, \"kind\": \"{}\", \"type\": {}}}",
+            json_string(name),
+            id,
+            kind,
+            type_name
+        ));
+    }
+
+    let text = format!(
+        "{{\n  \"version_hash\": {},\n  \"states\": [\n{}\n  ]\n}}\n",
+        version_hash,
+        lines.join(",\n")
+    );
+    fs::write(path.to_string(), text).map_err(|e| format!("Failed to write file: {}", e))
+}
@@ -1,5 +1,9 @@
+mod manifest;
 mod python;
 mod scripts;
 mod states_creator_build;
+mod typescript;
 
+pub use manifest::generate_manifest;
 pub use python::generate_python;
+pub use typescript::generate_typescript;
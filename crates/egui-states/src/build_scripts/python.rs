@@ -30,7 +30,7 @@ fn type_to_pytype(type_info: &ObjectType) -> String {
             format!("s.cl([{}], {})", fields.join(", "), name)
         }
         ObjectType::Tuple(vec) => {
-            let elems: Vec<String> = vec.iter().map(|t| type_to_pytype(t)).collect();
+            let elems: Vec<String> = vec.iter().map(type_to_pytype).collect();
             format!("s.tu([{}])", elems.join(", "))
         }
         ObjectType::List(size, element) => {
@@ -49,6 +49,22 @@ fn type_to_pytype(type_info: &ObjectType) -> String {
         ObjectType::Option(element) => {
             format!("s.opt({})", type_to_pytype(element))
         }
+        ObjectType::Union(name, variants) => {
+            let variants: Vec<String> = variants
+                .iter()
+                .map(|(variant, fields)| {
+                    let fields: Vec<String> = fields.iter().map(type_to_pytype).collect();
+                    format!(
+                        "(\"{}\", {}{}, [{}])",
+                        variant,
+                        name,
+                        variant,
+                        fields.join(", ")
+                    )
+                })
+                .collect();
+            format!("s.uni({}, [{}])", name, variants.join(", "))
+        }
     }
 }
 
@@ -93,6 +109,18 @@ fn process_type_info(values: &Vec<StateType>) -> (HashMap<String, TypeIndex>, Ve
                     type_map.insert(name.clone(), TypeIndex::Single(type_list.len() - 1));
                 }
             }
+            StateType::Bitset(name, _, _, _) => {
+                let obj_type = &ObjectType::U64;
+                if type_list.contains(obj_type) {
+                    type_map.insert(
+                        name.clone(),
+                        TypeIndex::Single(type_list.iter().position(|t| t == obj_type).unwrap()),
+                    );
+                } else {
+                    type_list.push(obj_type.clone());
+                    type_map.insert(name.clone(), TypeIndex::Single(type_list.len() - 1));
+                }
+            }
             StateType::ValueMap(name, key, value) => {
                 // let dict_type = ObjectType::Map(Box::new(key.clone()), Box::new(value.clone()));
                 let key_pos = if type_list.contains(key) {
@@ -122,51 +150,85 @@ fn process_type_info(values: &Vec<StateType>) -> (HashMap<String, TypeIndex>, Ve
     (type_map, type_list)
 }
 
+// the fixed sizes of the lists are written as the comment at the end of the line, so they are
+// still valid python inside nested types
 fn type_info_to_python_type(info: &ObjectType, list_comment: bool) -> String {
+    let py_type = python_type(info);
+    let mut sizes = Vec::new();
+    if list_comment {
+        list_sizes(info, &mut sizes);
+    }
+    match sizes.is_empty() {
+        true => py_type,
+        false => format!("{}  # fixed size {}", py_type, sizes.join(", ")),
+    }
+}
+
+fn list_sizes(info: &ObjectType, sizes: &mut Vec<String>) {
     match info {
-        ObjectType::U8
-        | ObjectType::U16
-        | ObjectType::U32
-        | ObjectType::U64
-        | ObjectType::I8
-        | ObjectType::I16
-        | ObjectType::I32
-        | ObjectType::I64 => "int".to_string(),
-        ObjectType::F32 | ObjectType::F64 => "float".to_string(),
-        ObjectType::Bool => "bool".to_string(),
-        ObjectType::String => "str".to_string(),
-        ObjectType::Empty => "".to_string(),
-        ObjectType::Enum(name, _) => name.clone(),
-        ObjectType::Struct(name, _) => name.clone(),
-        ObjectType::Tuple(elements) => {
-            let elems: Vec<String> = elements
-                .iter()
-                .map(|e| type_info_to_python_type(e, list_comment))
-                .collect();
-            format!("tuple[{}]", elems.join(", "))
-        }
         ObjectType::List(size, element) => {
-            let elem_str = type_info_to_python_type(element, list_comment);
-            if list_comment {
-                format!("list[{}]  # fixed size {}", elem_str, *size)
-            } else {
-                format!("list[{}]", elem_str)
-            }
-        }
-        ObjectType::Vec(t) => {
-            let elem_str = type_info_to_python_type(t, list_comment);
-            format!("list[{}]", elem_str)
+            sizes.push(size.to_string());
+            list_sizes(element, sizes);
         }
+        ObjectType::Vec(element) | ObjectType::Option(element) => list_sizes(element, sizes),
+        ObjectType::Tuple(elements) => elements.iter().for_each(|e| list_sizes(e, sizes)),
         ObjectType::Map(key, value) => {
-            let key_str = type_info_to_python_type(key, list_comment);
-            let value_str = type_info_to_python_type(value, list_comment);
-            format!("dict[{}, {}]", key_str, value_str)
+            list_sizes(key, sizes);
+            list_sizes(value, sizes);
         }
-        ObjectType::Option(element) => {
-            let elem_str = type_info_to_python_type(element, list_comment);
-            format!("{} | None", elem_str)
+        _ => {}
+    }
+}
+
+struct PythonNames;
+
+// the fixed size of the list is not part of the python type
+impl scripts::TypeNames for PythonNames {
+    fn primitive(&self, info: &ObjectType) -> &'static str {
+        match info {
+            ObjectType::U8
+            | ObjectType::U16
+            | ObjectType::U32
+            | ObjectType::U64
+            | ObjectType::I8
+            | ObjectType::I16
+            | ObjectType::I32
+            | ObjectType::I64 => "int",
+            ObjectType::F32 | ObjectType::F64 => "float",
+            ObjectType::Bool => "bool",
+            ObjectType::String => "str",
+            ObjectType::Empty => "",
+            _ => unreachable!("not a primitive type"),
         }
     }
+
+    fn tuple(&self, elements: Vec<String>) -> String {
+        format!("tuple[{}]", elements.join(", "))
+    }
+
+    fn list(&self, element: String, _size: u32) -> String {
+        self.vec(element)
+    }
+
+    fn vec(&self, element: String) -> String {
+        format!("list[{}]", element)
+    }
+
+    fn map(&self, key: String, value: String) -> String {
+        format!("dict[{}, {}]", key, value)
+    }
+
+    fn option(&self, element: String) -> String {
+        format!("{} | None", element)
+    }
+
+    fn data(&self, data_type: &DataType) -> String {
+        data_type_to_dtype(data_type).to_string()
+    }
+}
+
+fn python_type(info: &ObjectType) -> String {
+    scripts::type_name(info, &PythonNames)
 }
 
 fn init_to_python_value(init: &InitValue, object_type: &ObjectType) -> String {
@@ -230,6 +292,18 @@ fn init_to_python_value(init: &InitValue, object_type: &ObjectType) -> String {
                 .collect();
             format!("{}({})", name, field_strs.join(", "))
         }
+        (InitValue::Union(variant, items), ObjectType::Union(name, variants)) => {
+            let (_, field_types) = variants
+                .iter()
+                .find(|(variant_name, _)| variant_name == variant)
+                .expect("Union variant not found.");
+            let field_strs: Vec<String> = items
+                .iter()
+                .zip(field_types.iter())
+                .map(|(value, field_type)| init_to_python_value(value, field_type))
+                .collect();
+            format!("{}{}({})", name, variant, field_strs.join(", "))
+        }
         _ => panic!("Mismatched InitValue and ObjectType."),
     }
 }
@@ -249,10 +323,14 @@ fn data_type_to_dtype(data_type: &DataType) -> &'static str {
     }
 }
 
-fn state_to_line(state: &StateType, types_map: &HashMap<String, TypeIndex>) -> String {
-    match state {
+fn state_to_line(
+    state: &StateType,
+    types_map: &HashMap<String, TypeIndex>,
+    explicit_ids: &HashMap<String, u64>,
+) -> String {
+    let line = match state {
         StateType::Value(name, state_type, init, queue) => {
-            let last_name = name.split('.').last().unwrap();
+            let last_name = name.split('.').next_back().unwrap();
             let py_type = type_info_to_python_type(state_type, false);
             let init_value = init_to_python_value(init, state_type);
             let index = types_map.get(name).unwrap().get_single();
@@ -265,8 +343,21 @@ fn state_to_line(state: &StateType, types_map: &HashMap<String, TypeIndex>) -> S
                 last_name, py_type, py_type, index, init_value, queue_str
             )
         }
+        StateType::Bitset(name, flags_type, init, queue) => {
+            let last_name = name.split('.').next_back().unwrap();
+            let flags = type_info_to_python_type(flags_type, false);
+            let index = types_map.get(name).unwrap().get_single();
+            let queue_str = match queue {
+                true => ", True",
+                false => "",
+            };
+            format!(
+                "        self.{}: s.Bitset[{}] = s.Bitset[{}]({}, {}{})\n",
+                last_name, flags, flags, index, init, queue_str
+            )
+        }
         StateType::ValueTake(name, state_type) => {
-            let last_name = name.split('.').last().unwrap();
+            let last_name = name.split('.').next_back().unwrap();
             let py_type = type_info_to_python_type(state_type, false);
             let index = types_map.get(name).unwrap().get_single();
             match state_type {
@@ -283,7 +374,7 @@ fn state_to_line(state: &StateType, types_map: &HashMap<String, TypeIndex>) -> S
             }
         }
         StateType::Static(name, state_type, init) => {
-            let last_name = name.split('.').last().unwrap();
+            let last_name = name.split('.').next_back().unwrap();
             let py_type = type_info_to_python_type(state_type, false);
             let init_value = init_to_python_value(init, state_type);
             let index = types_map.get(name).unwrap().get_single();
@@ -293,7 +384,7 @@ fn state_to_line(state: &StateType, types_map: &HashMap<String, TypeIndex>) -> S
             )
         }
         StateType::Signal(name, state_type, queue) => {
-            let last_name = name.split('.').last().unwrap();
+            let last_name = name.split('.').next_back().unwrap();
             let py_type = type_info_to_python_type(state_type, false);
             let index = types_map.get(name).unwrap().get_single();
             match state_type {
@@ -320,7 +411,7 @@ fn state_to_line(state: &StateType, types_map: &HashMap<String, TypeIndex>) -> S
             }
         }
         StateType::ValueVec(name, state_type) => {
-            let last_name = name.split('.').last().unwrap();
+            let last_name = name.split('.').next_back().unwrap();
             let py_type = type_info_to_python_type(state_type, false);
             let index = types_map.get(name).unwrap().get_single();
             format!(
@@ -329,7 +420,7 @@ fn state_to_line(state: &StateType, types_map: &HashMap<String, TypeIndex>) -> S
             )
         }
         StateType::ValueMap(name, key_type, value_type) => {
-            let last_name = name.split('.').last().unwrap();
+            let last_name = name.split('.').next_back().unwrap();
             let py_key_type = type_info_to_python_type(key_type, false);
             let py_value_type = type_info_to_python_type(value_type, false);
             let (key, value) = types_map.get(name).unwrap().get_map();
@@ -339,52 +430,57 @@ fn state_to_line(state: &StateType, types_map: &HashMap<String, TypeIndex>) -> S
             )
         }
         StateType::Data(name, data_type) => {
-            let last_name = name.split('.').last().unwrap();
+            let last_name = name.split('.').next_back().unwrap();
             let dtype = data_type_to_dtype(data_type);
-            let dtype = format!("{}", dtype);
+            let dtype = dtype.to_string();
             format!(
                 "        self.{}: s.Data[{}] = s.Data({})\n",
                 last_name, dtype, dtype
             )
         }
         StateType::DataMulti(name, data_type) => {
-            let last_name = name.split('.').last().unwrap();
+            let last_name = name.split('.').next_back().unwrap();
             let dtype = data_type_to_dtype(data_type);
-            let dtype = format!("{}", dtype);
+            let dtype = dtype.to_string();
             format!(
                 "        self.{}: s.DataMulti[{}] = s.DataMulti({})\n",
                 last_name, dtype, dtype
             )
         }
         StateType::DataTake(name, data_type) => {
-            let last_name = name.split('.').last().unwrap();
+            let last_name = name.split('.').next_back().unwrap();
             let dtype = data_type_to_dtype(data_type);
-            let dtype = format!("{}", dtype);
+            let dtype = dtype.to_string();
             format!(
                 "        self.{}: s.DataTake[{}] = s.DataTake({})\n",
                 last_name, dtype, dtype
             )
         }
         StateType::DataMultiTake(name, data_type) => {
-            let last_name = name.split('.').last().unwrap();
+            let last_name = name.split('.').next_back().unwrap();
             let dtype = data_type_to_dtype(data_type);
-            let dtype = format!("{}", dtype);
+            let dtype = dtype.to_string();
             format!(
                 "        self.{}: s.DataMultiTake[{}] = s.DataMultiTake({})\n",
                 last_name, dtype, dtype
             )
         }
         StateType::Image(name) => {
-            let last_name = name.split('.').last().unwrap();
+            let last_name = name.split('.').next_back().unwrap();
             format!("        self.{}: s.Image = s.Image()\n", last_name)
         }
         StateType::SubState(name, state_class, _) => {
-            let last_name = name.split('.').last().unwrap();
+            let last_name = name.split('.').next_back().unwrap();
             format!(
                 "        self.{}: {} = {}(parent + \".{}\")\n",
                 last_name, state_class, state_class, last_name
             )
         }
+    };
+
+    match explicit_ids.get(state.name()) {
+        Some(id) => format!("{}.with_id({})\n", line.trim_end(), id),
+        None => line,
     }
 }
 
@@ -393,18 +489,26 @@ fn write_states(
     state_class: &str,
     states: &Vec<StateType>,
     types_map: &HashMap<String, TypeIndex>,
+    explicit_ids: &HashMap<String, u64>,
     used_states: &mut Vec<&str>,
 ) {
     let mut lines = Vec::new();
 
     for state in states {
-        lines.push(state_to_line(state, types_map));
+        lines.push(state_to_line(state, types_map, explicit_ids));
         if let StateType::SubState(_, state_class, sub_states) = state {
             if used_states.contains(state_class) {
                 continue;
             }
             used_states.push(state_class);
-            write_states(file, state_class, sub_states, types_map, used_states);
+            write_states(
+                file,
+                state_class,
+                sub_states,
+                types_map,
+                explicit_ids,
+                used_states,
+            );
         }
     }
 
@@ -418,30 +522,88 @@ fn write_states(
     }
 }
 
-fn order_structs(items: &Vec<(String, ObjectType)>, order: &mut VecDeque<String>) {
-    for (_, item_type) in items {
-        if let ObjectType::Struct(name, fields) = item_type {
-            if !order.contains(name) {
-                order.push_front(name.clone());
-                order_structs(fields, order);
+// structs and unions are ordered so that nested types are defined before they are used
+fn order_structs(type_info: &ObjectType, order: &mut VecDeque<String>) {
+    match type_info {
+        ObjectType::Struct(name, fields) if !order.contains(name) => {
+            for (_, field_type) in fields {
+                order_structs(field_type, order);
+            }
+            order.push_back(name.clone());
+        }
+        ObjectType::Union(name, variants) if !order.contains(name) => {
+            for (_, fields) in variants {
+                for field_type in fields {
+                    order_structs(field_type, order);
+                }
+            }
+            order.push_back(name.clone());
+        }
+        ObjectType::Tuple(elements) => {
+            for elem in elements {
+                order_structs(elem, order);
             }
         }
+        ObjectType::List(_, element) | ObjectType::Vec(element) | ObjectType::Option(element) => {
+            order_structs(element, order);
+        }
+        ObjectType::Map(key_type, value_type) => {
+            order_structs(key_type, order);
+            order_structs(value_type, order);
+        }
+        _ => { /* ignore basic types */ }
+    }
+}
+
+fn write_union(file: &mut fs::File, union_name: &str, variants: &[(String, Vec<ObjectType>)]) {
+    file.write_all(format!("\n\nclass {}(s._CustomUnion):\n    pass\n", union_name).as_bytes())
+        .unwrap();
+
+    for (variant, fields) in variants {
+        file.write_all(
+            format!(
+                "\n\n@dataclass\nclass {}{}({}):\n",
+                union_name, variant, union_name
+            )
+            .as_bytes(),
+        )
+        .unwrap();
+
+        if fields.is_empty() {
+            file.write_all(b"    pass\n").unwrap();
+            continue;
+        }
+
+        for (i, field_type) in fields.iter().enumerate() {
+            let py_type = type_info_to_python_type(field_type, true);
+            let text = format!("    value_{}: {}\n", i, py_type);
+            file.write_all(text.as_bytes()).unwrap();
+        }
     }
 }
 
 pub fn generate_python<S: State>(path: impl ToString) -> Result<(), String> {
-    let (states, version_hash) = scripts::parse_states::<S>();
+    let (states, version_hash, explicit_ids) = scripts::parse_states::<S>();
 
     let mut values_list = Vec::new();
     scripts::states_into_values_list(&states, &mut values_list);
-    let (enums, structs) = scripts::get_all_enums_struct(&values_list);
+    scripts::check_ids(&values_list, &explicit_ids)?;
+    let (enums, structs, unions) = scripts::get_all_enums_struct(&values_list);
     let mut order_list = VecDeque::new();
     for (struct_name, items) in &structs {
         if !order_list.contains(struct_name) {
-            order_list.push_front(struct_name.clone());
-            order_structs(items, &mut order_list);
+            for (_, item_type) in items {
+                order_structs(item_type, &mut order_list);
+            }
+            order_list.push_back(struct_name.clone());
         }
     }
+    for (union_name, variants) in &unions {
+        order_structs(
+            &ObjectType::Union(union_name.clone(), variants.clone()),
+            &mut order_list,
+        );
+    }
     let (types_map, types_list) = process_type_info(&values_list);
 
     let mut file =
@@ -449,16 +611,13 @@ pub fn generate_python<S: State>(path: impl ToString) -> Result<(), String> {
 
     file.write_all(b"# Generated by build.rs, do not edit\n")
         .unwrap();
-    file.write_all(b"# ruff: noqa: D101 D107\n").unwrap();
+    file.write_all(b"# ruff: noqa: D101 D107 E501\n").unwrap();
     file.write_all(b"from collections.abc import Callable\n")
         .unwrap();
-    if structs.len() > 0 {
+    if !structs.is_empty() || !unions.is_empty() {
         file.write_all(b"from dataclasses import dataclass\n")
             .unwrap();
     }
-    if enums.len() > 0 {
-        file.write_all(b"from enum import IntEnum\n").unwrap();
-    }
 
     file.write_all(b"\nimport numpy as np\n\n").unwrap();
 
@@ -471,7 +630,7 @@ pub fn generate_python<S: State>(path: impl ToString) -> Result<(), String> {
 
     // Write enums
     for (enum_name, variants) in &enums {
-        file.write_all(format!("\n\nclass {}(IntEnum):\n", enum_name).as_bytes())
+        file.write_all(format!("\n\nclass {}(s._CustomEnum):\n", enum_name).as_bytes())
             .unwrap();
         for (name, value) in variants {
             let text = format!("    {} = {}\n", name, value);
@@ -479,15 +638,20 @@ pub fn generate_python<S: State>(path: impl ToString) -> Result<(), String> {
         }
     }
 
-    // Write custom structs
+    // Write custom structs and unions
     for struct_name in &order_list {
+        if let Some(variants) = unions.get(struct_name) {
+            write_union(&mut file, struct_name, variants);
+            continue;
+        }
+
         let fields = &structs[struct_name];
         file.write_all(
             format!("\n\n@dataclass\nclass {}(s._CustomStruct):\n", struct_name).as_bytes(),
         )
         .unwrap();
 
-        if fields.len() == 0 {
+        if fields.is_empty() {
             file.write_all(b"    pass\n").unwrap();
             continue;
         }
@@ -514,6 +678,7 @@ pub fn generate_python<S: State>(path: impl ToString) -> Result<(), String> {
                     state_class,
                     sub_states,
                     &types_map,
+                    &explicit_ids,
                     &mut used_states,
                 );
             }
@@ -542,7 +707,7 @@ pub fn generate_python<S: State>(path: impl ToString) -> Result<(), String> {
         file.write_all(b"        parent = \"root\"\n").unwrap();
 
         for state in substates {
-            let line = state_to_line(state, &types_map);
+            let line = state_to_line(state, &types_map, &explicit_ids);
             file.write_all(line.as_bytes()).unwrap();
         }
 
@@ -567,6 +732,7 @@ class StatesServer(StateServerBase):
         ip_addr: tuple[int, int, int, int] | None = None,
         version: int | None = None,
         token: str | None = None,
+        queue_size: int | None = None,
     ) -> None:
         """Initialize the StateServer.
 
@@ -577,13 +743,14 @@ class StatesServer(StateServerBase):
             ip_addr (tuple[int, int, int, int] | None, optional): IP address to bind to. Defaults to None.
             version (int, optional): The optional version number for client connection.
             token (str, optional): The optional token string for client connection.
+            queue_size (int, optional): The maximal number of pending messages to the client. Defaults to 65536.
         """
         "#;
         file.write_all(text.as_bytes()).unwrap();
 
         file.write_all(
             format!(
-                "super().__init__({}, port, signals_workers, error_handler, ip_addr, version, token)\n",
+                "super().__init__({}, port, signals_workers, error_handler, ip_addr, version, token, queue_size)\n",
                 root_name
             )
             .as_bytes(),
@@ -1,30 +1,31 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
 
 use crate::State;
 use crate::build_scripts::states_creator_build::{StateType, StatesCreatorBuild};
+use crate::data_transport::DataType;
+use crate::hashing::generate_value_id;
 use crate::transport::ObjectType;
 
-pub(crate) fn parse_states<S: State>() -> (StateType, u64) {
+pub(crate) fn parse_states<S: State>() -> (StateType, u64, HashMap<String, u64>) {
     let mut creator = StatesCreatorBuild::new("root");
     let _ = S::new(&mut creator);
     let version_hash = creator.get_version_hash();
-    let states = creator.get_states();
+    let (states, explicit_ids) = creator.get_states();
     (
         StateType::SubState("root".to_string(), S::NAME, states),
         version_hash,
+        explicit_ids,
     )
 }
 
 fn collect_enums(type_info: &ObjectType, enums: &mut BTreeMap<String, Vec<(String, i32)>>) {
     match type_info {
         ObjectType::Enum(name, variants) => {
-            if enums.contains_key(name) {
-                if enums[name] != *variants {
-                    panic!(
-                        "Enum {} defined multiple times with different variants",
-                        name
-                    );
-                }
+            if enums.contains_key(name) && enums[name] != *variants {
+                panic!(
+                    "Enum {} defined multiple times with different variants",
+                    name
+                );
             }
 
             enums.insert(name.clone(), variants.clone());
@@ -52,28 +53,37 @@ fn collect_enums(type_info: &ObjectType, enums: &mut BTreeMap<String, Vec<(Strin
             collect_enums(key_type, enums);
             collect_enums(value_type, enums);
         }
+        ObjectType::Union(_, variants) => {
+            for (_, fields) in variants {
+                for field_type in fields {
+                    collect_enums(field_type, enums);
+                }
+            }
+        }
         _ => { /* ignore basic types */ }
     }
 }
 
+type Unions = BTreeMap<String, Vec<(String, Vec<ObjectType>)>>;
+
+// collects the structs and the unions, both can contain each other
 fn collect_structs(
     type_info: &ObjectType,
     structs: &mut BTreeMap<String, Vec<(String, ObjectType)>>,
+    unions: &mut Unions,
 ) {
     match type_info {
         ObjectType::Struct(name, fields) => {
-            if structs.contains_key(name) {
-                if structs[name] != *fields {
-                    panic!(
-                        "Struct {} defined multiple times with different fields",
-                        name
-                    );
-                }
+            if structs.contains_key(name) && structs[name] != *fields {
+                panic!(
+                    "Struct {} defined multiple times with different fields",
+                    name
+                );
             }
 
             structs.insert(name.clone(), fields.clone());
             for (_, field_type) in fields {
-                collect_structs(field_type, structs);
+                collect_structs(field_type, structs, unions);
             }
         }
         ObjectType::Enum(_, variants) => {
@@ -83,64 +93,85 @@ fn collect_structs(
         }
         ObjectType::Tuple(elements) => {
             for elem in elements {
-                collect_structs(elem, structs);
+                collect_structs(elem, structs, unions);
             }
         }
         ObjectType::List(_, element) => {
-            collect_structs(element, structs);
+            collect_structs(element, structs, unions);
         }
         ObjectType::Option(element) => {
-            collect_structs(element, structs);
+            collect_structs(element, structs, unions);
         }
         ObjectType::Vec(element) => {
-            collect_structs(element, structs);
+            collect_structs(element, structs, unions);
         }
         ObjectType::Map(key_type, value_type) => {
-            collect_structs(key_type, structs);
-            collect_structs(value_type, structs);
+            collect_structs(key_type, structs, unions);
+            collect_structs(value_type, structs, unions);
+        }
+        ObjectType::Union(name, variants) => {
+            if unions.contains_key(name) && unions[name] != *variants {
+                panic!(
+                    "Union {} defined multiple times with different variants",
+                    name
+                );
+            }
+
+            unions.insert(name.clone(), variants.clone());
+            for (_, fields) in variants {
+                for field_type in fields {
+                    collect_structs(field_type, structs, unions);
+                }
+            }
         }
         _ => { /* ignore basic types */ }
     }
 }
 
+#[allow(clippy::type_complexity)]
 pub(crate) fn get_all_enums_struct(
     values: &[StateType],
 ) -> (
     BTreeMap<String, Vec<(String, i32)>>,
     BTreeMap<String, Vec<(String, ObjectType)>>,
+    Unions,
 ) {
     let mut enums = BTreeMap::new();
     let mut structs = BTreeMap::new();
+    let mut unions = BTreeMap::new();
 
     for value in values {
         match value {
             StateType::Value(_, info, _, _) => {
                 collect_enums(info, &mut enums);
-                collect_structs(info, &mut structs);
+                collect_structs(info, &mut structs, &mut unions);
+            }
+            StateType::Bitset(_, info, _, _) => {
+                collect_enums(info, &mut enums);
             }
             StateType::Static(_, info, _) => {
                 collect_enums(info, &mut enums);
-                collect_structs(info, &mut structs);
+                collect_structs(info, &mut structs, &mut unions);
             }
             StateType::ValueMap(_, key_info, value_info) => {
                 collect_enums(key_info, &mut enums);
                 collect_enums(value_info, &mut enums);
-                collect_structs(key_info, &mut structs);
-                collect_structs(value_info, &mut structs);
+                collect_structs(key_info, &mut structs, &mut unions);
+                collect_structs(value_info, &mut structs, &mut unions);
             }
             StateType::ValueVec(_, elem_info) => {
                 collect_enums(elem_info, &mut enums);
-                collect_structs(elem_info, &mut structs);
+                collect_structs(elem_info, &mut structs, &mut unions);
             }
             StateType::Signal(_, info, _) => {
                 collect_enums(info, &mut enums);
-                collect_structs(info, &mut structs);
+                collect_structs(info, &mut structs, &mut unions);
             }
             _ => { /* ignore other types */ }
         }
     }
 
-    (enums, structs)
+    (enums, structs, unions)
 }
 
 pub(crate) fn states_into_values_list(state: &StateType, list: &mut Vec<StateType>) {
@@ -155,3 +186,179 @@ pub(crate) fn states_into_values_list(state: &StateType, list: &mut Vec<StateTyp
         }
     }
 }
+
+pub(crate) fn state_id(name: &str, explicit_ids: &HashMap<String, u64>) -> u64 {
+    match explicit_ids.get(name) {
+        Some(id) => *id,
+        None => generate_value_id(name),
+    }
+}
+
+// the server keeps the states by id, so a collision would silently lose one of them, the explicit
+// ids can collide with the hashed names too
+pub(crate) fn check_ids(
+    values: &[StateType],
+    explicit_ids: &HashMap<String, u64>,
+) -> Result<(), String> {
+    let mut ids: HashMap<u64, &str> = HashMap::new();
+    for state in values {
+        let name = state.name();
+        let id = state_id(name, explicit_ids);
+        if let Some(other) = ids.insert(id, name) {
+            return Err(format!(
+                "States {} and {} have the same id {}",
+                other, name, id
+            ));
+        }
+    }
+    Ok(())
+}
+
+// names of the types in the generated language, the nested types are walked by type_name, so the
+// generators map only the leaves and the containers
+pub(crate) trait TypeNames {
+    // numbers, bool, string and empty
+    fn primitive(&self, info: &ObjectType) -> &'static str;
+    fn tuple(&self, elements: Vec<String>) -> String;
+    fn list(&self, element: String, size: u32) -> String;
+    fn vec(&self, element: String) -> String;
+    fn map(&self, key: String, value: String) -> String;
+    fn option(&self, element: String) -> String;
+    fn data(&self, data_type: &DataType) -> String;
+}
+
+pub(crate) fn type_name(info: &ObjectType, names: &impl TypeNames) -> String {
+    match info {
+        ObjectType::Enum(name, _) | ObjectType::Struct(name, _) | ObjectType::Union(name, _) => {
+            name.clone()
+        }
+        ObjectType::Tuple(elements) => {
+            names.tuple(elements.iter().map(|e| type_name(e, names)).collect())
+        }
+        ObjectType::List(size, element) => names.list(type_name(element, names), *size),
+        ObjectType::Vec(element) => names.vec(type_name(element, names)),
+        ObjectType::Map(key, value) => names.map(type_name(key, names), type_name(value, names)),
+        ObjectType::Option(element) => names.option(type_name(element, names)),
+        _ => names.primitive(info).to_string(),
+    }
+}
+
+pub(crate) fn state_kind(state: &StateType) -> &'static str {
+    match state {
+        StateType::Value(..) => "Value",
+        StateType::Bitset(..) => "Bitset",
+        StateType::ValueTake(..) => "ValueTake",
+        StateType::Static(..) => "Static",
+        StateType::Image(_) => "Image",
+        StateType::ValueMap(..) => "ValueMap",
+        StateType::ValueVec(..) => "ValueVec",
+        StateType::Signal(..) => "Signal",
+        StateType::Data(..) => "Data",
+        StateType::DataTake(..) => "DataTake",
+        StateType::DataMulti(..) => "DataMulti",
+        StateType::DataMultiTake(..) => "DataMultiTake",
+        StateType::SubState(..) => "SubState",
+    }
+}
+
+// type of the whole state, none for the image and the substate
+pub(crate) fn state_type_name(state: &StateType, names: &impl TypeNames) -> Option<String> {
+    match state {
+        StateType::Value(_, info, _, _)
+        | StateType::Bitset(_, info, _, _)
+        | StateType::ValueTake(_, info)
+        | StateType::Static(_, info, _)
+        | StateType::Signal(_, info, _) => Some(type_name(info, names)),
+        StateType::ValueMap(_, key, value) => {
+            Some(names.map(type_name(key, names), type_name(value, names)))
+        }
+        StateType::ValueVec(_, info) => Some(names.vec(type_name(info, names))),
+        StateType::Data(_, data_type)
+        | StateType::DataTake(_, data_type)
+        | StateType::DataMulti(_, data_type)
+        | StateType::DataMultiTake(_, data_type) => Some(names.data(data_type)),
+        StateType::Image(_) | StateType::SubState(..) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{StatesCreator, Value};
+
+    struct SameIds {
+        _first: Value<u32>,
+        _second: Value<u32>,
+    }
+
+    impl State for SameIds {
+        const NAME: &'static str = "SameIds";
+
+        fn new(c: &mut impl StatesCreator) -> Self {
+            c.set_next_id(7);
+            let first = c.value("first", 0);
+            c.set_next_id(7);
+            let second = c.value("second", 0);
+            Self {
+                _first: first,
+                _second: second,
+            }
+        }
+    }
+
+    struct Inner {
+        _value: Value<u32>,
+    }
+
+    impl State for Inner {
+        const NAME: &'static str = "Inner";
+
+        fn new(c: &mut impl StatesCreator) -> Self {
+            c.set_next_id(1000);
+            Self {
+                _value: c.value("value", 0),
+            }
+        }
+    }
+
+    struct InnerTwice {
+        _first: Inner,
+        _second: Inner,
+    }
+
+    impl State for InnerTwice {
+        const NAME: &'static str = "InnerTwice";
+
+        fn new(c: &mut impl StatesCreator) -> Self {
+            Self {
+                _first: c.substate("first"),
+                _second: c.substate("second"),
+            }
+        }
+    }
+
+    fn check_state<S: State>() -> Result<(), String> {
+        let (states, _, explicit_ids) = parse_states::<S>();
+        let mut values = Vec::new();
+        states_into_values_list(&states, &mut values);
+        check_ids(&values, &explicit_ids)
+    }
+
+    #[test]
+    fn test_check_ids_rejects_same_ids() {
+        let error = check_state::<SameIds>().unwrap_err();
+        assert_eq!(
+            error,
+            "States root.first and root.second have the same id 7"
+        );
+    }
+
+    #[test]
+    fn test_check_ids_rejects_substate_used_twice() {
+        let error = check_state::<InnerTwice>().unwrap_err();
+        assert_eq!(
+            error,
+            "States root.first.value and root.second.value have the same id 1000"
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::hash::{Hash, Hasher};
 
 use serde::{Deserialize, Serialize};
@@ -9,19 +10,22 @@ use crate::client::data_take::{DataMultiTake, DataTake};
 use crate::client::image::Image;
 
 use crate::client::messages::MessageSender;
-use crate::client::states_creator::{self, StatesCreator, hash_id, hash_id_type};
+use crate::client::states_creator::{self, StatesCreator, hash_id, hash_id_type, take_value_id};
 use crate::client::value_map::MapState;
 use crate::client::value_vec::VecState;
 use crate::client::values::{
-    GetQueueType, Signal, Static, StaticAtomic, Value, ValueAtomic, ValueTake,
+    BitFlag, GetQueueType, Signal, Static, StaticAtomic, Value, ValueArray, ValueAtomic,
+    ValueBitset, ValueTake,
 };
 use crate::data_transport::DataType;
-use crate::hashing::{StableHasher, generate_value_id};
+use crate::hashing::StableHasher;
 use crate::transport::{InitValue, ObjectType, Transportable};
 
 #[derive(Clone)]
 pub(crate) enum StateType {
     Value(String, ObjectType, InitValue, bool),
+    // name, enum naming the bits, initial bits, queue
+    Bitset(String, ObjectType, u64, bool),
     ValueTake(String, ObjectType),
     Static(String, ObjectType, InitValue),
     Image(String),
@@ -35,11 +39,33 @@ pub(crate) enum StateType {
     SubState(String, &'static str, Vec<StateType>),
 }
 
+impl StateType {
+    pub(crate) fn name(&self) -> &str {
+        match self {
+            StateType::Value(name, ..)
+            | StateType::Bitset(name, ..)
+            | StateType::ValueTake(name, ..)
+            | StateType::Static(name, ..)
+            | StateType::Image(name)
+            | StateType::ValueMap(name, ..)
+            | StateType::ValueVec(name, ..)
+            | StateType::Signal(name, ..)
+            | StateType::Data(name, ..)
+            | StateType::DataTake(name, ..)
+            | StateType::DataMulti(name, ..)
+            | StateType::DataMultiTake(name, ..)
+            | StateType::SubState(name, ..) => name,
+        }
+    }
+}
+
 pub(crate) struct StatesCreatorBuild {
     states: Vec<StateType>,
     parent: String,
     sender: MessageSender,
     version_hasher: StableHasher,
+    next_id: Option<u64>,
+    explicit_ids: HashMap<String, u64>,
 }
 
 impl StatesCreatorBuild {
@@ -51,6 +77,8 @@ impl StatesCreatorBuild {
             parent: parent.to_string(),
             sender,
             version_hasher: StableHasher::new(),
+            next_id: None,
+            explicit_ids: HashMap::new(),
         }
     }
 
@@ -58,12 +86,23 @@ impl StatesCreatorBuild {
         self.version_hasher.finish()
     }
 
-    pub fn get_states(self) -> Vec<StateType> {
-        self.states
+    pub fn get_states(self) -> (Vec<StateType>, HashMap<String, u64>) {
+        (self.states, self.explicit_ids)
+    }
+
+    fn take_id(&mut self, name: &str) -> u64 {
+        if let Some(id) = self.next_id {
+            self.explicit_ids.insert(name.to_string(), id);
+        }
+        take_value_id(&mut self.next_id, name)
     }
 }
 
 impl StatesCreator for StatesCreatorBuild {
+    fn set_next_id(&mut self, id: u64) {
+        self.next_id = Some(id);
+    }
+
     fn substate<S: State>(&mut self, name: &str) -> S {
         let parent = format!("{}.{}", self.parent, name);
 
@@ -74,7 +113,8 @@ impl StatesCreator for StatesCreatorBuild {
             .finish()
             .hash(&mut self.version_hasher);
 
-        let states = builder.get_states();
+        let (states, explicit_ids) = builder.get_states();
+        self.explicit_ids.extend(explicit_ids);
         self.states
             .push(StateType::SubState(parent, S::NAME, states));
 
@@ -87,7 +127,7 @@ impl StatesCreator for StatesCreatorBuild {
         Q: GetQueueType,
     {
         let name = format!("{}.{}", self.parent, name);
-        let id = generate_value_id(&name);
+        let id = self.take_id(&name);
         let type_id = T::get_type().get_hash();
         hash_id_type(
             &mut self.version_hasher,
@@ -105,12 +145,25 @@ impl StatesCreator for StatesCreatorBuild {
         value
     }
 
+    fn array<T, const N: usize, Q>(
+        &mut self,
+        name: &'static str,
+        value: [T; N],
+    ) -> ValueArray<T, N, Q>
+    where
+        T: Transportable + Send + Sync + Clone + 'static,
+        [T; N]: for<'a> Deserialize<'a> + Serialize,
+        Q: GetQueueType,
+    {
+        ValueArray::new(self.value(name, value))
+    }
+
     fn value_take<T>(&mut self, name: &'static str) -> ValueTake<T>
     where
         T: for<'a> Deserialize<'a> + Serialize + Transportable + Send + Sync + 'static,
     {
         let name = format!("{}.{}", self.parent, name);
-        let id = generate_value_id(&name);
+        let id = self.take_id(&name);
         let type_id = T::get_type().get_hash();
         hash_id_type(
             &mut self.version_hasher,
@@ -133,7 +186,7 @@ impl StatesCreator for StatesCreatorBuild {
         Q: GetQueueType,
     {
         let name = format!("{}.{}", self.parent, name);
-        let id = generate_value_id(&name);
+        let id = self.take_id(&name);
         let type_id = T::get_type().get_hash();
         hash_id_type(
             &mut self.version_hasher,
@@ -151,12 +204,36 @@ impl StatesCreator for StatesCreatorBuild {
         value
     }
 
+    fn bitset<E, Q>(&mut self, name: &'static str, value: u64) -> ValueBitset<E, Q>
+    where
+        E: BitFlag + Transportable,
+        Q: GetQueueType,
+    {
+        let name = format!("{}.{}", self.parent, name);
+        let id = self.take_id(&name);
+        let type_id = <u64 as Transportable>::get_type().get_hash();
+        hash_id_type(
+            &mut self.version_hasher,
+            id,
+            type_id,
+            states_creator::BITSET_HASH_ID,
+        );
+        E::get_type().get_hash().hash(&mut self.version_hasher);
+
+        let bits = ValueAtomic::new(name.clone(), id, type_id, value, self.sender.clone());
+
+        self.states
+            .push(StateType::Bitset(name, E::get_type(), value, Q::is_queue()));
+
+        ValueBitset::new(bits)
+    }
+
     fn add_static<T>(&mut self, name: &'static str, value: T) -> Static<T>
     where
         T: for<'a> Deserialize<'a> + Serialize + Clone + Transportable,
     {
         let name = format!("{}.{}", self.parent, name);
-        let id = generate_value_id(&name);
+        let id = self.take_id(&name);
         let type_id = T::get_type().get_hash();
         hash_id_type(
             &mut self.version_hasher,
@@ -185,7 +262,7 @@ impl StatesCreator for StatesCreatorBuild {
             + 'static,
     {
         let name = format!("{}.{}", self.parent, name);
-        let id = generate_value_id(&name);
+        let id = self.take_id(&name);
         let type_id = T::get_type().get_hash();
         hash_id_type(
             &mut self.version_hasher,
@@ -204,7 +281,7 @@ impl StatesCreator for StatesCreatorBuild {
 
     fn image(&mut self, name: &'static str) -> Image {
         let name = format!("{}.{}", self.parent, name);
-        let id = generate_value_id(&name);
+        let id = self.take_id(&name);
         hash_id(&mut self.version_hasher, id);
 
         let value = Image::new(name.clone(), id, self.sender.clone());
@@ -220,7 +297,7 @@ impl StatesCreator for StatesCreatorBuild {
         Q: GetQueueType,
     {
         let name = format!("{}.{}", self.parent, name);
-        let id = generate_value_id(&name);
+        let id = self.take_id(&name);
         let type_id = T::get_type().get_hash();
         hash_id_type(
             &mut self.version_hasher,
@@ -243,7 +320,7 @@ impl StatesCreator for StatesCreatorBuild {
         V: Clone + for<'a> Deserialize<'a> + Transportable,
     {
         let name = format!("{}.{}", self.parent, name);
-        let id = generate_value_id(&name);
+        let id = self.take_id(&name);
         let type_id = V::get_type().get_hash_from(K::get_type().get_hash());
         hash_id_type(
             &mut self.version_hasher,
@@ -264,7 +341,7 @@ impl StatesCreator for StatesCreatorBuild {
         T: Clone + for<'a> Deserialize<'a> + Transportable,
     {
         let name = format!("{}.{}", self.parent, name);
-        let id = generate_value_id(&name);
+        let id = self.take_id(&name);
         let type_id = T::get_type().get_hash();
         hash_id_type(
             &mut self.version_hasher,
@@ -285,7 +362,7 @@ impl StatesCreator for StatesCreatorBuild {
         T: GetDataType + Send + Sync + 'static,
     {
         let name = format!("{}.{}", self.parent, name);
-        let id = generate_value_id(&name);
+        let id = self.take_id(&name);
         hash_id_type(
             &mut self.version_hasher,
             id,
@@ -304,7 +381,7 @@ impl StatesCreator for StatesCreatorBuild {
         T: GetDataType + Send + Sync + 'static,
     {
         let name = format!("{}.{}", self.parent, name);
-        let id = generate_value_id(&name);
+        let id = self.take_id(&name);
         hash_id_type(
             &mut self.version_hasher,
             id,
@@ -323,7 +400,7 @@ impl StatesCreator for StatesCreatorBuild {
         T: GetDataType + Send + Sync + 'static,
     {
         let name = format!("{}.{}", self.parent, name);
-        let id = generate_value_id(&name);
+        let id = self.take_id(&name);
         hash_id_type(
             &mut self.version_hasher,
             id,
@@ -342,7 +419,7 @@ impl StatesCreator for StatesCreatorBuild {
         T: GetDataType + Send + Sync + 'static,
     {
         let name = format!("{}.{}", self.parent, name);
-        let id = generate_value_id(&name);
+        let id = self.take_id(&name);
         hash_id_type(
             &mut self.version_hasher,
             id,
@@ -0,0 +1,228 @@
+use std::collections::HashMap;
+use std::{fs, io::Write};
+
+use crate::State;
+use crate::build_scripts::scripts;
+use crate::build_scripts::states_creator_build::StateType;
+use crate::data_transport::DataType;
+use crate::transport::ObjectType;
+
+struct TsNames;
+
+// 64 bit integers do not fit into the number, they are bigint
+impl scripts::TypeNames for TsNames {
+    fn primitive(&self, info: &ObjectType) -> &'static str {
+        match info {
+            ObjectType::U8
+            | ObjectType::U16
+            | ObjectType::U32
+            | ObjectType::I8
+            | ObjectType::I16
+            | ObjectType::I32
+            | ObjectType::F32
+            | ObjectType::F64 => "number",
+            ObjectType::U64 | ObjectType::I64 => "bigint",
+            ObjectType::Bool => "boolean",
+            ObjectType::String => "string",
+            ObjectType::Empty => "null",
+            _ => unreachable!("not a primitive type"),
+        }
+    }
+
+    fn tuple(&self, elements: Vec<String>) -> String {
+        format!("[{}]", elements.join(", "))
+    }
+
+    fn list(&self, element: String, _size: u32) -> String {
+        self.vec(element)
+    }
+
+    fn vec(&self, element: String) -> String {
+        format!("Array<{}>", element)
+    }
+
+    fn map(&self, key: String, value: String) -> String {
+        format!("Map<{}, {}>", key, value)
+    }
+
+    fn option(&self, element: String) -> String {
+        format!("{} | null", element)
+    }
+
+    fn data(&self, data_type: &DataType) -> String {
+        match data_type {
+            DataType::U64 | DataType::I64 => "Array<bigint>".to_string(),
+            _ => "Array<number>".to_string(),
+        }
+    }
+}
+
+fn ts_type(info: &ObjectType) -> String {
+    scripts::type_name(info, &TsNames)
+}
+
+fn state_to_line(state: &StateType) -> (String, String) {
+    if let StateType::SubState(name, state_class, _) = state {
+        let last_name = name.split('.').next_back().unwrap();
+        return (
+            format!("  readonly {}: {};\n", last_name, state_class),
+            format!(
+                "    this.{} = new {}(parent + \".{}\");\n",
+                last_name, state_class, last_name
+            ),
+        );
+    }
+
+    let kind = scripts::state_kind(state);
+    let ts_type = scripts::state_type_name(state, &TsNames).unwrap_or("null".to_string());
+    let last_name = state.name().split('.').next_back().unwrap();
+    (
+        format!(
+            "  readonly {}: StateRef<\"{}\", {}>;\n",
+            last_name, kind, ts_type
+        ),
+        format!(
+            "    this.{} = state(\"{}\", parent + \".{}\");\n",
+            last_name, kind, last_name
+        ),
+    )
+}
+
+fn write_class(file: &mut fs::File, state_class: &str, states: &[StateType], parent: &str) {
+    let lines: Vec<(String, String)> = states.iter().map(state_to_line).collect();
+
+    file.write_all(format!("\nexport class {} {{\n", state_class).as_bytes())
+        .unwrap();
+    for (field, _) in &lines {
+        file.write_all(field.as_bytes()).unwrap();
+    }
+    file.write_all(format!("\n  constructor(parent: string{}) {{\n", parent).as_bytes())
+        .unwrap();
+    for (_, init) in &lines {
+        file.write_all(init.as_bytes()).unwrap();
+    }
+    file.write_all(b"  }\n}\n").unwrap();
+}
+
+fn write_states(file: &mut fs::File, states: &[StateType], used_states: &mut Vec<&'static str>) {
+    for state in states {
+        if let StateType::SubState(_, state_class, sub_states) = state {
+            if used_states.contains(state_class) {
+                continue;
+            }
+            used_states.push(state_class);
+            write_states(file, sub_states, used_states);
+            write_class(file, state_class, sub_states, "");
+        }
+    }
+}
+
+/// Generate the TypeScript schema of the states: interfaces of the structs, string literal types of
+/// the enums, the ids of all states and the accessor classes mirroring the python states. Decoding
+/// of the messages is not part of the generated file.
+pub fn generate_typescript<S: State>(path: impl ToString) -> Result<(), String> {
+    let (states, version_hash, explicit_ids) = scripts::parse_states::<S>();
+
+    let mut values_list = Vec::new();
+    scripts::states_into_values_list(&states, &mut values_list);
+    scripts::check_ids(&values_list, &explicit_ids)?;
+    let (enums, structs, unions) = scripts::get_all_enums_struct(&values_list);
+
+    let mut file =
+        fs::File::create(path.to_string()).map_err(|e| format!("Failed to create file: {}", e))?;
+
+    file.write_all(b"// Generated by build.rs, do not edit\n")
+        .unwrap();
+    file.write_all(format!("\nexport const VERSION_HASH = {}n;\n", version_hash).as_bytes())
+        .unwrap();
+
+    for (enum_name, variants) in &enums {
+        let variants: Vec<String> = variants
+            .iter()
+            .map(|(name, _)| format!("\"{}\"", name))
+            .collect();
+        file.write_all(
+            format!("\nexport type {} = {};\n", enum_name, variants.join(" | ")).as_bytes(),
+        )
+        .unwrap();
+    }
+
+    for (struct_name, fields) in &structs {
+        file.write_all(format!("\nexport interface {} {{\n", struct_name).as_bytes())
+            .unwrap();
+        for (name, field_type) in fields {
+            file.write_all(format!("  {}: {};\n", name, ts_type(field_type)).as_bytes())
+                .unwrap();
+        }
+        file.write_all(b"}\n").unwrap();
+    }
+
+    // unions are externally tagged like serde does it
+    for (union_name, variants) in &unions {
+        let variants: Vec<String> = variants
+            .iter()
+            .map(|(variant, fields)| {
+                let fields: Vec<String> = fields.iter().map(ts_type).collect();
+                format!("{{ {}: [{}] }}", variant, fields.join(", "))
+            })
+            .collect();
+        file.write_all(
+            format!("\nexport type {} = {};\n", union_name, variants.join(" | ")).as_bytes(),
+        )
+        .unwrap();
+    }
+
+    // ids of all states by the name
+    let ids: HashMap<&str, u64> = values_list
+        .iter()
+        .map(|s| (s.name(), scripts::state_id(s.name(), &explicit_ids)))
+        .collect();
+    let mut names: Vec<&&str> = ids.keys().collect();
+    names.sort();
+    file.write_all(b"\nexport const STATE_IDS: Readonly<Record<string, bigint>> = {\n")
+        .unwrap();
+    for name in names {
+        file.write_all(format!("  \"{}\": {}n,\n", name, ids[*name]).as_bytes())
+            .unwrap();
+    }
+    file.write_all(b"};\n").unwrap();
+
+    let text = r#"
+export type StateKind =
+  | "Value"
+  | "Bitset"
+  | "ValueTake"
+  | "Static"
+  | "Image"
+  | "ValueMap"
+  | "ValueVec"
+  | "Signal"
+  | "Data"
+  | "DataTake"
+  | "DataMulti"
+  | "DataMultiTake";
+
+export interface StateRef<K extends StateKind, T> {
+  readonly kind: K;
+  readonly name: string;
+  readonly id: bigint;
+  // only marks the type of the state, it is never set
+  readonly value?: T;
+}
+
+function state<K extends StateKind, T>(kind: K, name: string): StateRef<K, T> {
+  return { kind, name, id: STATE_IDS[name] };
+}
+"#;
+    file.write_all(text.as_bytes()).unwrap();
+
+    if let StateType::SubState(_, root_name, substates) = &states {
+        let mut used_states = Vec::new();
+        write_states(&mut file, substates, &mut used_states);
+        write_class(&mut file, root_name, substates, " = \"root\"");
+    } else {
+        panic!("Root state must be a SubState");
+    }
+
+    Ok(())
+}
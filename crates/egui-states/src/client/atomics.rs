@@ -5,22 +5,45 @@ use std::sync::atomic::{
     Ordering::{Acquire, Release},
 };
 
+/// Value type of the static state kept without the lock.
+///
+/// # Safety
+///
+/// The lock has to load and store the whole value atomically, a reader can never see a torn value.
 pub unsafe trait AtomicStatic: Copy {
     type Lock: AtomicLockStatic<Self>;
 }
 
+/// Storage of the atomic value shared between the ui and the client thread.
+///
+/// # Safety
+///
+/// `load` and `store` have to be atomic for the whole value, a reader can never see a torn value.
 pub unsafe trait AtomicLockStatic<T: Copy>: Sync + Send {
     fn new(value: T) -> Self;
     fn load(&self) -> T;
     fn store(&self, value: T);
 }
 
+/// Value type of the state kept without the lock.
+///
+/// # Safety
+///
+/// The lock has to load and store the whole value atomically, a reader can never see a torn value.
 pub unsafe trait Atomic: Copy {
     type Lock: AtomicLock<Self>;
 }
 
+/// Storage of the atomic value which is also sent to the server.
+///
+/// # Safety
+///
+/// `update` and `modify` have to store the value atomically and call `before_store` exactly once
+/// before it, the concurrent updates have to be serialized so the store order matches the order of
+/// the calls. `modify` has to compute the new value from the current one in the same section.
 pub unsafe trait AtomicLock<T: Copy>: AtomicLockStatic<T> {
     fn update<F: FnOnce()>(&self, value: T, before_store: F);
+    fn modify<F: FnOnce(T) -> T, S: FnOnce(T)>(&self, f: F, before_store: S);
 }
 
 pub struct UpdateLock<L>(Mutex<()>, L);
@@ -49,6 +72,14 @@ unsafe impl<T: Copy, L: AtomicLockStatic<T>> AtomicLock<T> for UpdateLock<L> {
         before_store();
         self.1.store(value);
     }
+
+    #[inline]
+    fn modify<F: FnOnce(T) -> T, S: FnOnce(T)>(&self, f: F, before_store: S) {
+        let _guard = self.0.lock();
+        let value = f(self.1.load());
+        before_store(value);
+        self.1.store(value);
+    }
 }
 
 pub struct FallbackLock<T: Copy>(RwLock<T>);
@@ -77,6 +108,14 @@ unsafe impl<T: Copy + Send + Sync> AtomicLock<T> for FallbackLock<T> {
         before_store();
         *write = value;
     }
+
+    #[inline]
+    fn modify<F: FnOnce(T) -> T, S: FnOnce(T)>(&self, f: F, before_store: S) {
+        let mut write = self.0.write();
+        let value = f(*write);
+        before_store(value);
+        *write = value;
+    }
 }
 
 // ----------------------------------------------------
@@ -1,24 +1,41 @@
+use std::collections::HashMap;
 use std::net::{Ipv4Addr, SocketAddrV4};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
 
 use egui::Context;
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc::UnboundedReceiver;
 
-use crate::PROTOCOL_VERSION;
+use crate::ObjectType;
 use crate::State;
-use crate::client::messages::{ChannelMessage, MessageSender, MessagesSerializer, handle_message};
+use crate::client::data::{DataMessage, DataMultiMessage};
+use crate::client::data_take::{DataMultiTakeMessage, DataTakeMessage};
+use crate::client::image::{ImageMessage, ImageSetMessage};
+use crate::client::messages::{
+    ChannelMessage, MessageSender, MessagesSerializer, ServerMessage, apply_message, handle_message,
+};
 use crate::client::states_creator::{StatesCreatorClient, ValuesList};
 use crate::event::Event;
-use crate::serialization::ClientHeader;
+use crate::hashing::NoHashMap;
+#[cfg(feature = "checksum")]
+use crate::serialization::HANDSHAKE_CHECKSUM;
+#[cfg(feature = "compression")]
+use crate::serialization::HANDSHAKE_COMPRESSION;
+use crate::serialization::{
+    ClientHeader, HANDSHAKE_READ_ONLY, MessageData, deserialize, to_message,
+};
+use crate::{PROTOCOL_MINOR, PROTOCOL_VERSION};
 
 #[cfg(not(target_arch = "wasm32"))]
-use crate::client::websocket::build_ws;
+use crate::client::websocket::{Tls, build_ws};
 
 #[cfg(target_arch = "wasm32")]
-use crate::client::websocket_wasm::build_ws;
+use crate::client::websocket_wasm::{Tls, build_ws};
 
+#[allow(clippy::too_many_arguments)]
 async fn start_gui_client(
     addr: SocketAddrV4,
     vals: ValuesList,
@@ -27,31 +44,80 @@ async fn start_gui_client(
     client: Client,
     version: Option<u64>,
     hash: Option<String>,
+    request_types: bool,
+    options: u32,
+    tls: Tls,
+    ws: WsOptions,
+    reconnect: Option<AutoReconnect>,
 ) {
+    // failed attempts since the connection was lost, 0 waits for the connection signal
+    let mut attempt = 0;
     loop {
-        // wait for the connection signal
-        client.wait_connection().await;
-        client.set_state(ConnectionState::NotConnected);
+        if attempt == 0 {
+            // wait for the connection signal
+            client.wait_connection().await;
+            client.set_state(ConnectionState::NotConnected);
+        } else if let Some(reconnect) = reconnect {
+            client.set_state(ConnectionState::Reconnecting);
+            let delay = reconnect.delay(attempt);
+            #[cfg(not(target_arch = "wasm32"))]
+            tokio::time::sleep(delay).await;
+            #[cfg(target_arch = "wasm32")]
+            let _ = delay;
 
-        // try to connect to the server
-        let res = build_ws(addr).await;
-        if res.is_err() {
-            continue;
+            // disconnected by the user while waiting
+            if client.0.stopped.load(Ordering::Acquire) {
+                attempt = 0;
+                client.set_state(ConnectionState::Disconnected(DisconnectReason::Closed));
+                continue;
+            }
         }
-        let (mut socket_read, mut socket_send) = res.unwrap();
 
-        // clean message queue before starting
+        // try to connect to the server
+        let (mut socket_read, mut socket_send) = match build_ws(addr, &tls, &ws).await {
+            Ok(socket) => socket,
+            Err(reason) => {
+                attempt = match reconnect {
+                    Some(reconnect) if attempt > 0 && attempt < reconnect.max_retries => {
+                        attempt + 1
+                    }
+                    _ => 0,
+                };
+                if attempt == 0 {
+                    client.set_state(ConnectionState::Disconnected(reason));
+                }
+                continue;
+            }
+        };
+
+        // clean message queue before starting, keep the local value changes for reconciliation
+        let mut pending = NoHashMap::default();
         while !rx.is_empty() {
-            let _ = rx.recv().await;
+            match rx.recv().await {
+                Some(Some(ChannelMessage::Value(id, type_id, signal, data))) => {
+                    sender.stats().taken();
+                    pending.insert(id, (type_id, signal, data));
+                }
+                Some(Some(_)) => sender.stats().taken(),
+                _ => {}
+            }
         }
+        client.set_pending(pending);
 
         // communicate handshake and initialization -------------------------
-        let message = ClientHeader::serialize_handshake(PROTOCOL_VERSION, version, hash.clone());
-        if let Err(_) = socket_send.send(message).await {
-            #[cfg(all(debug_assertions, not(target_arch = "wasm32")))]
-            println!("Sending handshake failed.");
-            #[cfg(all(debug_assertions, target_arch = "wasm32"))]
-            log::error!("Sending handshake failed.");
+        let message = ClientHeader::serialize_handshake(
+            PROTOCOL_VERSION,
+            PROTOCOL_MINOR,
+            version,
+            hash.clone(),
+            request_types,
+            options,
+        );
+        if socket_send.send(message).await.is_err() {
+            print_error("Sending handshake failed.");
+            let reason = DisconnectReason::Io("sending handshake failed".to_string());
+            client.set_state(ConnectionState::Disconnected(reason));
+            attempt = 0;
             continue;
         }
 
@@ -61,37 +127,51 @@ async fn start_gui_client(
         let th_sender = sender.clone();
 
         let recv_future = async move {
-            loop {
+            let reason = loop {
                 // read the message
-                match socket_read.read().await {
+                match socket_read.read(th_sender.stats()).await {
+                    Ok(ServerMessage::Rejected(server)) => {
+                        break DisconnectReason::ProtocolMismatch {
+                            client: PROTOCOL_VERSION,
+                            server,
+                        };
+                    }
                     Ok(msg) => {
-                        if let Err(e) = handle_message(msg, &th_vals, &th_client).await {
+                        if let Some((_, kind)) = msg.update_kind() {
+                            th_sender.stats().record_kind(kind);
+                        }
+                        if let Err(e) = th_client.dispatch_message(msg, &th_vals) {
                             let error = format!("handling message from server failed: {:?}", e);
                             th_sender.send_message(&error);
                             print_error(&error);
                             // break; TODO: decide if we want to break the loop on error
                         }
                     }
-                    Err(e) => {
-                        print_error(&format!("Connection with server failed: {:?}", e));
-                        break;
+                    Err(reason) => {
+                        print_error(&format!("Connection with server failed: {}", reason));
+                        break reason;
                     }
                 }
-            }
+            };
             th_sender.close();
+            reason
         };
 
         #[cfg(not(target_arch = "wasm32"))]
         let recv_future = tokio::spawn(recv_future);
 
         // send -----------------------------------------
+        let send_sender = sender.clone();
         let send_future = async move {
-            let mut serializer = MessagesSerializer::new(rx);
+            let stats = send_sender.stats().clone();
+            let mut serializer = MessagesSerializer::new(rx, stats.clone());
 
             while let Some(message) = serializer.next().await {
-                if let Err(_) = socket_send.send(message).await {
+                let size = message.len();
+                if socket_send.send(message).await.is_err() {
                     break;
                 }
+                stats.record_sent(size);
             }
 
             socket_send.close().await;
@@ -102,11 +182,15 @@ async fn start_gui_client(
         let send_future = tokio::spawn(send_future);
 
         client.set_state(ConnectionState::Connected);
+        attempt = 0;
 
+        let reason;
         #[cfg(not(target_arch = "wasm32"))]
         {
             // wait for the read thread to finish
-            let _ = recv_future.await;
+            reason = recv_future
+                .await
+                .unwrap_or_else(|e| DisconnectReason::Io(e.to_string()));
 
             // wait for the send thread
             rx = send_future.await.unwrap();
@@ -114,11 +198,25 @@ async fn start_gui_client(
 
         #[cfg(target_arch = "wasm32")]
         {
-            let (_, rx_) = tokio::join!(recv_future, send_future);
+            let (reason_, rx_) = tokio::join!(recv_future, send_future);
+            reason = reason_;
             rx = rx_;
         }
 
-        client.set_state(ConnectionState::Disconnected);
+        // reconnecting does not help with different protocol or refused handshake
+        let reconnect_helps = !matches!(
+            reason,
+            DisconnectReason::ProtocolMismatch { .. } | DisconnectReason::HandshakeRejected(_)
+        );
+        client.set_state(ConnectionState::Disconnected(reason));
+        if !reconnect_helps {
+            continue;
+        }
+        // the handshake and synchronization of all values run again on the next connection
+        if reconnect.is_some_and(|r| r.max_retries > 0) && !client.0.stopped.load(Ordering::Acquire)
+        {
+            attempt = 1;
+        }
     }
 }
 
@@ -130,11 +228,252 @@ fn print_error(error: &str) {
     let _ = error;
 }
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum ConnectionState {
     NotConnected,
     Connected,
-    Disconnected,
+    /// Connection was closed or could not be established.
+    Disconnected(DisconnectReason),
+    /// Connection was lost and the client is trying to connect again, see
+    /// `ClientBuilder::with_auto_reconnect`.
+    Reconnecting,
+}
+
+/// Why the connection with the server ended, it can be shown to the user as the text.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DisconnectReason {
+    /// Connection was closed by the client or by the server.
+    Closed,
+    /// Server refused the handshake, for example because of a wrong token or version.
+    HandshakeRejected(String),
+    /// Server rejected the client because of a different major protocol version.
+    ProtocolMismatch { client: u16, server: u16 },
+    /// TLS handshake with the server failed, for example the certificate is not trusted.
+    TlsFailed(String),
+    /// Connecting to the server timed out.
+    Timeout,
+    /// Connecting to the server or the communication with it failed.
+    Io(String),
+    /// Server sent a message which can not be parsed.
+    InvalidMessage(String),
+}
+
+impl std::fmt::Display for DisconnectReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Closed => write!(f, "connection closed"),
+            Self::HandshakeRejected(reason) => write!(f, "handshake rejected: {}", reason),
+            Self::ProtocolMismatch { client, server } => write!(
+                f,
+                "version mismatch: client protocol {}, server protocol {}",
+                client, server
+            ),
+            Self::TlsFailed(error) => write!(f, "TLS failed: {}", error),
+            Self::Timeout => write!(f, "timeout"),
+            Self::Io(error) => write!(f, "io error: {}", error),
+            Self::InvalidMessage(error) => write!(f, "invalid message: {}", error),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct AutoReconnect {
+    max_retries: u32,
+    backoff: Duration,
+}
+
+impl AutoReconnect {
+    // the backoff is doubled with every failed attempt
+    fn delay(&self, attempt: u32) -> Duration {
+        self.backoff.saturating_mul(1 << (attempt - 1).min(16))
+    }
+}
+
+/// How the token from `ClientBuilder::build` is sent to the server.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TokenTransport {
+    /// In the first message after the websocket is opened.
+    #[default]
+    Handshake,
+    /// As the token query parameter of the websocket url, for proxies which check the url.
+    Query,
+}
+
+// options of the websocket request
+#[derive(Clone, Default)]
+pub(crate) struct WsOptions {
+    pub(crate) protocol: Option<String>,
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) auth_header: Option<String>,
+    pub(crate) query_token: Option<String>,
+}
+
+impl WsOptions {
+    pub(crate) fn url(&self, base: String) -> String {
+        match &self.query_token {
+            Some(token) => format!("{}?token={}", base, encode_query(token)),
+            None => base,
+        }
+    }
+}
+
+// percent-encoding of everything except the unreserved characters
+fn encode_query(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// What happens on reconnect with the local value changes which were not sent to the server.
+#[derive(Clone, Default)]
+pub enum Reconcile {
+    /// Discard the local changes, the state from the server is used.
+    #[default]
+    ServerWins,
+    /// Apply and send the local changes again after the state is synchronized from the server.
+    ClientWins,
+    /// Decide for every changed value from the local change and the value synchronized from the
+    /// server, the resolved value is applied and sent to the server.
+    Merge(Arc<dyn Fn(&Conflict) -> Resolved + Send + Sync>),
+}
+
+/// Value changed locally without connection, passed to the [`Reconcile::Merge`] callback.
+pub struct Conflict<'a> {
+    pub name: &'a str,
+    local: &'a [u8],
+    server: &'a [u8],
+}
+
+impl Conflict<'_> {
+    /// The local change, `T` has to be the type of the value, None if it cannot be parsed.
+    pub fn local<T: for<'a> Deserialize<'a>>(&self) -> Option<T> {
+        deserialize(self.local).ok()
+    }
+
+    /// The value synchronized from the server, `T` has to be the type of the value.
+    pub fn server<T: for<'a> Deserialize<'a>>(&self) -> Option<T> {
+        deserialize(self.server).ok()
+    }
+}
+
+/// Result of the [`Reconcile::Merge`] callback.
+pub struct Resolved(Resolution);
+
+enum Resolution {
+    Local,
+    Server,
+    Merged(MessageData),
+}
+
+impl Resolved {
+    /// Keep the local change and send it to the server.
+    pub fn local() -> Self {
+        Self(Resolution::Local)
+    }
+
+    /// Discard the local change.
+    pub fn server() -> Self {
+        Self(Resolution::Server)
+    }
+
+    /// Apply the merged value and send it to the server, `T` has to be the type of the value.
+    pub fn merged<T: Serialize>(value: &T) -> Self {
+        Self(Resolution::Merged(to_message(value)))
+    }
+}
+
+/// Kind of the value applied from the server, passed to the hook from `ClientBuilder::on_update`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UpdateKind {
+    Value,
+    Static,
+    ValueTake,
+    Image,
+    Vec,
+    Map,
+    Data,
+    DataTake,
+    DataMulti,
+    DataMultiTake,
+}
+
+impl UpdateKind {
+    pub(crate) const COUNT: usize = 10;
+}
+
+type UpdateHook = Arc<dyn Fn(u64, UpdateKind) + Send + Sync>;
+
+/// Snapshot of the transport counters since the client was built, see `Client::stats`.
+/// Messages are the websocket messages, every one can carry several value updates.
+#[derive(Clone, Debug, Default)]
+pub struct ClientStats {
+    pub messages_sent: u64,
+    pub bytes_sent: u64,
+    pub messages_received: u64,
+    pub bytes_received: u64,
+    /// Local changes waiting to be sent to the server.
+    pub queue_depth: usize,
+    /// Latency of the last message from the server, see `Client::get_latency`.
+    pub latency: Option<Duration>,
+    received: [u64; UpdateKind::COUNT],
+}
+
+impl ClientStats {
+    /// Count of the updates of the kind received from the server.
+    pub fn received(&self, kind: UpdateKind) -> u64 {
+        self.received[kind as usize]
+    }
+}
+
+/// Limits for the sizes declared by the server. Messages over the limits are rejected with an
+/// error, the declared sizes before anything is allocated, None means no limit.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Limits {
+    /// Maximal width or height of an image.
+    pub max_image_dimension: Option<u32>,
+    /// Maximal number of items of a single data, checked also after every append or splice.
+    pub max_data_points: Option<u64>,
+}
+
+// local value changes from the time without connection, replayed after the server sync
+struct PendingChanges {
+    values: NoHashMap<u64, (u32, bool, MessageData)>,
+    synced: bool,
+}
+
+// messages buffered by the transport thread until Client::pump_ui applies them
+struct UiDispatch {
+    messages: Mutex<Vec<ServerMessage>>,
+    // with ClientBuilder::ui_batches, messages without the update flag wait for the next update
+    staged: Option<Mutex<Vec<ServerMessage>>>,
+    vals: ValuesList,
+    context: RwLock<Option<Context>>,
+}
+
+impl UiDispatch {
+    // the staged messages are moved before the message, so they are applied together
+    fn commit(&self, message: Option<ServerMessage>) {
+        let mut messages = self.messages.lock();
+        if let Some(staged) = &self.staged {
+            messages.append(&mut staged.lock());
+        }
+        messages.extend(message);
+    }
+}
+
+// generation of the last change of every value, to answer Client::substate_changed
+struct ChangeTracker {
+    names: NoHashMap<u64, String>,
+    generation: u64,
+    changed: NoHashMap<u64, u64>,
+    checked: HashMap<String, u64>,
 }
 
 struct ClientInner {
@@ -142,6 +481,16 @@ struct ClientInner {
     connect_signal: Event,
     state: Arc<RwLock<ConnectionState>>,
     sender: MessageSender,
+    types: RwLock<Vec<(u64, u32, ObjectType)>>,
+    latency: RwLock<Option<Duration>>,
+    ui_dispatch: Option<UiDispatch>,
+    changes: Mutex<ChangeTracker>,
+    reconcile: Reconcile,
+    pending: Mutex<PendingChanges>,
+    update_hook: Option<UpdateHook>,
+    limits: Limits,
+    // disconnected by the user, the connection is not restored automatically
+    stopped: AtomicBool,
 }
 
 impl ClientInner {
@@ -154,12 +503,44 @@ impl ClientInner {
 pub struct Client(Arc<ClientInner>);
 
 impl Client {
-    pub(crate) fn new(context: Option<Context>, sender: MessageSender) -> Self {
+    pub(crate) fn new(
+        context: Option<Context>,
+        sender: MessageSender,
+        ui_dispatch: Option<(ValuesList, bool)>,
+        names: NoHashMap<u64, String>,
+        reconcile: Reconcile,
+        update_hook: Option<UpdateHook>,
+        limits: Limits,
+    ) -> Self {
+        let ui_dispatch = ui_dispatch.map(|(vals, batches)| UiDispatch {
+            messages: Mutex::new(Vec::new()),
+            staged: batches.then(|| Mutex::new(Vec::new())),
+            vals,
+            context: RwLock::new(None),
+        });
+
         let inner = ClientInner {
             context,
             connect_signal: Event::new(),
             state: Arc::new(RwLock::new(ConnectionState::NotConnected)),
             sender,
+            types: RwLock::new(Vec::new()),
+            latency: RwLock::new(None),
+            ui_dispatch,
+            changes: Mutex::new(ChangeTracker {
+                names,
+                generation: 0,
+                changed: NoHashMap::default(),
+                checked: HashMap::new(),
+            }),
+            reconcile,
+            pending: Mutex::new(PendingChanges {
+                values: NoHashMap::default(),
+                synced: false,
+            }),
+            update_hook,
+            limits,
+            stopped: AtomicBool::new(false),
         };
 
         Self(Arc::new(inner))
@@ -170,7 +551,13 @@ impl Client {
     }
 
     pub fn update(&self, time: f32) {
-        if let Some(ctx) = &self.0.context {
+        // without the context from the builder, use the one from pump_ui
+        let dispatch_context = match &self.0.ui_dispatch {
+            Some(dispatch) if self.0.context.is_none() => dispatch.context.read().clone(),
+            _ => None,
+        };
+
+        if let Some(ctx) = self.0.context.as_ref().or(dispatch_context.as_ref()) {
             if time > 0.0 {
                 ctx.request_repaint_after(Duration::from_secs_f32(time));
             } else {
@@ -179,16 +566,145 @@ impl Client {
         }
     }
 
+    pub(crate) fn dispatch_message(
+        &self,
+        message: ServerMessage,
+        vals: &ValuesList,
+    ) -> Result<(), String> {
+        if let ServerMessage::Update(_) = message {
+            // the server sends update after the synchronization of all values
+            self.0.pending.lock().synced = true;
+            // the update ends the batch of the staged messages
+            if let Some(dispatch) = &self.0.ui_dispatch {
+                dispatch.commit(None);
+            }
+            handle_message(message, vals, self)?;
+            if self.0.ui_dispatch.is_none() {
+                self.replay_pending(vals);
+            }
+            return Ok(());
+        }
+
+        match &self.0.ui_dispatch {
+            Some(dispatch)
+                if !matches!(
+                    message,
+                    ServerMessage::Update(_)
+                        | ServerMessage::Types(_)
+                        | ServerMessage::Timestamp(_)
+                ) =>
+            {
+                let update = message.requests_update();
+                match &dispatch.staged {
+                    Some(staged) if !update => staged.lock().push(message),
+                    _ => dispatch.commit(Some(message)),
+                }
+                // wake the ui, so the message is applied in the next pump_ui
+                if update {
+                    self.update(0.);
+                }
+                Ok(())
+            }
+            _ => handle_message(message, vals, self),
+        }
+    }
+
+    fn set_pending(&self, values: NoHashMap<u64, (u32, bool, MessageData)>) {
+        let values = match self.0.reconcile {
+            Reconcile::ServerWins => NoHashMap::default(),
+            _ => values,
+        };
+        *self.0.pending.lock() = PendingChanges {
+            values,
+            synced: false,
+        };
+    }
+
+    // take the pending changes only after the synchronization, so it is not overwritten
+    fn take_pending(&self) -> Option<NoHashMap<u64, (u32, bool, MessageData)>> {
+        let mut pending = self.0.pending.lock();
+        if !pending.synced || pending.values.is_empty() {
+            return None;
+        }
+        Some(std::mem::take(&mut pending.values))
+    }
+
+    fn replay_pending(&self, vals: &ValuesList) {
+        let Some(pending) = self.take_pending() else {
+            return;
+        };
+
+        for (id, (type_id, signal, mut data)) in pending {
+            let Some(value) = vals.values.get(&id) else {
+                continue;
+            };
+
+            if let Reconcile::Merge(merge) = &self.0.reconcile {
+                let Some(server) = value.value_data() else {
+                    continue;
+                };
+                let name = self.0.changes.lock().names.get(&id).cloned();
+                let conflict = Conflict {
+                    name: name.as_deref().unwrap_or_default(),
+                    local: data.as_slice(),
+                    server: server.as_slice(),
+                };
+                match merge(&conflict).0 {
+                    Resolution::Local => {}
+                    Resolution::Server => continue,
+                    Resolution::Merged(merged) => data = merged,
+                }
+            }
+
+            if let Err(e) = value.restore_value(data.as_slice()) {
+                print_error(&e);
+                continue;
+            }
+            self.0
+                .sender
+                .send(ChannelMessage::Value(id, type_id, signal, data));
+        }
+        self.update(0.);
+    }
+
+    // applies the messages buffered since the last call, call it from the egui update when the
+    // client is built with ClientBuilder::ui_dispatch, otherwise it does nothing
+    pub fn pump_ui(&self, ctx: &Context) {
+        let Some(dispatch) = &self.0.ui_dispatch else {
+            return;
+        };
+
+        if dispatch.context.read().is_none() {
+            dispatch.context.write().replace(ctx.clone());
+        }
+
+        // checked before taking the messages, so all synchronized values are applied before
+        let synced = self.0.pending.lock().synced;
+        let messages = std::mem::take(&mut *dispatch.messages.lock());
+        for message in messages {
+            if let Err(e) = apply_message(message, &dispatch.vals, self) {
+                let error = format!("handling message from server failed: {:?}", e);
+                self.0.sender.send_message(&error);
+                print_error(&error);
+            }
+        }
+        if synced {
+            self.replay_pending(&dispatch.vals);
+        }
+    }
+
     pub(crate) async fn wait_connection(&self) {
         self.0.connect_signal.clear();
         self.0.connect_signal.wait_clear_async().await;
     }
 
     pub fn connect(&self) {
+        self.0.stopped.store(false, Ordering::Release);
         self.0.connect_signal.set();
     }
 
     pub fn disconnect(&self) {
+        self.0.stopped.store(true, Ordering::Release);
         self.0.sender.close();
     }
 
@@ -200,7 +716,142 @@ impl Client {
     }
 
     pub fn get_state(&self) -> ConnectionState {
-        *self.0.state.read()
+        self.0.state.read().clone()
+    }
+
+    pub(crate) fn set_types(&self, types: Vec<(u64, u32, ObjectType)>) {
+        *self.0.types.write() = types;
+    }
+
+    // (value id, type id, object type) for all values registered on the server, empty until
+    // received from the server, types have to be requested by ClientBuilder::request_types
+    pub fn get_types(&self) -> Vec<(u64, u32, ObjectType)> {
+        self.0.types.read().clone()
+    }
+
+    // time is the send time of the message in microseconds since UNIX epoch
+    pub(crate) fn set_latency(&self, time: u64) {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Ok(now) = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH) {
+            let latency = now.saturating_sub(Duration::from_micros(time));
+            *self.0.latency.write() = Some(latency);
+        }
+        #[cfg(target_arch = "wasm32")]
+        let _ = time;
+    }
+
+    // latency of the last message from sending on the server to applying on the client, None if
+    // the server is not built with the timestamps feature, the clocks have to be synchronized
+    pub fn get_latency(&self) -> Option<Duration> {
+        *self.0.latency.read()
+    }
+
+    pub fn stats(&self) -> ClientStats {
+        let stats = self.0.sender.stats();
+        ClientStats {
+            messages_sent: stats.messages_sent.load(Ordering::Relaxed),
+            bytes_sent: stats.bytes_sent.load(Ordering::Relaxed),
+            messages_received: stats.messages_received.load(Ordering::Relaxed),
+            bytes_received: stats.bytes_received.load(Ordering::Relaxed),
+            queue_depth: stats.pending.load(Ordering::Relaxed),
+            latency: self.get_latency(),
+            received: std::array::from_fn(|i| stats.received[i].load(Ordering::Relaxed)),
+        }
+    }
+
+    pub(crate) fn mark_changed(&self, id: u64, kind: UpdateKind) {
+        let mut changes = self.0.changes.lock();
+        changes.generation += 1;
+        let generation = changes.generation;
+        changes.changed.insert(id, generation);
+        drop(changes);
+
+        if let Some(hook) = &self.0.update_hook {
+            hook(id, kind);
+        }
+    }
+
+    // checked by the data for the items after every change
+    pub(crate) fn max_data_points(&self) -> Option<u64> {
+        self.0.limits.max_data_points
+    }
+
+    // rejects the messages which declare sizes over the limits
+    pub(crate) fn check_limits(&self, message: &ServerMessage) -> Result<(), String> {
+        let limits = &self.0.limits;
+        if let Some(max) = limits.max_image_dimension
+            && let ServerMessage::Image(id, _, image_message, _) = message
+        {
+            let size = match image_message {
+                ImageMessage::Set(ImageSetMessage::All(size), _)
+                | ImageMessage::Set(ImageSetMessage::Start(size, _), _)
+                | ImageMessage::Encoded(size, _) => Some(*size),
+                ImageMessage::Update(rect, _) => Some([rect[2], rect[3]]),
+                _ => None,
+            };
+            if let Some(size) = size
+                && (size[0] > max || size[1] > max)
+            {
+                // the image acknowledges every message
+                self.0.sender.send(ChannelMessage::Ack(*id));
+                return Err(format!(
+                    "Image size {}x{} exceeds the limit {} for image id {}",
+                    size[0], size[1], max, id
+                ));
+            }
+        }
+
+        if let Some(max) = limits.max_data_points {
+            let declared = match message {
+                ServerMessage::Data(id, _, DataMessage::BatchStart(count, _))
+                | ServerMessage::DataMulti(
+                    id,
+                    _,
+                    DataMultiMessage::Modify(_, DataMessage::BatchStart(count, _)),
+                )
+                | ServerMessage::DataTake(id, _, _, DataTakeMessage::BatchStart(count, _))
+                | ServerMessage::DataMultiTake(
+                    id,
+                    _,
+                    DataMultiTakeMessage::Modify(_, DataTakeMessage::BatchStart(count, _), _),
+                ) => Some((*id, *count)),
+                _ => None,
+            };
+            if let Some((id, count)) = declared
+                && count > max
+            {
+                return Err(format!(
+                    "Data size {} exceeds the limit {} for data id {}",
+                    count, max, id
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    // full name of the value with the id (for example "root.settings.count")
+    pub fn value_name(&self, id: u64) -> Option<String> {
+        self.0.changes.lock().names.get(&id).cloned()
+    }
+
+    // true if any value under the path (for example "root.settings") was changed by the server
+    // since the last call with the same path, the first call reports changes since the start
+    pub fn substate_changed(&self, path: &str) -> bool {
+        let mut changes = self.0.changes.lock();
+        let last = changes.checked.get(path).copied().unwrap_or(0);
+
+        let changed = changes.changed.iter().any(|(id, generation)| {
+            *generation > last
+                && changes.names.get(id).is_some_and(|name| {
+                    name.strip_prefix(path)
+                        .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+                })
+        });
+
+        let generation = changes.generation;
+        changes.checked.insert(path.to_string(), generation);
+        changed
     }
 }
 
@@ -211,6 +862,26 @@ pub struct ClientBuilder<T> {
     rx: UnboundedReceiver<Option<ChannelMessage>>,
     addr: Ipv4Addr,
     context: Option<Context>,
+    request_types: bool,
+    options: u32,
+    ui_dispatch: bool,
+    ui_batches: bool,
+    reconcile: Reconcile,
+    update_hook: Option<UpdateHook>,
+    limits: Limits,
+    tls: Tls,
+    ws: WsOptions,
+    token_transport: TokenTransport,
+    reconnect: Option<AutoReconnect>,
+}
+
+impl<T> Default for ClientBuilder<T>
+where
+    T: State,
+{
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl<T> ClientBuilder<T>
@@ -231,6 +902,17 @@ where
             rx,
             addr,
             context: None,
+            request_types: false,
+            options: 0,
+            ui_dispatch: false,
+            ui_batches: false,
+            reconcile: Reconcile::default(),
+            update_hook: None,
+            limits: Limits::default(),
+            tls: Tls::default(),
+            ws: WsOptions::default(),
+            token_transport: TokenTransport::default(),
+            reconnect: None,
         }
     }
 
@@ -245,6 +927,130 @@ where
         }
     }
 
+    pub fn request_types(self) -> Self {
+        Self {
+            request_types: true,
+            ..self
+        }
+    }
+
+    // ask the server to compress large frames, small frames are always sent as they are
+    #[cfg(feature = "compression")]
+    pub fn compression(self) -> Self {
+        Self {
+            options: self.options | HANDSHAKE_COMPRESSION,
+            ..self
+        }
+    }
+
+    // ask the server to prefix every frame with its CRC32, a corrupted frame closes the
+    // connection with DisconnectReason::InvalidMessage
+    #[cfg(feature = "checksum")]
+    pub fn checksum(self) -> Self {
+        Self {
+            options: self.options | HANDSHAKE_CHECKSUM,
+            ..self
+        }
+    }
+
+    // connect besides the read-write client, the states are received but the changes made in
+    // the ui are ignored by the server, which has to allow read-only clients
+    pub fn read_only(self) -> Self {
+        Self {
+            options: self.options | HANDSHAKE_READ_ONLY,
+            ..self
+        }
+    }
+
+    // buffer the messages from the server and apply them on the ui thread in Client::pump_ui,
+    // so the value changes never happen in the middle of the frame
+    pub fn ui_dispatch(self) -> Self {
+        Self {
+            ui_dispatch: true,
+            ..self
+        }
+    }
+
+    // like ui_dispatch, but the messages without the update flag are held back until the next
+    // message asking for the update, so the changes sent together by the server, for example
+    // in the python batch, are never seen half applied; costs the latency of such messages
+    pub fn ui_batches(self) -> Self {
+        Self {
+            ui_dispatch: true,
+            ui_batches: true,
+            ..self
+        }
+    }
+
+    // how the local changes made without connection are handled on reconnect, the default is
+    // Reconcile::ServerWins
+    pub fn reconcile(self, reconcile: Reconcile) -> Self {
+        Self { reconcile, ..self }
+    }
+
+    // hook called after every update from the server is applied, with the value id and kind,
+    // for example to invalidate the region of a custom renderer
+    pub fn on_update(self, hook: impl Fn(u64, UpdateKind) + Send + Sync + 'static) -> Self {
+        Self {
+            update_hook: Some(Arc::new(hook)),
+            ..self
+        }
+    }
+
+    // limits for the sizes declared by the server, by default there are no limits
+    pub fn limits(self, limits: Limits) -> Self {
+        Self { limits, ..self }
+    }
+
+    // connect over TLS, the domain is used for the server name and checked with the certificate
+    #[cfg(all(feature = "tls", not(target_arch = "wasm32")))]
+    pub fn with_tls(mut self, domain: impl Into<String>) -> Self {
+        self.tls.domain = Some(domain.into());
+        self
+    }
+
+    // trusted roots and other TLS settings, by default the webpki roots are trusted
+    #[cfg(all(feature = "tls", not(target_arch = "wasm32")))]
+    pub fn tls_config(mut self, config: Arc<rustls::ClientConfig>) -> Self {
+        self.tls.config = Some(config);
+        self
+    }
+
+    // subprotocol requested in the websocket handshake, the server accepts the first requested one
+    pub fn with_websocket_protocol(mut self, name: impl Into<String>) -> Self {
+        self.ws.protocol = Some(name.into());
+        self
+    }
+
+    // Authorization header of the websocket request, for example "Bearer <token>", browsers do
+    // not allow to set it, use TokenTransport::Query with the wasm client
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_auth_header(mut self, value: impl Into<String>) -> Self {
+        self.ws.auth_header = Some(value.into());
+        self
+    }
+
+    // how the token from build is sent, the default is TokenTransport::Handshake
+    pub fn token_transport(self, token_transport: TokenTransport) -> Self {
+        Self {
+            token_transport,
+            ..self
+        }
+    }
+
+    // when the connection is lost, connect again up to max_retries times, the first attempt waits
+    // for the backoff and the wait is doubled after every failed attempt
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_auto_reconnect(self, max_retries: u32, backoff: Duration) -> Self {
+        Self {
+            reconnect: Some(AutoReconnect {
+                max_retries,
+                backoff,
+            }),
+            ..self
+        }
+    }
+
     pub fn get_version_hash(&self) -> u64 {
         self.creator.get_version_hash()
     }
@@ -257,11 +1063,38 @@ where
             rx,
             addr,
             context,
+            request_types,
+            options,
+            ui_dispatch,
+            ui_batches,
+            reconcile,
+            update_hook,
+            limits,
+            tls,
+            mut ws,
+            token_transport,
+            reconnect,
         } = self;
 
+        let token = match token_transport {
+            TokenTransport::Handshake => token,
+            TokenTransport::Query => {
+                ws.query_token = token;
+                None
+            }
+        };
+
         let addr = SocketAddrV4::new(addr, port);
         let values = creator.get_values();
-        let client = Client::new(context, sender.clone());
+        let client = Client::new(
+            context,
+            sender.clone(),
+            ui_dispatch.then(|| (values.clone(), ui_batches)),
+            values.names.clone(),
+            reconcile,
+            update_hook,
+            limits,
+        );
         let client_out = client.clone();
 
         #[cfg(not(target_arch = "wasm32"))]
@@ -272,6 +1105,7 @@ where
             let runtime = Builder::new_multi_thread()
                 .thread_name("Client Runtime")
                 .enable_io()
+                .enable_time()
                 .worker_threads(2)
                 .build()
                 .unwrap();
@@ -280,7 +1114,18 @@ where
 
             let _ = thread.spawn(move || {
                 runtime.block_on(start_gui_client(
-                    addr, values, rx, sender, client, version, token,
+                    addr,
+                    values,
+                    rx,
+                    sender,
+                    client,
+                    version,
+                    token,
+                    request_types,
+                    options,
+                    tls,
+                    ws,
+                    reconnect,
                 ))
             });
         }
@@ -288,10 +1133,500 @@ where
         #[cfg(target_arch = "wasm32")]
         {
             wasm_bindgen_futures::spawn_local(async move {
-                start_gui_client(addr, values, rx, sender, client, version, token).await;
+                start_gui_client(
+                    addr,
+                    values,
+                    rx,
+                    sender,
+                    client,
+                    version,
+                    token,
+                    request_types,
+                    options,
+                    tls,
+                    ws,
+                    reconnect,
+                )
+                .await;
             });
         }
 
         (states, client_out)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::data::{Data, DataMulti};
+    use crate::client::data_take::{DataMultiTake, DataTake};
+    use crate::client::messages::apply_message;
+    use crate::client::states_creator::StatesCreator;
+    use crate::client::value_map::MapState;
+    use crate::client::value_vec::VecState;
+    use crate::client::values::{Static, Value, ValueTake};
+    use crate::collections::{MapHeader, VecHeader};
+    use crate::data_transport::{DataType, TransportType};
+    use crate::image_transport::ImageType;
+    use crate::transport::Transportable;
+
+    // the value is changed to 5 without connection and synchronized to 3 from the server, returns
+    // the value after the reconciliation and the values sent to the server
+    fn reconnect(reconcile: Reconcile) -> (i32, Vec<i32>) {
+        let (sender, mut receiver) = MessageSender::new();
+        let mut creator = StatesCreatorClient::new(sender.clone(), "root".to_string());
+        let value: Value<i32> = creator.value("x", 0);
+        let vals = creator.get_values();
+        let id = *vals.names.keys().next().unwrap();
+        let type_id = i32::get_type().get_hash();
+        let names = vals.names.clone();
+        let client = Client::new(
+            None,
+            sender,
+            None,
+            names,
+            reconcile,
+            None,
+            Limits::default(),
+        );
+
+        let mut pending = NoHashMap::default();
+        pending.insert(id, (type_id, false, to_message(5i32)));
+        client.set_pending(pending);
+        vals.values[&id]
+            .update_value(type_id, to_message(3i32).as_slice())
+            .unwrap();
+        client.0.pending.lock().synced = true;
+        client.replay_pending(&vals);
+
+        let mut sent = Vec::new();
+        while let Ok(Some(message)) = receiver.try_recv() {
+            if let ChannelMessage::Value(_, _, _, data) = message {
+                sent.push(deserialize(data.as_slice()).unwrap());
+            }
+        }
+        (value.get(), sent)
+    }
+
+    #[test]
+    fn test_reconcile_server_wins() {
+        assert_eq!(reconnect(Reconcile::ServerWins), (3, vec![]));
+    }
+
+    #[test]
+    fn test_reconcile_client_wins() {
+        assert_eq!(reconnect(Reconcile::ClientWins), (5, vec![5]));
+    }
+
+    #[test]
+    fn test_reconcile_merge() {
+        let merge = Reconcile::Merge(Arc::new(|conflict: &Conflict| {
+            assert_eq!(conflict.name, "root.x");
+            let local: i32 = conflict.local().unwrap();
+            let server: i32 = conflict.server().unwrap();
+            Resolved::merged(&(local + server))
+        }));
+        assert_eq!(reconnect(merge), (8, vec![8]));
+
+        let keep = |local: bool| {
+            Reconcile::Merge(Arc::new(move |_: &Conflict| match local {
+                true => Resolved::local(),
+                false => Resolved::server(),
+            }))
+        };
+        assert_eq!(reconnect(keep(true)), (5, vec![5]));
+        assert_eq!(reconnect(keep(false)), (3, vec![]));
+    }
+
+    fn items(values: &[u16]) -> bytes::Bytes {
+        values.iter().flat_map(|v| v.to_le_bytes()).collect()
+    }
+
+    fn all(transport_type: TransportType, values: &[u16]) -> DataMessage {
+        DataMessage::All(DataType::U16, transport_type, items(values))
+    }
+
+    #[test]
+    fn test_limits_reject_data_over_max_points() {
+        let (sender, _receiver) = MessageSender::new();
+        let mut creator = StatesCreatorClient::new(sender.clone(), "root".to_string());
+        let data: Data<u16> = creator.data("data");
+        let multi: DataMulti<u16> = creator.data_multi("multi");
+        let vals = creator.get_values();
+        let data_id = *vals.data.keys().next().unwrap();
+        let multi_id = *vals.multi_data.keys().next().unwrap();
+        let limits = Limits {
+            max_image_dimension: None,
+            max_data_points: Some(4),
+        };
+        let client = Client::new(
+            None,
+            sender,
+            None,
+            vals.names.clone(),
+            Reconcile::ServerWins,
+            None,
+            limits,
+        );
+        let apply = |message: DataMessage| {
+            apply_message(ServerMessage::Data(data_id, false, message), &vals, &client)
+        };
+
+        apply(all(TransportType::Set(3), &[1, 2, 3])).unwrap();
+        apply(all(TransportType::Add(1), &[4])).unwrap();
+        // appends and splices over the limit keep the data
+        assert!(apply(all(TransportType::Add(1), &[5])).is_err());
+        assert!(apply(all(TransportType::Splice(0, 1, 2), &[6, 7])).is_err());
+        assert_eq!(data.get(), vec![1, 2, 3, 4]);
+        apply(all(TransportType::Splice(0, 2, 2), &[6, 7])).unwrap();
+        apply(all(TransportType::Replace(3, 1), &[8])).unwrap();
+        assert_eq!(data.get(), vec![6, 7, 3, 8]);
+
+        // the declared size of the batch is rejected before the data are received
+        assert!(apply(DataMessage::BatchStart(5, items(&[1]))).is_err());
+        apply(DataMessage::BatchStart(2, items(&[1]))).unwrap();
+        assert!(
+            apply(DataMessage::BatchEnd(
+                DataType::U16,
+                TransportType::Add(2),
+                items(&[2])
+            ))
+            .is_err()
+        );
+        assert_eq!(data.get(), vec![6, 7, 3, 8]);
+
+        // every key of the multi data has its own limit
+        let modify = |key: u32, message: DataMessage| {
+            let message = DataMultiMessage::Modify(key, message);
+            apply_message(
+                ServerMessage::DataMulti(multi_id, false, message),
+                &vals,
+                &client,
+            )
+        };
+        modify(1, all(TransportType::Set(4), &[1, 2, 3, 4])).unwrap();
+        modify(2, all(TransportType::Set(4), &[1, 2, 3, 4])).unwrap();
+        assert!(modify(1, all(TransportType::Add(1), &[5])).is_err());
+        assert!(modify(3, all(TransportType::Set(5), &[1, 2, 3, 4, 5])).is_err());
+        assert_eq!(multi.get(1), Some(vec![1, 2, 3, 4]));
+        assert_eq!(multi.get(3), None);
+    }
+
+    #[test]
+    fn test_limits_reject_large_image() {
+        let (sender, mut receiver) = MessageSender::new();
+        let mut creator = StatesCreatorClient::new(sender.clone(), "root".to_string());
+        let _image = creator.image("image");
+        let vals = creator.get_values();
+        let id = *vals.images.keys().next().unwrap();
+        let limits = Limits {
+            max_image_dimension: Some(16),
+            max_data_points: None,
+        };
+        let client = Client::new(
+            None,
+            sender,
+            None,
+            vals.names.clone(),
+            Reconcile::ServerWins,
+            None,
+            limits,
+        );
+
+        let set = ImageMessage::Set(ImageSetMessage::All([17, 1]), ImageType::Gray);
+        let message = ServerMessage::Image(id, false, set, items(&[0; 9]));
+        assert!(apply_message(message, &vals, &client).is_err());
+        // the rejected image is acknowledged, so the server sends the next one
+        assert!(matches!(receiver.try_recv(), Ok(Some(ChannelMessage::Ack(ack))) if ack == id));
+    }
+
+    // client with the value x, the messages from the server are applied by pump_ui, the receiver
+    // has to live, the value acknowledges every message
+    fn ui_client(batches: bool) -> (Value<i32>, Client, ValuesList, impl Sized) {
+        let (sender, receiver) = MessageSender::new();
+        let mut creator = StatesCreatorClient::new(sender.clone(), "root".to_string());
+        let value: Value<i32> = creator.value("x", 0);
+        let vals = creator.get_values();
+        let client = Client::new(
+            None,
+            sender,
+            Some((vals.clone(), batches)),
+            vals.names.clone(),
+            Reconcile::ServerWins,
+            None,
+            Limits::default(),
+        );
+        (value, client, vals, receiver)
+    }
+
+    fn value_message(value: &Value<i32>, v: i32, update: bool) -> ServerMessage {
+        let data = bytes::Bytes::copy_from_slice(to_message(v).as_slice());
+        ServerMessage::Value(value.id(), i32::get_type().get_hash(), update, data)
+    }
+
+    #[test]
+    fn test_ui_dispatch_applies_only_in_pump_ui() {
+        let (value, client, vals, _receiver) = ui_client(false);
+        let ctx = Context::default();
+        let ui_thread = std::thread::current().id();
+        let observed = Arc::new(Mutex::new(Vec::new()));
+        let observer = observed.clone();
+        value.observe(move |v| observer.lock().push((*v, std::thread::current().id())));
+
+        // received on the transport thread
+        std::thread::scope(|scope| {
+            scope.spawn(|| {
+                client
+                    .dispatch_message(value_message(&value, 3, true), &vals)
+                    .unwrap();
+            });
+        });
+        assert_eq!(value.get(), 0);
+        assert!(observed.lock().is_empty());
+
+        client.pump_ui(&ctx);
+        assert_eq!(value.get(), 3);
+        assert_eq!(*observed.lock(), vec![(3, ui_thread)]);
+
+        // nothing is applied twice
+        client.pump_ui(&ctx);
+        assert_eq!(observed.lock().len(), 1);
+    }
+
+    #[test]
+    fn test_ui_batches_apply_staged_messages_together() {
+        let (value, client, vals, _receiver) = ui_client(true);
+        let ctx = Context::default();
+        let observed = Arc::new(Mutex::new(Vec::new()));
+        let observer = observed.clone();
+        value.observe(move |v| observer.lock().push(*v));
+
+        // without the update flag the messages wait for the end of the batch
+        for v in [1, 2] {
+            client
+                .dispatch_message(value_message(&value, v, false), &vals)
+                .unwrap();
+        }
+        client.pump_ui(&ctx);
+        assert_eq!(value.get(), 0);
+
+        // the message with the update flag commits the batch, in order
+        client
+            .dispatch_message(value_message(&value, 3, true), &vals)
+            .unwrap();
+        client.pump_ui(&ctx);
+        assert_eq!(*observed.lock(), vec![1, 2, 3]);
+
+        // the update of the server commits the batch too
+        client
+            .dispatch_message(value_message(&value, 4, false), &vals)
+            .unwrap();
+        client.pump_ui(&ctx);
+        assert_eq!(value.get(), 3);
+        client
+            .dispatch_message(ServerMessage::Update(0.0), &vals)
+            .unwrap();
+        client.pump_ui(&ctx);
+        assert_eq!(*observed.lock(), vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_without_ui_dispatch_messages_apply_immediately() {
+        let (sender, _receiver) = MessageSender::new();
+        let mut creator = StatesCreatorClient::new(sender.clone(), "root".to_string());
+        let value: Value<i32> = creator.value("x", 0);
+        let vals = creator.get_values();
+        let client = Client::new(
+            None,
+            sender,
+            None,
+            vals.names.clone(),
+            Reconcile::ServerWins,
+            None,
+            Limits::default(),
+        );
+
+        client
+            .dispatch_message(value_message(&value, 3, false), &vals)
+            .unwrap();
+        assert_eq!(value.get(), 3);
+        // pump_ui does nothing without the ui dispatch
+        client.pump_ui(&Context::default());
+        assert_eq!(value.get(), 3);
+    }
+
+    struct Settings {
+        a: Value<i32>,
+    }
+
+    impl State for Settings {
+        const NAME: &'static str = "Settings";
+
+        fn new(c: &mut impl StatesCreator) -> Self {
+            Self { a: c.value("a", 0) }
+        }
+    }
+
+    #[test]
+    fn test_substate_changed_for_change_in_subtree() {
+        let (sender, _receiver) = MessageSender::new();
+        let mut creator = StatesCreatorClient::new(sender.clone(), "root".to_string());
+        let settings: Settings = creator.substate("settings");
+        let other: Value<i32> = creator.value("settingsx", 0);
+        let vals = creator.get_values();
+        let client = Client::new(
+            None,
+            sender,
+            None,
+            vals.names.clone(),
+            Reconcile::ServerWins,
+            None,
+            Limits::default(),
+        );
+        assert!(!client.substate_changed("root.settings"));
+
+        apply_message(value_message(&settings.a, 1, false), &vals, &client).unwrap();
+        assert!(client.substate_changed("root.settings"));
+        assert!(client.substate_changed("root"));
+        // reported once for every path
+        assert!(!client.substate_changed("root.settings"));
+
+        // the names sharing only the prefix are not in the subtree
+        apply_message(value_message(&other, 1, false), &vals, &client).unwrap();
+        assert!(!client.substate_changed("root.settings"));
+        assert!(client.substate_changed("root.settingsx"));
+        assert!(client.substate_changed("root"));
+
+        // the local changes are not tracked
+        settings.a.set(2);
+        assert!(!client.substate_changed("root"));
+    }
+
+    #[test]
+    fn test_update_hook_fires_for_every_update_kind() {
+        let (sender, _receiver) = MessageSender::new();
+        let mut creator = StatesCreatorClient::new(sender.clone(), "root".to_string());
+        let value: Value<i32> = creator.value("value", 0);
+        let _static: Static<i32> = creator.add_static("static", 0);
+        let _take: ValueTake<i32> = creator.value_take("take");
+        let _image = creator.image("image");
+        let _vec: VecState<i32> = creator.vec("vec");
+        let _map: MapState<u8, i32> = creator.map("map");
+        let _data: Data<u16> = creator.data("data");
+        let _data_take: DataTake<u16> = creator.data_take("data_take");
+        let _multi: DataMulti<u16> = creator.data_multi("multi");
+        let _multi_take: DataMultiTake<u16> = creator.data_multi_take("multi_take");
+        let vals = creator.get_values();
+
+        let hooked = Arc::new(Mutex::new(Vec::new()));
+        let hook = hooked.clone();
+        let client = Client::new(
+            None,
+            sender,
+            None,
+            vals.names.clone(),
+            Reconcile::ServerWins,
+            Some(Arc::new(move |id, kind| hook.lock().push((id, kind)))),
+            Limits::default(),
+        );
+
+        fn first<'a>(mut ids: impl Iterator<Item = &'a u64>) -> u64 {
+            *ids.next().unwrap()
+        }
+        let i32_type = i32::get_type().get_hash();
+        let one = bytes::Bytes::copy_from_slice(to_message(1i32).as_slice());
+        let take = || DataTakeMessage::All(DataType::U16, 1, items(&[1]));
+        let messages = [
+            (value_message(&value, 1, false), UpdateKind::Value),
+            (
+                ServerMessage::Static(
+                    first(vals.static_values.keys()),
+                    i32_type,
+                    false,
+                    one.clone(),
+                ),
+                UpdateKind::Static,
+            ),
+            (
+                ServerMessage::ValueTake(
+                    first(vals.values_take.keys()),
+                    i32_type,
+                    false,
+                    false,
+                    one,
+                ),
+                UpdateKind::ValueTake,
+            ),
+            (
+                ServerMessage::Image(
+                    first(vals.images.keys()),
+                    false,
+                    ImageMessage::Set(ImageSetMessage::All([1, 1]), ImageType::Gray),
+                    bytes::Bytes::from_static(&[0]),
+                ),
+                UpdateKind::Image,
+            ),
+            (
+                ServerMessage::ValueVec(
+                    first(vals.vecs.keys()),
+                    i32_type,
+                    false,
+                    VecHeader::All(0),
+                    Default::default(),
+                ),
+                UpdateKind::Vec,
+            ),
+            (
+                ServerMessage::ValueMap(
+                    first(vals.maps.keys()),
+                    i32::get_type().get_hash_from(u8::get_type().get_hash()),
+                    false,
+                    MapHeader::All(0, false),
+                    Default::default(),
+                ),
+                UpdateKind::Map,
+            ),
+            (
+                ServerMessage::Data(
+                    first(vals.data.keys()),
+                    false,
+                    all(TransportType::Set(1), &[1]),
+                ),
+                UpdateKind::Data,
+            ),
+            (
+                ServerMessage::DataTake(first(vals.data_take.keys()), false, false, take()),
+                UpdateKind::DataTake,
+            ),
+            (
+                ServerMessage::DataMulti(
+                    first(vals.multi_data.keys()),
+                    false,
+                    DataMultiMessage::Modify(1, all(TransportType::Set(1), &[1])),
+                ),
+                UpdateKind::DataMulti,
+            ),
+            (
+                ServerMessage::DataMultiTake(
+                    first(vals.data_multi_take.keys()),
+                    false,
+                    DataMultiTakeMessage::Modify(1, take(), false),
+                ),
+                UpdateKind::DataMultiTake,
+            ),
+        ];
+
+        let mut expected = Vec::new();
+        for (message, kind) in messages {
+            expected.push((message.update_kind().unwrap().0, kind));
+            apply_message(message, &vals, &client).unwrap();
+        }
+        assert_eq!(*hooked.lock(), expected);
+
+        // nothing is reported for the rejected update
+        let data = bytes::Bytes::copy_from_slice(to_message(1i32).as_slice());
+        let message = ServerMessage::Value(value.id(), i32_type + 1, false, data);
+        assert!(apply_message(message, &vals, &client).is_err());
+        assert_eq!(hooked.lock().len(), expected.len());
+    }
+}
@@ -1,5 +1,6 @@
 use std::collections::hash_map::Entry;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
 
 use bytes::Bytes;
 use parking_lot::{Mutex, RwLock};
@@ -15,6 +16,7 @@ pub(crate) enum DataMessage {
     BatchEnd(DataType, TransportType, Bytes),
     Drain(u64, u64),
     Clear,
+    Group(Option<(u32, [f64; 2])>),
 }
 
 pub(crate) enum DataMultiMessage {
@@ -24,42 +26,21 @@ pub(crate) enum DataMultiMessage {
 }
 
 pub(crate) mod private {
-    use super::DataType;
-
-    pub(crate) unsafe trait GetDataType: Clone + Copy {
-        fn get_type() -> DataType;
-        fn get_type_id() -> u32;
-    }
-
-    macro_rules! impl_get_data_type {
-        ($ty:ty, $variant:expr, $id:expr) => {
-            unsafe impl GetDataType for $ty {
-                fn get_type() -> DataType {
-                    $variant
-                }
-
-                fn get_type_id() -> u32 {
-                    $id
-                }
-            }
-        };
+    pub(crate) use crate::data_transport::GetDataType;
+
+    #[cfg(any(target_endian = "big", test))]
+    pub(crate) fn from_little_endian<T: GetDataType>(data: &mut [T]) {
+        let bytes_size = size_of_val(data);
+        let bytes =
+            unsafe { std::slice::from_raw_parts_mut(data.as_mut_ptr() as *mut u8, bytes_size) };
+        crate::data_transport::swap_items(bytes, size_of::<T>());
     }
-
-    impl_get_data_type!(u8, DataType::U8, 0);
-    impl_get_data_type!(u16, DataType::U16, 1);
-    impl_get_data_type!(u32, DataType::U32, 2);
-    impl_get_data_type!(u64, DataType::U64, 3);
-    impl_get_data_type!(i8, DataType::I8, 4);
-    impl_get_data_type!(i16, DataType::I16, 5);
-    impl_get_data_type!(i32, DataType::I32, 6);
-    impl_get_data_type!(i64, DataType::I64, 7);
-    impl_get_data_type!(f32, DataType::F32, 8);
-    impl_get_data_type!(f64, DataType::F64, 9);
 }
 
 // Data -------------------------------------------------------------------
 pub(crate) trait UpdateData: Sync + Send {
-    fn update_data(&self, message: DataMessage) -> Result<(), String>;
+    // max_points is the limit of the client for the items after the change
+    fn update_data(&self, message: DataMessage, max_points: Option<u64>) -> Result<(), String>;
 }
 
 pub struct Data<T> {
@@ -69,6 +50,9 @@ pub struct Data<T> {
     element_size: usize,
     inner: Arc<RwLock<Vec<T>>>,
     buffer: Arc<Mutex<Option<Vec<T>>>>,
+    #[allow(clippy::type_complexity)]
+    group: Arc<RwLock<Option<(u32, [f64; 2])>>>,
+    version: Arc<AtomicU64>,
     sender: MessageSender,
 }
 
@@ -85,6 +69,8 @@ where
             element_size: T::get_type().item_size(),
             inner: Arc::new(RwLock::new(Vec::new())),
             buffer: Arc::new(Mutex::new(None)),
+            group: Arc::new(RwLock::new(None)),
+            version: Arc::new(AtomicU64::new(0)),
             sender,
         }
     }
@@ -94,15 +80,42 @@ where
         inner.clone()
     }
 
-    pub fn read<R>(&self, f: impl Fn(&[T]) -> R) -> R {
+    // clone only the last n items, all items if there are less of them
+    pub fn get_last(&self, n: usize) -> Vec<T> {
+        let inner = self.inner.read();
+        inner[inner.len().saturating_sub(n)..].to_vec()
+    }
+
+    // group id and shared x range, data with the same group id should pan and zoom together
+    pub fn group(&self) -> Option<(u32, [f64; 2])> {
+        *self.group.read()
+    }
+
+    // true if the server changed the data since the version in seen, which is updated, every
+    // reader keeps its own seen, so all readers observe the same change
+    pub fn changed_since(&self, seen: &mut u64) -> bool {
+        let version = self.version.load(Ordering::Acquire);
+        let changed = version != *seen;
+        *seen = version;
+        changed
+    }
+
+    // borrow the data without cloning, the read lock is held while f runs, so f must not access
+    // this data again
+    pub fn read<R>(&self, f: impl FnOnce(&[T]) -> R) -> R {
         let inner = self.inner.read();
         f(&inner)
     }
 
-    fn set_all(&self, data: &[u8], transport_type: TransportType) -> Result<(), String> {
+    fn set_all(
+        &self,
+        data: &[u8],
+        transport_type: TransportType,
+        max_points: Option<u64>,
+    ) -> Result<(), String> {
         self.sender.send(ChannelMessage::Ack(self.id));
 
-        if data.len() % self.element_size != 0 {
+        if !data.len().is_multiple_of(self.element_size) {
             return Err(format!(
                 "Data size {} is not a multiple of element size {}",
                 data.len(),
@@ -120,7 +133,9 @@ where
             );
             buffer.set_len(count);
         }
-        self.save_data(buffer, transport_type)
+        #[cfg(target_endian = "big")]
+        private::from_little_endian(&mut buffer);
+        self.save_data(buffer, transport_type, max_points)
     }
 
     fn batch_start(&self, data: &[u8], elements_count: u64) -> Result<(), String> {
@@ -132,7 +147,7 @@ where
                 all_data_size
             ));
         }
-        if data.len() % self.element_size != 0 {
+        if !data.len().is_multiple_of(self.element_size) {
             return Err(format!(
                 "Batch start data size {} is not a multiple of element size {}",
                 data.len(),
@@ -156,7 +171,7 @@ where
     fn batch(&self, data: &[u8]) -> Result<(), String> {
         match *self.buffer.lock() {
             Some(ref mut buffer) => {
-                if data.len() % self.element_size != 0 {
+                if !data.len().is_multiple_of(self.element_size) {
                     return Err(format!(
                         "Batch data size {} is not a multiple of element size {}",
                         data.len(),
@@ -191,12 +206,17 @@ where
         }
     }
 
-    fn batch_end(&self, data: &[u8], transport_type: TransportType) -> Result<(), String> {
+    fn batch_end(
+        &self,
+        data: &[u8],
+        transport_type: TransportType,
+        max_points: Option<u64>,
+    ) -> Result<(), String> {
         self.sender.send(ChannelMessage::Ack(self.id));
 
         match self.buffer.lock().take() {
             Some(mut buffer) => {
-                if data.len() % self.element_size != 0 {
+                if !data.len().is_multiple_of(self.element_size) {
                     return Err(format!(
                         "Batch data size {} is not a multiple of element size {}",
                         data.len(),
@@ -222,7 +242,9 @@ where
                     buffer.set_len(buffer.len() + count);
                 }
 
-                self.save_data(buffer, transport_type)
+                #[cfg(target_endian = "big")]
+                private::from_little_endian(&mut buffer);
+                self.save_data(buffer, transport_type, max_points)
             }
             None => Err(format!(
                 "No header found for Data: {} when updating batch end",
@@ -248,7 +270,14 @@ where
         Ok(())
     }
 
-    fn save_data(&self, data: Vec<T>, transport_type: TransportType) -> Result<(), String> {
+    fn save_data(
+        &self,
+        data: Vec<T>,
+        transport_type: TransportType,
+        max_points: Option<u64>,
+    ) -> Result<(), String> {
+        let mut w = self.inner.write();
+        check_max_points(w.len(), &transport_type, max_points, &self.name)?;
         match transport_type {
             TransportType::Set(count) => {
                 if data.len() as u64 != count {
@@ -258,7 +287,7 @@ where
                         count
                     ));
                 }
-                *self.inner.write() = data;
+                *w = data;
             }
             TransportType::Add(count) => {
                 if data.len() as u64 != count {
@@ -268,7 +297,6 @@ where
                         count
                     ));
                 }
-                let mut w = self.inner.write();
                 w.extend(data);
             }
             TransportType::Replace(start, count) => {
@@ -279,7 +307,6 @@ where
                         count
                     ));
                 }
-                let mut w = self.inner.write();
                 if start as usize + data.len() > w.len() {
                     return Err(format!(
                         "Replace range ({} to {}) exceeds current data size {}",
@@ -290,6 +317,24 @@ where
                 }
                 w.splice(start as usize..(start as usize + data.len()), data);
             }
+            TransportType::Splice(start, removed, count) => {
+                if data.len() as u64 != count {
+                    return Err(format!(
+                        "Data size {} does not match expected count {} for Splice transport type",
+                        data.len(),
+                        count
+                    ));
+                }
+                if start + removed > w.len() as u64 {
+                    return Err(format!(
+                        "Splice range ({} to {}) exceeds current data size {}",
+                        start,
+                        start + removed,
+                        w.len()
+                    ));
+                }
+                w.splice(start as usize..(start + removed) as usize, data);
+            }
         }
         Ok(())
     }
@@ -299,25 +344,31 @@ impl<T: Sync + Send> UpdateData for Data<T>
 where
     T: private::GetDataType,
 {
-    fn update_data(&self, message: DataMessage) -> Result<(), String> {
+    fn update_data(&self, message: DataMessage, max_points: Option<u64>) -> Result<(), String> {
         match message {
             DataMessage::All(data_type, transport_type, data) => {
                 check_data_type(self.data_type, data_type, &self.name)?;
-                self.set_all(&data, transport_type)
+                self.set_all(&data, transport_type, max_points)?;
             }
-            DataMessage::BatchStart(count, data) => self.batch_start(&data, count),
-            DataMessage::Batch(data) => self.batch(&data),
+            DataMessage::BatchStart(count, data) => return self.batch_start(&data, count),
+            DataMessage::Batch(data) => return self.batch(&data),
             DataMessage::BatchEnd(data_type, transport_type, data) => {
                 check_data_type(self.data_type, data_type, &self.name)?;
-                self.batch_end(&data, transport_type)
+                self.batch_end(&data, transport_type, max_points)?;
             }
-            DataMessage::Drain(index, count) => self.drain(index, count),
+            DataMessage::Drain(index, count) => self.drain(index, count)?,
             DataMessage::Clear => {
                 self.inner.write().clear();
                 self.buffer.lock().take();
-                Ok(())
+            }
+            DataMessage::Group(group) => {
+                *self.group.write() = group;
+                return Ok(());
             }
         }
+
+        self.version.fetch_add(1, Ordering::Release);
+        Ok(())
     }
 }
 
@@ -330,6 +381,8 @@ impl<T> Clone for Data<T> {
             element_size: self.element_size,
             inner: self.inner.clone(),
             buffer: self.buffer.clone(),
+            group: self.group.clone(),
+            version: self.version.clone(),
             sender: self.sender.clone(),
         }
     }
@@ -337,7 +390,8 @@ impl<T> Clone for Data<T> {
 
 // MultiData -------------------------------------------------------------------
 pub(crate) trait UpdateMultiData: Sync + Send {
-    fn update(&self, key: u32, message: DataMessage) -> Result<(), String>;
+    fn update(&self, key: u32, message: DataMessage, max_points: Option<u64>)
+    -> Result<(), String>;
     fn remove(&self, key: u32);
     fn reset(&self);
 }
@@ -349,6 +403,8 @@ pub struct DataMulti<T> {
     element_size: usize,
     inner: Arc<RwLock<NoHashMap<u32, Vec<T>>>>,
     buffers: Arc<Mutex<NoHashMap<u32, Vec<T>>>>,
+    // last version and the version of the last change of every key
+    versions: Arc<Mutex<(u64, NoHashMap<u32, u64>)>>,
     sender: MessageSender,
 }
 
@@ -365,6 +421,7 @@ where
             element_size: T::get_type().item_size(),
             inner: Arc::new(RwLock::new(NoHashMap::default())),
             buffers: Arc::new(Mutex::new(NoHashMap::default())),
+            versions: Arc::new(Mutex::new((0, NoHashMap::default()))),
             sender,
         }
     }
@@ -374,29 +431,55 @@ where
         self.inner.read().get(&key).cloned()
     }
 
-    #[inline]
-    pub fn read<R>(&self, key: u32, f: impl Fn(Option<&[T]>) -> R) -> R {
+    // same as Data::get_last, None if the key does not exist
+    pub fn get_last(&self, key: u32, n: usize) -> Option<Vec<T>> {
         self.inner
             .read()
             .get(&key)
-            .map(|v| f(Some(&v)))
-            .unwrap_or_else(|| f(None))
+            .map(|data| data[data.len().saturating_sub(n)..].to_vec())
+    }
+
+    // same as Data::read, f must not access this data again
+    #[inline]
+    pub fn read<R>(&self, key: u32, f: impl FnOnce(Option<&[T]>) -> R) -> R {
+        f(self.inner.read().get(&key).map(|v| v.as_slice()))
+    }
+
+    // same as Data::changed_since for the key, removing the key is a change too
+    pub fn changed_since(&self, key: u32, seen: &mut u64) -> bool {
+        let version = self.versions.lock().1.get(&key).copied().unwrap_or(0);
+        let changed = version != *seen;
+        *seen = version;
+        changed
+    }
+
+    fn mark_changed(&self, key: u32) {
+        let mut versions = self.versions.lock();
+        versions.0 += 1;
+        let version = versions.0;
+        versions.1.insert(key, version);
     }
 
     #[inline]
-    pub fn read_all<R>(&self, f: impl Fn(&NoHashMap<u32, Vec<T>>) -> R) -> R {
+    pub fn read_all<R>(&self, f: impl FnOnce(&NoHashMap<u32, Vec<T>>) -> R) -> R {
         f(&self.inner.read())
     }
 
     #[inline]
     pub fn for_each<F>(&self, f: impl Fn(u32, &[T])) {
-        self.inner.read().iter().for_each(|(k, v)| f(*k, &v));
+        self.inner.read().iter().for_each(|(k, v)| f(*k, v));
     }
 
-    fn set_all(&self, key: u32, data: &[u8], transport_type: TransportType) -> Result<(), String> {
+    fn set_all(
+        &self,
+        key: u32,
+        data: &[u8],
+        transport_type: TransportType,
+        max_points: Option<u64>,
+    ) -> Result<(), String> {
         self.sender.send(ChannelMessage::Ack(self.id));
 
-        if data.len() % self.element_size != 0 {
+        if !data.len().is_multiple_of(self.element_size) {
             return Err(format!(
                 "Data size {} is not a multiple of element size {}",
                 data.len(),
@@ -414,7 +497,9 @@ where
             );
             buffer.set_len(count);
         }
-        self.save_data(key, buffer, transport_type)
+        #[cfg(target_endian = "big")]
+        private::from_little_endian(&mut buffer);
+        self.save_data(key, buffer, transport_type, max_points)
     }
 
     fn batch_start(&self, key: u32, data: &[u8], elements_count: u64) -> Result<(), String> {
@@ -426,7 +511,7 @@ where
                 all_data_size
             ));
         }
-        if data.len() % self.element_size != 0 {
+        if !data.len().is_multiple_of(self.element_size) {
             return Err(format!(
                 "Batch start data size {} is not a multiple of element size {}",
                 data.len(),
@@ -450,7 +535,7 @@ where
     fn batch(&self, key: u32, data: &[u8]) -> Result<(), String> {
         match self.buffers.lock().get_mut(&key) {
             Some(ref mut buffer) => {
-                if data.len() % self.element_size != 0 {
+                if !data.len().is_multiple_of(self.element_size) {
                     return Err(format!(
                         "Batch data size {} is not a multiple of element size {}",
                         data.len(),
@@ -490,12 +575,13 @@ where
         key: u32,
         data: &[u8],
         transport_type: TransportType,
+        max_points: Option<u64>,
     ) -> Result<(), String> {
         self.sender.send(ChannelMessage::Ack(self.id));
 
         match self.buffers.lock().remove(&key) {
             Some(mut buffer) => {
-                if data.len() % self.element_size != 0 {
+                if !data.len().is_multiple_of(self.element_size) {
                     return Err(format!(
                         "Batch data size {} is not a multiple of element size {}",
                         data.len(),
@@ -521,7 +607,9 @@ where
                     buffer.set_len(buffer.len() + count);
                 }
 
-                self.save_data(key, buffer, transport_type)
+                #[cfg(target_endian = "big")]
+                private::from_little_endian(&mut buffer);
+                self.save_data(key, buffer, transport_type, max_points)
             }
             None => Err(format!(
                 "No header found for Data: {} when updating batch end",
@@ -557,7 +645,11 @@ where
         key: u32,
         data: Vec<T>,
         transport_type: TransportType,
+        max_points: Option<u64>,
     ) -> Result<(), String> {
+        let mut inner = self.inner.write();
+        let len = inner.get(&key).map_or(0, Vec::len);
+        check_max_points(len, &transport_type, max_points, &self.name)?;
         match transport_type {
             TransportType::Set(count) => {
                 if data.len() as u64 != count {
@@ -567,7 +659,7 @@ where
                         count
                     ));
                 }
-                self.inner.write().insert(key, data);
+                inner.insert(key, data);
             }
             TransportType::Add(count) => {
                 if data.len() as u64 != count {
@@ -577,7 +669,7 @@ where
                         count
                     ));
                 }
-                match self.inner.write().entry(key) {
+                match inner.entry(key) {
                     Entry::Occupied(mut entry) => {
                         entry.get_mut().extend(data);
                     }
@@ -597,7 +689,7 @@ where
                         count
                     ));
                 }
-                match self.inner.write().entry(key) {
+                match inner.entry(key) {
                     Entry::Occupied(mut entry) => {
                         let w = entry.get_mut();
                         if start as usize + data.len() > w.len() {
@@ -620,6 +712,37 @@ where
                     }
                 }
             }
+            TransportType::Splice(start, removed, count) => {
+                if data.len() as u64 != count {
+                    return Err(format!(
+                        "Data size {} does not match expected count {} for Splice transport type",
+                        data.len(),
+                        count
+                    ));
+                }
+                match inner.entry(key) {
+                    Entry::Occupied(mut entry) => {
+                        let w = entry.get_mut();
+                        if start + removed > w.len() as u64 {
+                            return Err(format!(
+                                "Splice range ({} to {}) exceeds current data size {} for key {} in MultiData: {}",
+                                start,
+                                start + removed,
+                                w.len(),
+                                key,
+                                self.name
+                            ));
+                        }
+                        w.splice(start as usize..(start + removed) as usize, data);
+                    }
+                    Entry::Vacant(_) => {
+                        return Err(format!(
+                            "Key {} does not exist for Splice transport type in MultiData: {}",
+                            key, self.name
+                        ));
+                    }
+                }
+            }
         }
         Ok(())
     }
@@ -629,34 +752,51 @@ impl<T> UpdateMultiData for DataMulti<T>
 where
     T: private::GetDataType + Sync + Send,
 {
-    fn update(&self, index: u32, message: DataMessage) -> Result<(), String> {
+    fn update(
+        &self,
+        index: u32,
+        message: DataMessage,
+        max_points: Option<u64>,
+    ) -> Result<(), String> {
         match message {
             DataMessage::All(data_type, transport_type, data) => {
                 check_data_type(self.data_type, data_type, &self.name)?;
-                self.set_all(index, &data, transport_type)
+                self.set_all(index, &data, transport_type, max_points)?;
             }
-            DataMessage::BatchStart(count, data) => self.batch_start(index, &data, count),
-            DataMessage::Batch(data) => self.batch(index, &data),
+            DataMessage::BatchStart(count, data) => return self.batch_start(index, &data, count),
+            DataMessage::Batch(data) => return self.batch(index, &data),
             DataMessage::BatchEnd(data_type, transport_type, data) => {
                 check_data_type(self.data_type, data_type, &self.name)?;
-                self.batch_end(index, &data, transport_type)
+                self.batch_end(index, &data, transport_type, max_points)?;
             }
-            DataMessage::Drain(start, count) => self.drain(index, start, count),
+            DataMessage::Drain(start, count) => self.drain(index, start, count)?,
             DataMessage::Clear => {
                 if let Some(val) = self.inner.write().get_mut(&index) {
                     val.clear();
                 }
-                Ok(())
+            }
+            DataMessage::Group(_) => {
+                return Err(format!(
+                    "Groups are not supported for DataMulti: {}",
+                    self.name
+                ));
             }
         }
+
+        self.mark_changed(index);
+        Ok(())
     }
 
     fn remove(&self, index: u32) {
         self.inner.write().remove(&index);
+        self.mark_changed(index);
     }
 
     fn reset(&self) {
-        self.inner.write().clear();
+        let keys: Vec<u32> = self.inner.write().drain().map(|(key, _)| key).collect();
+        for key in keys {
+            self.mark_changed(key);
+        }
     }
 }
 
@@ -669,6 +809,7 @@ impl<T> Clone for DataMulti<T> {
             element_size: self.element_size,
             inner: self.inner.clone(),
             buffers: self.buffers.clone(),
+            versions: self.versions.clone(),
             sender: self.sender.clone(),
         }
     }
@@ -689,3 +830,130 @@ pub(crate) fn check_data_type(
     }
     Ok(())
 }
+
+// the item count after the change has to be within the limit of the client
+fn check_max_points(
+    len: usize,
+    transport_type: &TransportType,
+    max_points: Option<u64>,
+    name: &str,
+) -> Result<(), String> {
+    let Some(max) = max_points else {
+        return Ok(());
+    };
+    let len = len as u64;
+    let count = match transport_type {
+        TransportType::Set(count) => *count,
+        TransportType::Add(count) => len.saturating_add(*count),
+        TransportType::Replace(..) => len,
+        TransportType::Splice(_, removed, count) => {
+            (len - (*removed).min(len)).saturating_add(*count)
+        }
+    };
+    if count > max {
+        return Err(format!(
+            "Data size {} exceeds the limit {} for Data: {}",
+            count, max, name
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_transport::swap_items;
+
+    // bytes as a server on the target with the other endianness sends them, swapped by the same
+    // code the big-endian targets use
+    fn swapped_bytes<T: private::GetDataType>(values: &[T]) -> Vec<u8> {
+        let bytes = unsafe {
+            std::slice::from_raw_parts(values.as_ptr() as *const u8, size_of_val(values))
+        };
+        let mut bytes = bytes.to_vec();
+        swap_items(&mut bytes, size_of::<T>());
+        bytes
+    }
+
+    fn from_bytes<T: private::GetDataType + Copy>(bytes: &[u8]) -> Vec<T> {
+        bytes
+            .chunks_exact(size_of::<T>())
+            .map(|item| unsafe { std::ptr::read_unaligned(item.as_ptr() as *const T) })
+            .collect()
+    }
+
+    #[test]
+    fn test_swapped_items_round_trip() {
+        let values = [1u32, 0x0102_0304, u32::MAX - 5];
+        let bytes = swapped_bytes(&values);
+        let swapped: Vec<u8> = values
+            .iter()
+            .flat_map(|v| v.swap_bytes().to_ne_bytes())
+            .collect();
+        assert_eq!(bytes, swapped);
+        let mut received = from_bytes::<u32>(&bytes);
+        private::from_little_endian(&mut received);
+        assert_eq!(received, values);
+
+        let values = [1.5f64, -2.25e10, f64::MIN_POSITIVE];
+        let mut received = from_bytes::<f64>(&swapped_bytes(&values));
+        assert_ne!(received, values);
+        private::from_little_endian(&mut received);
+        assert_eq!(received, values);
+
+        let values = [1i16, -2, 0x0102];
+        let mut received = from_bytes::<i16>(&swapped_bytes(&values));
+        private::from_little_endian(&mut received);
+        assert_eq!(received, values);
+
+        // single bytes are not swapped
+        assert_eq!(swapped_bytes(&[1u8, 2, 3]), vec![1, 2, 3]);
+    }
+
+    fn set(values: &[u16]) -> DataMessage {
+        let bytes: Bytes = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+        DataMessage::All(
+            DataType::U16,
+            TransportType::Set(values.len() as u64),
+            bytes,
+        )
+    }
+
+    #[test]
+    fn test_data_change_seen_by_every_reader() {
+        let (sender, _receiver) = MessageSender::new();
+        let data = Data::<u16>::new("data".to_string(), 10, sender);
+        let (mut first, mut second) = (0, 0);
+        assert!(!data.changed_since(&mut first));
+
+        data.update_data(set(&[1, 2]), None).unwrap();
+        // reading the data does not consume the change
+        assert_eq!(data.get(), vec![1, 2]);
+        assert!(data.changed_since(&mut first));
+        assert!(data.changed_since(&mut second));
+        assert!(!data.changed_since(&mut first));
+        assert!(!data.changed_since(&mut second));
+    }
+
+    #[test]
+    fn test_data_multi_change_seen_per_key() {
+        let (sender, _receiver) = MessageSender::new();
+        let multi = DataMulti::<u16>::new("multi".to_string(), 10, sender);
+        let (mut first, mut second, mut other) = (0, 0, 0);
+
+        multi.update(1, set(&[1]), None).unwrap();
+        assert!(multi.changed_since(1, &mut first));
+        assert!(multi.changed_since(1, &mut second));
+        assert!(!multi.changed_since(2, &mut other));
+
+        multi.update(2, set(&[2]), None).unwrap();
+        assert!(!multi.changed_since(1, &mut first));
+        assert!(multi.changed_since(2, &mut other));
+
+        // removing the key is a change too
+        multi.remove(1);
+        assert!(multi.changed_since(1, &mut first));
+        assert!(multi.changed_since(1, &mut second));
+        assert_eq!(multi.get(1), None);
+    }
+}
@@ -31,6 +31,7 @@ pub struct DataTake<T> {
     id: u64,
     data_type: DataType,
     element_size: usize,
+    #[allow(clippy::type_complexity)]
     inner: Arc<RwLock<Option<(Vec<T>, bool)>>>,
     buffer: Arc<Mutex<Option<Vec<T>>>>,
     sender: MessageSender,
@@ -114,6 +115,8 @@ where
                     );
                     buffer.set_len(count as usize);
                 }
+                #[cfg(target_endian = "big")]
+                private::from_little_endian(&mut buffer);
                 *self.inner.write() = Some((buffer, blocking));
                 Ok(())
             }
@@ -216,6 +219,8 @@ where
                             buffer.set_len(buffer.len() + count_add);
                         }
 
+                        #[cfg(target_endian = "big")]
+                        private::from_little_endian(&mut buffer);
                         *self.inner.write() = Some((buffer, blocking));
                         Ok(())
                     }
@@ -241,6 +246,7 @@ pub struct DataMultiTake<T> {
     id: u64,
     data_type: DataType,
     element_size: usize,
+    #[allow(clippy::type_complexity)]
     inner: Arc<RwLock<NoHashMap<u32, (Vec<T>, bool)>>>,
     buffers: Arc<Mutex<NoHashMap<u32, Vec<T>>>>,
     sender: MessageSender,
@@ -324,6 +330,8 @@ where
                     );
                     buffer.set_len(count as usize);
                 }
+                #[cfg(target_endian = "big")]
+                private::from_little_endian(&mut buffer);
                 self.inner.write().insert(key, (buffer, blocking));
                 Ok(())
             }
@@ -426,6 +434,8 @@ where
                             buffer.set_len(buffer.len() + count_add);
                         }
 
+                        #[cfg(target_endian = "big")]
+                        private::from_little_endian(&mut buffer);
                         self.inner.write().insert(key, (buffer, blocking));
                         Ok(())
                     }
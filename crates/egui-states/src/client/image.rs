@@ -1,11 +1,12 @@
 use parking_lot::{Mutex, RwLock};
 use std::ptr::copy_nonoverlapping;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 
-use egui::{ColorImage, ImageData, TextureHandle};
+use egui::{Color32, ColorImage, ImageData, TextureHandle};
 
 use crate::client::messages::{ChannelMessage, MessageSender};
-use crate::image_transport::ImageType;
+use crate::image_transport::{ImageEncoding, ImageType};
 
 const TEXTURE_OPTIONS: egui::TextureOptions = egui::TextureOptions {
     magnification: egui::TextureFilter::Nearest,
@@ -24,13 +25,21 @@ pub(crate) enum ImageSetMessage {
 pub(crate) enum ImageMessage {
     Set(ImageSetMessage, ImageType),
     Update([u32; 4], ImageType),
+    Scroll(u32, ImageType),
+    Premultiply(bool),
+    Encoded([u32; 2], ImageEncoding),
 }
 
 pub struct Image {
     name: Arc<String>,
     id: u64,
+    #[allow(clippy::type_complexity)]
     inner: Arc<(RwLock<Option<(TextureHandle, [usize; 2])>>, MessageSender)>,
     buffer: Arc<Mutex<Option<(ColorImage, usize)>>>,
+    pixels: Arc<Mutex<Option<Arc<ColorImage>>>>, // last image, needed for scrolling
+    spare: Arc<Mutex<Option<Arc<ColorImage>>>>,  // previous image, reused for the next one
+    premultiply: Arc<AtomicBool>,                // received data has straight alpha
+    options: Arc<Mutex<egui::TextureOptions>>,
 }
 
 impl Image {
@@ -40,41 +49,61 @@ impl Image {
             id,
             inner: Arc::new((RwLock::new(None), sender)),
             buffer: Arc::new(Mutex::new(None)),
+            pixels: Arc::new(Mutex::new(None)),
+            spare: Arc::new(Mutex::new(None)),
+            premultiply: Arc::new(AtomicBool::new(false)),
+            options: Arc::new(Mutex::new(TEXTURE_OPTIONS)),
+        }
+    }
+
+    /// Access the texture and its size under a single read lock. The texture is None and the
+    /// size is [0, 0] until the image is initialized.
+    pub fn with_texture<R>(&self, f: impl FnOnce(Option<&TextureHandle>, [usize; 2]) -> R) -> R {
+        match *self.inner.0.read() {
+            Some((ref texture_handle, size)) => f(Some(texture_handle), size),
+            None => f(None, [0, 0]),
         }
     }
 
     pub fn get(&self) -> Option<(egui::TextureId, [usize; 2])> {
-        self.inner
-            .0
-            .read()
-            .as_ref()
-            .map(|(texture_handle, size)| (texture_handle.id(), *size))
+        self.with_texture(|texture_handle, size| texture_handle.map(|t| (t.id(), size)))
     }
 
     pub fn get_id(&self) -> Option<egui::TextureId> {
-        self.inner
-            .0
-            .read()
-            .as_ref()
-            .map(|(texture_handle, _)| texture_handle.id())
+        self.with_texture(|texture_handle, _| texture_handle.map(|t| t.id()))
     }
 
     pub fn get_size(&self) -> Option<[usize; 2]> {
-        self.inner.0.read().as_ref().map(|(_, size)| *size)
+        self.with_texture(|texture_handle, size| texture_handle.map(|_| size))
+    }
+
+    pub fn texture_options(&self) -> egui::TextureOptions {
+        *self.options.lock()
+    }
+
+    /// Set the filtering and wrapping of the texture, the default is nearest filtering. The
+    /// options are applied to the actual texture immediately.
+    pub fn set_texture_options(&self, options: egui::TextureOptions) {
+        *self.options.lock() = options;
+        let pixels = self.pixels.lock().clone();
+        if let Some(pixels) = pixels
+            && let Some((ref mut texture_handle, _)) = *self.inner.0.write()
+        {
+            texture_handle.set(pixels, options);
+        }
     }
 
     pub fn initialize(&self, ctx: &egui::Context, image: ColorImage) {
-        let image_data = ImageData::Color(Arc::new(image));
+        let image = Arc::new(image);
+        self.pixels.lock().get_or_insert_with(|| image.clone());
+        let image_data = ImageData::Color(image);
         let name = format!("image_{}", self.id);
-        let texture_handle = ctx.load_texture(name, image_data, TEXTURE_OPTIONS);
+        let texture_handle = ctx.load_texture(name, image_data, self.texture_options());
 
         let mut w = self.inner.0.write();
         let size = texture_handle.size();
-        match *w {
-            None => {
-                *w = Some((texture_handle, size));
-            }
-            _ => {}
+        if (*w).is_none() {
+            *w = Some((texture_handle, size));
         }
     }
 
@@ -88,30 +117,30 @@ impl Image {
             ImageSetMessage::All(size) => {
                 self.inner.1.send(ChannelMessage::Ack(self.id));
                 let image_size = [size[0] as usize, size[1] as usize];
-                if image_type.bytes_per_pixel() * image_size[0] * image_size[1] != data.len() {
-                    return Err(format!(
-                        "Data length does not match expected size: {}",
-                        data.len()
-                    ));
-                }
+                self.check_data_len(image_size, image_type, data)?;
 
-                let c_image = self.create_c_image(image_size, image_type, data)?;
-                if let Some((ref mut texture_handle, ref mut save_size)) = *self.inner.0.write() {
-                    texture_handle.set(c_image, TEXTURE_OPTIONS);
-                    *save_size = image_size;
-                }
+                let mut c_image = self.take_spare(image_size);
+                self.fill_c_image(&mut c_image, image_type, data)?;
+                self.set_c_image(c_image);
             }
             ImageSetMessage::Start(size, pixels) => {
+                self.inner.1.send(ChannelMessage::Ack(self.id));
                 let pixels = pixels as usize;
                 let size = [size[0] as usize, size[1] as usize];
-                let mut c_image = ColorImage::filled(size, egui::Color32::WHITE);
+                // check before allocating the buffer for the whole image
+                let pixel_count = pixel_count(size, &self.name)?;
+                if pixels > pixel_count || image_type.data_len(pixel_count).is_none() {
+                    return Err(format!("Pixels exceed image size in {}", self.name));
+                }
+                let mut c_image = self.take_spare(size);
                 self.update_c_image(&mut c_image, 0, pixels, data, image_type)?;
                 *self.buffer.lock() = Some((c_image, pixels))
             }
             ImageSetMessage::Batch(pixels) => {
+                self.inner.1.send(ChannelMessage::Ack(self.id));
                 let pixels = pixels as usize;
                 if let Some((ref mut c_image, ref mut actual_pixel)) = *self.buffer.lock() {
-                    let actual = *actual_pixel as usize;
+                    let actual = *actual_pixel;
 
                     if actual + pixels >= c_image.pixels.len() {
                         return Err(format!("Pixels exceed image size in {}", self.name));
@@ -137,13 +166,7 @@ impl Image {
                     }
 
                     self.update_c_image(&mut c_image, actual_pixel, pixels, data, image_type)?;
-
-                    if let Some((ref mut texture_handle, ref mut save_size)) = *self.inner.0.write()
-                    {
-                        let size = [c_image.width(), c_image.height()];
-                        texture_handle.set(c_image, TEXTURE_OPTIONS);
-                        *save_size = size;
-                    }
+                    self.set_c_image(c_image);
                 } else {
                     return Err(format!("No image buffer found for image: {}", self.name));
                 }
@@ -153,6 +176,81 @@ impl Image {
         Ok(())
     }
 
+    pub(crate) fn set_encoded(
+        &self,
+        size: [u32; 2],
+        encoding: ImageEncoding,
+        data: &[u8],
+    ) -> Result<(), String> {
+        self.inner.1.send(ChannelMessage::Ack(self.id));
+        let image_size = [size[0] as usize, size[1] as usize];
+        pixel_count(image_size, &self.name)?;
+
+        #[cfg(feature = "image-compression")]
+        {
+            let format = match encoding {
+                ImageEncoding::Png => image::ImageFormat::Png,
+                ImageEncoding::Jpeg => image::ImageFormat::Jpeg,
+            };
+            let reader = || image::ImageReader::with_format(std::io::Cursor::new(data), format);
+            let decode_error =
+                |e: image::ImageError| format!("Failed to decode image {}: {}", self.name, e);
+
+            // the declared size is already checked by the client limits, the size in the header
+            // has to match it before anything is decoded
+            let (width, height) = reader().into_dimensions().map_err(decode_error)?;
+            if [width, height] != size {
+                return Err(format!(
+                    "Encoded image size does not match expected size in {}",
+                    self.name
+                ));
+            }
+
+            let mut limits = image::Limits::default();
+            limits.max_image_width = Some(size[0]);
+            limits.max_image_height = Some(size[1]);
+            // 16 bit RGBA is the largest decoded pixel
+            limits.max_alloc = Some(size[0] as u64 * size[1] as u64 * 8);
+            let mut reader = reader();
+            reader.limits(limits);
+            let decoded = reader.decode().map_err(decode_error)?.into_rgba8();
+
+            let mut c_image = self.take_spare(image_size);
+            self.fill_c_image(&mut c_image, ImageType::ColorAlpha, decoded.as_raw())?;
+            self.set_c_image(c_image);
+            Ok(())
+        }
+
+        #[cfg(not(feature = "image-compression"))]
+        {
+            let _ = (encoding, data);
+            Err(format!(
+                "Received compressed image {}, but image-compression feature is not enabled",
+                self.name
+            ))
+        }
+    }
+
+    fn set_c_image(&self, c_image: ColorImage) {
+        let size = c_image.size;
+        let c_image = Arc::new(c_image);
+        let previous = self.pixels.lock().replace(c_image.clone());
+        *self.spare.lock() = previous;
+        if let Some((ref mut texture_handle, ref mut save_size)) = *self.inner.0.write() {
+            texture_handle.set(c_image, self.texture_options());
+            *save_size = size;
+        }
+    }
+
+    // reuse the buffer of the previous image if it has the same size and the texture
+    // does not hold it anymore, otherwise allocate a new one
+    fn take_spare(&self, size: [usize; 2]) -> ColorImage {
+        match self.spare.lock().take().map(Arc::try_unwrap) {
+            Some(Ok(image)) if image.size == size => image,
+            _ => ColorImage::filled(size, egui::Color32::TRANSPARENT),
+        }
+    }
+
     pub(crate) fn update_image(
         &self,
         rect: [u32; 4],
@@ -164,36 +262,75 @@ impl Image {
 
         let image_size = [rect[2] as usize, rect[3] as usize];
         let origin = [rect[0] as usize, rect[1] as usize];
-
-        if image_type.bytes_per_pixel() * image_size[0] * image_size[1] != data.len() {
-            return Err(format!(
-                "Data length does not match expected size: {}",
-                self.name
-            ));
-        }
-
-        let c_image = self.create_c_image(image_size, image_type, data)?;
+        self.check_data_len(image_size, image_type, data)?;
 
         let mut w = self.inner.0.write();
         if let Some((ref mut texture_handle, ref mut save_size)) = *w {
             if *save_size == image_size && origin == [0, 0] {
-                texture_handle.set(c_image, TEXTURE_OPTIONS);
+                let mut c_image = self.take_spare(image_size);
+                self.fill_c_image(&mut c_image, image_type, data)?;
+                let c_image = Arc::new(c_image);
+                let previous = self.pixels.lock().replace(c_image.clone());
+                *self.spare.lock() = previous;
+                texture_handle.set(c_image, self.texture_options());
             } else {
-                if origin[0] + image_size[0] > save_size[0]
-                    || origin[1] + image_size[1] > save_size[1]
+                // check against the actual texture, egui panics on rectangles outside of it
+                let texture_size = texture_handle.size();
+                if origin[0].saturating_add(image_size[0]) > texture_size[0]
+                    || origin[1].saturating_add(image_size[1]) > texture_size[1]
                 {
                     return Err(format!(
-                        "Image is larger than the texture for image: {}",
-                        self.name
+                        "Update rectangle {:?} exceeds the texture {:?} for image: {}",
+                        rect, texture_size, self.name
                     ));
                 }
-                texture_handle.set_partial(origin, c_image, TEXTURE_OPTIONS);
+                let c_image = Arc::new(self.create_c_image(image_size, image_type, data)?);
+                if let Some(ref mut pixels) = *self.pixels.lock()
+                    && pixels.size == *save_size
+                {
+                    write_rect(Arc::make_mut(pixels), origin, &c_image);
+                }
+                texture_handle.set_partial(origin, c_image, self.texture_options());
             }
         }
 
         Ok(())
     }
 
+    pub(crate) fn scroll_image(
+        &self,
+        rows: u32,
+        image_type: ImageType,
+        data: &[u8],
+    ) -> Result<(), String> {
+        self.inner.1.send(ChannelMessage::Ack(self.id));
+
+        let mut pixels = self.pixels.lock();
+        let Some(ref mut image) = *pixels else {
+            return Err(format!("No image to scroll for image: {}", self.name));
+        };
+
+        let [width, height] = image.size;
+        let rows = rows as usize;
+        if rows > height {
+            return Err(format!("Rows exceed image height in {}", self.name));
+        }
+        let c_rows = self.create_c_image([width, rows], image_type, data)?;
+
+        // shift the old rows down and put the new ones at the top
+        let image_mut = Arc::make_mut(image);
+        image_mut
+            .pixels
+            .copy_within(0..(height - rows) * width, rows * width);
+        write_rect(image_mut, [0, 0], &c_rows);
+
+        if let Some((ref mut texture_handle, _)) = *self.inner.0.write() {
+            texture_handle.set(image.clone(), self.texture_options());
+        }
+
+        Ok(())
+    }
+
     fn update_c_image(
         &self,
         image: &mut ColorImage,
@@ -202,11 +339,11 @@ impl Image {
         data: &[u8],
         image_type: ImageType,
     ) -> Result<(), String> {
-        if actual_pixel + pixels > image.pixels.len() {
+        if actual_pixel.saturating_add(pixels) > image.pixels.len() {
             return Err(format!("Pixels exceed image size in {}", self.name));
         }
 
-        if image_type.bytes_per_pixel() * pixels != data.len() {
+        if image_type.data_len(pixels) != Some(data.len()) {
             return Err(format!(
                 "Data length does not match expected size in {}",
                 self.name
@@ -219,32 +356,77 @@ impl Image {
         unsafe {
             fill_c_image(image_type, data_ptr, image_ptr, pixels);
         }
+        self.premultiply_alpha(
+            image_type,
+            &mut image.pixels[actual_pixel..actual_pixel + pixels],
+        );
 
         Ok(())
     }
 
-    fn create_c_image(
+    pub(crate) fn set_premultiply(&self, premultiply: bool) {
+        self.premultiply.store(premultiply, Ordering::Relaxed);
+    }
+
+    fn premultiply_alpha(&self, image_type: ImageType, pixels: &mut [Color32]) {
+        if !self.premultiply.load(Ordering::Relaxed) {
+            return;
+        }
+
+        if let ImageType::ColorAlpha | ImageType::GrayAlpha = image_type {
+            for pixel in pixels {
+                let [r, g, b, a] = pixel.to_array();
+                *pixel = Color32::from_rgba_unmultiplied(r, g, b, a);
+            }
+        }
+    }
+
+    // all unsafe copies rely on this check
+    fn check_data_len(
         &self,
         image_size: [usize; 2],
         image_type: ImageType,
         data: &[u8],
-    ) -> Result<ColorImage, String> {
-        if image_type.bytes_per_pixel() * image_size[0] * image_size[1] != data.len() {
+    ) -> Result<(), String> {
+        let expected = image_type.data_len(pixel_count(image_size, &self.name)?);
+        if expected != Some(data.len()) {
             return Err(format!(
-                "Data length does not match expected size in {}",
+                "Data length {} does not match expected size in {}",
+                data.len(),
                 self.name
             ));
         }
+        Ok(())
+    }
 
+    fn create_c_image(
+        &self,
+        image_size: [usize; 2],
+        image_type: ImageType,
+        data: &[u8],
+    ) -> Result<ColorImage, String> {
         let mut c_image = ColorImage::filled(image_size, egui::Color32::TRANSPARENT);
-        let pixel_count = image_size[0] * image_size[1];
+        self.fill_c_image(&mut c_image, image_type, data)?;
+        Ok(c_image)
+    }
+
+    // overwrite all pixels of the image
+    fn fill_c_image(
+        &self,
+        c_image: &mut ColorImage,
+        image_type: ImageType,
+        data: &[u8],
+    ) -> Result<(), String> {
+        self.check_data_len(c_image.size, image_type, data)?;
 
+        let pixel_count = c_image.size[0] * c_image.size[1];
         let data_ptr = data.as_ptr();
         let image_ptr = c_image.pixels.as_mut_ptr() as *mut u8;
 
         unsafe { fill_c_image(image_type, data_ptr, image_ptr, pixel_count) }
+        self.premultiply_alpha(image_type, &mut c_image.pixels);
 
-        Ok(c_image)
+        Ok(())
     }
 }
 
@@ -255,10 +437,32 @@ impl Clone for Image {
             id: self.id,
             inner: self.inner.clone(),
             buffer: self.buffer.clone(),
+            pixels: self.pixels.clone(),
+            spare: self.spare.clone(),
+            premultiply: self.premultiply.clone(),
+            options: self.options.clone(),
         }
     }
 }
 
+#[inline]
+fn pixel_count(size: [usize; 2], name: &str) -> Result<usize, String> {
+    size[0]
+        .checked_mul(size[1])
+        .ok_or_else(|| format!("Image size overflows in {}", name))
+}
+
+// caller checks that the rectangle fits into the image
+fn write_rect(image: &mut ColorImage, origin: [usize; 2], rect: &ColorImage) {
+    let [width, _] = image.size;
+    let [rect_width, rect_height] = rect.size;
+    for line in 0..rect_height {
+        let start = (origin[1] + line) * width + origin[0];
+        image.pixels[start..start + rect_width]
+            .copy_from_slice(&rect.pixels[line * rect_width..(line + 1) * rect_width]);
+    }
+}
+
 unsafe fn fill_c_image(
     image_type: ImageType,
     data_ptr: *const u8,
@@ -310,3 +514,111 @@ unsafe fn fill_c_image(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "image-compression")]
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut data = std::io::Cursor::new(Vec::new());
+        image::RgbaImage::from_pixel(width, height, image::Rgba([10, 20, 30, 255]))
+            .write_to(&mut data, image::ImageFormat::Png)
+            .unwrap();
+        data.into_inner()
+    }
+
+    // the receiver has to live, the image acknowledges every message
+    fn image() -> (Image, impl Sized) {
+        let (sender, receiver) = MessageSender::new();
+        (Image::new("image".to_string(), 1, sender), receiver)
+    }
+
+    #[test]
+    fn short_and_long_data_are_rejected() {
+        let (image, _receiver) = image();
+        // 2x2 rgb image has 12 bytes
+        for len in [11, 13] {
+            let message = ImageSetMessage::All([2, 2]);
+            let err = image
+                .set_image(message, ImageType::Color, &vec![0; len])
+                .unwrap_err();
+            assert!(err.contains("does not match"));
+        }
+        assert!(image.pixels.lock().is_none());
+
+        let message = ImageSetMessage::Start([2, 2], 2);
+        assert!(image.set_image(message, ImageType::Color, &[0; 5]).is_err());
+        assert!(image.buffer.lock().is_none());
+
+        image
+            .set_image(ImageSetMessage::All([2, 2]), ImageType::Color, &[0; 12])
+            .unwrap();
+        assert_eq!(image.pixels.lock().as_ref().unwrap().size, [2, 2]);
+    }
+
+    #[test]
+    fn update_with_wrong_data_keeps_texture() {
+        let (image, _receiver) = image();
+        let ctx = egui::Context::default();
+        image.initialize(&ctx, ColorImage::filled([2, 2], Color32::RED));
+
+        for len in [2, 4] {
+            let err = image
+                .update_image([0, 0, 1, 1], ImageType::Color, &vec![0; len])
+                .unwrap_err();
+            assert!(err.contains("does not match"));
+        }
+        image
+            .update_image([0, 0, 1, 1], ImageType::Color, &[0; 3])
+            .unwrap();
+        assert_eq!(image.get_size(), Some([2, 2]));
+    }
+
+    #[test]
+    fn straight_alpha_is_premultiplied() {
+        let (image, _receiver) = image();
+        let pixel = [200, 100, 50, 128];
+        let set = |data: &[u8]| {
+            image
+                .set_image(ImageSetMessage::All([1, 1]), ImageType::ColorAlpha, data)
+                .unwrap();
+            image.pixels.lock().as_ref().unwrap().pixels[0]
+        };
+
+        // by default the data are already premultiplied
+        assert_eq!(set(&pixel).to_array(), pixel);
+
+        image.set_premultiply(true);
+        let premultiplied = set(&pixel);
+        assert_eq!(
+            premultiplied,
+            Color32::from_rgba_unmultiplied(200, 100, 50, 128)
+        );
+        assert_eq!(premultiplied.to_array()[0], 100);
+        assert_eq!(premultiplied.a(), 128);
+    }
+
+    #[cfg(feature = "image-compression")]
+    #[test]
+    fn encoded_image_is_decoded() {
+        let (image, _receiver) = image();
+        image
+            .set_encoded([4, 2], ImageEncoding::Png, &png(4, 2))
+            .unwrap();
+        let pixels = image.pixels.lock().clone().unwrap();
+        assert_eq!(pixels.size, [4, 2]);
+        assert_eq!(pixels.pixels[0], Color32::from_rgb(10, 20, 30));
+    }
+
+    #[cfg(feature = "image-compression")]
+    #[test]
+    fn encoded_size_is_checked_before_decoding() {
+        let (image, _receiver) = image();
+        let err = image
+            .set_encoded([4, 2], ImageEncoding::Png, &png(400, 300))
+            .unwrap_err();
+        assert!(err.contains("does not match"));
+        assert!(image.pixels.lock().is_none());
+    }
+}
@@ -1,7 +1,10 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
 use bytes::Bytes;
 use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender, error, unbounded_channel};
 
-use crate::client::client::Client;
+use crate::client::client::{Client, UpdateKind};
 use crate::client::data::{DataMessage, DataMultiMessage};
 use crate::client::data_take::{DataMultiTakeMessage, DataTakeMessage};
 use crate::client::image::{ImageMessage, ImageSetMessage};
@@ -10,8 +13,8 @@ use crate::collections::{MapHeader, VecHeader};
 use crate::data_transport::{DataHeader, DataMultiTakeHeader, DataTakeHeader, MultiDataHeader};
 use crate::image_transport::{ImageHeader, ImageSetHeader};
 use crate::serialization::{
-    ClientHeader, FastVec, MAX_MSG_COUNT, MSG_SIZE_THRESHOLD, MessageData, ServerHeader, serialize,
-    serialize_to_data,
+    ClientHeader, FastVec, MAX_MSG_COUNT, MSG_SIZE_THRESHOLD, MessageData, ServerHeader,
+    deserialize, serialize, serialize_to_data,
 };
 
 pub(crate) enum ChannelMessage {
@@ -21,20 +24,64 @@ pub(crate) enum ChannelMessage {
     Ack(u64),
 }
 
+// transport counters updated from the read and write loops, see Client::stats
+#[derive(Default)]
+pub(crate) struct TransportStats {
+    pub(crate) pending: AtomicUsize,
+    pub(crate) messages_sent: AtomicU64,
+    pub(crate) bytes_sent: AtomicU64,
+    pub(crate) messages_received: AtomicU64,
+    pub(crate) bytes_received: AtomicU64,
+    pub(crate) received: [AtomicU64; UpdateKind::COUNT],
+}
+
+impl TransportStats {
+    #[inline]
+    pub(crate) fn record_sent(&self, size: usize) {
+        self.messages_sent.fetch_add(1, Ordering::Relaxed);
+        self.bytes_sent.fetch_add(size as u64, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub(crate) fn record_received(&self, size: usize) {
+        self.messages_received.fetch_add(1, Ordering::Relaxed);
+        self.bytes_received
+            .fetch_add(size as u64, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub(crate) fn record_kind(&self, kind: UpdateKind) {
+        self.received[kind as usize].fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub(crate) fn taken(&self) {
+        self.pending.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
 #[derive(Clone)]
 pub(crate) struct MessageSender {
     sender: UnboundedSender<Option<ChannelMessage>>,
+    stats: Arc<TransportStats>,
 }
 impl MessageSender {
     pub(crate) fn new() -> (Self, UnboundedReceiver<Option<ChannelMessage>>) {
         let (sender, receiver) = unbounded_channel();
-        (Self { sender }, receiver)
+        let stats = Arc::new(TransportStats::default());
+        (Self { sender, stats }, receiver)
     }
 
     pub(crate) fn send(&self, msg: ChannelMessage) {
+        // count before sending, so the serializer never decrements below zero
+        self.stats.pending.fetch_add(1, Ordering::Relaxed);
         self.sender.send(Some(msg)).unwrap();
     }
 
+    pub(crate) fn stats(&self) -> &Arc<TransportStats> {
+        &self.stats
+    }
+
     pub(crate) fn send_message(&self, msg: &String) {
         let data = serialize(msg).unwrap();
         self.send(ChannelMessage::Message(data));
@@ -71,12 +118,20 @@ fn parse_to_send(message: ChannelMessage, data: &mut FastVec<64>) {
 
 pub(crate) struct MessagesSerializer {
     rx: UnboundedReceiver<Option<ChannelMessage>>,
+    stats: Arc<TransportStats>,
     stopped: bool,
 }
 
 impl MessagesSerializer {
-    pub(crate) fn new(rx: UnboundedReceiver<Option<ChannelMessage>>) -> Self {
-        Self { rx, stopped: false }
+    pub(crate) fn new(
+        rx: UnboundedReceiver<Option<ChannelMessage>>,
+        stats: Arc<TransportStats>,
+    ) -> Self {
+        Self {
+            rx,
+            stats,
+            stopped: false,
+        }
     }
 
     pub(crate) async fn next(&mut self) -> Option<FastVec<64>> {
@@ -86,12 +141,14 @@ impl MessagesSerializer {
 
         match self.rx.recv().await {
             Some(Some(msg)) => {
+                self.stats.taken();
                 let mut message = FastVec::<64>::new();
                 parse_to_send(msg, &mut message);
                 let mut counter = 0;
                 loop {
                     match self.rx.try_recv() {
                         Ok(Some(msg)) => {
+                            self.stats.taken();
                             counter += 1;
                             parse_to_send(msg, &mut message);
                             if counter > MAX_MSG_COUNT || message.len() > MSG_SIZE_THRESHOLD {
@@ -108,9 +165,7 @@ impl MessagesSerializer {
                     }
                 }
             }
-            None | Some(None) => {
-                return None;
-            }
+            None | Some(None) => None,
         }
     }
 
@@ -131,6 +186,9 @@ pub(crate) enum ServerMessage {
     DataMulti(u64, bool, DataMultiMessage),
     DataMultiTake(u64, bool, DataMultiTakeMessage),
     Update(f32),
+    Types(Bytes),
+    Timestamp(u64),
+    Rejected(u16), // server major version
 }
 
 pub(crate) struct MessagesParser {
@@ -149,6 +207,8 @@ impl MessagesParser {
     }
 
     pub(crate) fn from_bytes(data: Bytes) -> Result<(Self, ServerMessage), &'static str> {
+        let data = Self::verify(data)?;
+        let data = Self::decompress(data)?;
         let mut obj = Self {
             data,
             pointer: 0,
@@ -158,6 +218,39 @@ impl MessagesParser {
         Ok((obj, message))
     }
 
+    // the checksum covers the rest of the frame as it was sent, before the decompression
+    fn verify(data: Bytes) -> Result<Bytes, &'static str> {
+        match ServerHeader::deserialize(&data) {
+            #[cfg(feature = "checksum")]
+            Ok((ServerHeader::Checksum(crc), head)) => {
+                if crc32fast::hash(&data[head..]) != crc {
+                    return Err("Frame checksum mismatch");
+                }
+                Ok(data.slice(head..))
+            }
+            #[cfg(not(feature = "checksum"))]
+            Ok((ServerHeader::Checksum(_), _)) => Err("Frame checksum without checksum support"),
+            _ => Ok(data),
+        }
+    }
+
+    // the whole frame is compressed under a single header
+    fn decompress(data: Bytes) -> Result<Bytes, &'static str> {
+        match ServerHeader::deserialize(&data) {
+            #[cfg(feature = "compression")]
+            Ok((ServerHeader::Compressed(size), head)) => {
+                lz4_flex::block::decompress(&data[head..], size as usize)
+                    .map(Bytes::from)
+                    .map_err(|_| "Failed to decompress message")
+            }
+            #[cfg(not(feature = "compression"))]
+            Ok((ServerHeader::Compressed(_), _)) => {
+                Err("Compressed message without compression support")
+            }
+            _ => Ok(data),
+        }
+    }
+
     pub(crate) fn next(&mut self) -> Result<Option<ServerMessage>, &'static str> {
         if self.is_empty {
             return Ok(None);
@@ -224,6 +317,23 @@ impl MessagesParser {
                 ServerMessage::ValueMap(id, type_id, update, header, data)
             }
             ServerHeader::Update(dt) => ServerMessage::Update(dt),
+            ServerHeader::Timestamp(time) => ServerMessage::Timestamp(time),
+            ServerHeader::Rejected(major, _) => ServerMessage::Rejected(major),
+            ServerHeader::Compressed(_) => {
+                return Err("Unexpected compressed message inside a frame");
+            }
+            ServerHeader::Checksum(_) => {
+                return Err("Unexpected checksum inside a frame");
+            }
+            ServerHeader::Types(size) => {
+                let size = size as usize;
+                if size + self.pointer > self.data.len() {
+                    return Err("Incomplete data for Types message");
+                }
+                let data = self.data.slice(self.pointer..self.pointer + size);
+                self.pointer += size;
+                ServerMessage::Types(data)
+            }
             ServerHeader::Image(id, header, size) => {
                 let size = size as usize;
                 if self.pointer + size > self.data.len() {
@@ -262,6 +372,24 @@ impl MessagesParser {
                         ImageMessage::Update(size, image_type),
                         data,
                     ),
+                    ImageHeader::Premultiply(premultiply) => ServerMessage::Image(
+                        id,
+                        false,
+                        ImageMessage::Premultiply(premultiply),
+                        data,
+                    ),
+                    ImageHeader::Scroll(rows, image_type, update) => ServerMessage::Image(
+                        id,
+                        update,
+                        ImageMessage::Scroll(rows, image_type),
+                        data,
+                    ),
+                    ImageHeader::Encoded(size, encoding, update) => ServerMessage::Image(
+                        id,
+                        update,
+                        ImageMessage::Encoded(size, encoding),
+                        data,
+                    ),
                 }
             }
             ServerHeader::Data(id, data_header) => {
@@ -358,6 +486,7 @@ impl MessagesParser {
             }
             DataHeader::Drain(start, count, update) => (DataMessage::Drain(start, count), update),
             DataHeader::Clear(update) => (DataMessage::Clear, update),
+            DataHeader::Group(group, update) => (DataMessage::Group(group), update),
         };
         Ok(res)
     }
@@ -410,16 +539,81 @@ impl MessagesParser {
     }
 }
 
-pub(crate) async fn handle_message(
+impl ServerMessage {
+    pub(crate) fn update_kind(&self) -> Option<(u64, UpdateKind)> {
+        match self {
+            ServerMessage::Value(id, ..) => Some((*id, UpdateKind::Value)),
+            ServerMessage::ValueTake(id, ..) => Some((*id, UpdateKind::ValueTake)),
+            ServerMessage::Static(id, ..) => Some((*id, UpdateKind::Static)),
+            ServerMessage::Image(id, ..) => Some((*id, UpdateKind::Image)),
+            ServerMessage::ValueVec(id, ..) => Some((*id, UpdateKind::Vec)),
+            ServerMessage::ValueMap(id, ..) => Some((*id, UpdateKind::Map)),
+            ServerMessage::Data(id, ..) => Some((*id, UpdateKind::Data)),
+            ServerMessage::DataTake(id, ..) => Some((*id, UpdateKind::DataTake)),
+            ServerMessage::DataMulti(id, ..) => Some((*id, UpdateKind::DataMulti)),
+            ServerMessage::DataMultiTake(id, ..) => Some((*id, UpdateKind::DataMultiTake)),
+            ServerMessage::Update(_)
+            | ServerMessage::Types(_)
+            | ServerMessage::Timestamp(_)
+            | ServerMessage::Rejected(_) => None,
+        }
+    }
+
+    pub(crate) fn requests_update(&self) -> bool {
+        match self {
+            ServerMessage::Value(_, _, update, _)
+            | ServerMessage::ValueTake(_, _, _, update, _)
+            | ServerMessage::Static(_, _, update, _)
+            | ServerMessage::Image(_, update, _, _)
+            | ServerMessage::ValueVec(_, _, update, _, _)
+            | ServerMessage::ValueMap(_, _, update, _, _)
+            | ServerMessage::Data(_, update, _)
+            | ServerMessage::DataTake(_, _, update, _)
+            | ServerMessage::DataMulti(_, update, _)
+            | ServerMessage::DataMultiTake(_, update, _) => *update,
+            ServerMessage::Update(_)
+            | ServerMessage::Types(_)
+            | ServerMessage::Timestamp(_)
+            | ServerMessage::Rejected(_) => false,
+        }
+    }
+}
+
+pub(crate) fn handle_message(
     message: ServerMessage,
     vals: &ValuesList,
     client: &Client,
 ) -> Result<(), String> {
+    if apply_message(message, vals, client)? {
+        client.update(0.);
+    }
+    Ok(())
+}
+
+// applies the message to the values, returns if the message requests the ui update
+pub(crate) fn apply_message(
+    message: ServerMessage,
+    vals: &ValuesList,
+    client: &Client,
+) -> Result<bool, String> {
+    client.check_limits(&message)?;
+    let applied = message.update_kind();
     let update = match message {
         ServerMessage::Update(t) => {
             client.update(t);
-            return Ok(());
+            return Ok(false);
+        }
+        ServerMessage::Types(data) => {
+            let types = deserialize(&data).map_err(|e| format!("Parse error: {} for types", e))?;
+            client.set_types(types);
+            return Ok(false);
         }
+        ServerMessage::Timestamp(time) => {
+            client.set_latency(time);
+            return Ok(false);
+        }
+        // handled by the read loop
+        ServerMessage::Rejected(_) => return Ok(false),
         ServerMessage::Value(id, type_id, update, data) => {
             match vals.values.get(&id) {
                 Some(value) => value.update_value(type_id, &data)?,
@@ -450,6 +644,13 @@ pub(crate) async fn handle_message(
                     ImageMessage::Update(size, image_type) => {
                         value.update_image(size, image_type, &data)?
                     }
+                    ImageMessage::Scroll(rows, image_type) => {
+                        value.scroll_image(rows, image_type, &data)?
+                    }
+                    ImageMessage::Premultiply(premultiply) => value.set_premultiply(premultiply),
+                    ImageMessage::Encoded(size, encoding) => {
+                        value.set_encoded(size, encoding, &data)?
+                    }
                 },
                 None => return Err(format!("Image with id {} not found", id)),
             }
@@ -471,7 +672,7 @@ pub(crate) async fn handle_message(
         }
         ServerMessage::Data(id, update, message) => {
             match vals.data.get(&id) {
-                Some(data) => data.update_data(message)?,
+                Some(data) => data.update_data(message, client.max_data_points())?,
                 None => return Err(format!("Data with id {} not found", id)),
             }
             update
@@ -489,7 +690,7 @@ pub(crate) async fn handle_message(
                     DataMultiMessage::Remove(key) => multi_data.remove(key),
                     DataMultiMessage::Reset => multi_data.reset(),
                     DataMultiMessage::Modify(key, data_message) => {
-                        multi_data.update(key, data_message)?
+                        multi_data.update(key, data_message, client.max_data_points())?
                     }
                 },
                 None => return Err(format!("MultiData with id {} not found", id)),
@@ -511,9 +712,8 @@ pub(crate) async fn handle_message(
         }
     };
 
-    if update {
-        client.update(0.);
+    if let Some((id, kind)) = applied {
+        client.mark_changed(id, kind);
     }
-
-    Ok(())
+    Ok(update)
 }
@@ -1,4 +1,5 @@
 pub(crate) mod atomics;
+#[allow(clippy::module_inception)]
 pub(crate) mod client;
 pub(crate) mod data;
 pub(crate) mod data_take;
@@ -8,6 +9,7 @@ pub(crate) mod states_creator;
 pub(crate) mod value_map;
 pub(crate) mod value_vec;
 pub(crate) mod values;
+pub(crate) mod widgets;
 
 #[cfg(not(target_arch = "wasm32"))]
 mod websocket;
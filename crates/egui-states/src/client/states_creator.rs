@@ -12,8 +12,8 @@ use crate::client::messages::MessageSender;
 use crate::client::value_map::{MapState, UpdateMap};
 use crate::client::value_vec::{UpdateList, VecState};
 use crate::client::values::{
-    GetQueueType, Signal, Static, StaticAtomic, UpdateValue, UpdateValueTake, Value, ValueAtomic,
-    ValueTake,
+    BitFlag, GetQueueType, Signal, Static, StaticAtomic, UpdateValue, UpdateValueTake, Value,
+    ValueArray, ValueAtomic, ValueBitset, ValueTake,
 };
 use crate::hashing::{NoHashMap, StableHasher, generate_value_id};
 use crate::transport::Transportable;
@@ -30,7 +30,18 @@ pub(crate) fn hash_id(hasher: &mut StableHasher, id: u64) {
     id.hash(hasher);
 }
 
+#[inline]
+pub(crate) fn take_value_id(next_id: &mut Option<u64>, name: &str) -> u64 {
+    next_id.take().unwrap_or_else(|| generate_value_id(name))
+}
+
 pub trait StatesCreator {
+    /// Use an explicit id for the next created state instead of the id hashed from its name. Used
+    /// by the `#[id(..)]` attribute, so the state can be renamed without breaking the clients.
+    /// The explicit ids of a substate used twice collide, the build scripts reject them. Creators
+    /// which do not support the explicit ids keep the hashed ones.
+    fn set_next_id(&mut self, _id: u64) {}
+
     fn substate<S: State>(&mut self, name: &str) -> S;
 
     fn value<T, Q>(&mut self, name: &'static str, value: T) -> Value<T, Q>
@@ -42,6 +53,16 @@ pub trait StatesCreator {
     where
         T: for<'a> Deserialize<'a> + Serialize + Transportable + Send + Sync + 'static;
 
+    fn array<T, const N: usize, Q>(
+        &mut self,
+        name: &'static str,
+        value: [T; N],
+    ) -> ValueArray<T, N, Q>
+    where
+        T: Transportable + Send + Sync + Clone + 'static,
+        [T; N]: for<'a> Deserialize<'a> + Serialize,
+        Q: GetQueueType;
+
     fn atomic<T, Q>(&mut self, name: &'static str, value: T) -> ValueAtomic<T, Q>
     where
         T: for<'a> Deserialize<'a>
@@ -54,6 +75,11 @@ pub trait StatesCreator {
             + 'static,
         Q: GetQueueType;
 
+    fn bitset<E, Q>(&mut self, name: &'static str, value: u64) -> ValueBitset<E, Q>
+    where
+        E: BitFlag + Transportable,
+        Q: GetQueueType;
+
     fn add_static<T>(&mut self, name: &'static str, value: T) -> Static<T>
     where
         T: for<'a> Deserialize<'a> + Serialize + Transportable + Clone + Send + Sync + 'static;
@@ -118,6 +144,7 @@ pub(crate) struct ValuesList {
     pub(crate) images: NoHashMap<u64, Image>,
     pub(crate) maps: NoHashMap<u64, Arc<dyn UpdateMap>>,
     pub(crate) vecs: NoHashMap<u64, Arc<dyn UpdateList>>,
+    pub(crate) names: NoHashMap<u64, String>,
 }
 
 impl ValuesList {
@@ -133,6 +160,7 @@ impl ValuesList {
             images: NoHashMap::default(),
             maps: NoHashMap::default(),
             vecs: NoHashMap::default(),
+            names: NoHashMap::default(),
         }
     }
 
@@ -147,6 +175,7 @@ impl ValuesList {
         self.images.shrink_to_fit();
         self.maps.shrink_to_fit();
         self.vecs.shrink_to_fit();
+        self.names.shrink_to_fit();
     }
 }
 
@@ -162,12 +191,14 @@ pub(crate) const DATA_HASH_ID: u8 = 8;
 pub(crate) const DATA_MULTI_HASH_ID: u8 = 9;
 pub(crate) const DATA_TAKE_HASH_ID: u8 = 10;
 pub(crate) const DATA_MULTI_TAKE_HASH_ID: u8 = 11;
+pub(crate) const BITSET_HASH_ID: u8 = 12;
 
 pub struct StatesCreatorClient {
     val: ValuesList,
     sender: MessageSender,
     parent: String,
     version_hasher: StableHasher,
+    next_id: Option<u64>,
 }
 
 impl StatesCreatorClient {
@@ -177,6 +208,7 @@ impl StatesCreatorClient {
             sender,
             parent,
             version_hasher: StableHasher::new(),
+            next_id: None,
         }
     }
 
@@ -192,6 +224,10 @@ impl StatesCreatorClient {
 }
 
 impl StatesCreator for StatesCreatorClient {
+    fn set_next_id(&mut self, id: u64) {
+        self.next_id = Some(id);
+    }
+
     fn substate<S: State>(&mut self, name: &str) -> S {
         let parent = format!("{}.{}", self.parent, name);
         let mut creator = StatesCreatorClient::new(self.sender.clone(), parent);
@@ -211,6 +247,7 @@ impl StatesCreator for StatesCreatorClient {
         self.val.images.extend(creator.val.images);
         self.val.maps.extend(creator.val.maps);
         self.val.vecs.extend(creator.val.vecs);
+        self.val.names.extend(creator.val.names);
 
         substate
     }
@@ -221,7 +258,8 @@ impl StatesCreator for StatesCreatorClient {
         Q: GetQueueType,
     {
         let name = format!("{}.{}", self.parent, name);
-        let id = generate_value_id(&name);
+        let id = take_value_id(&mut self.next_id, &name);
+        self.val.names.insert(id, name.clone());
         let type_id = T::get_type().get_hash();
         hash_id_type(&mut self.version_hasher, id, type_id, VALUE_HASH_ID);
 
@@ -231,12 +269,26 @@ impl StatesCreator for StatesCreatorClient {
         value
     }
 
+    fn array<T, const N: usize, Q>(
+        &mut self,
+        name: &'static str,
+        value: [T; N],
+    ) -> ValueArray<T, N, Q>
+    where
+        T: Transportable + Send + Sync + Clone + 'static,
+        [T; N]: for<'a> Deserialize<'a> + Serialize,
+        Q: GetQueueType,
+    {
+        ValueArray::new(self.value(name, value))
+    }
+
     fn value_take<T>(&mut self, name: &str) -> ValueTake<T>
     where
         T: for<'a> Deserialize<'a> + Serialize + Transportable + Send + Sync + 'static,
     {
         let name = format!("{}.{}", self.parent, name);
-        let id = generate_value_id(&name);
+        let id = take_value_id(&mut self.next_id, &name);
+        self.val.names.insert(id, name.clone());
         let type_id = T::get_type().get_hash();
         hash_id_type(&mut self.version_hasher, id, type_id, VALUE_TAKE_HASH_ID);
 
@@ -259,7 +311,8 @@ impl StatesCreator for StatesCreatorClient {
         Q: GetQueueType,
     {
         let name = format!("{}.{}", self.parent, name);
-        let id = generate_value_id(&name);
+        let id = take_value_id(&mut self.next_id, &name);
+        self.val.names.insert(id, name.clone());
         let type_id = T::get_type().get_hash();
         hash_id_type(&mut self.version_hasher, id, type_id, ATOMIC_HASH_ID);
 
@@ -269,12 +322,31 @@ impl StatesCreator for StatesCreatorClient {
         value
     }
 
+    fn bitset<E, Q>(&mut self, name: &str, value: u64) -> ValueBitset<E, Q>
+    where
+        E: BitFlag + Transportable,
+        Q: GetQueueType,
+    {
+        let name = format!("{}.{}", self.parent, name);
+        let id = take_value_id(&mut self.next_id, &name);
+        self.val.names.insert(id, name.clone());
+        let type_id = <u64 as Transportable>::get_type().get_hash();
+        hash_id_type(&mut self.version_hasher, id, type_id, BITSET_HASH_ID);
+        E::get_type().get_hash().hash(&mut self.version_hasher);
+
+        let value = ValueAtomic::new(name, id, type_id, value, self.sender.clone());
+
+        self.val.values.insert(id, Arc::new(value.clone()));
+        ValueBitset::new(value)
+    }
+
     fn add_static<T>(&mut self, name: &str, value: T) -> Static<T>
     where
         T: for<'a> Deserialize<'a> + Serialize + Transportable + Clone + Send + Sync + 'static,
     {
         let name = format!("{}.{}", self.parent, name);
-        let id = generate_value_id(&name);
+        let id = take_value_id(&mut self.next_id, &name);
+        self.val.names.insert(id, name.clone());
         let type_id = T::get_type().get_hash();
         hash_id_type(&mut self.version_hasher, id, type_id, STATIC_HASH_ID);
 
@@ -296,7 +368,8 @@ impl StatesCreator for StatesCreatorClient {
             + 'static,
     {
         let name = format!("{}.{}", self.parent, name);
-        let id = generate_value_id(&name);
+        let id = take_value_id(&mut self.next_id, &name);
+        self.val.names.insert(id, name.clone());
         let type_id = T::get_type().get_hash();
         hash_id_type(&mut self.version_hasher, id, type_id, STATIC_ATOMIC_HASH_ID);
 
@@ -308,7 +381,8 @@ impl StatesCreator for StatesCreatorClient {
 
     fn image(&mut self, name: &str) -> Image {
         let name = format!("{}.{}", self.parent, name);
-        let id = generate_value_id(&name);
+        let id = take_value_id(&mut self.next_id, &name);
+        self.val.names.insert(id, name.clone());
         hash_id(&mut self.version_hasher, id);
 
         let value = Image::new(name, id, self.sender.clone());
@@ -323,13 +397,11 @@ impl StatesCreator for StatesCreatorClient {
         Q: GetQueueType,
     {
         let name = format!("{}.{}", self.parent, name);
-        let id = generate_value_id(&name);
+        let id = take_value_id(&mut self.next_id, &name);
         let type_id = T::get_type().get_hash();
         hash_id_type(&mut self.version_hasher, id, type_id, SIGNAL_HASH_ID);
 
-        let signal = Signal::new(id, type_id, self.sender.clone());
-
-        signal
+        Signal::new(id, type_id, self.sender.clone())
     }
 
     fn map<K, V>(&mut self, name: &str) -> MapState<K, V>
@@ -338,7 +410,8 @@ impl StatesCreator for StatesCreatorClient {
         V: Clone + for<'a> Deserialize<'a> + Send + Sync + Transportable + 'static,
     {
         let name = format!("{}.{}", self.parent, name);
-        let id = generate_value_id(&name);
+        let id = take_value_id(&mut self.next_id, &name);
+        self.val.names.insert(id, name.clone());
         let type_id = V::get_type().get_hash_from(K::get_type().get_hash());
         hash_id_type(&mut self.version_hasher, id, type_id, MAP_HASH_ID);
 
@@ -352,7 +425,8 @@ impl StatesCreator for StatesCreatorClient {
         T: Clone + for<'a> Deserialize<'a> + Send + Sync + Transportable + 'static,
     {
         let name = format!("{}.{}", self.parent, name);
-        let id = generate_value_id(&name);
+        let id = take_value_id(&mut self.next_id, &name);
+        self.val.names.insert(id, name.clone());
         let type_id = T::get_type().get_hash();
         hash_id_type(&mut self.version_hasher, id, type_id, VEC_HASH_ID);
 
@@ -367,7 +441,8 @@ impl StatesCreator for StatesCreatorClient {
         T: GetDataType + Send + Sync + 'static,
     {
         let name = format!("{}.{}", self.parent, name);
-        let id = generate_value_id(&name);
+        let id = take_value_id(&mut self.next_id, &name);
+        self.val.names.insert(id, name.clone());
         hash_id_type(&mut self.version_hasher, id, T::get_type_id(), DATA_HASH_ID);
 
         let data = Data::new(name, id, self.sender.clone());
@@ -381,7 +456,8 @@ impl StatesCreator for StatesCreatorClient {
         T: GetDataType + Send + Sync + 'static,
     {
         let name = format!("{}.{}", self.parent, name);
-        let id = generate_value_id(&name);
+        let id = take_value_id(&mut self.next_id, &name);
+        self.val.names.insert(id, name.clone());
         hash_id_type(
             &mut self.version_hasher,
             id,
@@ -400,7 +476,8 @@ impl StatesCreator for StatesCreatorClient {
         T: GetDataType + Send + Sync + 'static,
     {
         let name = format!("{}.{}", self.parent, name);
-        let id = generate_value_id(&name);
+        let id = take_value_id(&mut self.next_id, &name);
+        self.val.names.insert(id, name.clone());
         hash_id_type(
             &mut self.version_hasher,
             id,
@@ -419,7 +496,8 @@ impl StatesCreator for StatesCreatorClient {
         T: GetDataType + Send + Sync + 'static,
     {
         let name = format!("{}.{}", self.parent, name);
-        let id = generate_value_id(&name);
+        let id = take_value_id(&mut self.next_id, &name);
+        self.val.names.insert(id, name.clone());
         hash_id_type(
             &mut self.version_hasher,
             id,
@@ -1,3 +1,4 @@
+use indexmap::IndexSet;
 use parking_lot::RwLock;
 use std::collections::HashMap;
 use std::hash::Hash;
@@ -13,10 +14,43 @@ pub(crate) trait UpdateMap: Sync + Send {
     fn update_map(&self, type_id: u32, header: MapHeader, data: &[u8]) -> Result<(), String>;
 }
 
+// keys of the ordered server map are kept in its order, new keys are appended
+struct MapItems<K, V> {
+    map: HashMap<K, V>,
+    order: Option<IndexSet<K>>,
+}
+
+impl<K: Clone + Hash + Eq, V> MapItems<K, V> {
+    fn insert(&mut self, key: K, value: V) {
+        if let Some(order) = &mut self.order
+            && !self.map.contains_key(&key)
+        {
+            order.insert(key.clone());
+        }
+        self.map.insert(key, value);
+    }
+
+    // ordered remove shifts the next keys, like the server map
+    fn remove(&mut self, key: &K) {
+        if self.map.remove(key).is_some()
+            && let Some(order) = &mut self.order
+        {
+            order.shift_remove(key);
+        }
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = (&K, &V)> + '_> {
+        match &self.order {
+            Some(order) => Box::new(order.iter().map(|k| (k, &self.map[k]))),
+            None => Box::new(self.map.iter()),
+        }
+    }
+}
+
 pub struct MapState<K, V> {
     name: String,
     type_id: u32,
-    dict: Arc<RwLock<HashMap<K, V>>>,
+    dict: Arc<RwLock<MapItems<K, V>>>,
 }
 
 impl<K, V> MapState<K, V>
@@ -28,35 +62,50 @@ where
         Self {
             name,
             type_id,
-            dict: Arc::new(RwLock::new(HashMap::new())),
+            dict: Arc::new(RwLock::new(MapItems {
+                map: HashMap::new(),
+                order: None,
+            })),
         }
     }
 
     #[inline]
     pub fn get(&self) -> HashMap<K, V> {
-        self.dict.read().clone()
+        self.dict.read().map.clone()
+    }
+
+    /// Items in the insertion order for the ordered server map, in any order otherwise.
+    pub fn get_ordered(&self) -> Vec<(K, V)> {
+        let d = self.dict.read();
+        d.iter().map(|(k, v)| (k.clone(), v.clone())).collect()
     }
 
     #[inline]
     pub fn get_item(&self, key: &K) -> Option<V> {
-        self.dict.read().get(key).cloned()
+        self.dict.read().map.get(key).cloned()
     }
 
     pub fn read<R>(&self, mut f: impl FnMut(&HashMap<K, V>) -> R) -> R {
         let d = self.dict.read();
-        f(&*d)
+        f(&d.map)
+    }
+
+    /// Read the items in the insertion order for the ordered server map, in any order otherwise.
+    pub fn read_ordered<R>(&self, mut f: impl FnMut(&mut dyn Iterator<Item = (&K, &V)>) -> R) -> R {
+        let d = self.dict.read();
+        f(&mut d.iter())
     }
 
     pub fn read_item<R>(&self, key: &K, mut f: impl FnMut(Option<&V>) -> R) -> R {
         let d = self.dict.read();
-        let v = d.get(key);
+        let v = d.map.get(key);
         f(v)
     }
 }
 
 impl<K, V> UpdateMap for MapState<K, V>
 where
-    K: for<'a> Deserialize<'a> + Eq + Hash + Send + Sync,
+    K: for<'a> Deserialize<'a> + Clone + Eq + Hash + Send + Sync,
     V: for<'a> Deserialize<'a> + Send + Sync,
 {
     fn update_map(&self, type_id: u32, header: MapHeader, data: &[u8]) -> Result<(), String> {
@@ -65,12 +114,13 @@ where
         }
 
         match header {
-            MapHeader::All(size) => {
+            MapHeader::All(size, ordered) => {
                 let mut deserializer = Deserializer::new(data);
 
-                let mut map = self.dict.write();
-                map.clear();
-                map.reserve(size as usize);
+                let mut d = self.dict.write();
+                d.map.clear();
+                d.map.reserve(size as usize);
+                d.order = ordered.then(|| IndexSet::with_capacity(size as usize));
 
                 for _ in 0..size {
                     let key: K = deserializer.get().map_err(|e| {
@@ -79,7 +129,7 @@ where
                     let value: V = deserializer.get().map_err(|e| {
                         format!("Error deserializing dict value for {}: {}", self.name, e)
                     })?;
-                    map.insert(key, value);
+                    d.insert(key, value);
                 }
             }
             MapHeader::Set => {
@@ -94,6 +144,21 @@ where
                 })?;
                 self.dict.write().remove(&key);
             }
+            MapHeader::RemoveMany(size) => {
+                let mut deserializer = Deserializer::new(data);
+                let mut d = self.dict.write();
+                for _ in 0..size {
+                    let key: K = deserializer.get().map_err(|e| {
+                        format!("Error deserializing dict key for {}: {}", self.name, e)
+                    })?;
+                    d.map.remove(&key);
+                }
+                // the order is filtered once, not shifted for every key
+                let MapItems { map, order } = &mut *d;
+                if let Some(order) = order {
+                    order.retain(|k| map.contains_key(k));
+                }
+            }
         }
         Ok(())
     }
@@ -108,3 +173,57 @@ impl<K, V> Clone for MapState<K, V> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn items<T: serde::Serialize>(items: &[T]) -> Vec<u8> {
+        items
+            .iter()
+            .flat_map(|item| postcard::to_allocvec(item).unwrap())
+            .collect()
+    }
+
+    fn map(ordered: bool) -> MapState<u32, u32> {
+        let map = MapState::new("map".to_string(), 0);
+        let data = items(&[(3u32, 30u32), (1, 10), (2, 20)]);
+        map.update_map(0, MapHeader::All(3, ordered), &data)
+            .unwrap();
+        map
+    }
+
+    #[test]
+    fn test_ordered_map_keeps_server_order() {
+        let map = map(true);
+        map.update_map(0, MapHeader::Set, &items(&[(0u32, 0u32)]))
+            .unwrap();
+        map.update_map(0, MapHeader::Set, &items(&[(3u32, 31u32)]))
+            .unwrap();
+        map.update_map(0, MapHeader::Remove, &items(&[1u32]))
+            .unwrap();
+        assert_eq!(map.get_ordered(), vec![(3, 31), (2, 20), (0, 0)]);
+
+        map.update_map(0, MapHeader::RemoveMany(2), &items(&[3u32, 5]))
+            .unwrap();
+        let keys = map.read_ordered(|items| items.map(|(k, _)| *k).collect::<Vec<_>>());
+        assert_eq!(keys, vec![2, 0]);
+    }
+
+    #[test]
+    fn test_unordered_map_keeps_no_order() {
+        let map = map(false);
+        assert!(map.dict.read().order.is_none());
+
+        map.update_map(0, MapHeader::Remove, &items(&[1u32]))
+            .unwrap();
+        let mut items = map.get_ordered();
+        items.sort();
+        assert_eq!(items, vec![(2, 20), (3, 30)]);
+
+        // the full set of the ordered server map switches to the server order
+        let data = self::items(&[(5u32, 50u32), (4, 40)]);
+        map.update_map(0, MapHeader::All(2, true), &data).unwrap();
+        assert_eq!(map.get_ordered(), vec![(5, 50), (4, 40)]);
+    }
+}
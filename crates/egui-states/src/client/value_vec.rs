@@ -89,6 +89,31 @@ impl<T: for<'a> Deserialize<'a> + Send + Sync> UpdateList for VecState<T> {
                     list.remove(idx);
                 }
             }
+            VecHeader::Reorder => {
+                let permutation: Vec<u32> = deserialize(data).map_err(|e| {
+                    format!("Error deserializing list order for {}: {}", self.name, e)
+                })?;
+                let mut list = self.list.write();
+                if permutation.len() != list.len() {
+                    return Err(format!("List order length mismatch for {}", self.name));
+                }
+
+                // checked before any item is moved, an invalid order keeps the list
+                let mut seen = vec![false; list.len()];
+                for idx in permutation.iter() {
+                    match seen.get_mut(*idx as usize) {
+                        Some(seen) if !*seen => *seen = true,
+                        _ => return Err(format!("Invalid list order for {}", self.name)),
+                    }
+                }
+
+                let mut items: Vec<Option<T>> = list.drain(..).map(Some).collect();
+                list.extend(
+                    permutation
+                        .into_iter()
+                        .filter_map(|idx| items[idx as usize].take()),
+                );
+            }
         }
         Ok(())
     }
@@ -1,11 +1,14 @@
-use parking_lot::RwLock;
+use parking_lot::{Condvar, Mutex, RwLock};
 use serde::{Deserialize, Serialize};
 use std::marker::PhantomData;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::{Duration, Instant};
 
 use crate::client::atomics::{Atomic, AtomicLock, AtomicLockStatic, AtomicStatic};
 use crate::client::messages::{ChannelMessage, MessageSender};
-use crate::serialization::{deserialize, to_message};
+use crate::serialization::{MessageData, deserialize, to_message};
 
 pub struct Diff<'a, T> {
     pub v: T,
@@ -48,7 +51,7 @@ impl<'a, T: Serialize + Clone + PartialEq + Atomic> DiffAtomic<'a, T> {
     pub fn new(value: &'a ValueAtomic<T>) -> Self {
         let v = value.get();
         Self {
-            v: v,
+            v,
             original: v,
             value,
         }
@@ -71,6 +74,12 @@ impl<'a, T: Serialize + Clone + PartialEq + Atomic> DiffAtomic<'a, T> {
 
 pub(crate) trait UpdateValue: Sync + Send {
     fn update_value(&self, type_id: u32, data: &[u8]) -> Result<(), String>;
+    // set the local change again without acknowledging, used by the reconnect reconciliation
+    fn restore_value(&self, data: &[u8]) -> Result<(), String>;
+    // the current value, compared with the local change by the reconnect reconciliation
+    fn value_data(&self) -> Option<MessageData> {
+        None
+    }
 }
 
 pub(crate) trait UpdateValueTake: Sync + Send {
@@ -100,11 +109,24 @@ impl GetQueueType for Queue {
 }
 
 // Value --------------------------------------------
+type Transform<T> = Arc<dyn Fn(&T) -> T + Send + Sync>;
+type Observers<T> = Vec<(usize, Arc<dyn Fn(&T) + Send + Sync>)>;
+
+/// The value was not updated by the server within the timeout of [`Value::wait_changed`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WaitTimeout;
+
 pub struct Value<T, Q: GetQueueType = NoQueue> {
     name: String,
     id: u64,
     type_id: u32,
     inner: Arc<(RwLock<T>, MessageSender)>,
+    #[allow(clippy::type_complexity)]
+    display: Arc<RwLock<Option<(Transform<T>, Transform<T>)>>>,
+    // counter of the server updates and condvar notified on every update
+    updates: Arc<(Mutex<u64>, Condvar)>,
+    // last observer id and observers notified after every change of the value
+    observers: Arc<RwLock<(usize, Observers<T>)>>,
     _phantom: PhantomData<Q>,
 }
 
@@ -124,10 +146,18 @@ where
             id,
             type_id,
             inner: Arc::new((RwLock::new(value), sender)),
+            display: Arc::new(RwLock::new(None)),
+            updates: Arc::new((Mutex::new(0), Condvar::new())),
+            observers: Arc::new(RwLock::new((0, Vec::new()))),
             _phantom: PhantomData,
         }
     }
 
+    #[inline]
+    pub(crate) fn id(&self) -> u64 {
+        self.id
+    }
+
     pub fn get(&self) -> T {
         self.inner.0.read().clone()
     }
@@ -137,9 +167,75 @@ where
         f(&r)
     }
 
+    /// True if the server changed the value since the update count in `seen`, which is updated.
+    /// Every reader keeps its own `seen`, so all of them observe the same change, like
+    /// [`Data::changed_since`](crate::Data::changed_since). Local changes do not count as update.
+    pub fn changed_since(&self, seen: &mut u64) -> bool {
+        let count = *self.updates.0.lock();
+        let changed = count != *seen;
+        *seen = count;
+        changed
+    }
+
+    /// Block until the server updates the value and return the new value. Counts the same updates
+    /// as [`Value::changed_since`].
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn wait_changed(&self, timeout: Duration) -> Result<T, WaitTimeout> {
+        let deadline = Instant::now() + timeout;
+        let (updates, condvar) = &*self.updates;
+        let mut count = updates.lock();
+        let start = *count;
+        while *count == start {
+            if condvar.wait_until(&mut count, deadline).timed_out() {
+                return Err(WaitTimeout);
+            }
+        }
+        drop(count);
+        Ok(self.get())
+    }
+
+    /// Register an observer called with the new value after every change, local or from the
+    /// server, so several widgets showing the value can refresh. The observer must not change
+    /// the value. Changes from the server call it on the network thread, so it should be short,
+    /// for example `Context::request_repaint`. Returns the id for [`Value::remove_observer`].
+    pub fn observe(&self, observer: impl Fn(&T) + Send + Sync + 'static) -> usize {
+        let mut w = self.observers.write();
+        w.0 += 1;
+        let id = w.0;
+        w.1.push((id, Arc::new(observer)));
+        id
+    }
+
+    pub fn remove_observer(&self, id: usize) {
+        self.observers
+            .write()
+            .1
+            .retain(|(observer_id, _)| *observer_id != id);
+    }
+
+    fn notify_observers(&self) {
+        // cloned, so the observers can read the value and remove themselves
+        let observers = self.observers.read().1.clone();
+        if observers.is_empty() {
+            return;
+        }
+
+        let value = self.get();
+        for (_, observer) in observers {
+            observer(&value);
+        }
+    }
+
+    fn notify_update(&self) {
+        let (updates, condvar) = &*self.updates;
+        *updates.lock() += 1;
+        condvar.notify_all();
+        self.notify_observers();
+    }
+
     #[inline]
     fn write_inner(&self, value: &T, signal: bool) {
-        let data = to_message(&value);
+        let data = to_message(value);
         self.inner
             .1
             .send(ChannelMessage::Value(self.id, self.type_id, signal, data));
@@ -149,6 +245,8 @@ where
         let mut w = self.inner.0.write();
         let result = f(&mut w);
         self.write_inner(&*w, false);
+        drop(w);
+        self.notify_observers();
         result
     }
 
@@ -156,6 +254,8 @@ where
         let mut w = self.inner.0.write();
         let result = f(&mut w);
         self.write_inner(&*w, true);
+        drop(w);
+        self.notify_observers();
         result
     }
 
@@ -168,6 +268,8 @@ where
             .1
             .send(ChannelMessage::Value(self.id, self.type_id, signal, data));
         *w = value;
+        drop(w);
+        self.notify_observers();
     }
 
     pub fn set(&self, value: T) {
@@ -177,10 +279,50 @@ where
     pub fn set_signal(&self, value: T) {
         self.set_inner(value, true);
     }
+
+    // transform only for displaying the value in the UI, the server value stays unchanged
+    pub fn set_display_transform(
+        &self,
+        to_display: impl Fn(&T) -> T + Send + Sync + 'static,
+        from_display: impl Fn(&T) -> T + Send + Sync + 'static,
+    ) {
+        *self.display.write() = Some((Arc::new(to_display), Arc::new(from_display)));
+    }
+
+    pub fn clear_display_transform(&self) {
+        *self.display.write() = None;
+    }
+
+    pub fn get_display(&self) -> T {
+        let value = self.inner.0.read();
+        match *self.display.read() {
+            Some((ref to_display, _)) => to_display(&value),
+            None => value.clone(),
+        }
+    }
+
+    pub fn set_display(&self, value: T) {
+        self.set_inner(self.from_display(value), false);
+    }
+
+    pub fn set_display_signal(&self, value: T) {
+        self.set_inner(self.from_display(value), true);
+    }
+
+    #[allow(clippy::wrong_self_convention)]
+    #[inline]
+    fn from_display(&self, value: T) -> T {
+        match *self.display.read() {
+            Some((_, ref from_display)) => from_display(&value),
+            None => value,
+        }
+    }
 }
 
-impl<T: for<'a> Deserialize<'a> + Send + Sync, Q: GetQueueType + Send + Sync> UpdateValue
-    for Value<T, Q>
+impl<T, Q> UpdateValue for Value<T, Q>
+where
+    T: for<'a> Deserialize<'a> + Serialize + Clone + Send + Sync,
+    Q: GetQueueType + Send + Sync,
 {
     fn update_value(&self, type_id: u32, data: &[u8]) -> Result<(), String> {
         if type_id != self.type_id {
@@ -195,9 +337,23 @@ impl<T: for<'a> Deserialize<'a> + Send + Sync, Q: GetQueueType + Send + Sync> Up
         let mut w = self.inner.0.write();
         self.inner.1.send(ChannelMessage::Ack(self.id));
         *w = value;
+        drop(w);
+        self.notify_update();
+        Ok(())
+    }
 
+    // the restored value replaces the synced one, so it counts as update too
+    fn restore_value(&self, data: &[u8]) -> Result<(), String> {
+        let value = deserialize(data)
+            .map_err(|e| format!("Parse error: {} for value: {}", e, self.name))?;
+        *self.inner.0.write() = value;
+        self.notify_update();
         Ok(())
     }
+
+    fn value_data(&self) -> Option<MessageData> {
+        Some(to_message(&*self.inner.0.read()))
+    }
 }
 
 impl<T, Q: GetQueueType> Clone for Value<T, Q> {
@@ -207,6 +363,9 @@ impl<T, Q: GetQueueType> Clone for Value<T, Q> {
             id: self.id,
             type_id: self.type_id,
             inner: self.inner.clone(),
+            display: self.display.clone(),
+            updates: self.updates.clone(),
+            observers: self.observers.clone(),
             _phantom: PhantomData,
         }
     }
@@ -245,18 +404,29 @@ where
     }
 
     pub fn set(&self, value: T) {
-        let message = ChannelMessage::Value(self.id, self.type_id, false, to_message(&value));
+        let message = ChannelMessage::Value(self.id, self.type_id, false, to_message(value));
         self.inner.0.update(value, || self.inner.1.send(message));
     }
 
     pub fn set_signal(&self, value: T) {
-        let message = ChannelMessage::Value(self.id, self.type_id, true, to_message(&value));
+        let message = ChannelMessage::Value(self.id, self.type_id, true, to_message(value));
         self.inner.0.update(value, || self.inner.1.send(message));
     }
+
+    // the new value is computed from the current one under the update lock, so concurrent changes
+    // are not lost
+    pub(crate) fn modify(&self, f: impl FnOnce(T) -> T, signal: bool) {
+        self.inner.0.modify(f, |value| {
+            let message = ChannelMessage::Value(self.id, self.type_id, signal, to_message(value));
+            self.inner.1.send(message);
+        });
+    }
 }
 
-impl<T: for<'a> Deserialize<'a> + Atomic + Send + Sync, Q: GetQueueType + Send + Sync> UpdateValue
-    for ValueAtomic<T, Q>
+impl<T, Q> UpdateValue for ValueAtomic<T, Q>
+where
+    T: for<'a> Deserialize<'a> + Serialize + Atomic + Send + Sync,
+    Q: GetQueueType + Send + Sync,
 {
     fn update_value(&self, type_id: u32, data: &[u8]) -> Result<(), String> {
         if type_id != self.type_id {
@@ -274,6 +444,17 @@ impl<T: for<'a> Deserialize<'a> + Atomic + Send + Sync, Q: GetQueueType + Send +
 
         Ok(())
     }
+
+    fn restore_value(&self, data: &[u8]) -> Result<(), String> {
+        let value = deserialize(data)
+            .map_err(|e| format!("Parse error: {} for value: {}", e, self.name))?;
+        self.inner.0.store(value);
+        Ok(())
+    }
+
+    fn value_data(&self) -> Option<MessageData> {
+        Some(to_message(self.inner.0.load()))
+    }
 }
 
 impl<T: Atomic, Q: GetQueueType> Clone for ValueAtomic<T, Q> {
@@ -288,12 +469,174 @@ impl<T: Atomic, Q: GetQueueType> Clone for ValueAtomic<T, Q> {
     }
 }
 
+// Bitset --------------------------------------------
+/// Named bit of a [`ValueBitset`]. Implemented by `#[derive(Transportable)]` for enums without
+/// data, the bit is the enum discriminant. A bitset of an enum with a bit over 63 does not compile.
+pub trait BitFlag {
+    /// The highest bit of all flags.
+    const MAX_BIT: u32;
+
+    fn bit(&self) -> u32;
+}
+
+#[inline]
+fn bit_mask<E: BitFlag>(flag: &E) -> u64 {
+    1 << flag.bit()
+}
+
+/// Up to 64 flags stored in a single `u64` value, the bits are named by the enum `E`.
+pub struct ValueBitset<E: BitFlag, Q: GetQueueType = NoQueue> {
+    value: ValueAtomic<u64, Q>,
+    _phantom: PhantomData<E>,
+}
+
+impl<E: BitFlag, Q: GetQueueType> ValueBitset<E, Q> {
+    pub(crate) fn new(value: ValueAtomic<u64, Q>) -> Self {
+        const {
+            assert!(
+                E::MAX_BIT < 64,
+                "Flags of the bitset have to be lower than 64"
+            )
+        };
+        Self {
+            value,
+            _phantom: PhantomData,
+        }
+    }
+
+    pub fn get(&self, flag: E) -> bool {
+        self.value.get() & bit_mask(&flag) != 0
+    }
+
+    pub fn get_bits(&self) -> u64 {
+        self.value.get()
+    }
+
+    pub fn set(&self, flag: E, on: bool) {
+        self.value
+            .modify(|bits| Self::with_flag(bits, &flag, on), false);
+    }
+
+    pub fn set_signal(&self, flag: E, on: bool) {
+        self.value
+            .modify(|bits| Self::with_flag(bits, &flag, on), true);
+    }
+
+    pub fn toggle(&self, flag: E) {
+        self.value.modify(|bits| bits ^ bit_mask(&flag), false);
+    }
+
+    pub fn toggle_signal(&self, flag: E) {
+        self.value.modify(|bits| bits ^ bit_mask(&flag), true);
+    }
+
+    pub fn set_bits(&self, bits: u64) {
+        self.value.set(bits);
+    }
+
+    pub fn set_bits_signal(&self, bits: u64) {
+        self.value.set_signal(bits);
+    }
+
+    #[inline]
+    fn with_flag(bits: u64, flag: &E, on: bool) -> u64 {
+        match on {
+            true => bits | bit_mask(flag),
+            false => bits & !bit_mask(flag),
+        }
+    }
+}
+
+impl<E: BitFlag, Q: GetQueueType> Clone for ValueBitset<E, Q> {
+    fn clone(&self) -> Self {
+        Self {
+            value: self.value.clone(),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+// Array --------------------------------------------
+/// Fixed size array value, the items are stored inline and sent as the whole array.
+pub struct ValueArray<T, const N: usize, Q: GetQueueType = NoQueue> {
+    value: Value<[T; N], Q>,
+}
+
+impl<T, const N: usize, Q: GetQueueType> ValueArray<T, N, Q>
+where
+    T: Clone,
+    [T; N]: Serialize,
+{
+    pub(crate) fn new(value: Value<[T; N], Q>) -> Self {
+        Self { value }
+    }
+
+    pub fn get(&self) -> [T; N] {
+        self.value.get()
+    }
+
+    pub fn get_index(&self, index: usize) -> Option<T> {
+        self.value.read(|array| array.get(index).cloned())
+    }
+
+    pub fn read<R>(&self, f: impl Fn(&[T; N]) -> R) -> R {
+        self.value.read(f)
+    }
+
+    pub fn set(&self, value: [T; N]) {
+        self.value.set(value);
+    }
+
+    pub fn set_signal(&self, value: [T; N]) {
+        self.value.set_signal(value);
+    }
+
+    /// Nothing is set or sent if the index is out of bounds.
+    pub fn set_index(&self, index: usize, value: T) -> Result<(), String> {
+        self.check_index(index)?;
+        self.value.write(|array| array[index] = value.clone());
+        Ok(())
+    }
+
+    /// Same as [`ValueArray::set_index`], sent as signal.
+    pub fn set_index_signal(&self, index: usize, value: T) -> Result<(), String> {
+        self.check_index(index)?;
+        self.value
+            .write_signal(|array| array[index] = value.clone());
+        Ok(())
+    }
+
+    fn check_index(&self, index: usize) -> Result<(), String> {
+        match index < N {
+            true => Ok(()),
+            false => Err(format!(
+                "Index {} out of bounds of array {} with length {}",
+                index, self.value.name, N
+            )),
+        }
+    }
+
+    /// The underlying value, for the value widgets and observers.
+    pub fn value(&self) -> &Value<[T; N], Q> {
+        &self.value
+    }
+}
+
+impl<T, const N: usize, Q: GetQueueType> Clone for ValueArray<T, N, Q> {
+    fn clone(&self) -> Self {
+        Self {
+            value: self.value.clone(),
+        }
+    }
+}
+
 // Static --------------------------------------------
 pub struct Static<T> {
     name: String,
     id: u64,
     type_id: u32,
     value: Arc<RwLock<T>>,
+    version: Arc<AtomicU64>,
 }
 
 impl<T: Clone> Static<T> {
@@ -303,9 +646,18 @@ impl<T: Clone> Static<T> {
             id,
             type_id,
             value: Arc::new(RwLock::new(value)),
+            version: Arc::new(AtomicU64::new(0)),
         }
     }
 
+    /// Same as [`Value::changed_since`].
+    pub fn changed_since(&self, seen: &mut u64) -> bool {
+        let version = self.version.load(Ordering::Acquire);
+        let changed = version != *seen;
+        *seen = version;
+        changed
+    }
+
     pub fn get(&self) -> T {
         self.value.read().clone()
     }
@@ -324,8 +676,13 @@ impl<T: for<'a> Deserialize<'a> + Send + Sync> UpdateValue for Static<T> {
         let value = deserialize(data)
             .map_err(|e| format!("Parse error: {} for value: {}", e, self.name))?;
         *self.value.write() = value;
+        self.version.fetch_add(1, Ordering::Release);
         Ok(())
     }
+
+    fn restore_value(&self, data: &[u8]) -> Result<(), String> {
+        self.update_value(self.type_id, data)
+    }
 }
 
 impl<T> Clone for Static<T> {
@@ -335,6 +692,7 @@ impl<T> Clone for Static<T> {
             id: self.id,
             type_id: self.type_id,
             value: self.value.clone(),
+            version: self.version.clone(),
         }
     }
 }
@@ -371,6 +729,10 @@ impl<T: for<'a> Deserialize<'a> + AtomicStatic + Send + Sync> UpdateValue for St
         self.value.store(value);
         Ok(())
     }
+
+    fn restore_value(&self, data: &[u8]) -> Result<(), String> {
+        self.update_value(self.type_id, data)
+    }
 }
 
 impl<T: AtomicStatic> Clone for StaticAtomic<T> {
@@ -389,6 +751,8 @@ pub struct Signal<T, Q: GetQueueType = NoQueue> {
     id: u64,
     type_id: u32,
     sender: Arc<MessageSender>,
+    // last callback id and callbacks called with every value sent by the signal
+    callbacks: Arc<RwLock<(usize, Observers<T>)>>,
     phantom: PhantomData<(T, Q)>,
 }
 
@@ -398,14 +762,41 @@ impl<T: Serialize + Clone, Q: GetQueueType> Signal<T, Q> {
             id,
             type_id,
             sender: Arc::new(sender),
+            callbacks: Arc::new(RwLock::new((0, Vec::new()))),
             phantom: PhantomData,
         }
     }
 
     pub fn set(&self, value: impl Into<T>) {
-        let message = to_message(&value.into());
+        let value = value.into();
+        let message = to_message(&value);
         self.sender
             .send(ChannelMessage::Signal(self.id, self.type_id, message));
+
+        // cloned, so the callbacks can disconnect themselves
+        let callbacks = self.callbacks.read().1.clone();
+        for (_, callback) in callbacks {
+            callback(&value);
+        }
+    }
+
+    /// Register a callback called with every value of the signal after it is sent, so other
+    /// parts of the UI can react to it without polling. The server never sends the signals to the
+    /// client, so the callbacks are called only by [`Signal::set`], on the thread which sets the
+    /// signal. Returns the id for [`Signal::disconnect`].
+    pub fn connect(&self, callback: impl Fn(&T) + Send + Sync + 'static) -> usize {
+        let mut w = self.callbacks.write();
+        w.0 += 1;
+        let id = w.0;
+        w.1.push((id, Arc::new(callback)));
+        id
+    }
+
+    pub fn disconnect(&self, id: usize) {
+        self.callbacks
+            .write()
+            .1
+            .retain(|(callback_id, _)| *callback_id != id);
     }
 }
 
@@ -415,6 +806,7 @@ impl<T, Q: GetQueueType> Clone for Signal<T, Q> {
             id: self.id,
             type_id: self.type_id,
             sender: self.sender.clone(),
+            callbacks: self.callbacks.clone(),
             phantom: PhantomData,
         }
     }
@@ -491,3 +883,203 @@ impl<T> Clone for ValueTake<T> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Copy)]
+    enum Flag {
+        A,
+        B,
+    }
+
+    impl BitFlag for Flag {
+        const MAX_BIT: u32 = 1;
+
+        fn bit(&self) -> u32 {
+            *self as u32
+        }
+    }
+
+    #[test]
+    fn test_bitset_concurrent_changes() {
+        let (sender, _receiver) = MessageSender::new();
+        let bitset = ValueBitset::<Flag>::new(ValueAtomic::new("b".to_string(), 0, 0, 0, sender));
+
+        let threads: Vec<_> = [Flag::A, Flag::B]
+            .into_iter()
+            .map(|flag| {
+                let bitset = bitset.clone();
+                std::thread::spawn(move || {
+                    // odd count of toggles, a lost toggle leaves the bit off
+                    for _ in 0..1001 {
+                        bitset.toggle(flag);
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        assert!(bitset.get(Flag::A));
+        assert!(bitset.get(Flag::B));
+        assert_eq!(bitset.get_bits(), 0b11);
+    }
+
+    #[test]
+    fn test_value_changed_since_for_every_reader() {
+        let (sender, _receiver) = MessageSender::new();
+        let value = Value::<u32>::new("v".to_string(), 10, 1, 0, sender);
+        let (mut first, mut second) = (0, 0);
+        assert!(!value.changed_since(&mut first));
+
+        value.update_value(1, to_message(5u32).as_slice()).unwrap();
+        assert!(value.changed_since(&mut first));
+        assert!(value.changed_since(&mut second));
+        assert!(!value.changed_since(&mut first));
+
+        // local changes are not updates
+        value.set(7);
+        assert!(!value.changed_since(&mut second));
+    }
+
+    #[test]
+    fn test_static_changed_since() {
+        let stat = Static::new("s".to_string(), 10, 1, 0u32);
+        let mut seen = 0;
+        stat.update_value(1, to_message(5u32).as_slice()).unwrap();
+        assert!(stat.changed_since(&mut seen));
+        assert!(!stat.changed_since(&mut seen));
+        assert_eq!(stat.get(), 5);
+    }
+
+    #[test]
+    fn test_value_wait_changed() {
+        let (sender, _receiver) = MessageSender::new();
+        let value = Value::<u32>::new("v".to_string(), 10, 1, 0, sender);
+        assert_eq!(
+            value.wait_changed(Duration::from_millis(20)),
+            Err(WaitTimeout)
+        );
+
+        let updated = value.clone();
+        let thread = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(20));
+            updated
+                .update_value(1, to_message(5u32).as_slice())
+                .unwrap();
+        });
+        assert_eq!(value.wait_changed(Duration::from_secs(5)), Ok(5));
+        thread.join().unwrap();
+    }
+
+    #[test]
+    fn test_display_transform_applies_on_read_and_inverts_on_write() {
+        let (sender, mut receiver) = MessageSender::new();
+        let value = Value::<f64>::new("t".to_string(), 10, 1, 0.0, sender);
+        value.set_display_transform(|c| c * 1.8 + 32.0, |f| (f - 32.0) / 1.8);
+
+        value
+            .update_value(1, to_message(100.0f64).as_slice())
+            .unwrap();
+        assert_eq!(value.get_display(), 212.0);
+        assert_eq!(value.get(), 100.0);
+
+        // the server gets the canonical value
+        value.set_display(32.0);
+        assert_eq!(value.get(), 0.0);
+        let mut sent = None;
+        while let Ok(Some(message)) = receiver.try_recv() {
+            if let ChannelMessage::Value(10, 1, false, data) = message {
+                sent = Some(deserialize::<f64>(data.as_slice()).unwrap());
+            }
+        }
+        assert_eq!(sent, Some(0.0));
+
+        value.clear_display_transform();
+        assert_eq!(value.get_display(), 0.0);
+    }
+
+    #[test]
+    fn test_observers_notified_once_per_change() {
+        let (sender, _receiver) = MessageSender::new();
+        let value = Value::<u32>::new("v".to_string(), 10, 1, 0, sender);
+        let seen: Arc<Mutex<Vec<(usize, u32)>>> = Arc::new(Mutex::new(Vec::new()));
+        let ids: Vec<usize> = (0..3)
+            .map(|widget| {
+                let seen = seen.clone();
+                value.observe(move |v| seen.lock().push((widget, *v)))
+            })
+            .collect();
+
+        value.set(5);
+        assert_eq!(*seen.lock(), vec![(0, 5), (1, 5), (2, 5)]);
+
+        seen.lock().clear();
+        value.update_value(1, to_message(7u32).as_slice()).unwrap();
+        assert_eq!(*seen.lock(), vec![(0, 7), (1, 7), (2, 7)]);
+
+        seen.lock().clear();
+        value.remove_observer(ids[1]);
+        value.clone().set(9);
+        assert_eq!(*seen.lock(), vec![(0, 9), (2, 9)]);
+    }
+
+    #[test]
+    fn test_array_set_index() {
+        let (sender, mut receiver) = MessageSender::new();
+        let array = ValueArray::new(Value::<[u16; 3]>::new(
+            "a".to_string(),
+            10,
+            1,
+            [0; 3],
+            sender,
+        ));
+
+        array.set_index(1, 5).unwrap();
+        array.set_index_signal(2, 6).unwrap();
+        assert_eq!(array.get(), [0, 5, 6]);
+        let mut sent = Vec::new();
+        while let Ok(Some(ChannelMessage::Value(10, 1, signal, data))) = receiver.try_recv() {
+            sent.push((signal, deserialize::<[u16; 3]>(data.as_slice()).unwrap()));
+        }
+        assert_eq!(sent, vec![(false, [0, 5, 0]), (true, [0, 5, 6])]);
+
+        // out of bounds index keeps the array and sends nothing
+        assert!(array.set_index(3, 7).is_err());
+        assert!(array.set_index_signal(usize::MAX, 7).is_err());
+        assert_eq!(array.get(), [0, 5, 6]);
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_signal_callbacks_called_by_set() {
+        let (sender, mut receiver) = MessageSender::new();
+        let signal = Signal::<u32>::new(10, 1, sender);
+        let thread = std::thread::current().id();
+        let called = Arc::new(Mutex::new(Vec::new()));
+        let ids: Vec<usize> = (0..2)
+            .map(|callback| {
+                let called = called.clone();
+                signal.connect(move |v| {
+                    assert_eq!(std::thread::current().id(), thread);
+                    called.lock().push((callback, *v));
+                })
+            })
+            .collect();
+
+        // the clones share the callbacks
+        signal.clone().set(5u32);
+        assert!(matches!(
+            receiver.try_recv(),
+            Ok(Some(ChannelMessage::Signal(10, 1, _)))
+        ));
+        assert_eq!(*called.lock(), vec![(0, 5), (1, 5)]);
+
+        signal.disconnect(ids[0]);
+        signal.set(6u32);
+        assert_eq!(*called.lock(), vec![(0, 5), (1, 5), (1, 6)]);
+    }
+}
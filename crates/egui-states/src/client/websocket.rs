@@ -2,42 +2,151 @@ use futures_util::{SinkExt, StreamExt, stream::SplitSink, stream::SplitStream};
 use std::net::SocketAddrV4;
 use tokio::net::TcpStream;
 use tokio_tungstenite::connect_async_with_config;
-use tokio_tungstenite::tungstenite::{Message, protocol::WebSocketConfig};
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::handshake::client::Request;
+use tokio_tungstenite::tungstenite::http::HeaderValue;
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::{Error, Message, protocol::WebSocketConfig};
 use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
 
-use crate::client::messages::{MessagesParser, ServerMessage};
+use crate::client::client::{DisconnectReason, WsOptions};
+use crate::client::messages::{MessagesParser, ServerMessage, TransportStats};
 use crate::serialization::FastVec;
 
-pub(crate) async fn build_ws(address: SocketAddrV4) -> Result<(WsClientRead, WsClientSend), ()> {
-    let address = format!("ws://{}/ws", address);
+// TLS is used when the domain is set, the certificates are verified by the webpki roots by default
+#[cfg(feature = "tls")]
+#[derive(Clone, Default)]
+pub(crate) struct Tls {
+    pub(crate) domain: Option<String>,
+    pub(crate) config: Option<std::sync::Arc<rustls::ClientConfig>>,
+}
+
+#[cfg(not(feature = "tls"))]
+pub(crate) type Tls = ();
+
+pub(crate) async fn build_ws(
+    address: SocketAddrV4,
+    tls: &Tls,
+    options: &WsOptions,
+) -> Result<(WsClientRead, WsClientSend), DisconnectReason> {
     let mut websocket_config = WebSocketConfig::default();
     websocket_config.max_message_size = Some(536870912); // 512 MB
     websocket_config.max_frame_size = Some(536870912); // 512 MB
-    let res = connect_async_with_config(&address, Some(websocket_config), false).await;
 
-    if res.is_err() {
-        #[cfg(debug_assertions)]
-        println!(
-            "connecting to server at {:?} failed: {:?}",
+    #[cfg(feature = "tls")]
+    if let Some(domain) = &tls.domain {
+        let url = format!("wss://{}:{}/ws", domain, address.port());
+        let request = request(url, options)?;
+        let socket = connect_tls(
             address,
-            res.err()
-        );
-        return Err(());
+            domain,
+            tls.config.clone(),
+            request,
+            websocket_config,
+        )
+        .await?;
+        return Ok(split_socket(socket));
     }
+    let _ = tls;
 
-    // get the socket
-    let socket = res.unwrap().0;
+    let address = format!("ws://{}/ws", address);
+    let request = request(address.clone(), options)?;
+    let (socket, _) = connect_async_with_config(request, Some(websocket_config), false)
+        .await
+        .map_err(|e| {
+            #[cfg(debug_assertions)]
+            println!("connecting to server at {:?} failed: {:?}", address, e);
+            error_reason(e)
+        })?;
+
+    Ok(split_socket(socket))
+}
 
-    // split the socket
+fn error_reason(error: Error) -> DisconnectReason {
+    match error {
+        Error::Io(e) => io_reason(e),
+        Error::Http(response) => {
+            DisconnectReason::HandshakeRejected(format!("server responded {}", response.status()))
+        }
+        e => DisconnectReason::Io(e.to_string()),
+    }
+}
+
+fn io_reason(error: std::io::Error) -> DisconnectReason {
+    match error.kind() {
+        std::io::ErrorKind::TimedOut => DisconnectReason::Timeout,
+        _ => DisconnectReason::Io(error.to_string()),
+    }
+}
+
+// websocket request with the subprotocol, the authorization header and the token query
+fn request(url: String, options: &WsOptions) -> Result<Request, DisconnectReason> {
+    let header = |value: &str| {
+        HeaderValue::from_str(value)
+            .map_err(|e| DisconnectReason::Io(format!("invalid websocket header value: {}", e)))
+    };
+
+    let mut request = options
+        .url(url)
+        .into_client_request()
+        .map_err(error_reason)?;
+    let headers = request.headers_mut();
+    if let Some(protocol) = &options.protocol {
+        headers.insert("Sec-WebSocket-Protocol", header(protocol)?);
+    }
+    if let Some(auth) = &options.auth_header {
+        headers.insert("Authorization", header(auth)?);
+    }
+    Ok(request)
+}
+
+fn split_socket(
+    socket: WebSocketStream<MaybeTlsStream<TcpStream>>,
+) -> (WsClientRead, WsClientSend) {
     let (socket_write, socket_read) = socket.split();
 
-    Ok((
+    (
         WsClientRead {
             stream: socket_read,
             parser: MessagesParser::empty(),
         },
         WsClientSend { sink: socket_write },
-    ))
+    )
+}
+
+// the TLS session is established before the websocket handshake, so its failure can be reported
+#[cfg(feature = "tls")]
+async fn connect_tls(
+    address: SocketAddrV4,
+    domain: &str,
+    config: Option<std::sync::Arc<rustls::ClientConfig>>,
+    request: Request,
+    websocket_config: WebSocketConfig,
+) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>, DisconnectReason> {
+    let stream = TcpStream::connect(address).await.map_err(io_reason)?;
+
+    let config = config.unwrap_or_else(|| {
+        let roots = rustls::RootCertStore {
+            roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+        };
+        let config = rustls::ClientConfig::builder()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        std::sync::Arc::new(config)
+    });
+    let server_name = rustls_pki_types::ServerName::try_from(domain.to_string())
+        .map_err(|e| DisconnectReason::TlsFailed(e.to_string()))?;
+    let stream = tokio_rustls::TlsConnector::from(config)
+        .connect(server_name, stream)
+        .await
+        .map_err(|e| DisconnectReason::TlsFailed(e.to_string()))?;
+
+    let stream = MaybeTlsStream::Rustls(stream);
+    let (socket, _) =
+        tokio_tungstenite::client_async_with_config(request, stream, Some(websocket_config))
+            .await
+            .map_err(error_reason)?;
+    Ok(socket)
 }
 
 pub(crate) struct WsClientRead {
@@ -46,8 +155,11 @@ pub(crate) struct WsClientRead {
 }
 
 impl WsClientRead {
-    pub(crate) async fn read(&mut self) -> Result<ServerMessage, &'static str> {
-        if let Some(message) = self.parser.next()? {
+    pub(crate) async fn read(
+        &mut self,
+        stats: &TransportStats,
+    ) -> Result<ServerMessage, DisconnectReason> {
+        if let Some(message) = self.parser.next().map_err(invalid_message)? {
             return Ok(message);
         }
 
@@ -55,34 +167,43 @@ impl WsClientRead {
             Some(message) => match message {
                 Ok(message) => match message {
                     Message::Binary(data) => {
-                        let (parser, message) = MessagesParser::from_bytes(data)?;
+                        stats.record_received(data.len());
+                        let (parser, message) =
+                            MessagesParser::from_bytes(data).map_err(invalid_message)?;
                         self.parser = parser;
                         Ok(message)
                     }
-                    Message::Close(_) => Err("Connection closed by server"),
+                    // the server closes with the policy code when it refuses the handshake
+                    Message::Close(Some(frame)) if frame.code == CloseCode::Policy => Err(
+                        DisconnectReason::HandshakeRejected(frame.reason.to_string()),
+                    ),
+                    Message::Close(_) => Err(DisconnectReason::Closed),
                     // TODO: handle also ping and pong messages?
                     _ => {
                         #[cfg(debug_assertions)]
                         println!("Unexpected message from server: {:?}", message);
-                        Err("Unexpected message from server")
+                        Err(invalid_message("Unexpected message from server"))
                     }
                 },
                 Err(e) => {
                     #[cfg(debug_assertions)]
                     println!("Reading message from server failed: {:?}", e);
-                    let _ = e;
-                    Err("Reading message from server failed")
+                    Err(error_reason(e))
                 }
             },
             None => {
                 #[cfg(debug_assertions)]
                 println!("Connection closed by server");
-                Err("Connection closed by server")
+                Err(DisconnectReason::Closed)
             }
         }
     }
 }
 
+fn invalid_message(error: &str) -> DisconnectReason {
+    DisconnectReason::InvalidMessage(error.to_string())
+}
+
 pub(crate) struct WsClientSend {
     sink: SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>,
 }
@@ -3,25 +3,25 @@ use futures_util::{SinkExt, StreamExt, stream::SplitSink, stream::SplitStream};
 use std::net::SocketAddrV4;
 use ws_stream_wasm::{WsMessage, WsMeta, WsStream};
 
-use crate::client::messages::{MessagesParser, ServerMessage};
+use crate::client::client::{DisconnectReason, WsOptions};
+use crate::client::messages::{MessagesParser, ServerMessage, TransportStats};
 use crate::serialization::FastVec;
 
-pub(crate) async fn build_ws(address: SocketAddrV4) -> Result<(WsClientRead, WsClientSend), ()> {
-    let address = format!("ws://{}/ws", address);
-    let res = WsMeta::connect(&address, None).await;
+// the browser handles TLS of wss connections
+pub(crate) type Tls = ();
 
-    if res.is_err() {
+pub(crate) async fn build_ws(
+    address: SocketAddrV4,
+    _tls: &Tls,
+    options: &WsOptions,
+) -> Result<(WsClientRead, WsClientSend), DisconnectReason> {
+    let address = options.url(format!("ws://{}/ws", address));
+    let protocols = options.protocol.as_deref().map(|protocol| vec![protocol]);
+    let (_, socket) = WsMeta::connect(&address, protocols).await.map_err(|e| {
         #[cfg(debug_assertions)]
-        log::error!(
-            "connecting to server at {:?} failed: {:?}",
-            address,
-            res.err()
-        );
-        return Err(());
-    }
-
-    // get the socket
-    let socket = res.unwrap().1;
+        log::error!("connecting to server at {:?} failed: {:?}", address, e);
+        DisconnectReason::Io(e.to_string())
+    })?;
 
     // split the socket
     let (socket_write, socket_read) = socket.split();
@@ -41,33 +41,42 @@ pub(crate) struct WsClientRead {
 }
 
 impl WsClientRead {
-    pub(crate) async fn read(&mut self) -> Result<ServerMessage, &'static str> {
-        if let Some(message) = self.parser.next()? {
+    pub(crate) async fn read(
+        &mut self,
+        stats: &TransportStats,
+    ) -> Result<ServerMessage, DisconnectReason> {
+        if let Some(message) = self.parser.next().map_err(invalid_message)? {
             return Ok(message);
         }
 
         match self.stream.next().await {
             Some(message) => match message {
                 WsMessage::Binary(data) => {
-                    let (parser, message) = MessagesParser::from_bytes(Bytes::from_owner(data))?;
+                    stats.record_received(data.len());
+                    let (parser, message) = MessagesParser::from_bytes(Bytes::from_owner(data))
+                        .map_err(invalid_message)?;
                     self.parser = parser;
                     Ok(message)
                 }
                 _ => {
                     #[cfg(debug_assertions)]
                     log::error!("Unexpected message from server: {:?}", message);
-                    Err("Unexpected message from server")
+                    Err(invalid_message("Unexpected message from server"))
                 }
             },
             None => {
                 #[cfg(debug_assertions)]
                 log::info!("Connection closed by server");
-                Err("Connection closed by server")
+                Err(DisconnectReason::Closed)
             }
         }
     }
 }
 
+fn invalid_message(error: &str) -> DisconnectReason {
+    DisconnectReason::InvalidMessage(error.to_string())
+}
+
 pub(crate) struct WsClientSend {
     sink: SplitSink<WsStream, WsMessage>,
 }
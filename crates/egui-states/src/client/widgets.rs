@@ -0,0 +1,514 @@
+use egui::{
+    Color32, Context, DragValue, Id, Key, Pos2, Rect, Response, Sense, Slider, Stroke, Ui, Vec2,
+    Widget, emath::Numeric,
+};
+use serde::Serialize;
+use std::ops::RangeInclusive;
+
+use crate::client::values::{GetQueueType, Value};
+
+#[derive(Clone)]
+struct TextEditBuffer {
+    text: String,
+    // server value at the moment the editing started
+    original: String,
+}
+
+/// Text edit bound to a `Value<String>`. The edited text is kept in a local buffer and it is
+/// committed to the server only when the edit loses focus (blur or Enter), Escape discards it.
+pub fn state_text<Q: GetQueueType>(ui: &mut Ui, value: &Value<String, Q>, label: &str) -> Response {
+    let id = Id::new(("egui_states_text", value.id()));
+    let server_text = value.get_display();
+    let buffer: Option<TextEditBuffer> = ui.data_mut(|d| d.get_temp(id));
+
+    let mut buffer = match buffer {
+        // server changed the value underneath while editing, if there are no local changes yet,
+        // take the new server value, otherwise keep the local edits
+        Some(buffer) if buffer.original != server_text && buffer.text == buffer.original => {
+            TextEditBuffer {
+                text: server_text.clone(),
+                original: server_text.clone(),
+            }
+        }
+        Some(buffer) => buffer,
+        None => TextEditBuffer {
+            text: server_text.clone(),
+            original: server_text.clone(),
+        },
+    };
+
+    let response = ui
+        .horizontal(|ui| {
+            ui.label(label);
+            ui.text_edit_singleline(&mut buffer.text)
+        })
+        .inner;
+
+    if response.lost_focus() {
+        let escaped = ui.input(|i| i.key_pressed(Key::Escape));
+        if !escaped && buffer.text != server_text {
+            value.set_display_signal(buffer.text);
+        }
+        ui.data_mut(|d| d.remove::<TextEditBuffer>(id));
+    } else if response.has_focus() {
+        ui.data_mut(|d| d.insert_temp(id, buffer));
+    } else {
+        ui.data_mut(|d| d.remove::<TextEditBuffer>(id));
+    }
+
+    response
+}
+
+/// Drag value bound to a numeric `Value`, the displayed value goes through the display transform.
+pub fn state_drag_value<T, Q>(ui: &mut Ui, value: &Value<T, Q>, label: &str) -> Response
+where
+    T: Numeric + Serialize,
+    Q: GetQueueType,
+{
+    let mut display = value.get_display();
+    let response = ui
+        .horizontal(|ui| {
+            ui.label(label);
+            ui.add(DragValue::new(&mut display))
+        })
+        .inner;
+
+    if response.changed() {
+        value.set_display_signal(display);
+    }
+
+    response
+}
+
+/// Slider bound to a numeric `Value`. Without `always_update` the dragged value is kept locally
+/// and sent to the server only when the drag is released, clicks and keys are sent immediately.
+/// The response is marked changed only when the value is sent.
+pub fn state_slider<T, Q>(
+    ui: &mut Ui,
+    value: &Value<T, Q>,
+    range: RangeInclusive<T>,
+    always_update: bool,
+    label: &str,
+) -> Response
+where
+    T: Numeric + Serialize + Send + Sync,
+    Q: GetQueueType,
+{
+    let id = Id::new(("egui_states_slider", value.id()));
+    let dragged: Option<T> = ui.data(|d| d.get_temp(id));
+    let mut display = dragged.unwrap_or_else(|| value.get_display());
+    let response = ui.add(Slider::new(&mut display, range).text(label));
+
+    let mut response = response;
+    if !always_update && response.dragged() {
+        ui.data_mut(|d| d.insert_temp(id, display));
+        // the value changes for the caller only when the drag is released
+        response.flags.remove(egui::response::Flags::CHANGED);
+        return response;
+    }
+    if dragged.is_some() {
+        ui.data_mut(|d| d.remove::<T>(id));
+    }
+    if response.changed() || response.drag_stopped() {
+        value.set_display_signal(display);
+        response.mark_changed();
+    }
+
+    response
+}
+
+/// Id under which [`mirror_value`] keeps the value in egui memory.
+pub fn mirror_id<T: Serialize + Clone, Q: GetQueueType>(value: &Value<T, Q>) -> Id {
+    Id::new(("egui_states_mirror", value.id()))
+}
+
+/// Mirror the value into egui temp memory under [`mirror_id`], so standard egui widgets can read
+/// and write it directly. Call it every frame, the widget changes are sent to the server as signal
+/// and the server changes are written back to the memory.
+pub fn mirror_value<T, Q>(ctx: &Context, value: &Value<T, Q>) -> Id
+where
+    T: Clone + PartialEq + Serialize + Send + Sync + 'static,
+    Q: GetQueueType,
+{
+    let id = mirror_id(value);
+    // value from the last sync, to find out which side changed
+    let synced_id = id.with("synced");
+    let server = value.get();
+
+    let changed = ctx.data_mut(|d| {
+        let local: Option<T> = d.get_temp(id);
+        let synced: Option<T> = d.get_temp(synced_id);
+        match (local, synced) {
+            (Some(local), Some(synced)) if local != synced => {
+                d.insert_temp(synced_id, local.clone());
+                Some(local)
+            }
+            (Some(_), Some(synced)) if synced == server => None,
+            _ => {
+                d.insert_temp(id, server.clone());
+                d.insert_temp(synced_id, server);
+                None
+            }
+        }
+    });
+
+    if let Some(local) = changed {
+        value.set_signal(local);
+    }
+    id
+}
+
+/// One selectable button per option, writes the chosen option to the value. Shown by `ui.add`,
+/// the response is marked as changed when the selection changes.
+pub struct ToggleGroup<'a, T> {
+    value: &'a mut T,
+    options: &'a [(T, &'a str)],
+    icons: Option<&'a [&'a str]>,
+    vertical: bool,
+}
+
+impl<'a, T: PartialEq + Clone> ToggleGroup<'a, T> {
+    /// The options are the enum variants with their labels.
+    pub fn new(value: &'a mut T, options: &'a [(T, &'a str)]) -> Self {
+        Self {
+            value,
+            options,
+            icons: None,
+            vertical: false,
+        }
+    }
+
+    /// Icons shown before the labels, in the order of the options.
+    pub fn icons(mut self, icons: &'a [&'a str]) -> Self {
+        self.icons = Some(icons);
+        self
+    }
+
+    pub fn vertical(mut self, vertical: bool) -> Self {
+        self.vertical = vertical;
+        self
+    }
+}
+
+impl<T: PartialEq + Clone> Widget for ToggleGroup<'_, T> {
+    fn ui(self, ui: &mut Ui) -> Response {
+        let Self {
+            value,
+            options,
+            icons,
+            vertical,
+        } = self;
+        let add_buttons = |ui: &mut Ui| {
+            let mut response: Option<Response> = None;
+            for (i, (option, label)) in options.iter().enumerate() {
+                let text = match icons.and_then(|icons| icons.get(i)) {
+                    Some(icon) => format!("{} {}", icon, label),
+                    None => label.to_string(),
+                };
+                let button = ui.selectable_value(value, option.clone(), text);
+                response = Some(match response {
+                    Some(response) => response | button,
+                    None => button,
+                });
+            }
+            response.unwrap_or_else(|| ui.allocate_response(Vec2::ZERO, Sense::hover()))
+        };
+
+        match vertical {
+            true => ui.vertical(add_buttons).inner,
+            false => ui.horizontal(add_buttons).inner,
+        }
+    }
+}
+
+type BinColor<'a> = Box<dyn Fn(usize, f64) -> Color32 + 'a>;
+
+/// Histogram drawn from the bin counts, for example read from `Data<f64>`. Shown by `ui.add`.
+pub struct Histogram<'a> {
+    counts: &'a [f64],
+    size: Vec2,
+    log_y: bool,
+    bin_color: Option<BinColor<'a>>,
+    cumulative: bool,
+}
+
+impl<'a> Histogram<'a> {
+    pub fn new(counts: &'a [f64]) -> Self {
+        Self {
+            counts,
+            size: Vec2::new(200.0, 100.0),
+            log_y: false,
+            bin_color: None,
+            cumulative: false,
+        }
+    }
+
+    pub fn size(mut self, size: Vec2) -> Self {
+        self.size = size;
+        self
+    }
+
+    /// Scale the bar heights by `ln(1 + count)`, for long tailed distributions.
+    pub fn log_y(mut self, log_y: bool) -> Self {
+        self.log_y = log_y;
+        self
+    }
+
+    /// Color of the bin from its index and count, the selection color is used by default.
+    pub fn bin_color(mut self, bin_color: impl Fn(usize, f64) -> Color32 + 'a) -> Self {
+        self.bin_color = Some(Box::new(bin_color));
+        self
+    }
+
+    /// Draw the cumulative distribution over the bars, scaled from 0 to the full height.
+    pub fn cumulative(mut self, cumulative: bool) -> Self {
+        self.cumulative = cumulative;
+        self
+    }
+}
+
+impl Widget for Histogram<'_> {
+    fn ui(self, ui: &mut Ui) -> Response {
+        let (rect, response) = ui.allocate_exact_size(self.size, Sense::hover());
+        if !ui.is_rect_visible(rect) || self.counts.is_empty() {
+            return response;
+        }
+
+        // negative and not finite counts are drawn as empty bins
+        let count = |i: usize| match self.counts[i] {
+            c if c.is_finite() && c > 0.0 => c,
+            _ => 0.0,
+        };
+        let scaled = |c: f64| if self.log_y { c.ln_1p() } else { c };
+        let max = (0..self.counts.len())
+            .map(|i| scaled(count(i)))
+            .fold(0.0, f64::max);
+
+        let painter = ui.painter_at(rect);
+        let default_color = ui.visuals().selection.bg_fill;
+        let width = rect.width() / self.counts.len() as f32;
+        for i in 0..self.counts.len() {
+            let height = match max > 0.0 {
+                true => (scaled(count(i)) / max) as f32 * rect.height(),
+                false => 0.0,
+            };
+            let color = match &self.bin_color {
+                Some(bin_color) => bin_color(i, self.counts[i]),
+                None => default_color,
+            };
+            let left = rect.left() + i as f32 * width;
+            let bar = Rect::from_min_max(
+                Pos2::new(left, rect.bottom() - height),
+                Pos2::new(left + width, rect.bottom()),
+            );
+            painter.rect_filled(bar, 0.0, color);
+        }
+
+        let total: f64 = (0..self.counts.len()).map(count).sum();
+        if self.cumulative && total > 0.0 {
+            let mut sum = 0.0;
+            let mut points = vec![rect.left_bottom()];
+            for i in 0..self.counts.len() {
+                sum += count(i);
+                let y = rect.bottom() - (sum / total) as f32 * rect.height();
+                points.push(Pos2::new(rect.left() + (i + 1) as f32 * width, y));
+            }
+            painter.line(points, Stroke::new(1.5, ui.visuals().text_color()));
+        }
+
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::messages::{ChannelMessage, MessageSender};
+    use crate::client::states_creator::{StatesCreator, StatesCreatorClient};
+    use crate::client::values::UpdateValue;
+    use crate::serialization::to_message;
+    use crate::transport::Transportable;
+    use egui::{Event, PointerButton, RawInput};
+
+    fn pointer(pos: Pos2, pressed: Option<bool>) -> Vec<Event> {
+        let mut events = vec![Event::PointerMoved(pos)];
+        if let Some(pressed) = pressed {
+            events.push(Event::PointerButton {
+                pos,
+                button: PointerButton::Primary,
+                pressed,
+                modifiers: Default::default(),
+            });
+        }
+        events
+    }
+
+    // runs the frames with the events, returns the changed flag of every frame and the count of
+    // the values sent to the server
+    fn drag_slider(always_update: bool) -> (Vec<bool>, usize) {
+        let (sender, mut receiver) = MessageSender::new();
+        let mut creator = StatesCreatorClient::new(sender, "root".to_string());
+        let value: Value<f32> = creator.value("x", 0.0);
+
+        let ctx = Context::default();
+        // the changed flag and the rect of the slider
+        let frame = |events: Vec<Event>| {
+            let input = RawInput {
+                events,
+                ..Default::default()
+            };
+            let mut result = (false, Rect::NOTHING);
+            let _ = ctx.run_ui(input, |ui| {
+                let response = state_slider(ui, &value, 0.0..=100.0, always_update, "x");
+                result = (response.changed(), response.rect);
+            });
+            result
+        };
+
+        let rect = frame(Vec::new()).1;
+        let (start, end) = (rect.left_center(), rect.center());
+        let changed = [
+            pointer(start, Some(true)),
+            pointer(start + Vec2::new(20.0, 0.0), None),
+            pointer(end, None),
+            pointer(end, Some(false)),
+        ]
+        .into_iter()
+        .map(|events| frame(events).0)
+        .collect();
+
+        let mut sent = 0;
+        while let Ok(Some(message)) = receiver.try_recv() {
+            sent += matches!(message, ChannelMessage::Value(..)) as usize;
+        }
+        (changed, sent)
+    }
+
+    #[test]
+    fn test_slider_changes_on_release_without_always_update() {
+        let (changed, sent) = drag_slider(false);
+        assert_eq!(changed, vec![false, false, false, true]);
+        assert_eq!(sent, 1);
+    }
+
+    #[test]
+    fn test_slider_changes_while_dragging_with_always_update() {
+        let (changed, sent) = drag_slider(true);
+        assert!(changed[1] && changed[2]);
+        assert!(sent >= 2);
+    }
+
+    fn key(key: Key) -> Event {
+        Event::Key {
+            key,
+            physical_key: None,
+            pressed: true,
+            repeat: false,
+            modifiers: Default::default(),
+        }
+    }
+
+    // types the text into the focused edit and ends the editing by the events, returns the value
+    // and the count of the values sent to the server
+    fn edit_text(end: Vec<Event>) -> (String, usize) {
+        let (sender, mut receiver) = MessageSender::new();
+        let mut creator = StatesCreatorClient::new(sender, "root".to_string());
+        let value: Value<String> = creator.value("text", "a".to_string());
+
+        let ctx = Context::default();
+        let frame = |events: Vec<Event>| {
+            let input = RawInput {
+                events,
+                ..Default::default()
+            };
+            let mut rect = Rect::NOTHING;
+            let _ = ctx.run_ui(input, |ui| {
+                rect = state_text(ui, &value, "text").rect;
+            });
+            rect
+        };
+
+        let center = frame(Vec::new()).right_center() - Vec2::new(5.0, 0.0);
+        frame(pointer(center, Some(true)));
+        frame(pointer(center, Some(false)));
+        frame(vec![Event::Text("b".to_string())]);
+        frame(end);
+        // the next frame does not commit again
+        frame(Vec::new());
+
+        let mut sent = 0;
+        while let Ok(Some(message)) = receiver.try_recv() {
+            sent += matches!(message, ChannelMessage::Value(..)) as usize;
+        }
+        (value.get(), sent)
+    }
+
+    #[test]
+    fn test_text_commits_on_enter() {
+        assert_eq!(edit_text(vec![key(Key::Enter)]), ("ab".to_string(), 1));
+    }
+
+    #[test]
+    fn test_text_commits_on_focus_loss() {
+        let outside = Pos2::new(500.0, 500.0);
+        let mut events = pointer(outside, Some(true));
+        events.extend(pointer(outside, Some(false)));
+        assert_eq!(edit_text(events), ("ab".to_string(), 1));
+    }
+
+    #[test]
+    fn test_text_escape_discards_edit() {
+        assert_eq!(edit_text(vec![key(Key::Escape)]), ("a".to_string(), 0));
+    }
+
+    #[test]
+    fn test_mirror_value_syncs_plain_slider() {
+        let (sender, mut receiver) = MessageSender::new();
+        let mut creator = StatesCreatorClient::new(sender, "root".to_string());
+        let value: Value<f32> = creator.value("x", 0.0);
+
+        let ctx = Context::default();
+        // plain slider on the memory, returns the shown value and the rect of the slider
+        let frame = |events: Vec<Event>| {
+            let input = RawInput {
+                events,
+                ..Default::default()
+            };
+            let mut result = (0.0, Rect::NOTHING);
+            let _ = ctx.run_ui(input, |ui| {
+                let id = mirror_value(ui.ctx(), &value);
+                let mut x: f32 = ui.data_mut(|d| d.get_temp(id).unwrap());
+                result.1 = ui.add(Slider::new(&mut x, 0.0..=100.0)).rect;
+                ui.data_mut(|d| d.insert_temp(id, x));
+                result.0 = x;
+            });
+            result
+        };
+
+        // widget to server, the change is sent as signal in the next frame
+        let center = frame(Vec::new()).1.center();
+        frame(pointer(center, Some(true)));
+        frame(pointer(center, Some(false)));
+        frame(Vec::new());
+        let shown = value.get();
+        assert!(shown > 0.0);
+        let mut signals = 0;
+        while let Ok(Some(message)) = receiver.try_recv() {
+            signals += matches!(message, ChannelMessage::Value(_, _, true, _)) as usize;
+        }
+        assert_eq!(signals, 1);
+
+        // server to widget
+        let type_id = f32::get_type().get_hash();
+        value
+            .update_value(type_id, to_message(10.0f32).as_slice())
+            .unwrap();
+        assert_eq!(frame(Vec::new()).0, 10.0);
+        assert_eq!(frame(Vec::new()).0, 10.0);
+        assert_eq!(value.get(), 10.0);
+        assert!(!matches!(
+            receiver.try_recv(),
+            Ok(Some(ChannelMessage::Value(..)))
+        ));
+    }
+}
@@ -2,9 +2,10 @@ use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize)]
 pub(crate) enum MapHeader {
-    All(u64),
+    All(u64, bool), // with the item count and if the map is ordered
     Set,
     Remove,
+    RemoveMany(u64), // data are the removed keys
 }
 
 #[derive(Serialize, Deserialize)]
@@ -13,4 +14,5 @@ pub(crate) enum VecHeader {
     Set(u64),
     Add,
     Remove(u64),
+    Reorder, // data is the permutation, new item i is the old item at permutation[i]
 }
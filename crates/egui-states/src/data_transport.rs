@@ -17,6 +17,16 @@ pub(crate) enum DataType {
     F64,
 }
 
+// data items travel little-endian, big-endian targets swap the bytes of every item
+#[cfg(any(target_endian = "big", all(test, feature = "client")))]
+pub(crate) fn swap_items(data: &mut [u8], item_size: usize) {
+    if item_size > 1 {
+        for item in data.chunks_exact_mut(item_size) {
+            item.reverse();
+        }
+    }
+}
+
 impl DataType {
     pub(crate) fn item_size(&self) -> usize {
         match self {
@@ -45,11 +55,50 @@ impl DataType {
     }
 }
 
+/// Rust types of the data items, used by the client and by the rust server api.
+///
+/// # Safety
+///
+/// The type is copied as raw bytes, it has to have the size of the [`DataType`] item, no padding
+/// and every bit pattern of that size has to be a valid value.
+pub(crate) unsafe trait GetDataType: Clone + Copy {
+    fn get_type() -> DataType;
+    #[cfg(feature = "client")]
+    fn get_type_id() -> u32;
+}
+
+macro_rules! impl_get_data_type {
+    ($ty:ty, $variant:expr, $id:expr) => {
+        unsafe impl GetDataType for $ty {
+            fn get_type() -> DataType {
+                $variant
+            }
+
+            #[cfg(feature = "client")]
+            fn get_type_id() -> u32 {
+                $id
+            }
+        }
+    };
+}
+
+impl_get_data_type!(u8, DataType::U8, 0);
+impl_get_data_type!(u16, DataType::U16, 1);
+impl_get_data_type!(u32, DataType::U32, 2);
+impl_get_data_type!(u64, DataType::U64, 3);
+impl_get_data_type!(i8, DataType::I8, 4);
+impl_get_data_type!(i16, DataType::I16, 5);
+impl_get_data_type!(i32, DataType::I32, 6);
+impl_get_data_type!(i64, DataType::I64, 7);
+impl_get_data_type!(f32, DataType::F32, 8);
+impl_get_data_type!(f64, DataType::F64, 9);
+
 #[derive(Serialize, Deserialize)]
 pub(crate) enum TransportType {
-    Set(u64),          // element count of data
-    Add(u64),          // element count of data
-    Replace(u64, u64), // start index and element count of data
+    Set(u64),              // element count of data
+    Add(u64),              // element count of data
+    Replace(u64, u64),     // start index and element count of data
+    Splice(u64, u64, u64), // start index, element count to remove and element count of data
 }
 
 #[derive(Serialize, Deserialize)]
@@ -60,6 +109,7 @@ pub(crate) enum DataHeader {
     End(DataType, TransportType, bool, u32), // data type, transport type, update flag, size of last batch
     Drain(u64, u64, bool), // start and element count of data to drain, update flag
     Clear(bool),           // update flag
+    Group(Option<(u32, [f64; 2])>, bool), // group id and shared x range, update flag
 }
 
 #[cfg(feature = "server")]
@@ -81,7 +131,7 @@ pub(crate) enum DataTakeHeader {
     End(DataType, u64, bool, u32), // data type, element count, update flag, size of last batch
 }
 
-#[cfg(feature = "server")]
+#[cfg(feature = "python")]
 impl DataTakeHeader {
     pub(crate) fn serialize(self, id: u64, blocking: bool, heap: bool) -> Result<FastVec<32>, ()> {
         let header = ServerHeader::DataTake(id, self, blocking);
@@ -110,6 +160,7 @@ impl MultiDataHeader {
         serialize_heap(&header).map_err(|_| ())
     }
 
+    #[cfg(feature = "python")]
     pub(crate) fn serialize(self, id: u64) -> Result<FastVec<32>, ()> {
         let message = ServerHeader::MultiData(id, self);
         serialize(&message).map_err(|_| ())
@@ -123,7 +174,7 @@ pub(crate) enum DataMultiTakeHeader {
     Reset(bool),                       // reset data collection to empty
 }
 
-#[cfg(feature = "server")]
+#[cfg(feature = "python")]
 impl DataMultiTakeHeader {
     pub(crate) fn serialize_modify(
         id: u64,
@@ -3,6 +3,9 @@ use std::sync::{
     atomic::{AtomicBool, Ordering},
 };
 
+#[cfg(feature = "server")]
+use std::time::{Duration, Instant};
+
 use event_listener::Event as ListenerEvent;
 #[cfg(feature = "server")]
 use event_listener::Listener;
@@ -48,7 +51,7 @@ impl Event {
         self.0.flag.store(false, Ordering::Release);
     }
 
-    #[cfg(feature = "server")]
+    #[cfg(feature = "python")]
     pub(crate) fn wait(&self) {
         loop {
             if self.0.flag.load(Ordering::Acquire) {
@@ -92,6 +95,37 @@ impl Event {
         }
     }
 
+    // returns false if the timeout elapsed before the event was set
+    #[cfg(feature = "server")]
+    pub(crate) fn wait_clear_timeout(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if self
+                .0
+                .flag
+                .compare_exchange(true, false, Ordering::AcqRel, Ordering::Acquire)
+                .is_ok()
+            {
+                return true;
+            }
+
+            let listener = self.0.notify.listen();
+
+            if self
+                .0
+                .flag
+                .compare_exchange(true, false, Ordering::AcqRel, Ordering::Acquire)
+                .is_ok()
+            {
+                return true;
+            }
+
+            if listener.wait_deadline(deadline).is_none() {
+                return false;
+            }
+        }
+    }
+
     pub(crate) async fn wait_clear_async(&self) {
         loop {
             if self
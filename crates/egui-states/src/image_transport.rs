@@ -21,6 +21,19 @@ impl ImageType {
             Self::GrayAlpha => 2,
         }
     }
+
+    // expected data length for the pixels count, None if it overflows
+    #[cfg(feature = "client")]
+    #[inline]
+    pub(crate) fn data_len(&self, pixels: usize) -> Option<usize> {
+        pixels.checked_mul(self.bytes_per_pixel())
+    }
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub(crate) enum ImageEncoding {
+    Png,
+    Jpeg,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -46,6 +59,9 @@ impl ImageSetHeader {
 
 #[derive(Serialize, Deserialize)]
 pub(crate) enum ImageHeader {
-    Set(ImageSetHeader, ImageType),    // header
-    Update([u32; 4], ImageType, bool), // [x, y, w, h], image_type, update
+    Set(ImageSetHeader, ImageType),         // header
+    Update([u32; 4], ImageType, bool),      // [x, y, w, h], image_type, update
+    Scroll(u32, ImageType, bool),           // new rows at the top, image_type, update
+    Premultiply(bool),                      // client premultiplies alpha of received images
+    Encoded([u32; 2], ImageEncoding, bool), // [x, y], whole image compressed in one message, update
 }
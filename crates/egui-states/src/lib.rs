@@ -21,7 +21,10 @@ mod server;
 pub use client::{
     atomics::{Atomic, AtomicLock, AtomicLockStatic, AtomicStatic, FallbackLock, UpdateLock},
     client::ClientBuilder,
-    client::{Client, ConnectionState},
+    client::{
+        Client, ClientStats, Conflict, ConnectionState, DisconnectReason, Limits, Reconcile,
+        Resolved, TokenTransport, UpdateKind,
+    },
     data::{Data, DataMulti},
     data_take::{DataMultiTake, DataTake},
     image::Image,
@@ -29,11 +32,20 @@ pub use client::{
     value_map::MapState,
     value_vec::VecState,
     values::{
-        Diff, DiffAtomic, GetQueueType, NoQueue, Queue, Signal, Static, StaticAtomic, Value,
-        ValueAtomic, ValueTake,
+        BitFlag, Diff, DiffAtomic, GetQueueType, NoQueue, Queue, Signal, Static, StaticAtomic,
+        Value, ValueArray, ValueAtomic, ValueBitset, ValueTake, WaitTimeout,
+    },
+    widgets::{
+        Histogram, ToggleGroup, mirror_id, mirror_value, state_drag_value, state_slider, state_text,
     },
 };
 
+#[cfg(feature = "server")]
+pub use server::embedded::{
+    EmbeddedServer, ImageFormat, ServerBuilder, ServerData, ServerImage, ServerSignal,
+    ServerStatic, ServerValue,
+};
+
 #[cfg(feature = "client")]
 pub trait State {
     const NAME: &'static str;
@@ -43,8 +55,15 @@ pub trait State {
 
 #[cfg(feature = "client")]
 pub use egui_states_macros::State;
-pub use egui_states_macros::Transportable;
+pub use egui_states_macros::{EnumConvert, Transportable};
 pub use serde;
-pub use transport::{InitValue, ObjectType, Transportable};
 
-pub(crate) const PROTOCOL_VERSION: u16 = 5;
+#[cfg(all(feature = "tls", not(target_arch = "wasm32")))]
+pub use rustls;
+pub use transport::{InitValue, InvalidEnumValue, ObjectType, Transportable};
+
+// major version, the server rejects clients with a different one
+pub(crate) const PROTOCOL_VERSION: u16 = 8;
+// increased with backward compatible additions, the lower one of the client and the server is used
+#[cfg(any(feature = "client", feature = "server"))]
+pub(crate) const PROTOCOL_MINOR: u16 = 1;
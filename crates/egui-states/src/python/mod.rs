@@ -9,6 +9,7 @@ use pyo3::prelude::*;
 pub fn init_module(m: &Bound<PyModule>) -> PyResult<()> {
     m.add_class::<pyserver::StateServerCore>()?;
     m.add_class::<pytypes::PyObjectClass>()?;
+    m.add_class::<pyimage::ImageArray>()?;
 
     m.add("u8", pytypes::U8)?;
     m.add("u16", pytypes::U16)?;
@@ -23,6 +24,12 @@ pub fn init_module(m: &Bound<PyModule>) -> PyResult<()> {
     m.add("bo", pytypes::BO)?;
     m.add("st", pytypes::STR)?;
     m.add("emp", pytypes::EMP)?;
+    m.add("TIMESTAMPS", cfg!(feature = "timestamps"))?;
+    m.add("METRICS_HTTP", cfg!(feature = "metrics-http"))?;
+    m.add("IMAGE_COMPRESSION", cfg!(feature = "image-compression"))?;
+    m.add("TLS", cfg!(feature = "tls"))?;
+    m.add("COMPRESSION", cfg!(feature = "compression"))?;
+    m.add("CHECKSUM", cfg!(feature = "checksum"))?;
 
     m.add_function(pyo3::wrap_pyfunction!(pytypes::opt, m)?)?;
     m.add_function(pyo3::wrap_pyfunction!(pytypes::tu, m)?)?;
@@ -31,6 +38,7 @@ pub fn init_module(m: &Bound<PyModule>) -> PyResult<()> {
     m.add_function(pyo3::wrap_pyfunction!(pytypes::vec, m)?)?;
     m.add_function(pyo3::wrap_pyfunction!(pytypes::map, m)?)?;
     m.add_function(pyo3::wrap_pyfunction!(pytypes::enu, m)?)?;
+    m.add_function(pyo3::wrap_pyfunction!(pytypes::uni, m)?)?;
 
     Ok(())
 }
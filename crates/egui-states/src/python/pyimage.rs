@@ -1,38 +1,92 @@
-use pyo3::buffer::PyBuffer;
-use pyo3::exceptions::PyValueError;
+use std::ffi::{c_int, c_void};
+use std::ptr;
+
+use pyo3::buffer::{Element, PyBuffer};
+use pyo3::exceptions::{PyBufferError, PyValueError};
+use pyo3::ffi;
 use pyo3::prelude::*;
 
 use crate::image_transport::ImageType;
 use crate::server::image_server::ImageData;
 
-fn check_image_type(shape: &[usize], strides: &[isize]) -> PyResult<ImageType> {
+// image buffer from python, u16 and f32 images are tone mapped to u8 before sending
+pub(crate) enum ImageSource {
+    U8(PyBuffer<u8>),
+    U16(PyBuffer<u16>),
+    F32(PyBuffer<f32>),
+}
+
+impl ImageSource {
+    pub(crate) fn new(image: &Bound<PyAny>) -> PyResult<Self> {
+        if let Ok(buffer) = PyBuffer::<u8>::get(image) {
+            return Ok(Self::U8(buffer));
+        }
+        if let Ok(buffer) = PyBuffer::<u16>::get(image) {
+            return Ok(Self::U16(buffer));
+        }
+        if let Ok(buffer) = PyBuffer::<f32>::get(image) {
+            return Ok(Self::F32(buffer));
+        }
+        Err(PyValueError::new_err(
+            "Image must be a uint8, uint16 or float32 buffer.",
+        ))
+    }
+
+    pub(crate) fn is_tone_mapped(&self) -> bool {
+        !matches!(self, Self::U8(_))
+    }
+
+    // the tone mapped image is written to the holder, which has to live as long as the image data,
+    // returns also the window used for the tone mapping
+    pub(crate) fn image_data(
+        &self,
+        window: Option<[f64; 2]>,
+        holder: &mut Vec<u8>,
+    ) -> PyResult<(ImageData, Option<[f64; 2]>)> {
+        match self {
+            Self::U8(image) => {
+                if window.is_some() {
+                    return Err(PyValueError::new_err(
+                        "Window can be used only for uint16 and float32 images.",
+                    ));
+                }
+                Ok((image_data(image)?, None))
+            }
+            Self::U16(image) => tone_mapped(image, window, u16::MAX as f64, holder),
+            Self::F32(image) => tone_mapped(image, window, 1.0, holder),
+        }
+    }
+}
+
+fn check_image_type(shape: &[usize], strides: &[isize], item_size: usize) -> PyResult<ImageType> {
+    let item_size = item_size as isize;
     match shape.len() {
         2 => {
-            if strides[1] == 1 {
+            if strides[1] == item_size {
                 return Ok(ImageType::Gray);
             }
             Err(PyValueError::new_err("Invalid strides"))
         }
         3 => {
-            if strides[2] != 1 {
+            if strides[2] != item_size {
                 return Err(PyValueError::new_err("Invalid strides"));
             }
             match shape[2] {
                 2 => {
-                    if strides[1] != 2 {
+                    if strides[1] != 2 * item_size {
                         return Err(PyValueError::new_err("Invalid strides"));
                     }
                     Ok(ImageType::GrayAlpha)
                 }
                 3 => {
-                    if strides[1] != 3 {
+                    if strides[1] != 3 * item_size {
                         return Err(PyValueError::new_err("Invalid strides"));
                     }
 
                     Ok(ImageType::Color)
                 }
                 4 => {
-                    if strides[1] != 4 {
+                    if strides[1] != 4 * item_size {
                         return Err(PyValueError::new_err("Invalid strides"));
                     }
                     Ok(ImageType::ColorAlpha)
@@ -44,18 +98,18 @@ fn check_image_type(shape: &[usize], strides: &[isize]) -> PyResult<ImageType> {
     }
 }
 
-pub(crate) fn image_data(image: &PyBuffer<u8>) -> PyResult<ImageData> {
+// returns image type, size and row stride in bytes
+fn image_layout<T: Element>(image: &PyBuffer<T>) -> PyResult<(ImageType, [usize; 2], usize)> {
     let shape = image.shape();
     let strides = image.strides();
-    let contiguous = image.is_c_contiguous();
-    let image_type = check_image_type(shape, strides)?;
+    let image_type = check_image_type(shape, strides, size_of::<T>())?;
     let size = [shape[0], shape[1]];
     if size[0] == 0 || size[1] == 0 {
         return Err(PyValueError::new_err("Image dimensions cannot be zero"));
     }
 
     // get data stride
-    let stride = if contiguous {
+    let stride = if image.is_c_contiguous() {
         0 // do not use strides
     } else {
         if strides[0] <= 0 {
@@ -64,13 +118,153 @@ pub(crate) fn image_data(image: &PyBuffer<u8>) -> PyResult<ImageData> {
         strides[0] as usize
     };
 
-    let data = image.buf_ptr() as *const u8;
+    Ok((image_type, size, stride))
+}
+
+fn image_data(image: &PyBuffer<u8>) -> PyResult<ImageData> {
+    let (image_type, size, stride) = image_layout(image)?;
 
     Ok(ImageData {
         size,
         stride,
-        contiguous,
+        contiguous: stride == 0,
         image_type,
-        data,
+        data: image.buf_ptr() as *const u8,
     })
 }
+
+// map the color channels from the window [min, max] to 0-255, without window the minimum and
+// maximum of the image are used, alpha is mapped from 0 to alpha_max
+fn tone_mapped<T: Element + Copy + Into<f64>>(
+    image: &PyBuffer<T>,
+    window: Option<[f64; 2]>,
+    alpha_max: f64,
+    holder: &mut Vec<u8>,
+) -> PyResult<(ImageData, Option<[f64; 2]>)> {
+    let (image_type, size, stride) = image_layout(image)?;
+    let channels = image_type.bytes_per_pixel();
+    let row_len = size[1] * channels;
+    let row_stride = match stride {
+        0 => row_len,
+        stride => stride / size_of::<T>(),
+    };
+    let has_alpha = matches!(image_type, ImageType::GrayAlpha | ImageType::ColorAlpha);
+    let is_alpha = |i: usize| has_alpha && i % channels == channels - 1;
+
+    let data = image.buf_ptr() as *const T;
+    let row = |y: usize| unsafe { std::slice::from_raw_parts(data.add(y * row_stride), row_len) };
+
+    let [min, max] = match window {
+        Some(window) => window,
+        None => {
+            let mut window = [f64::INFINITY, f64::NEG_INFINITY];
+            for y in 0..size[0] {
+                for (i, value) in row(y).iter().enumerate() {
+                    let value: f64 = (*value).into();
+                    if !is_alpha(i) && value.is_finite() {
+                        window = [window[0].min(value), window[1].max(value)];
+                    }
+                }
+            }
+            match window[0] <= window[1] {
+                true => window,
+                false => [0.0, 0.0], // no finite values
+            }
+        }
+    };
+    if min.is_nan() || max.is_nan() || max < min {
+        return Err(PyValueError::new_err(
+            "Invalid window, max has to be at least min",
+        ));
+    }
+    let scale = if max > min { 255.0 / (max - min) } else { 0.0 };
+
+    holder.clear();
+    holder.reserve_exact(size[0] * row_len);
+    for y in 0..size[0] {
+        holder.extend(row(y).iter().enumerate().map(|(i, value)| {
+            let value: f64 = (*value).into();
+            let mapped = match is_alpha(i) {
+                true => value / alpha_max * 255.0,
+                false => (value - min) * scale,
+            };
+            mapped.clamp(0.0, 255.0) as u8
+        }));
+    }
+
+    let image_data = ImageData {
+        size,
+        stride: 0,
+        contiguous: true,
+        image_type,
+        data: holder.as_ptr(),
+    };
+    Ok((image_data, Some([min, max])))
+}
+
+/// Read-only RGBA image with the buffer protocol, numpy sees it as uint8 array of shape
+/// (height, width, 4) without copying.
+#[pyclass(frozen)]
+pub(crate) struct ImageArray {
+    data: Vec<u8>,
+    shape: [isize; 3],
+    strides: [isize; 3],
+}
+
+impl ImageArray {
+    pub(crate) fn new(data: Vec<u8>, size: [usize; 2]) -> Self {
+        let (height, width) = (size[0] as isize, size[1] as isize);
+        Self {
+            data,
+            shape: [height, width, 4],
+            strides: [width * 4, 4, 1],
+        }
+    }
+}
+
+#[pymethods]
+impl ImageArray {
+    #[getter]
+    fn shape(&self) -> (isize, isize, isize) {
+        (self.shape[0], self.shape[1], self.shape[2])
+    }
+
+    // the shape and strides live in the object, the view keeps it alive until released
+    unsafe fn __getbuffer__(
+        slf: Bound<'_, Self>,
+        view: *mut ffi::Py_buffer,
+        flags: c_int,
+    ) -> PyResult<()> {
+        if view.is_null() {
+            return Err(PyBufferError::new_err("View is null."));
+        }
+        if flags & ffi::PyBUF_WRITABLE == ffi::PyBUF_WRITABLE {
+            return Err(PyBufferError::new_err("Image array is read-only."));
+        }
+
+        let array = slf.get();
+        unsafe {
+            (*view).buf = array.data.as_ptr() as *mut c_void;
+            (*view).len = array.data.len() as isize;
+            (*view).readonly = 1;
+            (*view).itemsize = 1;
+            (*view).format = match flags & ffi::PyBUF_FORMAT == ffi::PyBUF_FORMAT {
+                true => c"B".as_ptr().cast_mut(),
+                false => ptr::null_mut(),
+            };
+            (*view).ndim = 3;
+            (*view).shape = match flags & ffi::PyBUF_ND == ffi::PyBUF_ND {
+                true => array.shape.as_ptr().cast_mut(),
+                false => ptr::null_mut(),
+            };
+            (*view).strides = match flags & ffi::PyBUF_STRIDES == ffi::PyBUF_STRIDES {
+                true => array.strides.as_ptr().cast_mut(),
+                false => ptr::null_mut(),
+            };
+            (*view).suboffsets = ptr::null_mut();
+            (*view).internal = ptr::null_mut();
+            (*view).obj = slf.into_any().into_ptr();
+        }
+        Ok(())
+    }
+}
@@ -138,6 +138,26 @@ pub(crate) fn serialize_py(
             Ok(())
         }
         PyObjectType::Empty => Ok(()),
+        PyObjectType::Union(_, variants) => {
+            let obj_type = obj.get_type();
+            let index = variants
+                .iter()
+                .position(|(_, class, _)| obj_type.is(class.bind(obj.py())))
+                .ok_or_else(|| PyValueError::new_err("Value is not a variant of the union."))?;
+
+            creator
+                .add(&(index as u32))
+                .map_err(|_| PyRuntimeError::new_err("Failed to serialize value."))?;
+            let fields = &variants[index].2;
+            if !fields.is_empty() {
+                let list = obj.call_method0("__getstate__")?.cast::<PyDict>()?.values();
+                for (i, item_type) in fields.iter().enumerate() {
+                    let item = list.get_item(i)?;
+                    serialize_py(&item, item_type, creator)?;
+                }
+            }
+            Ok(())
+        }
     }
     .map_err(|_| PyRuntimeError::new_err("Failed to serialize value."))
 }
@@ -320,5 +340,141 @@ pub(crate) fn deserialize_py<'py, 'a>(
             }
         }
         PyObjectType::Empty => Ok(PyTuple::empty(py).into_any()),
+        PyObjectType::Union(_, variants) => {
+            let mut index = 0u32;
+            parser
+                .get(&mut index)
+                .map_err(|_| PyValueError::new_err("Failed to parse union variant"))?;
+
+            let (_, class, fields) = variants
+                .get(index as usize)
+                .ok_or_else(|| PyValueError::new_err("Invalid union variant"))?;
+            let mut items = Vec::with_capacity(fields.len());
+            for item_type in fields.iter() {
+                let item = deserialize_py(py, parser, item_type)?;
+                items.push(item);
+            }
+            let tuple = PyTuple::new(py, items)?;
+            class.bind(py).call1(tuple)
+        }
+    }
+}
+
+fn skip_py(parser: &mut ValueParser, object_type: &PyObjectType) -> PyResult<()> {
+    let res = match object_type {
+        PyObjectType::U8 => parser.get(&mut 0u8),
+        PyObjectType::U16 => parser.get(&mut 0u16),
+        PyObjectType::U32 => parser.get(&mut 0u32),
+        PyObjectType::U64 => parser.get(&mut 0u64),
+        PyObjectType::I8 => parser.get(&mut 0i8),
+        PyObjectType::I16 => parser.get(&mut 0i16),
+        PyObjectType::I32 => parser.get(&mut 0i32),
+        PyObjectType::I64 => parser.get(&mut 0i64),
+        PyObjectType::F32 => parser.get(&mut 0f32),
+        PyObjectType::F64 => parser.get(&mut 0f64),
+        PyObjectType::Bool => parser.get(&mut false),
+        PyObjectType::String => parser.get(&mut String::new()),
+        PyObjectType::Enum(_) => parser.get(&mut 0u32),
+        PyObjectType::Tuple(vec) | PyObjectType::Class(vec, _) => {
+            for item_type in vec.iter() {
+                skip_py(parser, item_type)?;
+            }
+            Ok(())
+        }
+        PyObjectType::List(size, items_type) => {
+            for _ in 0..*size {
+                skip_py(parser, items_type)?;
+            }
+            Ok(())
+        }
+        PyObjectType::Vec(items_type) => {
+            let mut vec_size = 0u64;
+            parser.get(&mut vec_size).and_then(|_| {
+                for _ in 0..vec_size {
+                    skip_py(parser, items_type).map_err(|_| ())?;
+                }
+                Ok(())
+            })
+        }
+        PyObjectType::Map(key_type, value_type) => {
+            let mut map_size = 0u64;
+            parser.get(&mut map_size).and_then(|_| {
+                for _ in 0..map_size {
+                    skip_py(parser, key_type).map_err(|_| ())?;
+                    skip_py(parser, value_type).map_err(|_| ())?;
+                }
+                Ok(())
+            })
+        }
+        PyObjectType::Option(object_type) => {
+            let mut has_value = 0u8;
+            parser.get(&mut has_value).and_then(|_| match has_value {
+                0 => Ok(()),
+                _ => skip_py(parser, object_type).map_err(|_| ()),
+            })
+        }
+        PyObjectType::Empty => Ok(()),
+        PyObjectType::Union(_, variants) => {
+            let mut index = 0u32;
+            parser.get(&mut index).and_then(|_| {
+                let (_, _, fields) = variants.get(index as usize).ok_or(())?;
+                for item_type in fields.iter() {
+                    skip_py(parser, item_type).map_err(|_| ())?;
+                }
+                Ok(())
+            })
+        }
+    };
+
+    res.map_err(|_| PyValueError::new_err("Failed to parse value."))
+}
+
+// Returns the indexes of the struct fields on the path and the field type.
+pub(crate) fn field_path<'a>(
+    py: Python,
+    object_type: &'a PyObjectType,
+    path: &[&str],
+) -> PyResult<(Vec<usize>, &'a PyObjectType)> {
+    let mut indexes = Vec::with_capacity(path.len());
+    let mut object_type = object_type;
+    for field in path {
+        let PyObjectType::Class(fields_types, py_class) = object_type else {
+            return Err(PyValueError::new_err(format!(
+                "Field {} is not in a struct.",
+                field
+            )));
+        };
+
+        let names = py_class
+            .bind(py)
+            .getattr("__match_args__")?
+            .extract::<Vec<String>>()?;
+        let index = names.iter().position(|name| name == field).ok_or_else(|| {
+            PyValueError::new_err(format!("Field {} not found in struct.", field))
+        })?;
+        indexes.push(index);
+        object_type = &fields_types[index];
+    }
+    Ok((indexes, object_type))
+}
+
+// Returns the byte range of the field given by the indexes from field_path, without Python.
+pub(crate) fn field_range(
+    parser: &mut ValueParser,
+    object_type: &PyObjectType,
+    indexes: &[usize],
+) -> PyResult<(usize, usize)> {
+    let Some((index, rest)) = indexes.split_first() else {
+        let start = parser.position();
+        skip_py(parser, object_type)?;
+        return Ok((start, parser.position()));
+    };
+
+    let PyObjectType::Class(fields_types, _) = object_type else {
+        return Err(PyValueError::new_err("Field is not in a struct."));
+    };
+    for item_type in fields_types.iter().take(*index) {
+        skip_py(parser, item_type)?;
     }
+    field_range(parser, &fields_types[*index], rest)
 }
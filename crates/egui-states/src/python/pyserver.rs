@@ -1,27 +1,31 @@
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 use std::collections::HashMap;
 use std::net::{Ipv4Addr, SocketAddrV4};
 use std::sync::Arc;
 use std::sync::OnceLock;
+use std::time::Duration;
 
-use pyo3::buffer::{PyBuffer, PyUntypedBuffer};
-use pyo3::exceptions::{PyRuntimeError, PyValueError};
+use bytes::Bytes;
+use pyo3::buffer::PyUntypedBuffer;
+use pyo3::exceptions::{PyBlockingIOError, PyRuntimeError, PyValueError};
 use pyo3::prelude::*;
 use pyo3::types::{PyByteArray, PyDict, PyList};
 
 use crate::hashing::NoHashMap;
 use crate::python::{
     pydata::check_data_type,
-    pyimage, pyparsing,
+    pyimage::{ImageArray, ImageSource},
+    pyparsing,
     pytypes::{PyObjectClass, PyObjectType},
 };
-use crate::server::data_server::{Data, DataHolder, DataMulti};
+use crate::server::data_server::{Data, DataHolder, DataMulti, Decimation};
 use crate::server::data_take_server::{DataMultiTake, DataTake};
+use crate::server::image_server::{Image, ImageCompression};
 use crate::server::server::Server;
 use crate::server::signals::{self, SignalsManager};
 use crate::server::value_parsing::{ValueCreator, ValueParser};
 use crate::server::values_server::{Signal, Value, ValueStatic, ValueTake};
-use crate::server::{image_server::Image, map_server::ValueMap, vec_server::ValueList};
+use crate::server::{map_server::ValueMap, vec_server::ValueList};
 
 struct ValuesInner {
     values: NoHashMap<u64, (Arc<Value>, PyObjectType)>,
@@ -44,6 +48,7 @@ pub(crate) struct StateServerCore {
     signals: SignalsManager,
     inner: OnceLock<ValuesInner>,
     temps: RwLock<Option<NoHashMap<u64, PyObjectType>>>,
+    batch: Mutex<(usize, bool)>, // nesting depth, update requested inside the batch
 }
 
 impl StateServerCore {
@@ -55,6 +60,27 @@ impl StateServerCore {
         }
     }
 
+    #[inline]
+    fn check_busy(&self) -> PyResult<()> {
+        match self.server.read().is_busy() {
+            true => Err(PyBlockingIOError::new_err(
+                "Server is busy, try again later.",
+            )),
+            false => Ok(()),
+        }
+    }
+
+    // inside a batch the update is deferred to the end of the batch
+    #[inline]
+    fn batch_update(&self, update: bool) -> bool {
+        let mut batch = self.batch.lock();
+        if batch.0 == 0 {
+            return update;
+        }
+        batch.1 |= update;
+        false
+    }
+
     #[inline]
     fn inner_values(&self, value_id: u64) -> PyResult<(&Arc<Value>, &PyObjectType)> {
         match self.get_values()?.values.get(&value_id) {
@@ -130,15 +156,104 @@ impl StateServerCore {
     }
 }
 
+// partial updates of uint16 and float32 images use the window of the image, so the updated part
+// keeps the same brightness as the rest, the given window is kept for the next updates
+fn partial_window(
+    image: &ImageSource,
+    image_val: &Image,
+    window: Option<[f64; 2]>,
+) -> PyResult<Option<[f64; 2]>> {
+    if !image.is_tone_mapped() {
+        return Ok(window);
+    }
+    match window.or_else(|| image_val.window()) {
+        Some(window) => {
+            image_val.set_window(Some(window));
+            Ok(Some(window))
+        }
+        None => Err(PyValueError::new_err(
+            "Window is required for partial updates until a uint16 or float32 image is set.",
+        )),
+    }
+}
+
+// timeout in seconds from python, infinite or too long timeout waits without the limit and negative
+// timeout does not wait
+fn timeout_duration(timeout: f64) -> PyResult<Option<Duration>> {
+    if timeout.is_nan() {
+        return Err(PyValueError::new_err("Timeout cannot be NaN."));
+    }
+    Ok(Duration::try_from_secs_f64(timeout.max(0.0)).ok())
+}
+
+// delta of value_increment, extracted before the value is locked
+enum Delta {
+    Int(i128),
+    Float(f64),
+}
+
+// the increment is computed in Rust, so no python code runs under the value lock
+fn increment(old: &Bytes, object_type: &PyObjectType, delta: &Delta) -> Result<Bytes, String> {
+    let mut parser = ValueParser::new(old.clone());
+    let mut creator = ValueCreator::new();
+
+    macro_rules! add_int {
+        ($t:ty) => {{
+            let Delta::Int(delta) = delta else {
+                return Err("Delta of an integer value has to be an integer.".to_string());
+            };
+            let mut value: $t = 0;
+            parser
+                .get(&mut value)
+                .map_err(|_| "Failed to parse the value.".to_string())?;
+            let value = (value as i128)
+                .checked_add(*delta)
+                .and_then(|value| <$t>::try_from(value).ok())
+                .ok_or_else(|| "Incremented value is out of range.".to_string())?;
+            creator.add(&value)
+        }};
+    }
+    macro_rules! add_float {
+        ($t:ty) => {{
+            let delta = match delta {
+                Delta::Int(delta) => *delta as f64,
+                Delta::Float(delta) => *delta,
+            };
+            let mut value: $t = 0.0;
+            parser
+                .get(&mut value)
+                .map_err(|_| "Failed to parse the value.".to_string())?;
+            creator.add(&((value as f64 + delta) as $t))
+        }};
+    }
+
+    let added = match object_type {
+        PyObjectType::U8 => add_int!(u8),
+        PyObjectType::U16 => add_int!(u16),
+        PyObjectType::U32 => add_int!(u32),
+        PyObjectType::U64 => add_int!(u64),
+        PyObjectType::I8 => add_int!(i8),
+        PyObjectType::I16 => add_int!(i16),
+        PyObjectType::I32 => add_int!(i32),
+        PyObjectType::I64 => add_int!(i64),
+        PyObjectType::F32 => add_float!(f32),
+        PyObjectType::F64 => add_float!(f64),
+        _ => return Err("Value is not numeric.".to_string()),
+    };
+    added.map_err(|_| "Failed to serialize the value.".to_string())?;
+    Ok(creator.finalize())
+}
+
 #[pymethods]
 impl StateServerCore {
     #[new]
-    #[pyo3(signature = (port, ip_addr=None, version=None, token=None))]
+    #[pyo3(signature = (port, ip_addr=None, version=None, token=None, queue_size=None))]
     fn new(
         port: u16,
         ip_addr: Option<[u8; 4]>,
         version: Option<u64>,
         token: Option<String>,
+        queue_size: Option<usize>,
     ) -> PyResult<Self> {
         let addr = match ip_addr {
             Some(addr) => {
@@ -147,13 +262,20 @@ impl StateServerCore {
             None => SocketAddrV4::new(Ipv4Addr::new(0, 0, 0, 0), port),
         };
 
-        let server = Server::new(addr, version, token);
+        let server = Server::new(addr, version, token, queue_size);
         let signals = server.get_signals_manager();
 
         let mut types = NoHashMap::default();
 
         // register reserved signal types
-        let logging_object_type = PyObjectType::Tuple(vec![PyObjectType::U8, PyObjectType::String]);
+        let logging_object_type = PyObjectType::Tuple(vec![
+            PyObjectType::U8,
+            PyObjectType::String,
+            PyObjectType::Option(Box::new(PyObjectType::Map(
+                Box::new(PyObjectType::String),
+                Box::new(PyObjectType::String),
+            ))),
+        ]);
         types.insert(signals::LOGGING_ID, logging_object_type);
         types.insert(signals::ON_CONNECT_ID, PyObjectType::String);
         types.insert(signals::ON_DISCONNECT_ID, PyObjectType::Empty);
@@ -164,12 +286,31 @@ impl StateServerCore {
             signals,
             inner: OnceLock::new(),
             temps: RwLock::new(Some(types)),
+            batch: Mutex::new((0, false)),
         })
     }
 
     fn finalize(&self, py: Python) -> PyResult<()> {
-        match (self.server.write().finalize(), self.temps.write().take()) {
+        let states = self.server.write().finalize();
+        let temps = self.temps.write().take();
+        match (states, temps) {
             (Some(states), Some(mut types)) => {
+                // registry of types for clients decoding values without compile-time knowledge
+                let mut registry = Vec::with_capacity(types.len());
+                for (id, object_type) in types.iter() {
+                    let type_id = match object_type {
+                        PyObjectType::Map(key_type, value_type) => {
+                            value_type.get_hash_from(py, key_type.get_hash(py)?)?
+                        }
+                        _ => object_type.get_hash(py)?,
+                    };
+                    registry.push((*id, type_id, object_type.get_core_type(py)?));
+                }
+                self.server
+                    .write()
+                    .set_types(registry)
+                    .map_err(PyRuntimeError::new_err)?;
+
                 let mut values = NoHashMap::default();
                 for (id, state) in states.values {
                     if let Some(object_type) = types.get(&id) {
@@ -287,6 +428,54 @@ impl StateServerCore {
         self.server.write().stop();
     }
 
+    fn __enter__(slf: Py<Self>) -> Py<Self> {
+        slf
+    }
+
+    // stopping also disconnects the client, it does nothing if the server is not running
+    #[pyo3(signature = (_exc_type=None, _exc_value=None, _traceback=None))]
+    fn __exit__(
+        &self,
+        _exc_type: Option<&Bound<PyAny>>,
+        _exc_value: Option<&Bound<PyAny>>,
+        _traceback: Option<&Bound<PyAny>>,
+    ) -> bool {
+        self.server.write().stop();
+        false
+    }
+
+    #[cfg(feature = "tls")]
+    fn set_tls(&self, cert_path: Option<String>, key_path: Option<String>) -> PyResult<()> {
+        let acceptor = match (cert_path, key_path) {
+            (Some(cert_path), Some(key_path)) => Some(
+                crate::server::tls::acceptor(&cert_path, &key_path)
+                    .map_err(PyValueError::new_err)?,
+            ),
+            (None, None) => None,
+            _ => {
+                return Err(PyValueError::new_err(
+                    "Certificate and private key have to be set together.",
+                ));
+            }
+        };
+        self.server.write().set_tls(acceptor);
+        Ok(())
+    }
+
+    fn set_read_only_clients(&self, count: usize) {
+        self.server.write().set_read_only_clients(count);
+    }
+
+    #[cfg(feature = "metrics-http")]
+    #[pyo3(signature = (port, ip_addr=None))]
+    fn set_metrics_addr(&self, port: Option<u16>, ip_addr: Option<[u8; 4]>) {
+        let addr = port.map(|port| {
+            let ip = ip_addr.map_or(Ipv4Addr::new(0, 0, 0, 0), Ipv4Addr::from);
+            SocketAddrV4::new(ip, port)
+        });
+        self.server.write().set_metrics_addr(addr);
+    }
+
     fn is_running(&self) -> bool {
         self.server.read().is_running()
     }
@@ -306,6 +495,49 @@ impl StateServerCore {
             .map_err(|_| PyRuntimeError::new_err("Update failed."))
     }
 
+    fn begin_batch(&self) {
+        self.batch.lock().0 += 1;
+    }
+
+    fn end_batch(&self) -> PyResult<()> {
+        let mut batch = self.batch.lock();
+        if batch.0 == 0 {
+            return Err(PyRuntimeError::new_err("No batch to end."));
+        }
+        batch.0 -= 1;
+        if batch.0 > 0 || !batch.1 {
+            return Ok(());
+        }
+        batch.1 = false;
+        drop(batch);
+
+        self.server
+            .read()
+            .update(None)
+            .map_err(|_| PyRuntimeError::new_err("Update failed."))
+    }
+
+    fn flush(&self, py: Python, timeout: f64) -> PyResult<bool> {
+        let sender = match self.server.read().flush_sender() {
+            Some(sender) => sender,
+            None => return Ok(false),
+        };
+        let timeout = timeout_duration(timeout)?;
+        Ok(py.detach(|| sender.flush(timeout)))
+    }
+
+    fn stats<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let (messages_sent, bytes_sent, messages_received, bytes_received, queue_depth) =
+            self.server.read().stats();
+        let stats = PyDict::new(py);
+        stats.set_item("messages_sent", messages_sent)?;
+        stats.set_item("bytes_sent", bytes_sent)?;
+        stats.set_item("messages_received", messages_received)?;
+        stats.set_item("bytes_received", bytes_received)?;
+        stats.set_item("queue_depth", queue_depth)?;
+        Ok(stats)
+    }
+
     fn id_to_name(&self, value_id: u64) -> PyResult<String> {
         let values = self.get_values()?;
         if let Some((value, _)) = values.values.get(&value_id) {
@@ -345,6 +577,50 @@ impl StateServerCore {
         Err(PyRuntimeError::new_err("Value not found."))
     }
 
+    // current values of values, statics, lists and maps as nested dict following the state tree
+    fn snapshot_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let values = self.get_values()?;
+        let mut items: Vec<(&str, Bound<'py, PyAny>)> = Vec::new();
+
+        for (id, (value, _)) in values.values.iter() {
+            items.push((&value.name, self.value_get(py, *id)?));
+        }
+        for (id, (value, _)) in values.static_values.iter() {
+            items.push((&value.name, self.static_get(py, *id)?));
+        }
+        for (id, (list, _)) in values.lists.iter() {
+            items.push((&list.name, self.list_get(py, *id)?.into_any()));
+        }
+        for (id, (map, _)) in values.maps.iter() {
+            items.push((&map.name, self.map_get(py, *id)?.into_any()));
+        }
+        items.sort_by(|a, b| a.0.cmp(b.0));
+
+        let root = PyDict::new(py);
+        for (name, value) in items {
+            // skip the root state name
+            let mut path = name.split('.').skip(1).peekable();
+            let mut dict = root.clone();
+            while let Some(part) = path.next() {
+                if path.peek().is_none() {
+                    dict.set_item(part, &value)?;
+                    break;
+                }
+
+                dict = match dict.get_item(part)? {
+                    Some(sub) => sub.cast_into::<PyDict>()?,
+                    None => {
+                        let sub = PyDict::new(py);
+                        dict.set_item(part, &sub)?;
+                        sub
+                    }
+                };
+            }
+        }
+
+        Ok(root)
+    }
+
     // values -----------------------------------------------------------
     fn value_get<'py>(&self, py: Python<'py>, value_id: u64) -> PyResult<Bound<'py, PyAny>> {
         let (value, object_type) = self.inner_values(value_id)?;
@@ -358,7 +634,9 @@ impl StateServerCore {
         value: &Bound<PyAny>,
         set_signal: bool,
         update: bool,
-    ) -> PyResult<()> {
+    ) -> PyResult<bool> {
+        self.check_busy()?;
+        let update = self.batch_update(update);
         let (val, object_type) = self.inner_values(value_id)?;
         let mut creator = ValueCreator::new();
         pyparsing::serialize_py(value, object_type, &mut creator)?;
@@ -367,6 +645,220 @@ impl StateServerCore {
             .map_err(|_| PyRuntimeError::new_err("Value set failed."))
     }
 
+    // all values are serialized before any is set, so a bad item leaves the values unchanged
+    fn value_set_many(
+        &self,
+        py: Python,
+        items: &Bound<PyDict>,
+        set_signal: bool,
+        update: bool,
+    ) -> PyResult<usize> {
+        self.check_busy()?;
+        let update = self.batch_update(update);
+
+        let mut prepared = Vec::with_capacity(items.len());
+        for (key, value) in items.iter() {
+            let value_id: u64 = key.extract()?;
+            let (val, object_type) = self.inner_values(value_id).map_err(|_| {
+                PyValueError::new_err(format!("Value with ID {} not found.", value_id))
+            })?;
+            let mut creator = ValueCreator::new();
+            pyparsing::serialize_py(&value, object_type, &mut creator).map_err(|e| {
+                PyErr::from_type(
+                    e.get_type(py),
+                    format!("Value with ID {}: {}", value_id, e.value(py)),
+                )
+            })?;
+            prepared.push((val, creator.finalize()));
+        }
+
+        // the values dropped by the max rate are not counted
+        let mut count = 0;
+        for (val, data) in prepared {
+            if val
+                .set(data, set_signal, false)
+                .map_err(|_| PyRuntimeError::new_err("Value set failed."))?
+            {
+                count += 1;
+            }
+        }
+
+        if update && count > 0 {
+            self.server
+                .read()
+                .update(None)
+                .map_err(|_| PyRuntimeError::new_err("Update failed."))?;
+        }
+        Ok(count)
+    }
+
+    fn value_compare_set(
+        &self,
+        value_id: u64,
+        expected: &Bound<PyAny>,
+        value: &Bound<PyAny>,
+        set_signal: bool,
+        update: bool,
+    ) -> PyResult<bool> {
+        self.check_busy()?;
+        let update = self.batch_update(update);
+        let (val, object_type) = self.inner_values(value_id)?;
+        let mut creator = ValueCreator::new();
+        pyparsing::serialize_py(expected, object_type, &mut creator)?;
+        let expected = creator.finalize();
+        let mut creator = ValueCreator::new();
+        pyparsing::serialize_py(value, object_type, &mut creator)?;
+        let data = creator.finalize();
+        val.compare_set(&expected, data, set_signal, update)
+            .map_err(|_| PyRuntimeError::new_err("Value set failed."))
+    }
+
+    fn value_increment<'py>(
+        &self,
+        py: Python<'py>,
+        value_id: u64,
+        delta: &Bound<'py, PyAny>,
+        set_signal: bool,
+        update: bool,
+    ) -> PyResult<Option<Bound<'py, PyAny>>> {
+        self.check_busy()?;
+        let update = self.batch_update(update);
+        let (val, object_type) = self.inner_values(value_id)?;
+        let delta = match object_type {
+            PyObjectType::U8
+            | PyObjectType::U16
+            | PyObjectType::U32
+            | PyObjectType::U64
+            | PyObjectType::I8
+            | PyObjectType::I16
+            | PyObjectType::I32
+            | PyObjectType::I64 => Delta::Int(delta.extract().map_err(|_| {
+                PyValueError::new_err("Delta of an integer value has to be an integer.")
+            })?),
+            PyObjectType::F32 | PyObjectType::F64 => Delta::Float(delta.extract()?),
+            _ => return Err(PyValueError::new_err("Value is not numeric.")),
+        };
+
+        let data = val
+            .modify(
+                |old| increment(old, object_type, &delta),
+                set_signal,
+                update,
+            )
+            .map_err(PyValueError::new_err)?;
+
+        match data {
+            Some(data) => {
+                let mut parser = ValueParser::new(data);
+                pyparsing::deserialize_py(py, &mut parser, object_type).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn value_update_bits(
+        &self,
+        value_id: u64,
+        set_mask: u64,
+        clear_mask: u64,
+        toggle_mask: u64,
+        set_signal: bool,
+        update: bool,
+    ) -> PyResult<Option<u64>> {
+        self.check_busy()?;
+        let update = self.batch_update(update);
+        let (val, object_type) = self.inner_values(value_id)?;
+        if !matches!(object_type, PyObjectType::U64) {
+            return Err(PyValueError::new_err("Value is not a bitset."));
+        }
+
+        let data = val
+            .modify(
+                |old| {
+                    let mut bits = 0u64;
+                    ValueParser::new(old.clone())
+                        .get(&mut bits)
+                        .map_err(|_| "Failed to parse the bitset.".to_string())?;
+                    bits = ((bits | set_mask) & !clear_mask) ^ toggle_mask;
+
+                    let mut creator = ValueCreator::new();
+                    creator
+                        .add(&bits)
+                        .map_err(|_| "Failed to serialize the bitset.".to_string())?;
+                    Ok(creator.finalize())
+                },
+                set_signal,
+                update,
+            )
+            .map_err(PyValueError::new_err)?;
+
+        let Some(data) = data else {
+            return Ok(None);
+        };
+        let mut bits = 0u64;
+        ValueParser::new(data)
+            .get(&mut bits)
+            .map_err(|_| PyValueError::new_err("Failed to parse the bitset."))?;
+        Ok(Some(bits))
+    }
+
+    fn value_set_field(
+        &self,
+        py: Python,
+        value_id: u64,
+        path: &str,
+        value: &Bound<PyAny>,
+        set_signal: bool,
+        update: bool,
+    ) -> PyResult<bool> {
+        self.check_busy()?;
+        let update = self.batch_update(update);
+        let (val, object_type) = self.inner_values(value_id)?;
+        let path: Vec<&str> = path.split('.').collect();
+        let (indexes, field_type) = pyparsing::field_path(py, object_type, &path)?;
+
+        let mut creator = ValueCreator::new();
+        pyparsing::serialize_py(value, field_type, &mut creator)?;
+        let field_data = creator.finalize();
+
+        // the field is patched under the value lock, so concurrent sets of other fields are kept
+        let modified = val
+            .modify(
+                |old| {
+                    let mut parser = ValueParser::new(old.clone());
+                    let (start, end) = pyparsing::field_range(&mut parser, object_type, &indexes)
+                        .map_err(|e| e.to_string())?;
+
+                    let mut data = Vec::with_capacity(old.len() - (end - start) + field_data.len());
+                    data.extend_from_slice(&old[..start]);
+                    data.extend_from_slice(&field_data);
+                    data.extend_from_slice(&old[end..]);
+                    Ok(Bytes::from(data))
+                },
+                set_signal,
+                update,
+            )
+            .map_err(PyValueError::new_err)?;
+        Ok(modified.is_some())
+    }
+
+    fn value_update_rate(&self, value_id: u64) -> PyResult<f64> {
+        let (val, _) = self.inner_values(value_id)?;
+        Ok(val.update_rate())
+    }
+
+    #[pyo3(signature = (value_id, hz, drop))]
+    fn value_set_max_rate(&self, value_id: u64, hz: Option<f64>, drop: bool) -> PyResult<()> {
+        let (val, _) = self.inner_values(value_id)?;
+        val.set_max_rate(hz, drop).map_err(PyValueError::new_err)
+    }
+
+    fn value_set_notify_client(&self, value_id: u64, notify: bool) -> PyResult<()> {
+        let (val, _) = self.inner_values(value_id)?;
+        val.set_notify_client(notify);
+        Ok(())
+    }
+
     // values take ------------------------------------------------------
     fn value_take_set(
         &self,
@@ -375,6 +867,8 @@ impl StateServerCore {
         blocking: bool,
         update: bool,
     ) -> PyResult<()> {
+        self.check_busy()?;
+        let update = self.batch_update(update);
         let (val, object_type) = match self.get_values()?.values_take.get(&value_id) {
             Some((value, object_type)) => Ok((value, object_type)),
             _ => Err(PyValueError::new_err("ValueTake with ID not found.")),
@@ -394,6 +888,8 @@ impl StateServerCore {
     }
 
     fn static_set(&self, value_id: u64, value: &Bound<PyAny>, update: bool) -> PyResult<()> {
+        self.check_busy()?;
+        let update = self.batch_update(update);
         let (val, object_type) = self.inner_static(value_id)?;
         let mut creator = ValueCreator::new();
         pyparsing::serialize_py(value, object_type, &mut creator)?;
@@ -402,6 +898,52 @@ impl StateServerCore {
             .map_err(|_| PyRuntimeError::new_err("Static value set failed."))
     }
 
+    fn static_update<'py>(
+        &self,
+        py: Python<'py>,
+        value_id: u64,
+        callback: &Bound<'py, PyAny>,
+        update: bool,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        self.check_busy()?;
+        let update = self.batch_update(update);
+        let (val, object_type) = self.inner_static(value_id)?;
+
+        // keeps the python exception of the callback
+        let mut error = None;
+        let result = val.modify(
+            |old| {
+                let modify = || -> PyResult<Bytes> {
+                    let mut parser = ValueParser::new(old.clone());
+                    let value = pyparsing::deserialize_py(py, &mut parser, object_type)?;
+                    let result = callback.call1((&value,))?;
+                    // callback can mutate the value in place or return the new one
+                    let new_value = match result.is_none() {
+                        true => value,
+                        false => result,
+                    };
+                    let mut creator = ValueCreator::new();
+                    pyparsing::serialize_py(&new_value, object_type, &mut creator)?;
+                    Ok(creator.finalize())
+                };
+                modify().map_err(|e| {
+                    let message = e.to_string();
+                    error = Some(e);
+                    message
+                })
+            },
+            update,
+        );
+
+        let data = match (result, error) {
+            (Ok(data), _) => data,
+            (Err(_), Some(e)) => return Err(e),
+            (Err(message), None) => return Err(PyRuntimeError::new_err(message)),
+        };
+        let mut parser = ValueParser::new(data);
+        pyparsing::deserialize_py(py, &mut parser, object_type)
+    }
+
     // signals ----------------------------------------------------------
     fn signal_set(&self, value_id: u64, value: &Bound<PyAny>) -> PyResult<()> {
         match self.get_values()?.signals.get(&value_id) {
@@ -424,17 +966,28 @@ impl StateServerCore {
         self.signals.set_register(value_id, register);
     }
 
+    // the wait releases the GIL, so the other python threads run while waiting
+    #[pyo3(signature = (last_id, timeout=None))]
     fn signal_get<'py>(
         &self,
         py: Python<'py>,
         last_id: Option<u64>,
-    ) -> PyResult<(u64, Bound<'py, PyAny>)> {
-        let (id, data) = py.detach(|| self.signals.wait_changed_value(last_id));
+        timeout: Option<f64>,
+    ) -> PyResult<Option<(u64, Bound<'py, PyAny>)>> {
+        let timeout = match timeout {
+            Some(timeout) => timeout_duration(timeout)?,
+            None => None,
+        };
+
+        let (id, data) = match py.detach(|| self.signals.wait_changed_value(last_id, timeout)) {
+            Some(signal) => signal,
+            None => return Ok(None),
+        };
         match self.get_values()?.signals_types.get(&id) {
             Some(object_type) => {
                 let mut parser = ValueParser::new(data);
                 let py_value = pyparsing::deserialize_py(py, &mut parser, object_type)?;
-                Ok((id, py_value))
+                Ok(Some((id, py_value)))
             }
             None => Err(pyo3::exceptions::PyValueError::new_err(format!(
                 "Signal with ID {} not found",
@@ -443,6 +996,25 @@ impl StateServerCore {
         }
     }
 
+    #[pyo3(signature = (level, message, fields=None))]
+    fn log(
+        &self,
+        level: u8,
+        message: &str,
+        fields: Option<HashMap<String, String>>,
+    ) -> PyResult<()> {
+        if level > 3 {
+            return Err(PyValueError::new_err("Invalid log level."));
+        }
+        let fields: Vec<(&str, String)> = fields
+            .iter()
+            .flatten()
+            .map(|(k, v)| (k.as_str(), v.clone()))
+            .collect();
+        self.signals.log(level, message, &fields);
+        Ok(())
+    }
+
     fn signal_set_to_queue(&self, value_id: u64) {
         self.signals.set_to_queue(value_id);
     }
@@ -451,8 +1023,28 @@ impl StateServerCore {
         self.signals.set_to_single(value_id);
     }
 
+    fn signal_set_suppressed(&self, value_id: u64, suppressed: bool) {
+        self.signals.set_suppressed(value_id, suppressed);
+    }
+
+    #[pyo3(signature = (value_id, hz))]
+    fn signal_set_rate_limit(&self, value_id: u64, hz: Option<f64>) -> PyResult<()> {
+        let interval = match hz {
+            Some(hz) if hz.is_finite() && hz > 0.0 => Some(
+                Duration::try_from_secs_f64(1.0 / hz)
+                    .map_err(|_| PyValueError::new_err("Rate limit is too low."))?,
+            ),
+            Some(_) => return Err(PyValueError::new_err("Rate limit must be positive.")),
+            None => None,
+        };
+        self.signals.set_rate_limit(value_id, interval);
+        Ok(())
+    }
+
     // lists ------------------------------------------------------------
     fn list_set(&self, value_id: u64, py_list: &Bound<PyList>, update: bool) -> PyResult<()> {
+        self.check_busy()?;
+        let update = self.batch_update(update);
         let (list, value_type) = self.inner_vec(value_id)?;
         let mut vec = Vec::with_capacity(py_list.len());
         for item in py_list.iter() {
@@ -477,6 +1069,77 @@ impl StateServerCore {
         Ok(py_list)
     }
 
+    fn list_get_range<'py>(
+        &self,
+        py: Python<'py>,
+        value_id: u64,
+        start: usize,
+        stop: usize,
+    ) -> PyResult<Bound<'py, PyList>> {
+        let (list, value_type) = self.inner_vec(value_id)?;
+        let vec = list.get_range(start, stop);
+        let py_list = PyList::empty(py);
+        for item in vec.into_iter() {
+            let mut parser = ValueParser::new(item);
+            let py_value = pyparsing::deserialize_py(py, &mut parser, value_type)?;
+            py_list.append(py_value)?;
+        }
+        Ok(py_list)
+    }
+
+    fn list_item_size(&self, value_id: u64) -> PyResult<Option<usize>> {
+        let (_, value_type) = self.inner_vec(value_id)?;
+        Ok(value_type.fixed_size())
+    }
+
+    fn list_set_buffer(&self, value_id: u64, data: &Bound<PyAny>, update: bool) -> PyResult<()> {
+        self.check_busy()?;
+        let update = self.batch_update(update);
+        let (list, value_type) = self.inner_vec(value_id)?;
+        let item_size = value_type
+            .fixed_size()
+            .ok_or_else(|| PyValueError::new_err("List items do not have a fixed size."))?;
+
+        let buffer = PyUntypedBuffer::get(data)
+            .map_err(|_| PyValueError::new_err("Data must be a bytes-like object."))?;
+        if !buffer.is_c_contiguous() {
+            return Err(PyValueError::new_err("Data buffer must be C-contiguous."));
+        }
+        if !buffer.len_bytes().is_multiple_of(item_size) {
+            return Err(PyValueError::new_err(format!(
+                "Data size {} is not a multiple of the item size {}.",
+                buffer.len_bytes(),
+                item_size
+            )));
+        }
+
+        let slice = unsafe {
+            std::slice::from_raw_parts(buffer.buf_ptr() as *const u8, buffer.len_bytes())
+        };
+        list.set_packed(Bytes::copy_from_slice(slice), item_size, update)
+            .map_err(|_| PyRuntimeError::new_err("Failed to set list."))
+    }
+
+    fn list_get_buffer<'py>(
+        &self,
+        py: Python<'py>,
+        value_id: u64,
+    ) -> PyResult<Bound<'py, PyByteArray>> {
+        let (list, value_type) = self.inner_vec(value_id)?;
+        let item_size = value_type
+            .fixed_size()
+            .ok_or_else(|| PyValueError::new_err("List items do not have a fixed size."))?;
+
+        list.read(|items| {
+            PyByteArray::new_with(py, items.len() * item_size, |buffer| {
+                for (chunk, item) in buffer.chunks_exact_mut(item_size).zip(items) {
+                    chunk.copy_from_slice(item);
+                }
+                Ok(())
+            })
+        })
+    }
+
     fn list_set_item(
         &self,
         value_id: u64,
@@ -484,12 +1147,14 @@ impl StateServerCore {
         item: &Bound<PyAny>,
         update: bool,
     ) -> PyResult<()> {
+        self.check_busy()?;
+        let update = self.batch_update(update);
         let (list, value_type) = self.inner_vec(value_id)?;
         let mut creator = ValueCreator::new();
         pyparsing::serialize_py(item, value_type, &mut creator)?;
         let data = creator.finalize();
         list.set_item_py(index, data, update)
-            .map_err(|e| PyValueError::new_err(e))?;
+            .map_err(PyValueError::new_err)?;
         Ok(())
     }
 
@@ -502,7 +1167,7 @@ impl StateServerCore {
         let (list, value_type) = self.inner_vec(value_id)?;
         let data = list
             .get_item(index)
-            .map_err(|e| pyo3::exceptions::PyValueError::new_err(e))?;
+            .map_err(pyo3::exceptions::PyValueError::new_err)?;
         let mut parser = ValueParser::new(data);
         let py_value = pyparsing::deserialize_py(py, &mut parser, value_type)?;
         Ok(py_value)
@@ -515,16 +1180,20 @@ impl StateServerCore {
         index: usize,
         update: bool,
     ) -> PyResult<Bound<'py, PyAny>> {
+        self.check_busy()?;
+        let update = self.batch_update(update);
         let (list, value_type) = self.inner_vec(value_id)?;
         let data = list
             .remove_item(index, update)
-            .map_err(|e| pyo3::exceptions::PyValueError::new_err(e))?;
+            .map_err(pyo3::exceptions::PyValueError::new_err)?;
         let mut parser = ValueParser::new(data);
         let py_value = pyparsing::deserialize_py(py, &mut parser, value_type)?;
         Ok(py_value)
     }
 
     fn list_append_item(&self, value_id: u64, item: &Bound<PyAny>, update: bool) -> PyResult<()> {
+        self.check_busy()?;
+        let update = self.batch_update(update);
         let (list, value_type) = self.inner_vec(value_id)?;
         let mut creator = ValueCreator::new();
         pyparsing::serialize_py(item, value_type, &mut creator)?;
@@ -533,6 +1202,68 @@ impl StateServerCore {
             .map_err(|_| PyRuntimeError::new_err("Failed to append item to list."))
     }
 
+    #[pyo3(signature = (value_id, key, reverse, update))]
+    fn list_sort(
+        &self,
+        py: Python,
+        value_id: u64,
+        key: Option<&Bound<PyAny>>,
+        reverse: bool,
+        update: bool,
+    ) -> PyResult<()> {
+        self.check_busy()?;
+        let update = self.batch_update(update);
+        let (list, value_type) = self.inner_vec(value_id)?;
+
+        // keeps the python exception of the key callback
+        let mut error = None;
+        let result = list.reorder(
+            |items| {
+                let order = || -> PyResult<Vec<u32>> {
+                    let keys = PyList::empty(py);
+                    for item in items.iter() {
+                        let mut parser = ValueParser::new(item.clone());
+                        let value = pyparsing::deserialize_py(py, &mut parser, value_type)?;
+                        match key {
+                            Some(key) => keys.append(key.call1((value,))?)?,
+                            None => keys.append(value)?,
+                        }
+                    }
+
+                    // sort the indices by the keys, python sort is stable
+                    let builtins = py.import("builtins")?;
+                    let indices = builtins.getattr("range")?.call1((items.len(),))?;
+                    let kwargs = PyDict::new(py);
+                    kwargs.set_item("key", keys.getattr("__getitem__")?)?;
+                    kwargs.set_item("reverse", reverse)?;
+                    builtins
+                        .getattr("sorted")?
+                        .call((indices,), Some(&kwargs))?
+                        .extract()
+                };
+                order().map_err(|e| {
+                    let message = e.to_string();
+                    error = Some(e);
+                    message
+                })
+            },
+            update,
+        );
+
+        match (result, error) {
+            (Ok(()), _) => Ok(()),
+            (Err(_), Some(e)) => Err(e),
+            (Err(message), None) => Err(PyRuntimeError::new_err(message)),
+        }
+    }
+
+    fn list_reverse(&self, value_id: u64, update: bool) -> PyResult<()> {
+        self.check_busy()?;
+        let update = self.batch_update(update);
+        let (list, _) = self.inner_vec(value_id)?;
+        list.reverse(update).map_err(PyRuntimeError::new_err)
+    }
+
     fn list_len(&self, value_id: u64) -> PyResult<usize> {
         match self.get_values()?.lists.get(&value_id) {
             Some((list, _)) => Ok(list.len()),
@@ -544,8 +1275,10 @@ impl StateServerCore {
 
     // maps -------------------------------------------------------------
     fn map_set(&self, value_id: u64, py_dict: &Bound<PyDict>, update: bool) -> PyResult<()> {
+        self.check_busy()?;
+        let update = self.batch_update(update);
         let (map, key_type, value_type) = self.inner_map(value_id)?;
-        let mut new_map = HashMap::with_capacity(py_dict.len());
+        let mut items = Vec::with_capacity(py_dict.len());
         for (key, value) in py_dict.iter() {
             let mut key_creator = ValueCreator::new();
             pyparsing::serialize_py(&key, key_type, &mut key_creator)?;
@@ -555,9 +1288,9 @@ impl StateServerCore {
             pyparsing::serialize_py(&value, value_type, &mut value_creator)?;
             let value_data = value_creator.finalize();
 
-            new_map.insert(key_data, value_data);
+            items.push((key_data, value_data));
         }
-        map.set(new_map, update)
+        map.set(items, update)
             .map_err(|_| PyRuntimeError::new_err("Failed to set map."))
     }
 
@@ -584,6 +1317,8 @@ impl StateServerCore {
         value: &Bound<PyAny>,
         update: bool,
     ) -> PyResult<()> {
+        self.check_busy()?;
+        let update = self.batch_update(update);
         let (map, key_type, value_type) = self.inner_map(value_id)?;
         let mut key_creator = ValueCreator::new();
         pyparsing::serialize_py(key, key_type, &mut key_creator)?;
@@ -597,6 +1332,63 @@ impl StateServerCore {
             .map_err(|_| PyRuntimeError::new_err("Failed to set item in map."))
     }
 
+    fn map_update_item<'py>(
+        &self,
+        py: Python<'py>,
+        value_id: u64,
+        key: &Bound<PyAny>,
+        callback: &Bound<'py, PyAny>,
+        update: bool,
+    ) -> PyResult<Option<Bound<'py, PyAny>>> {
+        self.check_busy()?;
+        let update = self.batch_update(update);
+        let (map, key_type, value_type) = self.inner_map(value_id)?;
+        let mut key_creator = ValueCreator::new();
+        pyparsing::serialize_py(key, key_type, &mut key_creator)?;
+        let key_data = key_creator.finalize();
+
+        // keeps the python exception of the callback
+        let mut error = None;
+        let mut new_value = None;
+        let result = map.update_item(
+            key_data,
+            |old| {
+                let mut modify = || -> PyResult<Option<Bytes>> {
+                    let value = match old {
+                        Some(old) => {
+                            let mut parser = ValueParser::new(old.clone());
+                            Some(pyparsing::deserialize_py(py, &mut parser, value_type)?)
+                        }
+                        None => None,
+                    };
+                    let result = callback.call1((&value,))?;
+                    // existing value can be mutated in place, missing one is inserted only if returned
+                    let value = match (result.is_none(), value) {
+                        (false, _) => result,
+                        (true, Some(value)) => value,
+                        (true, None) => return Ok(None),
+                    };
+                    let mut creator = ValueCreator::new();
+                    pyparsing::serialize_py(&value, value_type, &mut creator)?;
+                    new_value = Some(value);
+                    Ok(Some(creator.finalize()))
+                };
+                modify().map_err(|e| {
+                    let message = e.to_string();
+                    error = Some(e);
+                    message
+                })
+            },
+            update,
+        );
+
+        match (result, error) {
+            (Ok(_), _) => Ok(new_value),
+            (Err(_), Some(e)) => Err(e),
+            (Err(message), None) => Err(PyRuntimeError::new_err(message)),
+        }
+    }
+
     fn map_get_item<'py>(
         &self,
         py: Python<'py>,
@@ -624,6 +1416,8 @@ impl StateServerCore {
         key: &Bound<PyAny>,
         update: bool,
     ) -> PyResult<Bound<'py, PyAny>> {
+        self.check_busy()?;
+        let update = self.batch_update(update);
         let (map, key_type, value_type) = self.inner_map(value_id)?;
         let mut key_creator = ValueCreator::new();
         pyparsing::serialize_py(key, key_type, &mut key_creator)?;
@@ -642,6 +1436,21 @@ impl StateServerCore {
         }
     }
 
+    fn map_del_items(&self, value_id: u64, keys: &Bound<PyAny>, update: bool) -> PyResult<usize> {
+        self.check_busy()?;
+        let update = self.batch_update(update);
+        let (map, key_type, _) = self.inner_map(value_id)?;
+        let mut keys_data = Vec::new();
+        for key in keys.try_iter()? {
+            let mut key_creator = ValueCreator::new();
+            pyparsing::serialize_py(&key?, key_type, &mut key_creator)?;
+            keys_data.push(key_creator.finalize());
+        }
+
+        map.remove_items(&keys_data, update)
+            .map_err(|_| PyRuntimeError::new_err("Failed to remove items from map."))
+    }
+
     fn map_len(&self, value_id: u64) -> PyResult<usize> {
         match self.get_values()?.maps.get(&value_id) {
             Some((map, _)) => Ok(map.len()),
@@ -669,36 +1478,80 @@ impl StateServerCore {
         Ok((array, size))
     }
 
-    #[pyo3(signature = (value_id, image, update))]
+    fn image_get_array(&self, value_id: u64) -> PyResult<ImageArray> {
+        let (data, size) = self
+            .inner_image(value_id)?
+            .get_image(|(data, size)| (data.clone(), *size));
+        Ok(ImageArray::new(data, size))
+    }
+
+    #[pyo3(signature = (value_id, image, update, window=None))]
     fn image_set(
         &self,
         py: Python,
         value_id: u64,
-        image: PyBuffer<u8>,
+        image: &Bound<PyAny>,
         update: bool,
+        window: Option<[f64; 2]>,
     ) -> PyResult<()> {
+        self.check_busy()?;
+        let update = self.batch_update(update);
+        let image = ImageSource::new(image)?;
         py.detach(|| {
             let image_val = self.inner_image(value_id)?;
-            let image_data = pyimage::image_data(&image)?;
+            let mut holder = Vec::new();
+            let (image_data, window) = image.image_data(window, &mut holder)?;
+            image_val.set_window(window);
             image_val
                 .set_image(image_data, update)
-                .map_err(|e| PyValueError::new_err(e))
+                .map_err(PyValueError::new_err)
         })
     }
 
-    #[pyo3(signature = (value_id, image, origin, update, force=false))]
+    #[pyo3(signature = (value_id, image, max_dim, update, window=None))]
+    fn image_set_scaled(
+        &self,
+        py: Python,
+        value_id: u64,
+        image: &Bound<PyAny>,
+        max_dim: usize,
+        update: bool,
+        window: Option<[f64; 2]>,
+    ) -> PyResult<()> {
+        self.check_busy()?;
+        let update = self.batch_update(update);
+        let image = ImageSource::new(image)?;
+        py.detach(|| {
+            let image_val = self.inner_image(value_id)?;
+            let mut holder = Vec::new();
+            let (image_data, window) = image.image_data(window, &mut holder)?;
+            image_val.set_window(window);
+            image_val
+                .set_image_scaled(image_data, max_dim, update)
+                .map_err(PyValueError::new_err)
+        })
+    }
+
+    #[pyo3(signature = (value_id, image, origin, update, force=false, window=None))]
+    #[allow(clippy::too_many_arguments)]
     fn image_update(
         &self,
         py: Python,
         value_id: u64,
-        image: PyBuffer<u8>,
+        image: &Bound<PyAny>,
         origin: [u32; 2],
         update: bool,
         force: bool,
+        window: Option<[f64; 2]>,
     ) -> PyResult<()> {
+        self.check_busy()?;
+        let update = self.batch_update(update);
+        let image = ImageSource::new(image)?;
         py.detach(|| {
             let image_val = self.inner_image(value_id)?;
-            let image_data = pyimage::image_data(&image)?;
+            let window = partial_window(&image, image_val, window)?;
+            let mut holder = Vec::new();
+            let (image_data, _) = image.image_data(window, &mut holder)?;
             image_val
                 .update_image(
                     &[origin[0] as usize, origin[1] as usize],
@@ -706,10 +1559,55 @@ impl StateServerCore {
                     update,
                     force,
                 )
-                .map_err(|e| PyValueError::new_err(e))
+                .map_err(PyValueError::new_err)
         })
     }
 
+    #[pyo3(signature = (value_id, rows, update, window=None))]
+    fn image_scroll_append(
+        &self,
+        py: Python,
+        value_id: u64,
+        rows: &Bound<PyAny>,
+        update: bool,
+        window: Option<[f64; 2]>,
+    ) -> PyResult<()> {
+        self.check_busy()?;
+        let update = self.batch_update(update);
+        let rows = ImageSource::new(rows)?;
+        py.detach(|| {
+            let image_val = self.inner_image(value_id)?;
+            let window = partial_window(&rows, image_val, window)?;
+            let mut holder = Vec::new();
+            let (image_data, _) = rows.image_data(window, &mut holder)?;
+            image_val
+                .scroll_append(image_data, update)
+                .map_err(PyValueError::new_err)
+        })
+    }
+
+    #[pyo3(signature = (value_id, compression, quality=90))]
+    fn image_set_compression(
+        &self,
+        value_id: u64,
+        compression: Option<&str>,
+        quality: u8,
+    ) -> PyResult<()> {
+        let compression = match compression {
+            None => None,
+            Some("png") => Some(ImageCompression::Png),
+            Some("jpeg") => Some(ImageCompression::Jpeg(quality)),
+            Some(_) => {
+                return Err(PyValueError::new_err(
+                    "Compression must be 'png', 'jpeg' or None.",
+                ));
+            }
+        };
+        self.inner_image(value_id)?
+            .set_compression(compression)
+            .map_err(PyValueError::new_err)
+    }
+
     // data -------------------------------------------------------------
     fn data_get<'py>(&self, py: Python<'py>, value_id: u64) -> PyResult<Bound<'py, PyByteArray>> {
         Ok(self
@@ -717,19 +1615,23 @@ impl StateServerCore {
             .get(|data| PyByteArray::new(py, data)))
     }
 
+    #[pyo3(signature = (value_id, data, update, max_points=None, xy=false))]
     fn data_set(
         &self,
         py: Python,
         value_id: u64,
         data: &Bound<PyAny>,
         update: bool,
+        max_points: Option<usize>,
+        xy: bool,
     ) -> PyResult<()> {
+        self.check_busy()?;
+        let update = self.batch_update(update);
         let buffer_untyped = PyUntypedBuffer::get(data)
             .map_err(|_| PyValueError::new_err("Data must be a bytes-like object."))?;
 
         let data_value = self.inner_data(value_id)?;
-        check_data_type(&buffer_untyped, data_value.data_type)
-            .map_err(|e| PyValueError::new_err(e))?;
+        check_data_type(&buffer_untyped, data_value.data_type).map_err(PyValueError::new_err)?;
 
         let data_holder = DataHolder {
             data: buffer_untyped.buf_ptr() as *const u8,
@@ -738,10 +1640,14 @@ impl StateServerCore {
             data_type: data_value.data_type,
         };
 
+        let decimation = max_points.map(|max_points| Decimation {
+            max_points,
+            point_size: if xy { 2 } else { 1 },
+        });
         py.detach(|| {
             data_value
-                .set(data_holder, update)
-                .map_err(|e| PyValueError::new_err(e))
+                .set(data_holder, decimation, update)
+                .map_err(PyValueError::new_err)
         })
     }
 
@@ -751,13 +1657,14 @@ impl StateServerCore {
         value_id: u64,
         data: &Bound<PyAny>,
         update: bool,
-    ) -> PyResult<()> {
+    ) -> PyResult<usize> {
+        self.check_busy()?;
+        let update = self.batch_update(update);
         let buffer_untyped = PyUntypedBuffer::get(data)
             .map_err(|_| PyValueError::new_err("Data must be a bytes-like object."))?;
 
         let data_value = self.inner_data(value_id)?;
-        check_data_type(&buffer_untyped, data_value.data_type)
-            .map_err(|e| PyValueError::new_err(e))?;
+        check_data_type(&buffer_untyped, data_value.data_type).map_err(PyValueError::new_err)?;
 
         let data_holder = DataHolder {
             data: buffer_untyped.buf_ptr() as *const u8,
@@ -769,7 +1676,7 @@ impl StateServerCore {
         py.detach(|| {
             data_value
                 .add(data_holder, update)
-                .map_err(|e| PyValueError::new_err(e))
+                .map_err(PyValueError::new_err)
         })
     }
 
@@ -781,12 +1688,13 @@ impl StateServerCore {
         index: usize,
         update: bool,
     ) -> PyResult<()> {
+        self.check_busy()?;
+        let update = self.batch_update(update);
         let buffer_untyped = PyUntypedBuffer::get(data)
             .map_err(|_| PyValueError::new_err("Data must be a bytes-like object."))?;
 
         let data_value = self.inner_data(value_id)?;
-        check_data_type(&buffer_untyped, data_value.data_type)
-            .map_err(|e| PyValueError::new_err(e))?;
+        check_data_type(&buffer_untyped, data_value.data_type).map_err(PyValueError::new_err)?;
 
         let data_holder = DataHolder {
             data: buffer_untyped.buf_ptr() as *const u8,
@@ -798,7 +1706,38 @@ impl StateServerCore {
         py.detach(|| {
             data_value
                 .replace(data_holder, index, update)
-                .map_err(|e| PyValueError::new_err(e))
+                .map_err(PyValueError::new_err)
+        })
+    }
+
+    fn data_splice(
+        &self,
+        py: Python,
+        value_id: u64,
+        data: &Bound<PyAny>,
+        index: usize,
+        removed: usize,
+        update: bool,
+    ) -> PyResult<()> {
+        self.check_busy()?;
+        let update = self.batch_update(update);
+        let buffer_untyped = PyUntypedBuffer::get(data)
+            .map_err(|_| PyValueError::new_err("Data must be a bytes-like object."))?;
+
+        let data_value = self.inner_data(value_id)?;
+        check_data_type(&buffer_untyped, data_value.data_type).map_err(PyValueError::new_err)?;
+
+        let data_holder = DataHolder {
+            data: buffer_untyped.buf_ptr() as *const u8,
+            count: buffer_untyped.item_count(),
+            data_size: buffer_untyped.len_bytes(),
+            data_type: data_value.data_type,
+        };
+
+        py.detach(|| {
+            data_value
+                .splice(data_holder, index, removed, update)
+                .map_err(PyValueError::new_err)
         })
     }
 
@@ -810,21 +1749,77 @@ impl StateServerCore {
         count: usize,
         update: bool,
     ) -> PyResult<()> {
+        self.check_busy()?;
+        let update = self.batch_update(update);
         py.detach(|| {
             self.inner_data(value_id)?
                 .remove(index, count, update)
-                .map_err(|e| PyValueError::new_err(e))
+                .map_err(PyValueError::new_err)
         })
     }
 
     fn data_clear(&self, py: Python, value_id: u64, update: bool) -> PyResult<()> {
+        self.check_busy()?;
+        let update = self.batch_update(update);
         py.detach(|| {
             self.inner_data(value_id)?
                 .clear(update)
-                .map_err(|e| PyValueError::new_err(e))
+                .map_err(PyValueError::new_err)
         })
     }
 
+    #[pyo3(signature = (group, value_ids, x_range, update))]
+    fn data_set_group(
+        &self,
+        group: u32,
+        value_ids: Vec<u64>,
+        x_range: [f64; 2],
+        update: bool,
+    ) -> PyResult<()> {
+        self.check_busy()?;
+        let update = self.batch_update(update);
+        let data = value_ids
+            .iter()
+            .map(|value_id| self.inner_data(*value_id))
+            .collect::<PyResult<Vec<_>>>()?;
+        for d in data {
+            d.set_group(Some((group, x_range)), update)
+                .map_err(PyValueError::new_err)?;
+        }
+        Ok(())
+    }
+
+    fn data_clear_group(&self, value_ids: Vec<u64>, update: bool) -> PyResult<()> {
+        self.check_busy()?;
+        let update = self.batch_update(update);
+        let data = value_ids
+            .iter()
+            .map(|value_id| self.inner_data(*value_id))
+            .collect::<PyResult<Vec<_>>>()?;
+        for d in data {
+            d.set_group(None, update).map_err(PyValueError::new_err)?;
+        }
+        Ok(())
+    }
+
+    fn data_set_max_len(
+        &self,
+        value_id: u64,
+        max_len: Option<usize>,
+        update: bool,
+    ) -> PyResult<()> {
+        self.check_busy()?;
+        let update = self.batch_update(update);
+        self.inner_data(value_id)?
+            .set_max_len(max_len, update)
+            .map_err(PyValueError::new_err)
+    }
+
+    fn data_get_group(&self, value_id: u64) -> PyResult<Option<(u32, (f64, f64))>> {
+        let group = self.inner_data(value_id)?.get_group();
+        Ok(group.map(|(group, x_range)| (group, (x_range[0], x_range[1]))))
+    }
+
     // data take -------------------------------------------------------
     fn data_take_set(
         &self,
@@ -835,12 +1830,13 @@ impl StateServerCore {
         update: bool,
         cache: bool,
     ) -> PyResult<()> {
+        self.check_busy()?;
+        let update = self.batch_update(update);
         let buffer_untyped = PyUntypedBuffer::get(data)
             .map_err(|_| PyValueError::new_err("Data must be a bytes-like object."))?;
 
         let data_value = self.inner_data_take(value_id)?;
-        check_data_type(&buffer_untyped, data_value.data_type)
-            .map_err(|e| PyValueError::new_err(e))?;
+        check_data_type(&buffer_untyped, data_value.data_type).map_err(PyValueError::new_err)?;
 
         let data_holder = DataHolder {
             data: buffer_untyped.buf_ptr() as *const u8,
@@ -852,7 +1848,7 @@ impl StateServerCore {
         py.detach(|| {
             data_value
                 .set(data_holder, blocking, update, cache)
-                .map_err(|e| PyValueError::new_err(e))
+                .map_err(PyValueError::new_err)
         })
     }
 
@@ -878,12 +1874,13 @@ impl StateServerCore {
         data: &Bound<PyAny>,
         update: bool,
     ) -> PyResult<()> {
+        self.check_busy()?;
+        let update = self.batch_update(update);
         let buffer_untyped = PyUntypedBuffer::get(data)
             .map_err(|_| PyValueError::new_err("Data must be a bytes-like object."))?;
 
         let data_value = self.inner_data_multi(value_id)?;
-        check_data_type(&buffer_untyped, data_value.data_type)
-            .map_err(|e| PyValueError::new_err(e))?;
+        check_data_type(&buffer_untyped, data_value.data_type).map_err(PyValueError::new_err)?;
 
         let data_holder = DataHolder {
             data: buffer_untyped.buf_ptr() as *const u8,
@@ -895,7 +1892,7 @@ impl StateServerCore {
         py.detach(|| {
             data_value
                 .set(index, data_holder, update)
-                .map_err(|e| PyValueError::new_err(e))
+                .map_err(PyValueError::new_err)
         })
     }
 
@@ -907,12 +1904,13 @@ impl StateServerCore {
         data: &Bound<PyAny>,
         update: bool,
     ) -> PyResult<()> {
+        self.check_busy()?;
+        let update = self.batch_update(update);
         let buffer_untyped = PyUntypedBuffer::get(data)
             .map_err(|_| PyValueError::new_err("Data must be a bytes-like object."))?;
 
         let data_value = self.inner_data_multi(value_id)?;
-        check_data_type(&buffer_untyped, data_value.data_type)
-            .map_err(|e| PyValueError::new_err(e))?;
+        check_data_type(&buffer_untyped, data_value.data_type).map_err(PyValueError::new_err)?;
 
         let data_holder = DataHolder {
             data: buffer_untyped.buf_ptr() as *const u8,
@@ -924,7 +1922,7 @@ impl StateServerCore {
         py.detach(|| {
             data_value
                 .add(index, data_holder, update)
-                .map_err(|e| PyValueError::new_err(e))
+                .map_err(PyValueError::new_err)
         })
     }
 
@@ -937,12 +1935,13 @@ impl StateServerCore {
         data_index: usize,
         update: bool,
     ) -> PyResult<()> {
+        self.check_busy()?;
+        let update = self.batch_update(update);
         let buffer_untyped = PyUntypedBuffer::get(data)
             .map_err(|_| PyValueError::new_err("Data must be a bytes-like object."))?;
 
         let data_value = self.inner_data_multi(value_id)?;
-        check_data_type(&buffer_untyped, data_value.data_type)
-            .map_err(|e| PyValueError::new_err(e))?;
+        check_data_type(&buffer_untyped, data_value.data_type).map_err(PyValueError::new_err)?;
 
         let data_holder = DataHolder {
             data: buffer_untyped.buf_ptr() as *const u8,
@@ -954,7 +1953,54 @@ impl StateServerCore {
         py.detach(|| {
             data_value
                 .replace(index, data_index, data_holder, update)
-                .map_err(|e| PyValueError::new_err(e))
+                .map_err(PyValueError::new_err)
+        })
+    }
+
+    fn data_multi_set_max_len(
+        &self,
+        value_id: u64,
+        index: u32,
+        max_len: Option<usize>,
+        update: bool,
+    ) -> PyResult<()> {
+        self.check_busy()?;
+        let update = self.batch_update(update);
+        self.inner_data_multi(value_id)?
+            .set_max_len(index, max_len, update)
+            .map_err(PyValueError::new_err)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn data_multi_splice(
+        &self,
+        py: Python,
+        value_id: u64,
+        index: u32,
+        data: &Bound<PyAny>,
+        data_index: usize,
+        removed: usize,
+        update: bool,
+    ) -> PyResult<()> {
+        self.check_busy()?;
+        let update = self.batch_update(update);
+        let buffer_untyped = PyUntypedBuffer::get(data)
+            .map_err(|_| PyValueError::new_err("Data must be a bytes-like object."))?;
+
+        let data_value = self.inner_data_multi(value_id)?;
+        check_data_type(&buffer_untyped, data_value.data_type).map_err(PyValueError::new_err)?;
+
+        let data_holder = DataHolder {
+            data: buffer_untyped.buf_ptr() as *const u8,
+            count: buffer_untyped.item_count(),
+            data_size: buffer_untyped.len_bytes(),
+            data_type: data_value.data_type,
+        };
+
+        py.detach(|| {
+            data_value
+                .splice(index, data_index, removed, data_holder, update)
+                .map_err(PyValueError::new_err)
         })
     }
 
@@ -967,32 +2013,41 @@ impl StateServerCore {
         count: usize,
         update: bool,
     ) -> PyResult<()> {
+        self.check_busy()?;
+        let update = self.batch_update(update);
         py.detach(|| {
             self.inner_data_multi(value_id)?
                 .remove(index, data_index, count, update)
-                .map_err(|e| PyValueError::new_err(e))
+                .map_err(PyValueError::new_err)
         })
     }
 
     fn data_multi_clear(&self, value_id: u64, index: u32, update: bool) -> PyResult<()> {
+        self.check_busy()?;
+        let update = self.batch_update(update);
         self.inner_data_multi(value_id)?
             .clear(index, update)
-            .map_err(|e| PyValueError::new_err(e))
+            .map_err(PyValueError::new_err)
     }
 
     fn data_multi_remove_index(&self, value_id: u64, index: u32, update: bool) -> PyResult<()> {
+        self.check_busy()?;
+        let update = self.batch_update(update);
         self.inner_data_multi(value_id)?
             .remove_index(index, update)
-            .map_err(|e| PyValueError::new_err(e))
+            .map_err(PyValueError::new_err)
     }
 
     fn data_multi_reset(&self, value_id: u64, update: bool) -> PyResult<()> {
+        self.check_busy()?;
+        let update = self.batch_update(update);
         self.inner_data_multi(value_id)?
             .reset(update)
-            .map_err(|e| PyValueError::new_err(e))
+            .map_err(PyValueError::new_err)
     }
 
     // data multi take --------------------------------------------------
+    #[allow(clippy::too_many_arguments)]
     fn data_multi_take_set(
         &self,
         py: Python,
@@ -1003,12 +2058,13 @@ impl StateServerCore {
         update: bool,
         cache: bool,
     ) -> PyResult<()> {
+        self.check_busy()?;
+        let update = self.batch_update(update);
         let buffer_untyped = PyUntypedBuffer::get(data)
             .map_err(|_| PyValueError::new_err("Data must be a bytes-like object."))?;
 
         let data_value = self.inner_data_multi_take(value_id)?;
-        check_data_type(&buffer_untyped, data_value.data_type)
-            .map_err(|e| PyValueError::new_err(e))?;
+        check_data_type(&buffer_untyped, data_value.data_type).map_err(PyValueError::new_err)?;
 
         let data_holder = DataHolder {
             data: buffer_untyped.buf_ptr() as *const u8,
@@ -1020,7 +2076,7 @@ impl StateServerCore {
         py.detach(|| {
             data_value
                 .set(index, data_holder, blocking, update, cache)
-                .map_err(|e| PyValueError::new_err(e))
+                .map_err(PyValueError::new_err)
         })
     }
 
@@ -1030,19 +2086,24 @@ impl StateServerCore {
         index: u32,
         update: bool,
     ) -> PyResult<()> {
+        self.check_busy()?;
+        let update = self.batch_update(update);
         self.inner_data_multi_take(value_id)?
             .remove_index(index, update)
-            .map_err(|e| PyValueError::new_err(e))
+            .map_err(PyValueError::new_err)
     }
 
     fn data_multi_take_reset(&self, value_id: u64, update: bool) -> PyResult<()> {
+        self.check_busy()?;
+        let update = self.batch_update(update);
         self.inner_data_multi_take(value_id)?
             .reset(update)
-            .map_err(|e| PyValueError::new_err(e))
+            .map_err(PyValueError::new_err)
     }
 
     // add states -------------------------------------------------------
     // ------------------------------------------------------------------
+    #[pyo3(signature = (name, object_type, initial_value, queue, value_id=None))]
     fn add_value(
         &self,
         py: Python,
@@ -1050,6 +2111,7 @@ impl StateServerCore {
         object_type: &Bound<PyObjectClass>,
         initial_value: &Bound<PyAny>,
         queue: bool,
+        value_id: Option<u64>,
     ) -> PyResult<u64> {
         let object_type = object_type.borrow().object_type.clone_py(py);
         let type_id = object_type.get_core_type(py)?.get_hash();
@@ -1061,7 +2123,7 @@ impl StateServerCore {
         let value_id = self
             .server
             .write()
-            .add_value(&name, type_id, data, queue)
+            .add_value(&name, type_id, data, queue, value_id)
             .map_err(|e| {
                 pyo3::exceptions::PyValueError::new_err(format!("Failed to add Value: {}", e))
             })?;
@@ -1072,11 +2134,13 @@ impl StateServerCore {
         Ok(value_id)
     }
 
+    #[pyo3(signature = (name, object_type, value_id=None))]
     fn add_value_take(
         &self,
         py: Python,
         name: String,
         object_type: &Bound<PyObjectClass>,
+        value_id: Option<u64>,
     ) -> PyResult<u64> {
         let object_type = object_type.borrow().object_type.clone_py(py);
         let type_id = object_type.get_core_type(py)?.get_hash();
@@ -1084,7 +2148,7 @@ impl StateServerCore {
         let value_id = self
             .server
             .write()
-            .add_value_take(&name, type_id)
+            .add_value_take(&name, type_id, value_id)
             .map_err(|e| {
                 pyo3::exceptions::PyValueError::new_err(format!("Failed to add ValueTake: {}", e))
             })?;
@@ -1095,12 +2159,14 @@ impl StateServerCore {
         Ok(value_id)
     }
 
+    #[pyo3(signature = (name, object_type, initial_value, value_id=None))]
     fn add_static(
         &self,
         py: Python,
         name: String,
         object_type: &Bound<PyObjectClass>,
         initial_value: &Bound<PyAny>,
+        value_id: Option<u64>,
     ) -> PyResult<u64> {
         let object_type = object_type.borrow().object_type.clone_py(py);
         let type_id = object_type.get_hash(py)?;
@@ -1112,7 +2178,7 @@ impl StateServerCore {
         let value_id = self
             .server
             .write()
-            .add_static(&name, type_id, data)
+            .add_static(&name, type_id, data, value_id)
             .map_err(|e| PyValueError::new_err(format!("Failed to add Static: {}", e)))?;
 
         if let Some(types_map) = self.temps.write().as_mut() {
@@ -1121,12 +2187,14 @@ impl StateServerCore {
         Ok(value_id)
     }
 
+    #[pyo3(signature = (name, object_type, queue, value_id=None))]
     fn add_signal(
         &self,
         py: Python,
         name: String,
         object_type: &Bound<PyObjectClass>,
         queue: bool,
+        value_id: Option<u64>,
     ) -> PyResult<u64> {
         let object_type = object_type.borrow().object_type.clone_py(py);
         let type_id = object_type.get_hash(py)?;
@@ -1134,7 +2202,7 @@ impl StateServerCore {
         let value_id = self
             .server
             .write()
-            .add_signal(&name, type_id, queue)
+            .add_signal(&name, type_id, queue, value_id)
             .map_err(|e| PyValueError::new_err(format!("Failed to add Signal: {}", e)))?;
 
         if let Some(types_map) = self.temps.write().as_mut() {
@@ -1143,11 +2211,13 @@ impl StateServerCore {
         Ok(value_id)
     }
 
+    #[pyo3(signature = (name, object_type, value_id=None))]
     fn add_vec(
         &self,
         py: Python,
         name: String,
         object_type: &Bound<PyObjectClass>,
+        value_id: Option<u64>,
     ) -> PyResult<u64> {
         let object_type = object_type.borrow().object_type.clone_py(py);
         let type_id = object_type.get_hash(py)?;
@@ -1155,7 +2225,7 @@ impl StateServerCore {
         let value_id = self
             .server
             .write()
-            .add_vec(&name, type_id)
+            .add_vec(&name, type_id, value_id)
             .map_err(|e| PyValueError::new_err(format!("Failed to add ValueVec: {}", e)))?;
 
         if let Some(types_map) = self.temps.write().as_mut() {
@@ -1164,12 +2234,15 @@ impl StateServerCore {
         Ok(value_id)
     }
 
+    #[pyo3(signature = (name, key_type, value_type, ordered=false, value_id=None))]
     fn add_map(
         &self,
         py: Python,
         name: String,
         key_type: &Bound<PyObjectClass>,
         value_type: &Bound<PyObjectClass>,
+        ordered: bool,
+        value_id: Option<u64>,
     ) -> PyResult<u64> {
         let key_object_type = key_type.borrow().object_type.clone_py(py);
         let value_object_type = value_type.borrow().object_type.clone_py(py);
@@ -1180,7 +2253,7 @@ impl StateServerCore {
         let value_id = self
             .server
             .write()
-            .add_map(&name, type_id)
+            .add_map(&name, type_id, ordered, value_id)
             .map_err(|e| PyValueError::new_err(format!("Failed to add ValueMap: {}", e)))?;
 
         if let Some(types_map) = self.temps.write().as_mut() {
@@ -1189,47 +2262,62 @@ impl StateServerCore {
         Ok(value_id)
     }
 
-    fn add_image(&self, name: String) -> PyResult<u64> {
+    #[pyo3(signature = (name, premultiply_alpha=false, value_id=None))]
+    fn add_image(
+        &self,
+        name: String,
+        premultiply_alpha: bool,
+        value_id: Option<u64>,
+    ) -> PyResult<u64> {
         let value_id = self
             .server
             .write()
-            .add_image(&name)
+            .add_image(&name, premultiply_alpha, value_id)
             .map_err(|e| PyValueError::new_err(format!("Failed to add ValueImage: {}", e)))?;
         Ok(value_id)
     }
 
-    fn add_data(&self, name: String, data_type: u8) -> PyResult<u64> {
+    #[pyo3(signature = (name, data_type, value_id=None))]
+    fn add_data(&self, name: String, data_type: u8, value_id: Option<u64>) -> PyResult<u64> {
         let value_id = self
             .server
             .write()
-            .add_data(&name, data_type)
+            .add_data(&name, data_type, value_id)
             .map_err(|e| PyValueError::new_err(format!("Failed to add Data: {}", e)))?;
         Ok(value_id)
     }
 
-    fn add_data_multi(&self, name: String, data_type: u8) -> PyResult<u64> {
+    #[pyo3(signature = (name, data_type, value_id=None))]
+    fn add_data_multi(&self, name: String, data_type: u8, value_id: Option<u64>) -> PyResult<u64> {
         let value_id = self
             .server
             .write()
-            .add_data_multi(&name, data_type)
+            .add_data_multi(&name, data_type, value_id)
             .map_err(|e| PyValueError::new_err(format!("Failed to add DataMulti: {}", e)))?;
         Ok(value_id)
     }
 
-    fn add_data_take(&self, name: String, data_type: u8) -> PyResult<u64> {
+    #[pyo3(signature = (name, data_type, value_id=None))]
+    fn add_data_take(&self, name: String, data_type: u8, value_id: Option<u64>) -> PyResult<u64> {
         let value_id = self
             .server
             .write()
-            .add_data_take(&name, data_type)
+            .add_data_take(&name, data_type, value_id)
             .map_err(|e| PyValueError::new_err(format!("Failed to add DataTake: {}", e)))?;
         Ok(value_id)
     }
 
-    fn add_data_multi_take(&self, name: String, data_type: u8) -> PyResult<u64> {
+    #[pyo3(signature = (name, data_type, value_id=None))]
+    fn add_data_multi_take(
+        &self,
+        name: String,
+        data_type: u8,
+        value_id: Option<u64>,
+    ) -> PyResult<u64> {
         let value_id = self
             .server
             .write()
-            .add_data_multi_take(&name, data_type)
+            .add_data_multi_take(&name, data_type, value_id)
             .map_err(|e| PyValueError::new_err(format!("Failed to add DataMultiTake: {}", e)))?;
         Ok(value_id)
     }
@@ -24,6 +24,8 @@ pub(crate) enum PyObjectType {
     Map(Box<PyObjectType>, Box<PyObjectType>),
     Option(Box<PyObjectType>),
     Empty,
+    // union class, (variant name, variant class, field types) for each variant
+    Union(Py<PyAny>, Vec<(String, Py<PyAny>, Vec<PyObjectType>)>),
 }
 
 impl PyObjectType {
@@ -62,6 +64,35 @@ impl PyObjectType {
                 PyObjectType::Option(Box::new(inner_type.clone_py(py)))
             }
             PyObjectType::Empty => PyObjectType::Empty,
+            PyObjectType::Union(py_obj, variants) => {
+                let cloned_variants = variants
+                    .iter()
+                    .map(|(name, class, fields)| {
+                        let fields = fields.iter().map(|t| t.clone_py(py)).collect();
+                        (name.clone(), class.clone_ref(py), fields)
+                    })
+                    .collect();
+                PyObjectType::Union(py_obj.clone_ref(py), cloned_variants)
+            }
+        }
+    }
+
+    // size of the serialized value if it is the same for all values, bool is excluded because
+    // not every byte is a valid bool, empty values have no size to pack them by
+    pub(crate) fn fixed_size(&self) -> Option<usize> {
+        self.serialized_size().filter(|size| *size > 0)
+    }
+
+    fn serialized_size(&self) -> Option<usize> {
+        match self {
+            PyObjectType::U8 | PyObjectType::I8 => Some(1),
+            PyObjectType::F32 => Some(4),
+            PyObjectType::F64 => Some(8),
+            PyObjectType::Tuple(elements) | PyObjectType::Class(elements, _) => elements
+                .iter()
+                .try_fold(0, |size, element| Some(size + element.serialized_size()?)),
+            PyObjectType::List(len, element) => Some(*len as usize * element.serialized_size()?),
+            _ => None,
         }
     }
 
@@ -127,6 +158,19 @@ impl PyObjectType {
                 ObjectType::Option(Box::new(inner_type.get_core_type(py)?))
             }
             PyObjectType::Empty => ObjectType::Empty,
+            PyObjectType::Union(obj, variants) => {
+                let name = obj.bind(py).getattr("__name__")?.extract::<String>()?;
+                let mut core_variants = Vec::with_capacity(variants.len());
+                for (variant_name, _, fields) in variants {
+                    let mut core_fields = Vec::with_capacity(fields.len());
+                    for t in fields {
+                        core_fields.push(t.get_core_type(py)?);
+                    }
+                    core_variants.push((variant_name.clone(), core_fields));
+                }
+
+                ObjectType::Union(name, core_variants)
+            }
         };
 
         Ok(obj)
@@ -316,3 +360,29 @@ pub(crate) fn enu(enum_type: Bound<PyAny>) -> PyResult<PyObjectClass> {
         object_type: PyObjectType::Enum(obj),
     })
 }
+
+#[pyfunction]
+pub(crate) fn uni(
+    py: Python,
+    union_type: Py<PyAny>,
+    variants: Vec<(String, Py<PyAny>, Vec<Bound<PyObjectClass>>)>,
+) -> PyResult<PyObjectClass> {
+    let mut object_variants = Vec::with_capacity(variants.len());
+    for (name, class_type, fields) in variants {
+        let fields: Vec<PyObjectType> = fields
+            .iter()
+            .map(|t| t.borrow().object_type.clone_py(py))
+            .collect();
+
+        if fields.iter().any(|t| matches!(t, PyObjectType::Empty)) {
+            return Err(pyo3::exceptions::PyValueError::new_err(
+                "Union variant cannot contain Empty type",
+            ));
+        }
+        object_variants.push((name, class_type, fields));
+    }
+
+    Ok(PyObjectClass {
+        object_type: PyObjectType::Union(union_type, object_variants),
+    })
+}
@@ -9,6 +9,19 @@ use crate::image_transport::ImageHeader;
 // pub(crate) const VALUE_MAX_SIZE: usize = 1024 * 1024; // 1 MB
 pub(crate) const MSG_SIZE_THRESHOLD: usize = 1024 * 1024 * 10; // 10 MB
 pub(crate) const MAX_MSG_COUNT: usize = 10;
+// frames below 4 KB did not get smaller with LZ4 for image, float and random data, compressing
+// them costs 1-2 us per frame for nothing
+#[cfg(all(feature = "server", feature = "compression"))]
+pub(crate) const COMPRESSION_THRESHOLD: usize = 4096;
+// handshake option flags
+#[cfg(any(feature = "server", feature = "compression"))]
+pub(crate) const HANDSHAKE_COMPRESSION: u32 = 1;
+// the client only receives the states, its messages are ignored
+#[cfg(any(feature = "server", feature = "client"))]
+pub(crate) const HANDSHAKE_READ_ONLY: u32 = 2;
+// every frame starts with the CRC32 of the rest of it
+#[cfg(any(feature = "server", feature = "checksum"))]
+pub(crate) const HANDSHAKE_CHECKSUM: u32 = 4;
 
 pub(crate) struct StackVec<const N: usize>([u8; N], usize);
 
@@ -38,6 +51,7 @@ impl<const N: usize> FastVec<N> {
     }
 
     #[cfg(not(target_arch = "wasm32"))]
+    #[allow(clippy::wrong_self_convention)]
     #[inline]
     pub fn to_bytes(self) -> bytes::Bytes {
         match self {
@@ -63,6 +77,15 @@ impl<const N: usize> FastVec<N> {
         }
     }
 
+    #[cfg(feature = "client")]
+    #[inline]
+    pub fn as_slice(&self) -> &[u8] {
+        match self {
+            Self::Heap(vec) => vec,
+            Self::Stack(stack_vec) => stack_vec.as_ref(),
+        }
+    }
+
     #[cfg(feature = "server")]
     pub(crate) fn reserve_exact(&mut self, additional: usize) {
         match self {
@@ -106,12 +129,12 @@ impl<const N: usize> FastVec<N> {
             Self::Stack(stack_vec) => match data {
                 FastVec::Heap(dvec) => {
                     if stack_vec.1 + dvec.len() <= N {
-                        stack_vec.0[stack_vec.1..stack_vec.1 + dvec.len()].copy_from_slice(&dvec);
+                        stack_vec.0[stack_vec.1..stack_vec.1 + dvec.len()].copy_from_slice(dvec);
                         stack_vec.1 += dvec.len();
                     } else {
                         let mut new_vec = Vec::with_capacity(stack_vec.1 + dvec.len());
                         new_vec.extend_from_slice(&stack_vec.0[..stack_vec.1]);
-                        new_vec.extend_from_slice(&dvec);
+                        new_vec.extend_from_slice(dvec);
                         *self = Self::Heap(new_vec);
                     }
                 }
@@ -199,6 +222,13 @@ pub(crate) enum ServerHeader {
     ValueVec(u64, u32, bool, VecHeader, u32),
     ValueMap(u64, u32, bool, MapHeader, u32),
     Update(f32),
+    Types(u32),
+    Timestamp(u64),  // send time in microseconds since UNIX epoch
+    Compressed(u32), // rest of the frame is LZ4 block of the given uncompressed size
+    // server major and minor version, sent before closing a client with different major version,
+    // the variant index has to stay the same in all protocol versions
+    Rejected(u16, u16),
+    Checksum(u32), // CRC32 of the rest of the frame, always the first header
 }
 
 #[cfg(feature = "server")]
@@ -229,6 +259,7 @@ impl ServerHeader {
         Ok(data)
     }
 
+    #[cfg(feature = "python")]
     pub fn serialize_value_take<const N: usize>(
         id: u64,
         type_id: u32,
@@ -243,6 +274,50 @@ impl ServerHeader {
         data.extend_from_slice(value_data);
         Ok(data)
     }
+
+    pub fn serialize_types<const N: usize>(types_data: &[u8]) -> Result<FastVec<N>, ()> {
+        let header = ServerHeader::Types(types_data.len() as u32);
+        let mut data = FastVec::<N>::new_heap();
+        serialize_to_data(&header, &mut data)?;
+        data.extend_from_slice(types_data);
+        Ok(data)
+    }
+
+    #[cfg(feature = "timestamps")]
+    pub fn serialize_timestamp<const N: usize>() -> Result<FastVec<N>, ()> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_err(|_| ())?;
+        let header = ServerHeader::Timestamp(now.as_micros() as u64);
+        let mut data = FastVec::<N>::new();
+        serialize_to_data(&header, &mut data)?;
+        Ok(data)
+    }
+
+    // returns None if the frame does not get smaller
+    #[cfg(feature = "compression")]
+    pub fn compress_frame<const N: usize>(frame: &[u8]) -> Option<FastVec<N>> {
+        let compressed = lz4_flex::block::compress(frame);
+        let header = ServerHeader::Compressed(frame.len() as u32);
+        let mut data = FastVec::<N>::new_heap();
+        match serialize_to_data(&header, &mut data) {
+            Ok(()) if data.len() + compressed.len() < frame.len() => {
+                data.extend_from_slice(&compressed);
+                Some(data)
+            }
+            _ => None,
+        }
+    }
+
+    #[cfg(feature = "checksum")]
+    pub fn checksum_frame<const N: usize>(frame: &[u8]) -> FastVec<N> {
+        let header = ServerHeader::Checksum(crc32fast::hash(frame));
+        let mut data = FastVec::<N>::new_heap();
+        // serializing the u32 header into the vector can not fail
+        let _ = serialize_to_data(&header, &mut data);
+        data.extend_from_slice(frame);
+        data
+    }
 }
 
 #[cfg(feature = "client")]
@@ -265,17 +340,23 @@ pub(crate) enum ClientHeader {
     Signal(u64, u32, u32),
     Ack(u64),
     Message(u32),
-    Handshake(u16, Option<u64>, Option<String>),
+    Handshake(u16, Option<u64>, Option<String>, bool),
+    // with minor version and HANDSHAKE_* flags
+    HandshakeOptions(u16, u16, Option<u64>, Option<String>, bool, u32),
 }
 
 impl ClientHeader {
     #[cfg(feature = "client")]
     pub fn serialize_handshake(
         protocol: u16,
+        minor: u16,
         version: Option<u64>,
         token: Option<String>,
+        request_types: bool,
+        options: u32,
     ) -> FastVec<64> {
-        let header = ClientHeader::Handshake(protocol, version, token);
+        let header =
+            ClientHeader::HandshakeOptions(protocol, minor, version, token, request_types, options);
         let data = postcard::to_stdvec(&header).expect("Failed to serialize handshake");
         FastVec::Heap(data)
     }
@@ -329,7 +410,7 @@ impl<'a> Deserializer<'a> {
 
     pub(crate) fn get<T: for<'b> Deserialize<'b>>(&mut self) -> Result<T, String> {
         let (value, new_data) =
-            postcard::take_from_bytes::<T>(&self.data).map_err(|e| e.to_string())?;
+            postcard::take_from_bytes::<T>(self.data).map_err(|e| e.to_string())?;
         self.data = new_data;
         Ok(value)
     }
@@ -376,9 +457,9 @@ impl<'a, const N: usize> Flavor for FastVecRef<'a, N> {
 }
 
 #[inline]
-pub(crate) fn serialize_to_data<'a, T, const N: usize>(
+pub(crate) fn serialize_to_data<T, const N: usize>(
     value: &T,
-    data: &'a mut FastVec<N>,
+    data: &mut FastVec<N>,
 ) -> Result<(), ()>
 where
     T: Serialize,
@@ -1,14 +1,17 @@
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
 
-use parking_lot::{Mutex, RwLock, RwLockWriteGuard};
+#[cfg(feature = "python")]
+use parking_lot::Mutex;
+use parking_lot::{RwLock, RwLockWriteGuard};
 
 use crate::data_transport::{DataHeader, DataType, MultiDataHeader, TransportType};
 use crate::event::Event;
+#[cfg(feature = "python")]
 use crate::hashing::NoHashMap;
 use crate::serialization::{FastVec, MSG_SIZE_THRESHOLD};
 use crate::server::sender::MessageSender;
-use crate::server::server::{Acknowledge, SyncTrait};
+use crate::server::server::{Acknowledge, Snapshot, SyncTrait};
 
 pub(crate) struct DataHolder {
     pub data: *const u8,
@@ -21,12 +24,32 @@ unsafe impl Send for DataHolder {}
 unsafe impl Sync for DataHolder {}
 
 // Data --------------------------------------------------
+// the data are sent as the minimum and the maximum of every bucket of points, by the last item of
+// the point, so x and y of the point stay together
+#[derive(Clone, Copy)]
+pub(crate) struct Decimation {
+    pub(crate) max_points: usize,
+    pub(crate) point_size: usize, // items of one point, 2 for x and y pairs
+}
+
+impl Decimation {
+    // points in one bucket, so at most max points are sent
+    fn stride(&self, points: usize) -> usize {
+        points.div_ceil(self.max_points / 2).max(1)
+    }
+}
+
 pub(crate) struct Data {
     pub(crate) name: String,
     id: u64,
     pub(crate) data_type: DataType,
     item_size: usize,
     value: RwLock<(Vec<u8>, usize)>,
+    group: RwLock<Option<(u32, [f64; 2])>>,
+    max_len: RwLock<Option<usize>>,
+    // only decimated data are sent to the client, the full data are kept on the server, with
+    // the points in one bucket
+    decimation: RwLock<Option<(Decimation, usize)>>,
     sender: MessageSender,
     connected: Arc<AtomicBool>,
     event: Event,
@@ -46,22 +69,117 @@ impl Data {
             data_type,
             item_size: data_type.item_size(),
             value: RwLock::new((Vec::new(), 0)),
+            group: RwLock::new(None),
+            max_len: RwLock::new(None),
+            decimation: RwLock::new(None),
             sender,
             connected,
             event: Event::new(),
         })
     }
 
-    pub(crate) fn set(&self, data: DataHolder, update: bool) -> Result<(), String> {
+    // cap the number of items, the oldest items are dropped when new items are added
+    #[cfg(feature = "python")]
+    pub(crate) fn set_max_len(&self, max_len: Option<usize>, update: bool) -> Result<(), String> {
+        let _change = self.sender.change();
+        if max_len == Some(0) {
+            return Err("Invalid max length: max length must be greater than 0".to_string());
+        }
+        if let Some(max_len) = max_len {
+            self.check_points(max_len)?;
+        }
+        *self.max_len.write() = max_len;
+
+        let len = self.value.read().1;
+        match max_len {
+            Some(max_len) if len > max_len => self.remove(0, len - max_len, update),
+            _ => Ok(()),
+        }
+    }
+
+    #[cfg(feature = "python")]
+    pub(crate) fn get_group(&self) -> Option<(u32, [f64; 2])> {
+        *self.group.read()
+    }
+
+    #[cfg(feature = "python")]
+    pub(crate) fn set_group(
+        &self,
+        group: Option<(u32, [f64; 2])>,
+        update: bool,
+    ) -> Result<(), String> {
+        let _change = self.sender.change();
+        let mut w = self.group.write();
+        *w = group;
+
+        if self.connected.load(Ordering::Acquire) {
+            let header = DataHeader::Group(group, update);
+            let message = header
+                .serialize(self.id, false)
+                .map_err(|_| "Failed to serialize header".to_string())?;
+
+            self.sender.send(message);
+        }
+
+        Ok(())
+    }
+
+    // with decimation, the data are decimated before sending also for all the next changes
+    pub(crate) fn set(
+        &self,
+        data: DataHolder,
+        decimation: Option<Decimation>,
+        update: bool,
+    ) -> Result<(), String> {
+        let _change = self.sender.change();
         check_data_type(&data, self.data_type, self.item_size)?;
+        if let Some(decimation) = decimation {
+            if decimation.max_points < 2 {
+                return Err("Invalid max points: max points must be at least 2".to_string());
+            }
+            if decimation.point_size == 0 {
+                return Err("Invalid point size: point size must be greater than 0".to_string());
+            }
+            let point_size = decimation.point_size;
+            if !data.count.is_multiple_of(point_size) {
+                return Err(format!(
+                    "Data count {} is not a multiple of the point size {}",
+                    data.count, point_size
+                ));
+            }
+            if let Some(max_len) = *self.max_len.read()
+                && !max_len.is_multiple_of(point_size)
+            {
+                return Err(format!(
+                    "Max length {} is not a multiple of the point size {}",
+                    max_len, point_size
+                ));
+            }
+        }
+
+        // keep only the newest items which fit into the max length
+        let data = match *self.max_len.read() {
+            Some(max_len) if data.count > max_len => DataHolder {
+                data: unsafe { data.data.add((data.count - max_len) * self.item_size) },
+                count: max_len,
+                data_size: max_len * self.item_size,
+                data_type: data.data_type,
+            },
+            _ => data,
+        };
 
         let mut w = self.value.write();
-        let slice = unsafe { std::slice::from_raw_parts(data.data as *const u8, data.data_size) };
+        let slice = unsafe { std::slice::from_raw_parts(data.data, data.data_size) };
         w.0.clear();
         w.0.extend_from_slice(slice);
         w.1 = data.count;
+        *self.decimation.write() = decimation.map(|decimation| (decimation, 1));
 
         let r = RwLockWriteGuard::downgrade(w);
+        if decimation.is_some() {
+            return self.send_decimated(&r.0, None, update);
+        }
+
         if self.connected.load(Ordering::Acquire) {
             let count = data.count as u64;
             let transport_type = TransportType::Set(count);
@@ -88,16 +206,44 @@ impl Data {
         Ok(())
     }
 
-    pub(crate) fn add(&self, data: DataHolder, update: bool) -> Result<(), String> {
+    // returns the number of items after adding
+    pub(crate) fn add(&self, data: DataHolder, update: bool) -> Result<usize, String> {
+        let _change = self.sender.change();
         check_data_type(&data, self.data_type, self.item_size)?;
+        self.check_points(data.count)?;
+
+        let max_len = *self.max_len.read();
+        if let Some(max_len) = max_len
+            && data.count >= max_len
+        {
+            // the new items alone fill the whole buffer
+            let decimation = self.decimation.read().map(|(decimation, _)| decimation);
+            self.set(data, decimation, update)?;
+            return Ok(max_len);
+        }
 
         let slice = unsafe { std::slice::from_raw_parts(data.data, data.data_size) };
         let mut w = self.value.write();
+        let dropped = max_len.map_or(0, |max_len| (w.1 + data.count).saturating_sub(max_len));
+        if dropped > 0 {
+            w.0.drain(..dropped * self.item_size);
+            w.1 -= dropped;
+        }
         let original_len = w.0.len();
         w.0.extend_from_slice(slice);
         w.1 += data.count;
+        let len = w.1;
         let r = RwLockWriteGuard::downgrade(w);
 
+        if self.decimation.read().is_some() {
+            match dropped {
+                // the buckets of the dropped items move, all data are sent again
+                0 => self.send_decimated_tail(&r.0, original_len, update)?,
+                _ => self.send_decimated(&r.0, None, update)?,
+            }
+            return Ok(len);
+        }
+
         if self.connected.load(Ordering::Acquire) {
             let count = data.count as u64;
             let transport_type = TransportType::Add(count);
@@ -113,23 +259,31 @@ impl Data {
 
             self.event.wait_clear();
             if !self.connected.load(Ordering::Acquire) {
-                return Ok(());
+                return Ok(len);
             }
 
+            if dropped > 0 {
+                let message = DataHeader::Drain(0, dropped as u64, false)
+                    .serialize(self.id, false)
+                    .map_err(|_| "Failed to serialize header".to_string())?;
+                self.sender.send(message);
+            }
             for (message, single) in messages {
                 self.sender.send_set(message, single);
             }
         }
 
-        Ok(())
+        Ok(len)
     }
 
+    #[cfg(feature = "python")]
     pub(crate) fn replace(
         &self,
         data: DataHolder,
         index: usize,
         update: bool,
     ) -> Result<(), String> {
+        let _change = self.sender.change();
         check_data_type(&data, self.data_type, self.item_size)?;
 
         let slice = unsafe { std::slice::from_raw_parts(data.data, data.data_size) };
@@ -144,6 +298,10 @@ impl Data {
         w.0[byte_index..byte_index + data.data_size].copy_from_slice(slice);
         let r = RwLockWriteGuard::downgrade(w);
 
+        if self.decimation.read().is_some() {
+            return self.send_decimated(&r.0, None, update);
+        }
+
         if self.connected.load(Ordering::Acquire) {
             let count = data.count as u64;
             let transport_type = TransportType::Replace(index as u64, count);
@@ -170,10 +328,91 @@ impl Data {
         Ok(())
     }
 
+    // replace the removed items starting at index with the new items, the length can change
+    #[cfg(feature = "python")]
+    pub(crate) fn splice(
+        &self,
+        data: DataHolder,
+        index: usize,
+        removed: usize,
+        update: bool,
+    ) -> Result<(), String> {
+        let _change = self.sender.change();
+        check_data_type(&data, self.data_type, self.item_size)?;
+        self.check_points(index)?;
+        self.check_points(removed)?;
+        self.check_points(data.count)?;
+
+        let slice = unsafe { std::slice::from_raw_parts(data.data, data.data_size) };
+        let mut w = self.value.write();
+        if index + removed > w.1 {
+            return Err(format!(
+                "Splice range out of bounds: index {} + removed count {} exceeds current size {}",
+                index, removed, w.1
+            ));
+        }
+        let byte_index = index * self.item_size;
+        w.0.splice(
+            byte_index..byte_index + removed * self.item_size,
+            slice.iter().copied(),
+        );
+        w.1 = w.1 - removed + data.count;
+
+        // keep the newest items which fit into the max length
+        let dropped = self
+            .max_len
+            .read()
+            .map_or(0, |max_len| w.1.saturating_sub(max_len));
+        if dropped > 0 {
+            w.0.drain(..dropped * self.item_size);
+            w.1 -= dropped;
+        }
+        let r = RwLockWriteGuard::downgrade(w);
+
+        if self.decimation.read().is_some() {
+            return self.send_decimated(&r.0, None, update);
+        }
+
+        if self.connected.load(Ordering::Acquire) {
+            let count = data.count as u64;
+            let transport_type = TransportType::Splice(index as u64, removed as u64, count);
+            let messages = pack_data(
+                self.id,
+                slice,
+                transport_type,
+                count,
+                self.data_type,
+                None,
+                update && dropped == 0,
+            )?;
+
+            self.event.wait_clear();
+            if !self.connected.load(Ordering::Acquire) {
+                return Ok(());
+            }
+
+            for (message, single) in messages {
+                self.sender.send_set(message, single);
+            }
+            if dropped > 0 {
+                let message = DataHeader::Drain(0, dropped as u64, update)
+                    .serialize(self.id, false)
+                    .map_err(|_| "Failed to serialize header".to_string())?;
+                self.sender.send(message);
+            }
+        }
+
+        Ok(())
+    }
+
+    #[cfg(feature = "python")]
     pub(crate) fn remove(&self, index: usize, size: usize, update: bool) -> Result<(), String> {
+        let _change = self.sender.change();
         if size == 0 {
             return Err("Invalid remove size: size must be greater than 0".to_string());
         }
+        self.check_points(index)?;
+        self.check_points(size)?;
 
         let mut w = self.value.write();
         if index + size > w.1 {
@@ -186,7 +425,11 @@ impl Data {
         let byte_index = index * self.item_size;
         w.0.drain(byte_index..byte_index + size * self.item_size);
         w.1 -= size;
-        let _r = RwLockWriteGuard::downgrade(w);
+        let r = RwLockWriteGuard::downgrade(w);
+
+        if self.decimation.read().is_some() {
+            return self.send_decimated(&r.0, None, update);
+        }
 
         if self.connected.load(Ordering::Acquire) {
             let header = DataHeader::Drain(index as u64, size as u64, update);
@@ -206,6 +449,7 @@ impl Data {
     }
 
     pub(crate) fn clear(&self, update: bool) -> Result<(), String> {
+        let _change = self.sender.change();
         let mut w = self.value.write();
         w.0.clear();
         w.1 = 0;
@@ -226,6 +470,103 @@ impl Data {
         let value = self.value.read();
         f(&value.0)
     }
+
+    // decimated data keep the x and y pairs, the count of items has to keep them too
+    fn check_points(&self, count: usize) -> Result<(), String> {
+        match *self.decimation.read() {
+            Some((decimation, _)) if !count.is_multiple_of(decimation.point_size) => Err(format!(
+                "Count {} is not a multiple of the point size {}",
+                count, decimation.point_size
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    // all data are sent again, with the stride fitted to the count of points if not given
+    fn send_decimated(
+        &self,
+        data: &[u8],
+        stride: Option<usize>,
+        update: bool,
+    ) -> Result<(), String> {
+        let mut w = self.decimation.write();
+        let Some((decimation, ref mut current)) = *w else {
+            return Ok(());
+        };
+        let points = data.len() / (decimation.point_size * self.item_size);
+        let stride = stride.unwrap_or_else(|| decimation.stride(points));
+        *current = stride;
+        drop(w);
+
+        if !self.connected.load(Ordering::Acquire) {
+            return Ok(());
+        }
+
+        let messages = pack_decimated(
+            self.id,
+            data,
+            self.data_type,
+            decimation.point_size,
+            stride,
+            update,
+        )?;
+        self.send_messages(messages)
+    }
+
+    // only the buckets from the last one before adding are sent, if the stride still fits
+    fn send_decimated_tail(&self, data: &[u8], old_len: usize, update: bool) -> Result<(), String> {
+        let Some((decimation, stride)) = *self.decimation.read() else {
+            return Ok(());
+        };
+        let point_bytes = decimation.point_size * self.item_size;
+        let points = data.len() / point_bytes;
+        if points.div_ceil(stride) > decimation.max_points / 2 {
+            // the stride at least doubles, so all data are not sent again with every addition
+            let stride = decimation.stride(points).max(stride * 2);
+            return self.send_decimated(data, Some(stride), update);
+        }
+
+        if !self.connected.load(Ordering::Acquire) {
+            return Ok(());
+        }
+
+        let (start, removed, tail) = decimate_tail(
+            data,
+            old_len / point_bytes,
+            self.data_type,
+            decimation.point_size,
+            stride,
+        );
+        let point_size = decimation.point_size as u64;
+        let count = (tail.len() / self.item_size) as u64;
+        let transport_type = TransportType::Splice(
+            start as u64 * point_size,
+            removed as u64 * point_size,
+            count,
+        );
+        let messages = pack_data(
+            self.id,
+            &tail,
+            transport_type,
+            count,
+            self.data_type,
+            None,
+            update,
+        )?;
+        self.send_messages(messages)
+    }
+
+    fn send_messages(&self, messages: Vec<(FastVec<32>, bool)>) -> Result<(), String> {
+        self.event.wait_clear();
+        if !self.connected.load(Ordering::Acquire) {
+            return Ok(());
+        }
+
+        for (message, single) in messages {
+            self.sender.send_set(message, single);
+        }
+        Ok(())
+    }
 }
 
 impl Acknowledge for Data {
@@ -234,52 +575,88 @@ impl Acknowledge for Data {
     }
 }
 
-impl SyncTrait for Data {
-    fn sync(&self) -> Result<(), ()> {
-        let r = self.value.read();
+impl Data {
+    // the group and the clear or all the data
+    fn pack_all(&self, data: &[u8]) -> Result<Vec<(FastVec<32>, bool)>, ()> {
+        let mut messages = Vec::new();
+        if let Some(group) = *self.group.read() {
+            let header = DataHeader::Group(Some(group), false);
+            messages.push((header.serialize(self.id, false).map_err(|_| ())?, false));
+        }
 
-        let count = r.0.len() / self.data_type.item_size();
+        let count = data.len() / self.data_type.item_size();
         if count == 0 {
             let header = DataHeader::Clear(false);
-            let message = header.serialize(self.id, false).map_err(|_| ())?;
-            self.sender.send(message);
-            self.event.set();
-        } else {
-            let transport_type = TransportType::Set(count as u64);
-            let messages = pack_data(
+            messages.push((header.serialize(self.id, false).map_err(|_| ())?, false));
+            return Ok(messages);
+        }
+
+        let data_messages = match *self.decimation.read() {
+            Some((decimation, stride)) => pack_decimated(
                 self.id,
-                &r.0,
-                transport_type,
+                data,
+                self.data_type,
+                decimation.point_size,
+                stride,
+                false,
+            ),
+            None => pack_data(
+                self.id,
+                data,
+                TransportType::Set(count as u64),
                 count as u64,
                 self.data_type,
                 None,
                 false,
-            )
-            .map_err(|_| ())?;
+            ),
+        }
+        .map_err(|_| ())?;
+        messages.extend(data_messages);
+        Ok(messages)
+    }
+}
 
-            self.event.clear();
-            for (message, single) in messages {
-                self.sender.send_set(message, single);
-            }
+impl SyncTrait for Data {
+    fn sync(&self) -> Result<(), ()> {
+        let r = self.value.read();
+        let messages = self.pack_all(&r.0)?;
+
+        match r.0.is_empty() {
+            true => self.event.set(),
+            false => self.event.clear(),
+        }
+        for (message, single) in messages {
+            self.sender.send_set(message, single);
         }
 
         Ok(())
     }
+
+    fn snapshot(&self, snapshot: &mut Snapshot) -> Result<(), ()> {
+        let r = self.value.read();
+        for (message, single) in self.pack_all(&r.0)? {
+            snapshot.push(message, single);
+        }
+        Ok(())
+    }
 }
 
 // DataMulti --------------------------------------------------
+#[cfg(feature = "python")]
 pub(crate) struct DataMulti {
     pub(crate) name: String,
     id: u64,
     pub(crate) data_type: DataType,
     item_size: usize,
     values: RwLock<NoHashMap<u32, (Vec<u8>, usize)>>,
+    max_lens: RwLock<NoHashMap<u32, usize>>,
     sync_counter: Mutex<usize>,
     sender: MessageSender,
     connected: Arc<AtomicBool>,
     event: Event,
 }
 
+#[cfg(feature = "python")]
 impl DataMulti {
     pub(crate) fn new(
         name: String,
@@ -294,6 +671,7 @@ impl DataMulti {
             data_type,
             item_size: data_type.item_size(),
             values: RwLock::new(NoHashMap::default()),
+            max_lens: RwLock::new(NoHashMap::default()),
             sync_counter: Mutex::new(0),
             sender,
             connected,
@@ -301,15 +679,39 @@ impl DataMulti {
         })
     }
 
+    // cap the number of items of the index, the oldest items are dropped when new items are added
+    pub(crate) fn set_max_len(
+        &self,
+        index: u32,
+        max_len: Option<usize>,
+        update: bool,
+    ) -> Result<(), String> {
+        let _change = self.sender.change();
+        match max_len {
+            Some(0) => {
+                return Err("Invalid max length: max length must be greater than 0".to_string());
+            }
+            Some(max_len) => self.max_lens.write().insert(index, max_len),
+            None => self.max_lens.write().remove(&index),
+        };
+
+        let len = self.values.read().get(&index).map_or(0, |(_, len)| *len);
+        match max_len {
+            Some(max_len) if len > max_len => self.remove(index, 0, len - max_len, update),
+            _ => Ok(()),
+        }
+    }
+
     pub(crate) fn remove_index(&self, index: u32, update: bool) -> Result<(), String> {
+        let _change = self.sender.change();
         let mut w = self.values.write();
-        if let Some(_) = w.remove(&index) {
+        if w.remove(&index).is_some() {
             let _r = RwLockWriteGuard::downgrade(w);
             if self.connected.load(std::sync::atomic::Ordering::Relaxed) {
                 let header = MultiDataHeader::Remove(index, update);
                 let message = header
                     .serialize(self.id)
-                    .map_err(|_| format!("Failed to serialize remove index header"))?;
+                    .map_err(|_| "Failed to serialize remove index header".to_string())?;
                 self.sender.send(message);
             }
         }
@@ -318,6 +720,7 @@ impl DataMulti {
     }
 
     pub(crate) fn reset(&self, update: bool) -> Result<(), String> {
+        let _change = self.sender.change();
         let mut w = self.values.write();
         if !w.is_empty() {
             w.clear();
@@ -327,7 +730,7 @@ impl DataMulti {
                 let header = MultiDataHeader::Reset(update);
                 let message = header
                     .serialize(self.id)
-                    .map_err(|_| format!("Failed to serialize reset header"))?;
+                    .map_err(|_| "Failed to serialize reset header".to_string())?;
                 self.sender.send(message);
             }
         }
@@ -336,8 +739,20 @@ impl DataMulti {
     }
 
     pub(crate) fn set(&self, index: u32, data: DataHolder, update: bool) -> Result<(), String> {
+        let _change = self.sender.change();
         check_data_type(&data, self.data_type, self.item_size)?;
 
+        // keep only the newest items which fit into the max length
+        let data = match self.max_lens.read().get(&index) {
+            Some(&max_len) if data.count > max_len => DataHolder {
+                data: unsafe { data.data.add((data.count - max_len) * self.item_size) },
+                count: max_len,
+                data_size: max_len * self.item_size,
+                data_type: data.data_type,
+            },
+            _ => data,
+        };
+
         let mut w = self.values.write();
         match w.get_mut(&index) {
             Some((vec, size)) => {
@@ -348,6 +763,7 @@ impl DataMulti {
             }
             None => {
                 let mut vec = Vec::with_capacity(data.data_size);
+                #[allow(clippy::uninit_vec)]
                 unsafe {
                     vec.set_len(data.data_size);
                     std::ptr::copy_nonoverlapping(data.data, vec.as_mut_ptr(), data.data_size);
@@ -387,13 +803,28 @@ impl DataMulti {
     }
 
     pub(crate) fn add(&self, index: u32, data: DataHolder, update: bool) -> Result<(), String> {
+        let _change = self.sender.change();
         check_data_type(&data, self.data_type, self.item_size)?;
 
+        let max_len = self.max_lens.read().get(&index).copied();
+        if let Some(max_len) = max_len
+            && data.count >= max_len
+            && self.values.read().contains_key(&index)
+        {
+            // the new items alone fill the whole buffer
+            return self.set(index, data, update);
+        }
+
         let slice = unsafe { std::slice::from_raw_parts(data.data, data.data_size) };
         let mut w = self.values.write();
         let entry = w
             .get_mut(&index)
             .ok_or_else(|| format!("DataMulti index {} does not exist", index))?;
+        let dropped = max_len.map_or(0, |max_len| (entry.1 + data.count).saturating_sub(max_len));
+        if dropped > 0 {
+            entry.0.drain(..dropped * self.item_size);
+            entry.1 -= dropped;
+        }
         let original_len = entry.0.len();
         entry.0.extend_from_slice(slice);
         entry.1 += data.count;
@@ -420,6 +851,12 @@ impl DataMulti {
                 return Ok(());
             }
 
+            if dropped > 0 {
+                let header = DataHeader::Drain(0, dropped as u64, false);
+                let message = MultiDataHeader::serialize_modify(self.id, index, header)
+                    .map_err(|_| "Failed to serialize header".to_string())?;
+                self.sender.send(message);
+            }
             for (message, single) in messages {
                 self.sender.send_set(message, single);
             }
@@ -435,6 +872,7 @@ impl DataMulti {
         data: DataHolder,
         update: bool,
     ) -> Result<(), String> {
+        let _change = self.sender.change();
         check_data_type(&data, self.data_type, self.item_size)?;
 
         let slice = unsafe { std::slice::from_raw_parts(data.data, data.data_size) };
@@ -481,6 +919,79 @@ impl DataMulti {
         Ok(())
     }
 
+    pub(crate) fn splice(
+        &self,
+        index: u32,
+        data_index: usize,
+        removed: usize,
+        data: DataHolder,
+        update: bool,
+    ) -> Result<(), String> {
+        let _change = self.sender.change();
+        check_data_type(&data, self.data_type, self.item_size)?;
+
+        let slice = unsafe { std::slice::from_raw_parts(data.data, data.data_size) };
+        let mut w = self.values.write();
+        let value = w
+            .get_mut(&index)
+            .ok_or_else(|| format!("DataMulti index {} does not exist", index))?;
+        if data_index + removed > value.1 {
+            return Err(format!(
+                "Splice range out of bounds for DataMulti index {}: data_index {} + removed count {} exceeds current size {}",
+                index, data_index, removed, value.1
+            ));
+        }
+        let byte_index = data_index * self.item_size;
+        value.0.splice(
+            byte_index..byte_index + removed * self.item_size,
+            slice.iter().copied(),
+        );
+        value.1 = value.1 - removed + data.count;
+
+        // keep the newest items which fit into the max length
+        let dropped = self
+            .max_lens
+            .read()
+            .get(&index)
+            .map_or(0, |max_len| value.1.saturating_sub(*max_len));
+        if dropped > 0 {
+            value.0.drain(..dropped * self.item_size);
+            value.1 -= dropped;
+        }
+        let _r = RwLockWriteGuard::downgrade(w);
+
+        if self.connected.load(Ordering::Acquire) {
+            let count = data.count as u64;
+            let transport_type = TransportType::Splice(data_index as u64, removed as u64, count);
+            let messages = pack_data(
+                self.id,
+                slice,
+                transport_type,
+                count,
+                self.data_type,
+                Some(index),
+                update && dropped == 0,
+            )?;
+
+            self.event.wait_clear();
+            if !self.connected.load(Ordering::Acquire) {
+                return Ok(());
+            }
+
+            for (message, single) in messages {
+                self.sender.send_set(message, single);
+            }
+            if dropped > 0 {
+                let header = DataHeader::Drain(0, dropped as u64, update);
+                let message = MultiDataHeader::serialize_modify(self.id, index, header)
+                    .map_err(|_| "Failed to serialize header".to_string())?;
+                self.sender.send(message);
+            }
+        }
+
+        Ok(())
+    }
+
     pub(crate) fn remove(
         &self,
         index: u32,
@@ -488,6 +999,7 @@ impl DataMulti {
         size: usize,
         update: bool,
     ) -> Result<(), String> {
+        let _change = self.sender.change();
         if size == 0 {
             return Err("Invalid remove size: size must be greater than 0".to_string());
         }
@@ -526,6 +1038,7 @@ impl DataMulti {
     }
 
     pub(crate) fn clear(&self, index: u32, update: bool) -> Result<(), String> {
+        let _change = self.sender.change();
         let mut w = self.values.write();
         let value = w
             .get_mut(&index)
@@ -555,6 +1068,7 @@ impl DataMulti {
     }
 }
 
+#[cfg(feature = "python")]
 impl Acknowledge for DataMulti {
     fn acknowledge(&self) {
         let mut w = self.sync_counter.lock();
@@ -569,6 +1083,7 @@ impl Acknowledge for DataMulti {
     }
 }
 
+#[cfg(feature = "python")]
 impl SyncTrait for DataMulti {
     fn sync(&self) -> Result<(), ()> {
         let r = self.values.read();
@@ -605,6 +1120,31 @@ impl SyncTrait for DataMulti {
 
         Ok(())
     }
+
+    fn snapshot(&self, snapshot: &mut Snapshot) -> Result<(), ()> {
+        let r = self.values.read();
+        if r.is_empty() {
+            snapshot.push(MultiDataHeader::Reset(false).serialize(self.id)?, false);
+        }
+        for (index, (data, count)) in r.iter() {
+            let transport_type = TransportType::Set(*count as u64);
+            let messages = pack_data(
+                self.id,
+                data,
+                transport_type,
+                *count as u64,
+                self.data_type,
+                Some(*index),
+                false,
+            )
+            .map_err(|_| ())?;
+
+            for (message, single) in messages {
+                snapshot.push(message, single);
+            }
+        }
+        Ok(())
+    }
 }
 
 // functions ------------------------------------------------
@@ -620,7 +1160,7 @@ pub(crate) fn check_data_type(
         ));
     }
 
-    if data.data_size % item_size != 0 {
+    if !data.data_size.is_multiple_of(item_size) {
         return Err(format!(
             "Data size must be a multiple of element size: expected multiple of {}, got {}",
             item_size, data.data_size
@@ -630,6 +1170,106 @@ pub(crate) fn check_data_type(
     Ok(())
 }
 
+fn pack_decimated(
+    id: u64,
+    data: &[u8],
+    data_type: DataType,
+    point_size: usize,
+    stride: usize,
+    update: bool,
+) -> Result<Vec<(FastVec<32>, bool)>, String> {
+    let decimated = decimate(data, data_type, point_size, stride);
+    let count = (decimated.len() / data_type.item_size()) as u64;
+    pack_data(
+        id,
+        &decimated,
+        TransportType::Set(count),
+        count,
+        data_type,
+        None,
+        update,
+    )
+}
+
+// keep the points with the minimum and the maximum of every bucket in the original order, so the
+// peaks stay visible, full buckets always give two points so they keep the same spacing
+fn decimate(data: &[u8], data_type: DataType, point_size: usize, stride: usize) -> Vec<u8> {
+    match data_type {
+        DataType::U8 => decimate_points(data, point_size, stride, u8::from_ne_bytes),
+        DataType::U16 => decimate_points(data, point_size, stride, u16::from_ne_bytes),
+        DataType::U32 => decimate_points(data, point_size, stride, u32::from_ne_bytes),
+        DataType::U64 => decimate_points(data, point_size, stride, u64::from_ne_bytes),
+        DataType::I8 => decimate_points(data, point_size, stride, i8::from_ne_bytes),
+        DataType::I16 => decimate_points(data, point_size, stride, i16::from_ne_bytes),
+        DataType::I32 => decimate_points(data, point_size, stride, i32::from_ne_bytes),
+        DataType::I64 => decimate_points(data, point_size, stride, i64::from_ne_bytes),
+        DataType::F32 => decimate_points(data, point_size, stride, f32::from_ne_bytes),
+        DataType::F64 => decimate_points(data, point_size, stride, f64::from_ne_bytes),
+    }
+}
+
+// the decimated points from the last bucket before adding, with the index of the first decimated point
+// and the count of replaced decimated points, full buckets are two points or one with the stride of one
+fn decimate_tail(
+    data: &[u8],
+    old_points: usize,
+    data_type: DataType,
+    point_size: usize,
+    stride: usize,
+) -> (usize, usize, Vec<u8>) {
+    let first = old_points / stride;
+    let start = first * stride.min(2);
+    let removed = (old_points - first * stride).min(2);
+    let point_bytes = point_size * data_type.item_size();
+    let tail = decimate(
+        &data[first * stride * point_bytes..],
+        data_type,
+        point_size,
+        stride,
+    );
+    (start, removed, tail)
+}
+
+fn decimate_points<T: PartialOrd, const N: usize>(
+    data: &[u8],
+    point_size: usize,
+    stride: usize,
+    from_bytes: fn([u8; N]) -> T,
+) -> Vec<u8> {
+    let point_bytes = point_size * N;
+    let points = data.len() / point_bytes;
+    // points are compared by the last item, y of the x and y pair
+    let item = |i: usize| {
+        let at = (i + 1) * point_bytes - N;
+        from_bytes(data[at..at + N].try_into().unwrap())
+    };
+
+    let mut result = Vec::with_capacity(points.div_ceil(stride) * 2 * point_bytes);
+    for start in (0..points).step_by(stride) {
+        let end = (start + stride).min(points);
+
+        let (mut min, mut max) = (start, start);
+        for i in start + 1..end {
+            let value = item(i);
+            if value < item(min) {
+                min = i;
+            } else if value > item(max) {
+                max = i;
+            }
+        }
+        if min == max {
+            max = end - 1;
+        }
+
+        let (first, second) = (min.min(max), min.max(max));
+        result.extend_from_slice(&data[first * point_bytes..(first + 1) * point_bytes]);
+        if second != first {
+            result.extend_from_slice(&data[second * point_bytes..(second + 1) * point_bytes]);
+        }
+    }
+    result
+}
+
 pub(crate) fn pack_data(
     id: u64,
     data: &[u8],
@@ -639,6 +1279,12 @@ pub(crate) fn pack_data(
     multi_data: Option<u32>,
     update: bool,
 ) -> Result<Vec<(FastVec<32>, bool)>, String> {
+    #[cfg(target_endian = "big")]
+    let data = &{
+        let mut data = data.to_vec();
+        crate::data_transport::swap_items(&mut data, data_type.item_size());
+        data
+    };
     let mut messages = Vec::with_capacity(1);
 
     if data.len() <= MSG_SIZE_THRESHOLD {
@@ -649,7 +1295,7 @@ pub(crate) fn pack_data(
         }
         .map_err(|_| "Failed to serialize header".to_string())?;
         message.reserve_exact(data.len());
-        message.extend_from_slice(&data);
+        message.extend_from_slice(data);
         messages.push((message, true));
     } else {
         let element_size = data_type.item_size();
@@ -695,3 +1341,83 @@ pub(crate) fn pack_data(
 
     Ok(messages)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_bytes(values: &[f64]) -> Vec<u8> {
+        values.iter().flat_map(|v| v.to_ne_bytes()).collect()
+    }
+
+    fn from_bytes(data: &[u8]) -> Vec<f64> {
+        data.chunks_exact(8)
+            .map(|v| f64::from_ne_bytes(v.try_into().unwrap()))
+            .collect()
+    }
+
+    // x and y pairs, y is a saw with peaks at different x in every bucket
+    fn points(count: usize) -> Vec<f64> {
+        (0..count)
+            .flat_map(|i| [i as f64, ((i * 7) % 13) as f64 - 6.0])
+            .collect()
+    }
+
+    #[test]
+    fn test_decimate_keeps_xy_pairs() {
+        let values = points(1000);
+        let decimated = from_bytes(&decimate(&to_bytes(&values), DataType::F64, 2, 10));
+
+        assert_eq!(decimated.len(), 100 * 2 * 2);
+        for pair in decimated.chunks_exact(2) {
+            let i = pair[0] as usize;
+            assert_eq!(pair, &values[i * 2..i * 2 + 2]);
+        }
+
+        // the extremes of every bucket by y, in the original order
+        for (bucket, pairs) in decimated.chunks_exact(4).enumerate() {
+            let ys = &values[bucket * 20..bucket * 20 + 20];
+            let ys: Vec<f64> = ys.chunks_exact(2).map(|p| p[1]).collect();
+            let min = ys.iter().cloned().fold(f64::INFINITY, f64::min);
+            let max = ys.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+            assert!(pairs[0] < pairs[2]);
+            let mut found = [pairs[1], pairs[3]];
+            found.sort_by(f64::total_cmp);
+            assert_eq!(found, [min, max]);
+        }
+    }
+
+    #[test]
+    fn test_decimate_full_buckets_give_two_points() {
+        let values = vec![1.0; 95];
+        let decimated = from_bytes(&decimate(&to_bytes(&values), DataType::F64, 1, 10));
+
+        // 9 full buckets and the last one with 5 items
+        assert_eq!(decimated.len(), 10 * 2);
+
+        let decimated = from_bytes(&decimate(&to_bytes(&[3.0; 91]), DataType::F64, 1, 10));
+        assert_eq!(decimated.len(), 9 * 2 + 1);
+
+        let decimated = from_bytes(&decimate(&to_bytes(&[3.0, 4.0]), DataType::F64, 1, 1));
+        assert_eq!(decimated, vec![3.0, 4.0]);
+    }
+
+    #[test]
+    fn test_decimate_tail_matches_full() {
+        for (old, new, stride) in [
+            (1000, 1100, 10),
+            (995, 1010, 10),
+            (1000, 1001, 1),
+            (3, 4, 4),
+        ] {
+            let values = to_bytes(&points(new));
+            let mut client = from_bytes(&decimate(&values[..old * 16], DataType::F64, 2, stride));
+
+            let (start, removed, tail) = decimate_tail(&values, old, DataType::F64, 2, stride);
+            client.splice(start * 2..(start + removed) * 2, from_bytes(&tail));
+
+            let full = from_bytes(&decimate(&values, DataType::F64, 2, stride));
+            assert_eq!(client, full);
+        }
+    }
+}
@@ -9,7 +9,7 @@ use crate::hashing::NoHashMap;
 use crate::serialization::{FastVec, MSG_SIZE_THRESHOLD};
 use crate::server::data_server::{DataHolder, check_data_type};
 use crate::server::sender::MessageSender;
-use crate::server::server::{Acknowledge, SyncTrait};
+use crate::server::server::{Acknowledge, Snapshot, SyncTrait};
 
 // DataTake --------------------------------------------------
 pub(crate) struct DataTake {
@@ -52,7 +52,7 @@ impl DataTake {
     ) -> Result<(), String> {
         check_data_type(&data, self.data_type, self.item_size)?;
 
-        let slice = unsafe { std::slice::from_raw_parts(data.data as *const u8, data.data_size) };
+        let slice = unsafe { std::slice::from_raw_parts(data.data, data.data_size) };
 
         if self.connected.load(Ordering::Acquire) {
             let messages = pack_data_take(
@@ -71,7 +71,7 @@ impl DataTake {
 
             let mut guard = self.lock.lock();
             if cache {
-                *guard = Some((slice.to_vec(), data.count as usize));
+                *guard = Some((slice.to_vec(), data.count));
             } else {
                 *guard = None;
             }
@@ -86,7 +86,7 @@ impl DataTake {
         } else {
             let mut guard = self.lock.lock();
             if cache {
-                *guard = Some((slice.to_vec(), data.count as usize));
+                *guard = Some((slice.to_vec(), data.count));
             } else {
                 *guard = None;
             }
@@ -120,6 +120,11 @@ impl SyncTrait for DataTake {
 
         Ok(())
     }
+
+    // the taken data are only for the client
+    fn snapshot(&self, _snapshot: &mut Snapshot) -> Result<(), ()> {
+        Ok(())
+    }
 }
 
 // DataMultiTake --------------------------------------------------
@@ -164,7 +169,7 @@ impl DataMultiTake {
     ) -> Result<(), String> {
         check_data_type(&data, self.data_type, self.item_size)?;
 
-        let slice = unsafe { std::slice::from_raw_parts(data.data as *const u8, data.data_size) };
+        let slice = unsafe { std::slice::from_raw_parts(data.data, data.data_size) };
 
         if self.connected.load(Ordering::Acquire) {
             let messages = pack_data_multi_take(
@@ -275,6 +280,10 @@ impl SyncTrait for DataMultiTake {
 
         Ok(())
     }
+
+    fn snapshot(&self, _snapshot: &mut Snapshot) -> Result<(), ()> {
+        Ok(())
+    }
 }
 
 fn pack_data_take(
@@ -285,6 +294,12 @@ fn pack_data_take(
     blocking: bool,
     update: bool,
 ) -> Result<Vec<(FastVec<32>, bool)>, String> {
+    #[cfg(target_endian = "big")]
+    let data = &{
+        let mut data = data.to_vec();
+        crate::data_transport::swap_items(&mut data, data_type.item_size());
+        data
+    };
     let mut messages = Vec::with_capacity(1);
 
     if data.len() <= MSG_SIZE_THRESHOLD {
@@ -298,7 +313,7 @@ fn pack_data_take(
             .serialize(id, blocking, true)
             .map_err(|_| "Failed to serialize DataTake header".to_string())?;
         message.reserve_exact(data.len());
-        message.extend_from_slice(&data);
+        message.extend_from_slice(data);
         messages.push((message, true));
     } else {
         let element_size = data_type.item_size();
@@ -354,6 +369,12 @@ fn pack_data_multi_take(
     blocking: bool,
     update: bool,
 ) -> Result<Vec<(FastVec<32>, bool)>, String> {
+    #[cfg(target_endian = "big")]
+    let data = &{
+        let mut data = data.to_vec();
+        crate::data_transport::swap_items(&mut data, data_type.item_size());
+        data
+    };
     let mut messages = Vec::with_capacity(1);
 
     if data.len() <= MSG_SIZE_THRESHOLD {
@@ -361,7 +382,7 @@ fn pack_data_multi_take(
         let mut message = DataMultiTakeHeader::serialize_modify(id, index, header, blocking)
             .map_err(|_| "Failed to serialize DataMultiTake header".to_string())?;
         message.reserve_exact(data.len());
-        message.extend_from_slice(&data);
+        message.extend_from_slice(data);
         messages.push((message, true));
     } else {
         let element_size = data_type.item_size();
@@ -0,0 +1,590 @@
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::net::SocketAddrV4;
+use std::sync::Arc;
+
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+
+use crate::data_transport::GetDataType;
+use crate::image_transport::ImageType;
+use crate::serialization::{deserialize_value, serialize};
+use crate::server::data_server::{Data, DataHolder};
+use crate::server::image_server::{Image, ImageData};
+use crate::server::server::Server;
+use crate::server::signals::{self, SignalsManager};
+use crate::server::values_server::{Value, ValueStatic};
+use crate::transport::{ObjectType, Transportable};
+
+fn to_bytes<T: Serialize>(value: &T) -> Result<Bytes, String> {
+    serialize::<T, 32>(value)
+        .map(|data| data.to_bytes())
+        .map_err(|_| "Failed to serialize value".to_string())
+}
+
+fn from_bytes<T: for<'a> Deserialize<'a>>(data: &[u8]) -> Result<T, String> {
+    deserialize_value::<T>(data)
+        .map(|(value, _)| value)
+        .map_err(|_| "Failed to deserialize value".to_string())
+}
+
+/// Creates the states of the server running without Python. The names have to match the states
+/// of the client, for example `root.counter` for the field `counter` of the root state.
+pub struct ServerBuilder {
+    server: Server,
+    types: Vec<(u64, u32, ObjectType)>,
+}
+
+impl ServerBuilder {
+    pub fn new(
+        addr: SocketAddrV4,
+        version: Option<u64>,
+        token: Option<String>,
+        queue_size: Option<usize>,
+    ) -> Self {
+        let server = Server::new(addr, version, token, queue_size);
+
+        // reserved signal types
+        let logging_type = <(u8, String, Option<HashMap<String, String>>)>::get_type();
+        let types = vec![
+            (signals::LOGGING_ID, logging_type.get_hash(), logging_type),
+            (
+                signals::ON_CONNECT_ID,
+                String::get_type().get_hash(),
+                String::get_type(),
+            ),
+            (
+                signals::ON_DISCONNECT_ID,
+                <()>::get_type().get_hash(),
+                <()>::get_type(),
+            ),
+            (
+                signals::CLIENT_MESSAGE_ID,
+                String::get_type().get_hash(),
+                String::get_type(),
+            ),
+        ];
+
+        Self { server, types }
+    }
+
+    // maximal count of read-only clients connected besides the client
+    pub fn read_only_clients(mut self, count: usize) -> Self {
+        self.server.set_read_only_clients(count);
+        self
+    }
+
+    // clients have to connect over TLS, certificate chain and private key in PEM files
+    #[cfg(feature = "tls")]
+    pub fn tls(mut self, cert_path: &str, key_path: &str) -> Result<Self, String> {
+        let acceptor = crate::server::tls::acceptor(cert_path, key_path)?;
+        self.server.set_tls(Some(acceptor));
+        Ok(self)
+    }
+
+    // Prometheus metrics served over HTTP while the server is running
+    #[cfg(feature = "metrics-http")]
+    pub fn metrics(mut self, addr: SocketAddrV4) -> Self {
+        self.server.set_metrics_addr(Some(addr));
+        self
+    }
+
+    pub fn add_value<T>(&mut self, name: &str, value: T) -> Result<ServerValue<T>, String>
+    where
+        T: Transportable + Serialize + for<'a> Deserialize<'a>,
+    {
+        let object_type = T::get_type();
+        let type_id = object_type.get_hash();
+        let id = self
+            .server
+            .add_value(name, type_id, to_bytes(&value)?, false, None)?;
+        self.types.push((id, type_id, object_type));
+
+        Ok(ServerValue {
+            value: self.server.states().values[&id].clone(),
+            id,
+            signals: self.server.get_signals_manager(),
+            _type: PhantomData,
+        })
+    }
+
+    pub fn add_static<T>(&mut self, name: &str, value: T) -> Result<ServerStatic<T>, String>
+    where
+        T: Transportable + Serialize + for<'a> Deserialize<'a>,
+    {
+        let object_type = T::get_type();
+        let type_id = object_type.get_hash();
+        let id = self
+            .server
+            .add_static(name, type_id, to_bytes(&value)?, None)?;
+        self.types.push((id, type_id, object_type));
+
+        Ok(ServerStatic {
+            value: self.server.states().static_values[&id].clone(),
+            _type: PhantomData,
+        })
+    }
+
+    pub fn add_signal<T>(&mut self, name: &str) -> Result<ServerSignal<T>, String>
+    where
+        T: Transportable + for<'a> Deserialize<'a>,
+    {
+        let object_type = T::get_type();
+        let type_id = object_type.get_hash();
+        let id = self.server.add_signal(name, type_id, false, None)?;
+        self.types.push((id, type_id, object_type));
+
+        Ok(ServerSignal {
+            id,
+            signals: self.server.get_signals_manager(),
+            _type: PhantomData,
+        })
+    }
+
+    pub fn add_image(&mut self, name: &str) -> Result<ServerImage, String> {
+        let id = self.server.add_image(name, false, None)?;
+        Ok(ServerImage {
+            image: self.server.states().images[&id].clone(),
+        })
+    }
+
+    #[allow(private_bounds)]
+    pub fn add_data<T: GetDataType>(&mut self, name: &str) -> Result<ServerData<T>, String> {
+        let id = self.server.add_data(name, T::get_type() as u8, None)?;
+        Ok(ServerData {
+            data: self.server.states().data[&id].clone(),
+            _type: PhantomData,
+        })
+    }
+
+    // no states can be added after the build, the handles stay valid
+    pub fn build(mut self) -> Result<EmbeddedServer, String> {
+        self.server
+            .finalize()
+            .ok_or("Server has been already finalized")?;
+        self.server.set_types(self.types)?;
+        Ok(EmbeddedServer {
+            server: self.server,
+        })
+    }
+}
+
+/// States server for pure Rust producers, the counterpart of the Python `StatesServer`. The
+/// states are set through the handles returned by the [`ServerBuilder`].
+pub struct EmbeddedServer {
+    server: Server,
+}
+
+impl EmbeddedServer {
+    pub fn start(&mut self) -> Result<(), String> {
+        self.server.start().map_err(|e| e.to_string())
+    }
+
+    pub fn stop(&mut self) {
+        self.server.stop();
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.server.is_running()
+    }
+
+    // the read-only clients are not counted
+    pub fn is_connected(&self) -> bool {
+        self.server.is_connected()
+    }
+
+    // true if the queue of the client holds more messages than the queue size given to the builder
+    pub fn is_busy(&self) -> bool {
+        self.server.is_busy()
+    }
+
+    // disconnects also the read-only clients
+    pub fn disconnect_client(&mut self) {
+        self.server.disconnect_client();
+    }
+
+    // request the repaint of the client ui, after the duration in seconds if given
+    pub fn update(&self, duration: Option<f32>) -> Result<(), String> {
+        self.server
+            .update(duration)
+            .map_err(|_| "Failed to send update".to_string())
+    }
+}
+
+// Value -----------------------------------------------------------------
+pub struct ServerValue<T> {
+    value: Arc<Value>,
+    id: u64,
+    signals: SignalsManager,
+    _type: PhantomData<fn() -> T>,
+}
+
+impl<T> ServerValue<T>
+where
+    T: Serialize + for<'a> Deserialize<'a> + 'static,
+{
+    pub fn get(&self) -> Result<T, String> {
+        from_bytes(&self.value.get())
+    }
+
+    // returns false if the update was dropped by the max rate of the value
+    pub fn set(&self, value: &T, set_signal: bool, update: bool) -> Result<bool, String> {
+        self.value
+            .set(to_bytes(value)?, set_signal, update)
+            .map_err(|_| "Value set failed".to_string())
+    }
+
+    // called on every change from the client and on the server changes with set_signal, in a
+    // separate thread shared by all callbacks
+    pub fn on_change(&self, callback: impl Fn(T) + Send + Sync + 'static) {
+        self.value.set_notify_client(true);
+        self.signals.on_signal(self.id, callback);
+    }
+
+    pub fn remove_callback(&self) {
+        self.value.set_notify_client(false);
+        self.signals.remove_signal_handler(self.id);
+    }
+}
+
+// Static ----------------------------------------------------------------
+pub struct ServerStatic<T> {
+    value: Arc<ValueStatic>,
+    _type: PhantomData<fn() -> T>,
+}
+
+impl<T> ServerStatic<T>
+where
+    T: Serialize + for<'a> Deserialize<'a>,
+{
+    pub fn get(&self) -> Result<T, String> {
+        from_bytes(&self.value.get())
+    }
+
+    pub fn set(&self, value: &T, update: bool) -> Result<(), String> {
+        self.value
+            .set(to_bytes(value)?, update)
+            .map_err(|_| format!("Failed to serialize Static: {}", self.value.name))
+    }
+}
+
+// Signal ----------------------------------------------------------------
+pub struct ServerSignal<T> {
+    id: u64,
+    signals: SignalsManager,
+    _type: PhantomData<fn() -> T>,
+}
+
+impl<T> ServerSignal<T>
+where
+    T: for<'a> Deserialize<'a> + 'static,
+{
+    // called in a separate thread shared by all callbacks
+    pub fn on_signal(&self, callback: impl Fn(T) + Send + Sync + 'static) {
+        self.signals.on_signal(self.id, callback);
+    }
+
+    pub fn remove_callback(&self) {
+        self.signals.remove_signal_handler(self.id);
+    }
+}
+
+// Image -----------------------------------------------------------------
+#[derive(Clone, Copy)]
+pub enum ImageFormat {
+    Rgb,
+    Rgba,
+    Gray,
+    GrayAlpha,
+}
+
+pub struct ServerImage {
+    image: Arc<Image>,
+}
+
+impl ServerImage {
+    // size is [height, width], the rows follow each other without padding
+    pub fn set(
+        &self,
+        data: &[u8],
+        size: [usize; 2],
+        format: ImageFormat,
+        update: bool,
+    ) -> Result<(), String> {
+        let image_type = match format {
+            ImageFormat::Rgb => ImageType::Color,
+            ImageFormat::Rgba => ImageType::ColorAlpha,
+            ImageFormat::Gray => ImageType::Gray,
+            ImageFormat::GrayAlpha => ImageType::GrayAlpha,
+        };
+        if size[0] == 0 || size[1] == 0 {
+            return Err(format!(
+                "Image dimensions cannot be zero for {}",
+                self.image.name
+            ));
+        }
+        let expected = size[0]
+            .checked_mul(size[1])
+            .and_then(|pixels| pixels.checked_mul(image_type.bytes_per_pixel()));
+        if expected != Some(data.len()) {
+            return Err(format!(
+                "Image data size mismatch for {}: expected {} bytes, got {}",
+                self.image.name,
+                expected.unwrap_or(usize::MAX),
+                data.len()
+            ));
+        }
+
+        let image = ImageData {
+            size,
+            stride: 0,
+            contiguous: true,
+            image_type,
+            data: data.as_ptr(),
+        };
+        self.image.set_image(image, update)
+    }
+
+    // [height, width]
+    pub fn size(&self) -> [usize; 2] {
+        self.image.get_size()
+    }
+}
+
+// Data ------------------------------------------------------------------
+pub struct ServerData<T> {
+    data: Arc<Data>,
+    _type: PhantomData<fn() -> T>,
+}
+
+#[allow(private_bounds)]
+impl<T: GetDataType> ServerData<T> {
+    fn error(&self, error: String) -> String {
+        format!("{} for data {}", error, self.data.name)
+    }
+
+    fn holder(data: &[T]) -> DataHolder {
+        DataHolder {
+            data: data.as_ptr() as *const u8,
+            count: data.len(),
+            data_size: size_of_val(data),
+            data_type: T::get_type(),
+        }
+    }
+
+    pub fn set(&self, data: &[T], update: bool) -> Result<(), String> {
+        self.data
+            .set(Self::holder(data), None, update)
+            .map_err(|e| self.error(e))
+    }
+
+    // returns the number of items after adding
+    pub fn add(&self, data: &[T], update: bool) -> Result<usize, String> {
+        self.data
+            .add(Self::holder(data), update)
+            .map_err(|e| self.error(e))
+    }
+
+    pub fn clear(&self, update: bool) -> Result<(), String> {
+        self.data.clear(update).map_err(|e| self.error(e))
+    }
+
+    pub fn get(&self) -> Vec<T> {
+        self.data.get(|bytes| {
+            let count = bytes.len() / size_of::<T>();
+            let mut items = Vec::<T>::with_capacity(count);
+            unsafe {
+                std::ptr::copy_nonoverlapping(
+                    bytes.as_ptr(),
+                    items.as_mut_ptr() as *mut u8,
+                    count * size_of::<T>(),
+                );
+                items.set_len(count);
+            }
+            items
+        })
+    }
+}
+
+#[cfg(all(test, feature = "client"))]
+mod tests {
+    use std::net::{Ipv4Addr, TcpListener};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    use parking_lot::Mutex;
+
+    use super::*;
+    use crate::State;
+    use crate::client::client::{Client, ClientBuilder, ConnectionState};
+    use crate::client::data::Data;
+    use crate::client::states_creator::StatesCreator;
+    use crate::client::values::{Signal, Value};
+
+    struct States {
+        count: Value<i32>,
+        number: Signal<i32>,
+        points: Data<f32>,
+    }
+
+    impl State for States {
+        const NAME: &'static str = "States";
+
+        fn new(c: &mut impl StatesCreator) -> Self {
+            Self {
+                count: c.value("count", 0),
+                number: c.signal("number"),
+                points: c.data("points"),
+            }
+        }
+    }
+
+    fn wait_until(predicate: impl Fn() -> bool) -> bool {
+        let end = Instant::now() + Duration::from_secs(2);
+        while !predicate() && Instant::now() < end {
+            thread::sleep(Duration::from_millis(5));
+        }
+        predicate()
+    }
+
+    fn builder() -> (ServerBuilder, u16) {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let addr = SocketAddrV4::new(Ipv4Addr::LOCALHOST, port);
+        (ServerBuilder::new(addr, None, None, None), port)
+    }
+
+    // builds and starts the server, returns after the client is connected
+    fn connect<S: State>(builder: ServerBuilder, port: u16) -> (EmbeddedServer, S, Client) {
+        let mut server = builder.build().unwrap();
+        server.start().unwrap();
+        let (states, client) = ClientBuilder::<S>::new().build(port, None, None);
+        // the client thread clears the connect signal when it starts to wait for it
+        assert!(wait_until(|| {
+            client.connect();
+            server.is_connected() && client.get_state() == ConnectionState::Connected
+        }));
+        (server, states, client)
+    }
+
+    #[test]
+    fn client_signal_reaches_rust_handler() {
+        let (mut builder, port) = builder();
+        let signal = builder.add_signal::<i32>("root.number").unwrap();
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let r = received.clone();
+        signal.on_signal(move |value: i32| r.lock().push(value));
+
+        let (mut server, states, client) = connect::<States>(builder, port);
+        states.number.set(7);
+        assert!(wait_until(|| received.lock().len() == 1));
+        states.number.set(-3);
+        assert!(wait_until(|| received.lock().len() == 2));
+        assert_eq!(*received.lock(), vec![7, -3]);
+
+        client.disconnect();
+        server.stop();
+    }
+
+    #[test]
+    fn value_set_reaches_client() {
+        let (mut builder, port) = builder();
+        let count = builder.add_value("root.count", 1i32).unwrap();
+
+        let (mut server, states, client) = connect::<States>(builder, port);
+        assert!(wait_until(|| states.count.get() == 1));
+        assert!(count.set(&5, false, true).unwrap());
+        assert!(wait_until(|| states.count.get() == 5));
+        assert_eq!(count.get().unwrap(), 5);
+
+        client.disconnect();
+        server.stop();
+    }
+
+    #[test]
+    fn data_add_round_trips() {
+        let (mut builder, port) = builder();
+        let data = builder.add_data::<f32>("root.points").unwrap();
+
+        // every change waits for the acknowledgement of the previous one by the client
+        let (mut server, states, client) = connect::<States>(builder, port);
+        data.set(&[1.0, 2.5], false).unwrap();
+        assert_eq!(data.add(&[-4.0, 8.25, 0.5], true).unwrap(), 5);
+        let expected = vec![1.0, 2.5, -4.0, 8.25, 0.5];
+        assert_eq!(data.get(), expected);
+        assert!(wait_until(|| states.points.get() == expected));
+        data.clear(false).unwrap();
+        assert!(data.get().is_empty());
+
+        client.disconnect();
+        server.stop();
+    }
+
+    #[test]
+    fn image_set_rejects_wrong_size() {
+        let (mut builder, port) = builder();
+        let image = builder.add_image("root.image").unwrap();
+
+        let (mut server, _states, client) = connect::<States>(builder, port);
+        assert!(
+            image
+                .set(&[0; 11], [2, 2], ImageFormat::Rgb, false)
+                .is_err()
+        );
+        assert!(
+            image
+                .set(&[0; 16], [2, 0], ImageFormat::Rgba, false)
+                .is_err()
+        );
+        image
+            .set(&[0; 12], [2, 2], ImageFormat::Rgb, false)
+            .unwrap();
+        assert_eq!(image.size(), [2, 2]);
+        image
+            .set(&[0; 12], [3, 2], ImageFormat::GrayAlpha, true)
+            .unwrap();
+        assert_eq!(image.size(), [3, 2]);
+
+        client.disconnect();
+        server.stop();
+    }
+
+    #[test]
+    fn read_only_client_joining_during_adds_matches_server() {
+        let (builder, port) = builder();
+        let mut builder = builder.read_only_clients(1);
+        let data = builder.add_data::<f32>("root.points").unwrap();
+
+        let (mut server, _states, client) = connect::<States>(builder, port);
+        let adding = AtomicBool::new(true);
+        let (viewer, viewer_client) = thread::scope(|scope| {
+            scope.spawn(|| {
+                let mut item = 0.0;
+                while adding.load(Ordering::Relaxed) {
+                    data.add(&[item], false).unwrap();
+                    item += 1.0;
+                }
+            });
+
+            // joins while the additions are queued, none of them is in the snapshot and sent again
+            let (viewer, viewer_client) = ClientBuilder::<States>::new()
+                .read_only()
+                .build(port, None, None);
+            assert!(wait_until(|| {
+                viewer_client.connect();
+                viewer_client.get_state() == ConnectionState::Connected
+            }));
+            thread::sleep(Duration::from_millis(50));
+            adding.store(false, Ordering::Relaxed);
+            (viewer, viewer_client)
+        });
+        let expected = data.get();
+        assert!(!expected.is_empty());
+        assert!(wait_until(|| viewer.points.get() == expected));
+
+        viewer_client.disconnect();
+        client.disconnect();
+        server.stop();
+    }
+}
@@ -9,17 +9,50 @@ use crate::image_transport::{ImageHeader, ImageSetHeader, ImageType};
 use crate::serialization::ServerHeader;
 use crate::serialization::{FastVec, MSG_SIZE_THRESHOLD};
 use crate::server::sender::MessageSender;
-use crate::server::server::{Acknowledge, SyncTrait};
+use crate::server::server::{Acknowledge, Snapshot, SyncTrait};
 
 enum Buffer {
-    Set(Vec<(FastVec<32>, bool)>),
+    Set(VecDeque<(FastVec<32>, bool)>),
+    #[cfg(feature = "python")]
     Update([usize; 4], VecDeque<(FastVec<32>, bool)>),
 }
 
+impl Buffer {
+    // parts waiting for the acknowledge of the client
+    fn queue_mut(&mut self) -> &mut VecDeque<(FastVec<32>, bool)> {
+        match self {
+            Buffer::Set(dat) => dat,
+            #[cfg(feature = "python")]
+            Buffer::Update(_, dat) => dat,
+        }
+    }
+}
+
 struct ImageDataInner {
     data: Vec<u8>,
     size: [usize; 2],
     buffer: Buffer,
+    max_dim: Option<usize>, // transmitted image is downscaled to this size
+    compression: Option<ImageCompression>,
+}
+
+#[cfg(feature = "python")]
+impl ImageDataInner {
+    #[inline]
+    fn is_scaled(&self) -> bool {
+        match self.max_dim {
+            Some(max_dim) => self.size[0].max(self.size[1]) > max_dim,
+            None => false,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub(crate) enum ImageCompression {
+    #[cfg(feature = "python")]
+    Png,
+    #[cfg(feature = "python")]
+    Jpeg(u8), // quality 1-100
 }
 
 pub(crate) struct ImageData {
@@ -38,6 +71,10 @@ pub(crate) struct Image {
     sender: MessageSender,
     connected: Arc<AtomicBool>,
     event: Event,
+    premultiply: bool,
+    // tone mapping window of the last uint16 or float32 image, used by the partial updates
+    #[cfg(feature = "python")]
+    window: Mutex<Option<[f64; 2]>>,
 }
 
 impl Image {
@@ -46,6 +83,7 @@ impl Image {
         id: u64,
         sender: MessageSender,
         connected: Arc<AtomicBool>,
+        premultiply: bool,
     ) -> Arc<Self> {
         let event = Event::new();
         event.set(); // initially set so the first send does not block
@@ -56,28 +94,97 @@ impl Image {
             image: RwLock::new(ImageDataInner {
                 data: Vec::with_capacity(0),
                 size: [0, 0],
-                buffer: Buffer::Set(Vec::new()),
+                buffer: Buffer::Set(VecDeque::new()),
+                max_dim: None,
+                compression: None,
             }),
             lock: Mutex::new(()),
             sender,
             connected,
             event,
+            premultiply,
+            #[cfg(feature = "python")]
+            window: Mutex::new(None),
         })
     }
 
+    #[cfg(feature = "python")]
+    pub(crate) fn window(&self) -> Option<[f64; 2]> {
+        *self.window.lock()
+    }
+
+    #[cfg(feature = "python")]
+    pub(crate) fn set_window(&self, window: Option<[f64; 2]>) {
+        *self.window.lock() = window;
+    }
+
     pub(crate) fn get_size(&self) -> [usize; 2] {
         self.image.read().size
     }
 
+    #[cfg(feature = "python")]
     pub(crate) fn get_image<T>(&self, getter: impl FnOnce((&Vec<u8>, &[usize; 2])) -> T) -> T {
         let w = self.image.read();
         getter((&w.data, &w.size))
     }
 
     pub(crate) fn set_image(&self, image: ImageData, update: bool) -> Result<(), String> {
+        self.set_image_inner(image, None, update)
+    }
+
+    #[cfg(feature = "python")]
+    pub(crate) fn set_image_scaled(
+        &self,
+        image: ImageData,
+        max_dim: usize,
+        update: bool,
+    ) -> Result<(), String> {
+        if max_dim == 0 {
+            return Err("Maximum dimension cannot be zero".to_string());
+        }
+        self.set_image_inner(image, Some(max_dim), update)
+    }
+
+    #[cfg(feature = "python")]
+    // only the whole image is compressed, updates and scrolling are always sent raw
+    pub(crate) fn set_compression(
+        &self,
+        compression: Option<ImageCompression>,
+    ) -> Result<(), String> {
+        if !cfg!(feature = "image-compression") && compression.is_some() {
+            return Err("Image compression feature is not enabled".to_string());
+        }
+        if let Some(ImageCompression::Jpeg(quality)) = compression
+            && !(1..=100).contains(&quality)
+        {
+            return Err("JPEG quality has to be between 1 and 100".to_string());
+        }
+        self.image.write().compression = compression;
+        Ok(())
+    }
+
+    fn set_image_inner(
+        &self,
+        image: ImageData,
+        max_dim: Option<usize>,
+        update: bool,
+    ) -> Result<(), String> {
         // Prepare data to send if connected
         let to_send = if self.connected.load(Ordering::Relaxed) {
-            Some(pack_set_data(self.id, &image, update)?)
+            let compression = self.image.read().compression;
+            match max_dim.and_then(|max_dim| scale_image(&image, max_dim)) {
+                Some((data, size)) => {
+                    let scaled = ImageData {
+                        size,
+                        stride: 0,
+                        contiguous: true,
+                        image_type: image.image_type,
+                        data: data.as_ptr(),
+                    };
+                    Some(pack_image(self.id, &scaled, compression, update)?)
+                }
+                None => Some(pack_image(self.id, &image, compression, update)?),
+            }
         } else {
             None
         };
@@ -85,13 +192,16 @@ impl Image {
         let pixels_count = image.size[0] * image.size[1];
 
         // this is main lock for set and update operation
-        let lock = self.lock.lock();
+        let _lock = self.lock.lock();
         let mut w = self.image.write();
 
+        w.max_dim = max_dim;
+
         // only allocate new data if size has changed
         if image.size != w.size {
             w.size = image.size;
             let mut new_data = Vec::with_capacity(pixels_count * 4);
+            #[allow(clippy::uninit_vec)]
             unsafe {
                 new_data.set_len(pixels_count * 4);
             }
@@ -119,54 +229,24 @@ impl Image {
             };
         }
 
-        let Some(to_send) = to_send else {
+        let Some(mut to_send) = to_send else {
             return Ok(());
         };
 
-        if let Buffer::Set(ref mut dat) = w.buffer {
-            if dat.is_empty() {
-                if self.event.is_set() {
-                    self.event.clear();
-                    for (message, send_now) in to_send {
-                        self.sender.send_set(message, send_now);
-                    }
-                    return Ok(());
-                } else {
-                    dat.extend(to_send);
-                    return Ok(());
-                }
+        // parts are sent one by one after acknowledge, so the new image replaces not yet sent parts
+        // of the previous one, set has always priority over update
+        if self.event.is_set() {
+            self.event.clear();
+            if let Some((message, send_now)) = to_send.pop_front() {
+                self.sender.send_set(message, send_now);
             }
-        } else {
-            //rewrite any update buffer, set has always priority over update
-            if self.event.is_set() {
-                self.event.clear();
-                for (message, send_now) in to_send {
-                    self.sender.send_set(message, send_now);
-                }
-                return Ok(());
-            } else {
-                w.buffer = Buffer::Set(to_send);
-                return Ok(());
-            }
-        }
-
-        // drop lock to allow acknowledge to process the buffer and send data while we prepare the next one
-        drop(w);
-
-        self.event.wait_clear();
-        if !self.connected.load(Ordering::Relaxed) {
-            return Ok(());
-        }
-
-        for (message, send_now) in to_send {
-            self.sender.send_set(message, send_now);
         }
-
-        drop(lock);
+        w.buffer = Buffer::Set(to_send);
 
         Ok(())
     }
 
+    #[cfg(feature = "python")]
     pub(crate) fn update_image(
         &self,
         origin: &[usize; 2],
@@ -184,8 +264,17 @@ impl Image {
         let _lock = self.lock.lock();
         let mut w = self.image.write();
 
-        if origin[0] + image.size[0] > w.size[0] || origin[1] + image.size[1] > w.size[1] {
-            return Err("Image size exceeds bounds".to_string());
+        if origin[0].saturating_add(image.size[0]) > w.size[0]
+            || origin[1].saturating_add(image.size[1]) > w.size[1]
+        {
+            return Err(format!(
+                "Update rectangle at {:?} with size {:?} exceeds the image size {:?}",
+                origin, image.size, w.size
+            ));
+        }
+
+        if w.is_scaled() {
+            return Err("Cannot update part of downscaled image".to_string());
         }
 
         // update local image data
@@ -268,48 +357,95 @@ impl Image {
 
         Ok(())
     }
+
+    #[cfg(feature = "python")]
+    // shift the image down by the number of new rows and write them at the top
+    pub(crate) fn scroll_append(&self, rows: ImageData, update: bool) -> Result<(), String> {
+        let _change = self.sender.change();
+        // this is main lock for set and update operation
+        let _lock = self.lock.lock();
+        let mut w = self.image.write();
+
+        if w.size[0] == 0 || w.size[1] == 0 {
+            return Err("Cannot scroll empty image".to_string());
+        }
+        if rows.size[1] != w.size[1] {
+            return Err("Rows width does not match image width".to_string());
+        }
+        if rows.size[0] > w.size[0] {
+            return Err("Rows count exceeds image height".to_string());
+        }
+        if w.is_scaled() {
+            return Err("Cannot scroll downscaled image".to_string());
+        }
+
+        let line_size = w.size[1] * 4;
+        let shifted = (w.size[0] - rows.size[0]) * line_size;
+        w.data.copy_within(0..shifted, rows.size[0] * line_size);
+
+        let old_stride = w.size[1];
+        unsafe {
+            write_rectangle(
+                rows.data,
+                rows.stride,
+                w.data.as_mut_ptr(),
+                old_stride,
+                &[0, 0],
+                &rows.size,
+                rows.image_type,
+            );
+        }
+
+        if !self.connected.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+
+        let message = pack_scroll_data(self.id, &rows, update)?;
+        let idle = w.buffer.queue_mut().is_empty();
+        if idle && self.event.is_set() {
+            self.event.clear();
+            self.sender.send_set(message, true);
+            return Ok(());
+        }
+
+        // scroll cannot be merged with other messages, wait until the client processed them
+        drop(w);
+        self.event.wait_clear();
+        if !self.connected.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+        self.sender.send_set(message, true);
+
+        Ok(())
+    }
 }
 
 impl Acknowledge for Image {
     fn acknowledge(&self) {
         let mut w = self.image.write();
 
-        match w.buffer {
-            Buffer::Set(ref mut dat) => {
-                if dat.is_empty() {
-                    self.event.set();
-                    return;
-                }
-
-                for (message, send_now) in dat.drain(..) {
-                    self.sender.send_set(message, send_now);
-                }
+        match w.buffer.queue_mut().pop_front() {
+            Some((message, send_now)) => {
+                self.sender.send_set(message, send_now);
+            }
+            None => {
+                self.event.set();
             }
-            Buffer::Update(_, ref mut dat) => match dat.pop_front() {
-                Some((message, send_now)) => {
-                    self.sender.send_set(message, send_now);
-                }
-                None => {
-                    self.event.set();
-                    return;
-                }
-            },
         }
     }
 
     fn reset(&self) {
         self.event.set();
         let mut w = self.image.write();
-        w.buffer = Buffer::Set(Vec::new());
+        w.buffer = Buffer::Set(VecDeque::new());
     }
 }
 
-impl SyncTrait for Image {
-    fn sync(&self) -> Result<(), ()> {
-        let mut w = self.image.write();
+impl Image {
+    // the parts of the full image, None if the image is not set yet
+    fn pack_all(&self, w: &ImageDataInner) -> Result<Option<VecDeque<(FastVec<32>, bool)>>, ()> {
         if w.size[0] == 0 || w.size[1] == 0 {
-            self.event.set();
-            return Ok(());
+            return Ok(None);
         }
 
         let image_data = ImageData {
@@ -320,22 +456,219 @@ impl SyncTrait for Image {
             data: w.data.as_ptr(),
         };
 
-        let data = pack_set_data(self.id, &image_data, false).map_err(|_| ())?;
+        let data = match w
+            .max_dim
+            .and_then(|max_dim| scale_image(&image_data, max_dim))
+        {
+            Some((scaled_data, size)) => {
+                let scaled = ImageData {
+                    size,
+                    stride: 0,
+                    contiguous: true,
+                    image_type: ImageType::ColorAlpha,
+                    data: scaled_data.as_ptr(),
+                };
+                pack_image(self.id, &scaled, w.compression, false).map_err(|_| ())?
+            }
+            None => pack_image(self.id, &image_data, w.compression, false).map_err(|_| ())?,
+        };
+        Ok(Some(data))
+    }
+
+    fn premultiply_message(&self) -> Result<Option<FastVec<32>>, ()> {
+        // client default is no premultiplication
+        match self.premultiply {
+            true => {
+                let header = ServerHeader::Image(self.id, ImageHeader::Premultiply(true), 0);
+                Ok(Some(crate::serialization::serialize_heap(&header)?))
+            }
+            false => Ok(None),
+        }
+    }
+}
+
+impl SyncTrait for Image {
+    fn sync(&self) -> Result<(), ()> {
+        if let Some(message) = self.premultiply_message()? {
+            self.sender.send(message);
+        }
+
+        let mut w = self.image.write();
+        let Some(mut data) = self.pack_all(&w)? else {
+            self.event.set();
+            return Ok(());
+        };
 
-        w.buffer = Buffer::Set(Vec::new());
         self.event.clear();
-        for (message, send_now) in data {
+        if let Some((message, send_now)) = data.pop_front() {
             self.sender.send_set(message, send_now);
         }
+        w.buffer = Buffer::Set(data);
+        Ok(())
+    }
+
+    fn snapshot(&self, snapshot: &mut Snapshot) -> Result<(), ()> {
+        if let Some(message) = self.premultiply_message()? {
+            snapshot.push(message, false);
+        }
+
+        let r = self.image.read();
+        for (message, single) in self.pack_all(&r)?.unwrap_or_default() {
+            snapshot.push(message, single);
+        }
         Ok(())
     }
 }
 
+// downscale image with box filter so the larger side is max_dim, None if no scaling is needed
+fn scale_image(image: &ImageData, max_dim: usize) -> Option<(Vec<u8>, [usize; 2])> {
+    let [height, width] = image.size;
+    let larger = height.max(width);
+    if larger <= max_dim {
+        return None;
+    }
+
+    let new_size = [
+        (height * max_dim / larger).max(1),
+        (width * max_dim / larger).max(1),
+    ];
+    let pixel_size = image.image_type.bytes_per_pixel();
+    let stride = match image.contiguous {
+        true => width * pixel_size,
+        false => image.stride,
+    };
+
+    let mut data = Vec::with_capacity(new_size[0] * new_size[1] * pixel_size);
+    let mut sums = [0u32; 4];
+    for y in 0..new_size[0] {
+        let y_start = y * height / new_size[0];
+        let y_end = ((y + 1) * height / new_size[0]).max(y_start + 1);
+        for x in 0..new_size[1] {
+            let x_start = x * width / new_size[1];
+            let x_end = ((x + 1) * width / new_size[1]).max(x_start + 1);
+
+            sums[..pixel_size].fill(0);
+            for line in y_start..y_end {
+                let line_data = unsafe {
+                    std::slice::from_raw_parts(image.data.add(line * stride), width * pixel_size)
+                };
+                for pixel in line_data[x_start * pixel_size..x_end * pixel_size].chunks(pixel_size)
+                {
+                    for (sum, value) in sums.iter_mut().zip(pixel) {
+                        *sum += *value as u32;
+                    }
+                }
+            }
+
+            let count = ((y_end - y_start) * (x_end - x_start)) as u32;
+            for sum in &sums[..pixel_size] {
+                data.push(((sum + count / 2) / count) as u8);
+            }
+        }
+    }
+
+    Some((data, new_size))
+}
+
+fn pack_image(
+    id: u64,
+    image: &ImageData,
+    compression: Option<ImageCompression>,
+    update: bool,
+) -> Result<VecDeque<(FastVec<32>, bool)>, String> {
+    match compression {
+        #[cfg(all(feature = "image-compression", feature = "python"))]
+        Some(compression) => pack_encoded_data(id, image, compression, update),
+        _ => pack_set_data(id, image, update),
+    }
+}
+
+#[cfg(all(feature = "image-compression", feature = "python"))]
+fn pack_encoded_data(
+    id: u64,
+    image: &ImageData,
+    compression: ImageCompression,
+    update: bool,
+) -> Result<VecDeque<(FastVec<32>, bool)>, String> {
+    use image::codecs::{jpeg::JpegEncoder, png::PngEncoder};
+    use image::{ExtendedColorType, ImageEncoder};
+
+    use crate::image_transport::ImageEncoding;
+
+    let [height, width] = image.size;
+    let bytes_line_size = width * image.image_type.bytes_per_pixel();
+    let pixels = match image.contiguous {
+        true => {
+            unsafe { std::slice::from_raw_parts(image.data, height * bytes_line_size) }.to_vec()
+        }
+        false => {
+            let mut pixels = Vec::with_capacity(height * bytes_line_size);
+            for line in 0..height {
+                let data = unsafe {
+                    std::slice::from_raw_parts(image.data.add(line * image.stride), bytes_line_size)
+                };
+                pixels.extend_from_slice(data);
+            }
+            pixels
+        }
+    };
+
+    let color_type = match image.image_type {
+        ImageType::Color => ExtendedColorType::Rgb8,
+        ImageType::ColorAlpha => ExtendedColorType::Rgba8,
+        ImageType::Gray => ExtendedColorType::L8,
+        ImageType::GrayAlpha => ExtendedColorType::La8,
+    };
+
+    let mut encoded = Vec::new();
+    let (result, encoding) = match compression {
+        ImageCompression::Png => (
+            PngEncoder::new(&mut encoded).write_image(
+                &pixels,
+                width as u32,
+                height as u32,
+                color_type,
+            ),
+            ImageEncoding::Png,
+        ),
+        ImageCompression::Jpeg(quality) => {
+            if let ImageType::ColorAlpha | ImageType::GrayAlpha = image.image_type {
+                return Err(format!(
+                    "JPEG compression does not support alpha channel for image {}",
+                    id
+                ));
+            }
+            (
+                JpegEncoder::new_with_quality(&mut encoded, quality).write_image(
+                    &pixels,
+                    width as u32,
+                    height as u32,
+                    color_type,
+                ),
+                ImageEncoding::Jpeg,
+            )
+        }
+    };
+    result.map_err(|e| format!("Failed to encode image {}: {}", id, e))?;
+
+    let size = [width as u32, height as u32]; // reverse for egui
+    let header = ServerHeader::Image(
+        id,
+        ImageHeader::Encoded(size, encoding, update),
+        encoded.len() as u32,
+    );
+    let mut message: FastVec<32> = crate::serialization::serialize_heap(&header)
+        .map_err(|_| format!("Failed to serialize header for image {}", id))?;
+    message.reserve_exact(encoded.len());
+    message.extend_from_slice(&encoded);
+    Ok(VecDeque::from([(message, true)]))
+}
+
 fn pack_set_data(
     id: u64,
     image: &ImageData,
     update: bool,
-) -> Result<Vec<(FastVec<32>, bool)>, String> {
+) -> Result<VecDeque<(FastVec<32>, bool)>, String> {
     let size = [image.size[1] as u32, image.size[0] as u32]; // reverse for egui
     let bytes_line_size = image.size[1] * image.image_type.bytes_per_pixel();
     let bytes_size = image.size[0] * bytes_line_size;
@@ -371,9 +704,9 @@ fn pack_set_data(
 
         message.reserve_exact(bytes_size);
         append_data(&mut message, 0, bytes_size);
-        return Ok(vec![(message, true)]);
+        Ok(VecDeque::from([(message, true)]))
     } else {
-        let mut messages = Vec::new();
+        let mut messages = VecDeque::new();
         let pixel_size = image.image_type.bytes_per_pixel();
         let pixel_count = image.size[0] * image.size[1];
         let chunk_pixels = MSG_SIZE_THRESHOLD / pixel_size;
@@ -389,7 +722,7 @@ fn pack_set_data(
             .map_err(|_| format!("Failed to serialize header for image {}", id))?;
         message.reserve_exact(first_size);
         append_data(&mut message, 0, first_size);
-        messages.push((message, true));
+        messages.push_back((message, true));
         processed_pixels += first_pixels;
         processed += first_size;
 
@@ -402,7 +735,7 @@ fn pack_set_data(
                     .serialize(id, image.image_type, remaining_size as u32)
                     .map_err(|_| format!("Failed to serialize header for image {}", id))?;
                 append_data(&mut message, processed, remaining_size);
-                messages.push((message, false));
+                messages.push_back((message, false));
                 break;
             }
 
@@ -412,15 +745,16 @@ fn pack_set_data(
                 .map_err(|_| format!("Failed to serialize header for image {}", id))?;
             message.reserve_exact(chunk_size);
             append_data(&mut message, processed, chunk_size);
-            messages.push((message, true));
+            messages.push_back((message, true));
             processed_pixels += chunk_pixels;
             processed += chunk_size;
         }
 
-        return Ok(messages);
+        Ok(messages)
     }
 }
 
+#[cfg(feature = "python")]
 fn pack_update_data(
     id: u64,
     origin: &[usize; 2],
@@ -466,7 +800,7 @@ fn pack_update_data(
         append_lines(&mut message, 0, image.size[0]);
         let mut result = VecDeque::with_capacity(1);
         result.push_back((message, update));
-        return Ok(result);
+        Ok(result)
     } else {
         let mut messages = VecDeque::new();
         let chunk_lines = (MSG_SIZE_THRESHOLD / bytes_line_size).max(1);
@@ -496,8 +830,36 @@ fn pack_update_data(
             processed_lines += lines;
         }
 
-        return Ok(messages);
+        Ok(messages)
+    }
+}
+
+#[cfg(feature = "python")]
+fn pack_scroll_data(id: u64, rows: &ImageData, update: bool) -> Result<FastVec<32>, String> {
+    let bytes_line_size = rows.size[1] * rows.image_type.bytes_per_pixel();
+    let bytes_size = rows.size[0] * bytes_line_size;
+
+    let header = ServerHeader::Image(
+        id,
+        ImageHeader::Scroll(rows.size[0] as u32, rows.image_type, update),
+        bytes_size as u32,
+    );
+    let mut message: FastVec<32> = crate::serialization::serialize_heap(&header)
+        .map_err(|_| format!("Failed to serialize scroll header for image {}", id))?;
+
+    message.reserve_exact(bytes_size);
+    if rows.contiguous {
+        let data = unsafe { std::slice::from_raw_parts(rows.data, bytes_size) };
+        message.extend_from_slice(data);
+    } else {
+        for line in 0..rows.size[0] {
+            let data = unsafe {
+                std::slice::from_raw_parts(rows.data.add(line * rows.stride), bytes_line_size)
+            };
+            message.extend_from_slice(data);
+        }
     }
+    Ok(message)
 }
 
 unsafe fn write_all_new(
@@ -596,6 +958,7 @@ unsafe fn write_all_new_stride(
     }
 }
 
+#[cfg(feature = "python")]
 unsafe fn write_rectangle(
     data: *const u8,
     mut stride: usize,
@@ -1,5 +1,5 @@
+use indexmap::IndexMap;
 use parking_lot::RwLock;
-use std::collections::HashMap;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
 
@@ -8,13 +8,50 @@ use tokio_tungstenite::tungstenite::Bytes;
 use crate::collections::MapHeader;
 use crate::serialization::{ServerHeader, serialize};
 use crate::server::sender::{MessageSender, SenderData};
-use crate::server::server::SyncTrait;
+use crate::server::server::{Snapshot, SyncTrait};
+
+// keys are kept in insertion order, the remove of the unordered map moves the last key instead
+// of shifting all the next keys
+struct MapItems {
+    map: IndexMap<Bytes, Bytes>,
+    ordered: bool,
+}
+
+impl MapItems {
+    fn new(ordered: bool) -> Self {
+        Self {
+            map: IndexMap::new(),
+            ordered,
+        }
+    }
+
+    // for duplicate keys the last value is kept at the first position
+    fn with_items(ordered: bool, items: Vec<(Bytes, Bytes)>) -> Self {
+        Self {
+            map: items.into_iter().collect(),
+            ordered,
+        }
+    }
+
+    // existing key keeps its position
+    fn insert(&mut self, key: Bytes, value: Bytes) {
+        self.map.insert(key, value);
+    }
+
+    fn remove(&mut self, key: &Bytes) -> Option<Bytes> {
+        match self.ordered {
+            true => self.map.shift_remove(key),
+            false => self.map.swap_remove(key),
+        }
+    }
+}
 
 pub(crate) struct ValueMap {
     pub(crate) name: String,
     id: u64,
     type_id: u32,
-    map: RwLock<HashMap<Bytes, Bytes>>,
+    ordered: bool,
+    map: RwLock<MapItems>,
     sender: MessageSender,
     connected: Arc<AtomicBool>,
 }
@@ -24,6 +61,7 @@ impl ValueMap {
         name: String,
         id: u64,
         type_id: u32,
+        ordered: bool,
         sender: MessageSender,
         connected: Arc<AtomicBool>,
     ) -> Arc<Self> {
@@ -31,16 +69,17 @@ impl ValueMap {
             name,
             id,
             type_id,
-            map: RwLock::new(HashMap::new()),
+            ordered,
+            map: RwLock::new(MapItems::new(ordered)),
             sender,
             connected,
         })
     }
 
-    fn serialize_all(&self, map: &HashMap<Bytes, Bytes>, update: bool) -> Result<SenderData, ()> {
-        let len = map.len() as u64;
+    fn serialize_all(&self, items: &MapItems, update: bool) -> Result<SenderData, ()> {
+        let len = items.map.len() as u64;
         let mut size = 0;
-        map.iter().for_each(|(k, v)| {
+        items.map.iter().for_each(|(k, v)| {
             size += k.len();
             size += v.len();
         });
@@ -48,38 +87,41 @@ impl ValueMap {
             self.id,
             self.type_id,
             update,
-            MapHeader::All(len),
+            MapHeader::All(len, self.ordered),
             size as u32,
         );
 
         let mut data = serialize(&header)?;
-        map.iter().for_each(|(k, v)| {
-            data.extend_from_slice(&k);
-            data.extend_from_slice(&v);
+        items.map.iter().for_each(|(k, v)| {
+            data.extend_from_slice(k);
+            data.extend_from_slice(v);
         });
 
         Ok(data)
     }
 
-    pub(crate) fn set(&self, map: HashMap<Bytes, Bytes>, update: bool) -> Result<(), ()> {
+    // for duplicate keys the last value is kept at the first position
+    pub(crate) fn set(&self, items: Vec<(Bytes, Bytes)>, update: bool) -> Result<(), ()> {
+        let _change = self.sender.change();
+        let items = MapItems::with_items(self.ordered, items);
         let mut w = self.map.write();
 
         if self.connected.load(Ordering::Relaxed) {
-            let data = self.serialize_all(&map, update)?;
+            let data = self.serialize_all(&items, update)?;
             self.sender.send(data);
         }
 
-        *w = map;
+        *w = items;
         Ok(())
     }
 
-    pub(crate) fn get(&self) -> HashMap<Bytes, Bytes> {
-        self.map.read().clone()
+    // items in insertion order for ordered map
+    pub(crate) fn get(&self) -> Vec<(Bytes, Bytes)> {
+        let r = self.map.read();
+        r.map.iter().map(|(k, v)| (k.clone(), v.clone())).collect()
     }
 
-    pub(crate) fn set_item(&self, key: Bytes, value: Bytes, update: bool) -> Result<(), ()> {
-        let mut w = self.map.write();
-
+    fn send_item(&self, key: &Bytes, value: &Bytes, update: bool) -> Result<(), ()> {
         if self.connected.load(Ordering::Relaxed) {
             let header = ServerHeader::ValueMap(
                 self.id,
@@ -89,28 +131,55 @@ impl ValueMap {
                 (key.len() + value.len()) as u32,
             );
             let mut data = serialize(&header)?;
-            data.extend_from_slice(&key);
-            data.extend_from_slice(&value);
+            data.extend_from_slice(key);
+            data.extend_from_slice(value);
             self.sender.send(data);
         }
+        Ok(())
+    }
+
+    pub(crate) fn set_item(&self, key: Bytes, value: Bytes, update: bool) -> Result<(), ()> {
+        let _change = self.sender.change();
+        let mut w = self.map.write();
+        self.send_item(&key, &value, update)?;
+        w.insert(key, value);
+        Ok(())
+    }
 
-        match w.get_mut(&key) {
-            Some(v) => *v = value,
-            None => {
-                w.insert(key, value);
+    // f gets the current value and runs without the map lock, it is called again if the item was
+    // changed meanwhile, when it returns None the map is not changed
+    pub(crate) fn update_item(
+        &self,
+        key: Bytes,
+        mut f: impl FnMut(Option<&Bytes>) -> Result<Option<Bytes>, String>,
+        update: bool,
+    ) -> Result<Option<Bytes>, String> {
+        let _change = self.sender.change();
+        loop {
+            let old = self.get_item(&key);
+            let value = match f(old.as_ref())? {
+                Some(value) => value,
+                None => return Ok(None),
+            };
+
+            let mut w = self.map.write();
+            if w.map.get(&key) != old.as_ref() {
+                continue;
             }
+
+            self.send_item(&key, &value, update)
+                .map_err(|_| format!("Failed to serialize item of map {}", self.name))?;
+            w.insert(key, value.clone());
+            return Ok(Some(value));
         }
-        Ok(())
     }
 
     pub(crate) fn get_item(&self, key: &Bytes) -> Option<Bytes> {
-        match self.map.read().get(key) {
-            Some(v) => Some(v.clone()),
-            None => None,
-        }
+        self.map.read().map.get(key).cloned()
     }
 
     pub(crate) fn remove_item(&self, key: &Bytes, update: bool) -> Result<Option<Bytes>, ()> {
+        let _change = self.sender.change();
         let mut w = self.map.write();
         let old = match w.remove(key) {
             Some(v) => v,
@@ -126,7 +195,7 @@ impl ValueMap {
                 key.len() as u32,
             );
             let mut data = serialize(&header)?;
-            data.extend_from_slice(&key);
+            data.extend_from_slice(key);
             self.sender.send(data);
         }
 
@@ -134,8 +203,34 @@ impl ValueMap {
         Ok(Some(old))
     }
 
+    // only the existing keys are sent in one message, returns the number of removed items
+    pub(crate) fn remove_items(&self, keys: &[Bytes], update: bool) -> Result<usize, ()> {
+        let _change = self.sender.change();
+        let mut w = self.map.write();
+        let removed: Vec<&Bytes> = keys.iter().filter(|k| w.remove(k).is_some()).collect();
+        if removed.is_empty() {
+            return Ok(0);
+        }
+
+        if self.connected.load(Ordering::Relaxed) {
+            let size: usize = removed.iter().map(|k| k.len()).sum();
+            let header = ServerHeader::ValueMap(
+                self.id,
+                self.type_id,
+                update,
+                MapHeader::RemoveMany(removed.len() as u64),
+                size as u32,
+            );
+            let mut data = serialize(&header)?;
+            removed.iter().for_each(|k| data.extend_from_slice(k));
+            self.sender.send(data);
+        }
+
+        Ok(removed.len())
+    }
+
     pub(crate) fn len(&self) -> usize {
-        self.map.read().len()
+        self.map.read().map.len()
     }
 }
 
@@ -146,4 +241,50 @@ impl SyncTrait for ValueMap {
         self.sender.send(data);
         Ok(())
     }
+
+    fn snapshot(&self, snapshot: &mut Snapshot) -> Result<(), ()> {
+        let r = self.map.read();
+        snapshot.push(self.serialize_all(&r, false)?, false);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn update_item_runs_without_lock_and_retries() {
+        let (sender, _receiver) = MessageSender::new(None);
+        let map = ValueMap::new(
+            "map".to_string(),
+            1,
+            0,
+            false,
+            sender,
+            Arc::new(AtomicBool::new(false)),
+        );
+        let key = Bytes::from_static(&[1]);
+
+        let mut calls = Vec::new();
+        let result = map
+            .update_item(
+                key.clone(),
+                |old| {
+                    calls.push(old.map(|old| old[0]));
+                    if calls.len() == 1 {
+                        map.set_item(key.clone(), Bytes::from_static(&[5]), false)
+                            .unwrap();
+                    }
+                    Ok(Some(Bytes::copy_from_slice(&[
+                        old.map_or(0, |old| old[0]) + 1
+                    ])))
+                },
+                false,
+            )
+            .unwrap();
+        assert_eq!(calls, vec![None, Some(5)]);
+        assert_eq!(result, Some(Bytes::from_static(&[6])));
+        assert_eq!(map.get_item(&key), result);
+    }
 }
@@ -0,0 +1,142 @@
+use std::fmt::Write;
+use std::net::SocketAddrV4;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::hashing::NoHashMap;
+use crate::server::sender::MessageSender;
+use crate::server::server_core::ReadOnlyClients;
+use crate::server::signals::SignalsManager;
+use crate::server::values_server::Value;
+
+const MAX_REQUEST_SIZE: usize = 8192;
+
+#[derive(Clone)]
+pub(crate) struct MetricsSource {
+    pub(crate) sender: MessageSender,
+    pub(crate) client_connected: Arc<AtomicBool>,
+    pub(crate) read_only: ReadOnlyClients,
+    pub(crate) values: NoHashMap<u64, Arc<Value>>,
+}
+
+impl MetricsSource {
+    // metrics in the Prometheus text format
+    pub(crate) fn render(&self) -> String {
+        let mut text = String::new();
+        let (messages, bytes) = self.sender.sent();
+
+        let mut metric = |name: &str, kind: &str, help: &str, value: String| {
+            let _ = writeln!(text, "# HELP {name} {help}");
+            let _ = writeln!(text, "# TYPE {name} {kind}");
+            let _ = writeln!(text, "{name} {value}");
+        };
+        metric(
+            "egui_states_connected",
+            "gauge",
+            "Whether the read-write client is connected.",
+            (self.client_connected.load(Ordering::Acquire) as u8).to_string(),
+        );
+        metric(
+            "egui_states_read_only_clients",
+            "gauge",
+            "Read-only clients connected.",
+            self.read_only.len().to_string(),
+        );
+        metric(
+            "egui_states_queue_depth",
+            "gauge",
+            "Messages waiting to be sent to the client.",
+            self.sender.pending().to_string(),
+        );
+        metric(
+            "egui_states_messages_sent_total",
+            "counter",
+            "Messages sent to the client.",
+            messages.to_string(),
+        );
+        metric(
+            "egui_states_bytes_sent_total",
+            "counter",
+            "Bytes sent to the client.",
+            bytes.to_string(),
+        );
+
+        let mut values: Vec<_> = self.values.values().collect();
+        values.sort_by(|a, b| a.name.cmp(&b.name));
+        text.push_str(
+            "# HELP egui_states_value_update_rate Updates of the value in the last second.\n",
+        );
+        text.push_str("# TYPE egui_states_value_update_rate gauge\n");
+        for value in values {
+            let _ = writeln!(
+                text,
+                "egui_states_value_update_rate{{name=\"{}\"}} {}",
+                escape_label(&value.name),
+                value.update_rate()
+            );
+        }
+
+        text
+    }
+}
+
+fn escape_label(label: &str) -> String {
+    label
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+pub(crate) async fn serve(addr: SocketAddrV4, source: MetricsSource, signals: SignalsManager) {
+    let listener = match TcpListener::bind(addr).await {
+        Ok(l) => l,
+        Err(e) => {
+            signals.error(format!("binding metrics endpoint failed: {:?}", e));
+            return;
+        }
+    };
+
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                let source = source.clone();
+                tokio::spawn(async move {
+                    let _ = respond(stream, &source).await;
+                });
+            }
+            Err(e) => signals.error(format!("accepting metrics connection failed: {:?}", e)),
+        }
+    }
+}
+
+async fn respond(mut stream: TcpStream, source: &MetricsSource) -> std::io::Result<()> {
+    // read the request head, the body is not used
+    let mut request = Vec::new();
+    let mut buffer = [0u8; 1024];
+    while !request.windows(4).any(|w| w == b"\r\n\r\n") && request.len() < MAX_REQUEST_SIZE {
+        let size = stream.read(&mut buffer).await?;
+        if size == 0 {
+            break;
+        }
+        request.extend_from_slice(&buffer[..size]);
+    }
+
+    let request = String::from_utf8_lossy(&request);
+    let mut parts = request.split_whitespace();
+    let (status, body) = match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/metrics")) => ("200 OK", source.render()),
+        _ => ("404 Not Found", "Not Found\n".to_string()),
+    };
+
+    let head = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        body.len()
+    );
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(body.as_bytes()).await?;
+    stream.shutdown().await
+}
@@ -1,13 +1,23 @@
+#[cfg(feature = "metrics-http")]
+mod metrics;
 mod sender;
 mod server_core;
 mod socket_reader;
+#[cfg(feature = "tls")]
+pub(crate) mod tls;
 
 pub(crate) mod data_server;
+#[cfg(feature = "python")]
 pub(crate) mod data_take_server;
+pub(crate) mod embedded;
 pub(crate) mod image_server;
+#[cfg(feature = "python")]
 pub(crate) mod map_server;
+#[allow(clippy::module_inception)]
 pub(crate) mod server;
 pub(crate) mod signals;
+#[cfg(feature = "python")]
 pub(crate) mod value_parsing;
 pub(crate) mod values_server;
+#[cfg(feature = "python")]
 pub(crate) mod vec_server;
@@ -1,37 +1,546 @@
-use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender, unbounded_channel};
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+#[cfg(feature = "python")]
+use std::time::{Duration, Instant};
 
+use parking_lot::{Condvar, Mutex, RwLock, RwLockReadGuard};
+use tokio::sync::Notify;
+use tokio::sync::mpsc::error::TryRecvError;
+
+use crate::hashing::NoHashMap;
 use crate::serialization::FastVec;
 
 pub(crate) type SenderData = FastVec<32>;
-pub(crate) type MessageReceiver = UnboundedReceiver<Option<(SenderData, bool)>>;
+type ChannelData = Option<(SenderData, bool)>;
+
+// pending messages after which the set methods fail if the queue size is not given
+const DEFAULT_QUEUE_SIZE: usize = 65536;
+
+// Coalescing of the queued messages when the client does not keep up:
+// - Value and Static sets are latest wins, a new set replaces the message of the same value still
+//   waiting in the queue, the new message moves to the end of the queue so it is sent after all
+//   messages queued before it, it keeps the update request of the replaced message
+// - images and data sets are not queued at all, they wait for the acknowledge of the previous
+//   message and a new image replaces the parts which were not sent yet
+// - everything else (list and map changes, data additions, signals, control messages) is never
+//   dropped or replaced, the set methods fail when the queue is full
+enum Queued {
+    Message(SenderData, bool),
+    // the message in the latest slots, the generation of the slot tells if it was replaced
+    Latest(u64, u64),
+}
+
+#[derive(Default)]
+struct Queue {
+    // the items with their queue positions, which do not change when earlier items are replaced
+    items: VecDeque<(u64, Option<Queued>)>,
+    latest: NoHashMap<u64, (SenderData, bool, u64)>,
+    generation: u64,
+    // replaced latest entries still in the items
+    stale: usize,
+    // positions where the read-only clients start, a frame does not contain messages from both sides
+    starts: VecDeque<u64>,
+}
+
+impl Queue {
+    fn is_stale(&self, item: &Option<Queued>) -> bool {
+        match item {
+            Some(Queued::Latest(id, generation)) => {
+                self.latest.get(id).is_none_or(|slot| slot.2 != *generation)
+            }
+            _ => false,
+        }
+    }
+
+    // the stale entries are removed once they are the majority, so the queue stays bounded by
+    // the live messages
+    fn compact(&mut self) {
+        if self.stale > self.items.len() / 2 {
+            let mut items = std::mem::take(&mut self.items);
+            items.retain(|(_, item)| !self.is_stale(item));
+            self.items = items;
+            self.stale = 0;
+        }
+    }
+}
+
+// position of the queue written to the sockets
+#[derive(Default)]
+struct Written {
+    client: u64,
+    // the frames still waiting for a read-only client are not written
+    read_only: NoHashMap<u64, u64>,
+    next_id: u64,
+}
+
+impl Written {
+    #[cfg(feature = "python")]
+    fn min(&self) -> u64 {
+        self.read_only
+            .values()
+            .fold(self.client, |min, position| min.min(*position))
+    }
+}
+
+#[derive(Default)]
+struct QueueStats {
+    pending: AtomicUsize,
+    messages_sent: AtomicU64,
+    bytes_sent: AtomicU64,
+    messages_received: AtomicU64,
+    bytes_received: AtomicU64,
+    // total count of queued messages, including the replaced ones, and of messages written to the
+    // socket
+    queued: AtomicU64,
+    written: Mutex<Written>,
+    flushed: Condvar,
+}
+
+#[derive(Default)]
+struct Shared {
+    queue: Mutex<Queue>,
+    notify: Notify,
+    stats: QueueStats,
+    // held while a state is changed and its messages are queued, the read-only snapshot is taken
+    // between the changes
+    changes: RwLock<()>,
+}
+
+pub(crate) struct MessageReceiver {
+    shared: Arc<Shared>,
+    // queue positions after the last received message and before it
+    received: u64,
+    previous: u64,
+}
+
+impl MessageReceiver {
+    pub(crate) async fn recv(&mut self) -> Option<ChannelData> {
+        loop {
+            if let Ok(data) = self.try_recv() {
+                return Some(data);
+            }
+            self.shared.notify.notified().await;
+        }
+    }
+
+    #[inline]
+    pub(crate) fn try_recv(&mut self) -> Result<ChannelData, TryRecvError> {
+        self.receive(false)
+    }
+
+    // the next message of the same frame, empty at the start of a read-only client
+    #[inline]
+    pub(crate) fn try_recv_batched(&mut self) -> Result<ChannelData, TryRecvError> {
+        self.receive(true)
+    }
+
+    fn receive(&mut self, batched: bool) -> Result<ChannelData, TryRecvError> {
+        let mut queue = self.shared.queue.lock();
+        loop {
+            let (position, item) = queue.items.pop_front().ok_or(TryRecvError::Empty)?;
+            if queue.is_stale(&item) {
+                queue.stale -= 1;
+                continue;
+            }
+
+            if queue.starts.front().is_some_and(|start| *start <= position) {
+                if batched {
+                    queue.items.push_front((position, item));
+                    return Err(TryRecvError::Empty);
+                }
+                while queue.starts.front().is_some_and(|start| *start <= position) {
+                    queue.starts.pop_front();
+                }
+            }
+
+            self.shared.stats.pending.fetch_sub(1, Ordering::Relaxed);
+            self.previous = self.received;
+            self.received = position + 1;
+            return Ok(match item {
+                None => None,
+                Some(Queued::Message(msg, single)) => Some((msg, single)),
+                Some(Queued::Latest(id, _)) => {
+                    // the live entry is the only one with the generation of the slot
+                    let (msg, _, _) = queue.latest.remove(&id).expect("missing latest slot");
+                    Some((msg, false))
+                }
+            });
+        }
+    }
+
+    // position of the received messages, the held back message is received but not sent yet
+    #[inline]
+    pub(crate) fn position(&self, held_back: bool) -> u64 {
+        match held_back {
+            true => self.previous,
+            false => self.received,
+        }
+    }
+
+    // counts the message written to the socket
+    #[inline]
+    pub(crate) fn record_sent(&self, size: usize, held_back: bool) {
+        self.shared
+            .stats
+            .messages_sent
+            .fetch_add(1, Ordering::Relaxed);
+        self.shared
+            .stats
+            .bytes_sent
+            .fetch_add(size as u64, Ordering::Relaxed);
+
+        self.shared.stats.written.lock().client = self.position(held_back);
+        self.shared.stats.flushed.notify_all();
+    }
+
+    #[inline]
+    pub(crate) fn is_empty(&self) -> bool {
+        self.shared.stats.pending.load(Ordering::Relaxed) == 0
+    }
+}
 
 #[derive(Clone)]
 pub(crate) struct MessageSender {
-    internal_sender: UnboundedSender<Option<(SenderData, bool)>>,
+    shared: Arc<Shared>,
+    queue_size: usize,
 }
+
 impl MessageSender {
-    pub(crate) fn new() -> (Self, MessageReceiver) {
-        let (sender, receiver) = unbounded_channel();
+    pub(crate) fn new(queue_size: Option<usize>) -> (Self, MessageReceiver) {
+        let shared = Arc::new(Shared::default());
         (
             Self {
-                internal_sender: sender,
+                shared: shared.clone(),
+                queue_size: queue_size.unwrap_or(DEFAULT_QUEUE_SIZE),
+            },
+            MessageReceiver {
+                shared,
+                received: 0,
+                previous: 0,
             },
-            receiver,
         )
     }
 
+    #[inline]
+    fn push(&self, queue: &mut Queue, data: Option<Queued>) {
+        self.shared.stats.pending.fetch_add(1, Ordering::Relaxed);
+        self.push_item(queue, data);
+    }
+
+    #[inline]
+    fn push_item(&self, queue: &mut Queue, data: Option<Queued>) {
+        let position = self.shared.stats.queued.fetch_add(1, Ordering::Relaxed);
+        queue.items.push_back((position, data));
+        self.shared.notify.notify_one();
+    }
+
+    #[inline]
+    fn send_data(&self, data: Option<Queued>) {
+        self.push(&mut self.shared.queue.lock(), data);
+    }
+
     #[inline]
     pub(crate) fn send(&self, msg: SenderData) {
-        let _ = self.internal_sender.send(Some((msg, false)));
+        self.send_data(Some(Queued::Message(msg, false)));
     }
 
     #[inline]
     pub(crate) fn send_set(&self, msg: SenderData, single: bool) {
-        let _ = self.internal_sender.send(Some((msg, single)));
+        self.send_data(Some(Queued::Message(msg, single)));
+    }
+
+    // latest wins message of the value, the message is created with the update request, returns
+    // true if it replaced the message still waiting in the queue
+    pub(crate) fn send_latest<E>(
+        &self,
+        id: u64,
+        update: bool,
+        message: impl FnOnce(bool) -> Result<SenderData, E>,
+    ) -> Result<bool, E> {
+        let mut queue = self.shared.queue.lock();
+        // keep the update request of the replaced message
+        let replaced = queue.latest.get(&id).map(|slot| slot.1);
+        let update = update || replaced.unwrap_or(false);
+        let msg = message(update)?;
+
+        queue.generation += 1;
+        let generation = queue.generation;
+        queue.latest.insert(id, (msg, update, generation));
+        match replaced {
+            Some(_) => {
+                // the replaced entry stays in the queue as stale, the message is not pending again
+                queue.stale += 1;
+                self.push_item(&mut queue, Some(Queued::Latest(id, generation)));
+                queue.compact();
+                Ok(true)
+            }
+            None => {
+                self.push(&mut queue, Some(Queued::Latest(id, generation)));
+                Ok(false)
+            }
+        }
+    }
+
+    // held while a state is changed and its messages are queued, for the changes which cannot be
+    // applied twice
+    #[inline]
+    pub(crate) fn change(&self) -> RwLockReadGuard<'_, ()> {
+        self.shared.changes.read_recursive()
+    }
+
+    // the states from f and the start of the read-only client are taken in one step without any
+    // change in progress, the messages queued before the start are in the states
+    pub(crate) fn read_only_snapshot<R>(&self, f: impl FnOnce() -> R) -> (R, ReadOnlyPosition) {
+        let _changes = self.shared.changes.write();
+        let position = {
+            let mut queue = self.shared.queue.lock();
+            let start = self.shared.stats.queued.load(Ordering::Relaxed);
+            queue.starts.push_back(start);
+            self.read_only_position(start)
+        };
+        (f(), position)
+    }
+
+    // the read-only client receives the messages after the position
+    fn read_only_position(&self, start: u64) -> ReadOnlyPosition {
+        let mut written = self.shared.stats.written.lock();
+        let id = written.next_id;
+        written.next_id += 1;
+        written.read_only.insert(id, start);
+        ReadOnlyPosition {
+            shared: self.shared.clone(),
+            id,
+            start,
+        }
     }
 
     #[inline]
     pub(crate) fn close(&self) {
-        let _ = self.internal_sender.send(None);
+        self.send_data(None);
+    }
+
+    #[inline]
+    pub(crate) fn is_busy(&self) -> bool {
+        self.shared.stats.pending.load(Ordering::Relaxed) >= self.queue_size
+    }
+
+    #[cfg(feature = "python")]
+    // wait until all messages queued so far are written to the socket
+    pub(crate) fn flush(&self, timeout: Option<Duration>) -> bool {
+        let target = self.shared.stats.queued.load(Ordering::Relaxed);
+        // without the timeout, or with too long one, waits until all messages are written
+        let deadline = timeout.and_then(|timeout| Instant::now().checked_add(timeout));
+        let mut written = self.shared.stats.written.lock();
+        // the read-only clients have to write the messages as well
+        while written.min() < target {
+            let flushed = &self.shared.stats.flushed;
+            match deadline {
+                Some(deadline) => {
+                    if flushed.wait_until(&mut written, deadline).timed_out() {
+                        return written.min() >= target;
+                    }
+                }
+                None => flushed.wait(&mut written),
+            }
+        }
+        true
+    }
+
+    // counts the message read from the socket
+    #[inline]
+    pub(crate) fn record_received(&self, size: usize) {
+        self.shared
+            .stats
+            .messages_received
+            .fetch_add(1, Ordering::Relaxed);
+        self.shared
+            .stats
+            .bytes_received
+            .fetch_add(size as u64, Ordering::Relaxed);
+    }
+
+    #[cfg(any(test, feature = "python", feature = "metrics-http"))]
+    pub(crate) fn pending(&self) -> usize {
+        self.shared.stats.pending.load(Ordering::Relaxed)
+    }
+
+    // messages and bytes written to the socket
+    #[cfg(any(feature = "python", feature = "metrics-http"))]
+    pub(crate) fn sent(&self) -> (u64, u64) {
+        (
+            self.shared.stats.messages_sent.load(Ordering::Relaxed),
+            self.shared.stats.bytes_sent.load(Ordering::Relaxed),
+        )
+    }
+
+    // messages and bytes read from the socket
+    #[cfg(feature = "python")]
+    pub(crate) fn received(&self) -> (u64, u64) {
+        (
+            self.shared.stats.messages_received.load(Ordering::Relaxed),
+            self.shared.stats.bytes_received.load(Ordering::Relaxed),
+        )
+    }
+}
+
+// messages written by the read-only client, removed from the flush when the client leaves
+pub(crate) struct ReadOnlyPosition {
+    shared: Arc<Shared>,
+    id: u64,
+    start: u64,
+}
+
+impl ReadOnlyPosition {
+    // the frames up to the start are in the snapshot
+    #[inline]
+    pub(crate) fn is_sent(&self, position: u64) -> bool {
+        position <= self.start
+    }
+
+    pub(crate) fn record(&self, position: u64) {
+        let mut written = self.shared.stats.written.lock();
+        if let Some(written) = written.read_only.get_mut(&self.id) {
+            *written = position;
+        }
+        self.shared.stats.flushed.notify_all();
+    }
+}
+
+impl Drop for ReadOnlyPosition {
+    fn drop(&mut self) {
+        self.shared.stats.written.lock().read_only.remove(&self.id);
+        self.shared.stats.flushed.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(value: u8) -> SenderData {
+        let mut data = SenderData::new();
+        data.extend_from_slice(&[value]);
+        data
+    }
+
+    fn latest(sender: &MessageSender, id: u64, value: u8, update: bool) -> bool {
+        sender
+            .send_latest(id, update, |_| Ok::<_, ()>(message(value)))
+            .unwrap()
+    }
+
+    fn received(receiver: &mut MessageReceiver) -> Vec<u8> {
+        let mut values = Vec::new();
+        while let Ok(Some((data, _))) = receiver.try_recv() {
+            values.push(data.to_bytes()[0]);
+        }
+        values
+    }
+
+    #[test]
+    fn replaced_message_moves_to_tail() {
+        let (sender, mut receiver) = MessageSender::new(None);
+        assert!(!latest(&sender, 1, 10, false));
+        sender.send(message(20));
+        assert!(latest(&sender, 1, 11, false));
+        assert!(!latest(&sender, 2, 30, false));
+
+        assert_eq!(sender.pending(), 3);
+        assert_eq!(received(&mut receiver), vec![20, 11, 30]);
+        assert!(receiver.is_empty());
+    }
+
+    #[test]
+    fn replaced_message_keeps_update_request() {
+        let (sender, _receiver) = MessageSender::new(None);
+        latest(&sender, 1, 10, true);
+        let mut requested = false;
+        sender
+            .send_latest(1, false, |update| {
+                requested = update;
+                Ok::<_, ()>(message(11))
+            })
+            .unwrap();
+        assert!(requested);
+    }
+
+    #[test]
+    fn stale_messages_do_not_grow_queue() {
+        let (sender, mut receiver) = MessageSender::new(None);
+        sender.send(message(1));
+        for i in 0..10_000 {
+            latest(&sender, 1, (i % 200) as u8, false);
+            latest(&sender, 2, (i % 200) as u8, false);
+        }
+        assert!(sender.shared.queue.lock().items.len() <= 8);
+        assert_eq!(sender.pending(), 3);
+        assert_eq!(received(&mut receiver), vec![1, 199, 199]);
+    }
+
+    #[test]
+    fn frame_ends_at_read_only_start() {
+        let (sender, mut receiver) = MessageSender::new(None);
+        sender.send(message(1));
+        let ((), position) = sender.read_only_snapshot(|| ());
+        sender.send(message(2));
+
+        receiver.try_recv().unwrap();
+        assert!(receiver.try_recv_batched().is_err());
+        assert!(position.is_sent(receiver.position(false)));
+        receiver.try_recv().unwrap();
+        assert!(!position.is_sent(receiver.position(false)));
+    }
+
+    #[test]
+    fn replaced_message_keeps_read_only_positions() {
+        let (sender, mut receiver) = MessageSender::new(None);
+        latest(&sender, 1, 10, false);
+        sender.send(message(20));
+        let ((), position) = sender.read_only_snapshot(|| ());
+        assert!(latest(&sender, 1, 11, false));
+
+        // the message queued before the start stays before it, the replacement is after it
+        let (data, _) = receiver.try_recv().unwrap().unwrap();
+        assert_eq!(data.to_bytes()[0], 20);
+        assert!(position.is_sent(receiver.position(false)));
+        let (data, _) = receiver.try_recv().unwrap().unwrap();
+        assert_eq!(data.to_bytes()[0], 11);
+        assert!(!position.is_sent(receiver.position(false)));
+    }
+
+    #[test]
+    fn queue_is_bounded_without_queue_size() {
+        let (sender, mut receiver) = MessageSender::new(None);
+        for _ in 0..DEFAULT_QUEUE_SIZE {
+            assert!(!sender.is_busy());
+            sender.send(message(0));
+        }
+        assert!(sender.is_busy());
+        receiver.try_recv().unwrap();
+        assert!(!sender.is_busy());
+    }
+
+    #[cfg(feature = "python")]
+    #[test]
+    fn flush_waits_for_read_only_clients() {
+        let (sender, mut receiver) = MessageSender::new(None);
+        let position = sender.read_only_position(0);
+        sender.send(message(1));
+        receiver.try_recv().unwrap();
+        receiver.record_sent(1, false);
+
+        // the client wrote the message, the read-only client not yet
+        let timeout = Some(Duration::from_millis(10));
+        assert!(!sender.flush(timeout));
+        position.record(receiver.position(false));
+        assert!(sender.flush(timeout));
+
+        // the messages of the left client are not waited for
+        sender.send(message(2));
+        receiver.try_recv().unwrap();
+        receiver.record_sent(1, false);
+        drop(position);
+        assert!(sender.flush(timeout));
     }
 }
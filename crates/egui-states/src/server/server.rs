@@ -12,19 +12,64 @@ use tokio::runtime::Builder;
 use crate::data_transport::DataType;
 use crate::event::Event;
 use crate::hashing::{NoHashMap, generate_value_id};
-use crate::serialization::{ServerHeader, serialize};
-use crate::server::data_server::{Data, DataMulti};
+use crate::serialization::{MAX_MSG_COUNT, MSG_SIZE_THRESHOLD, ServerHeader, serialize};
+use crate::server::data_server::Data;
+#[cfg(feature = "python")]
+use crate::server::data_server::DataMulti;
+#[cfg(feature = "python")]
 use crate::server::data_take_server::{DataMultiTake, DataTake};
 use crate::server::image_server::Image;
+#[cfg(feature = "python")]
 use crate::server::map_server::ValueMap;
-use crate::server::sender::{MessageReceiver, MessageSender};
+#[cfg(feature = "metrics-http")]
+use crate::server::metrics::{self, MetricsSource};
+use crate::server::sender::{MessageReceiver, MessageSender, SenderData};
 use crate::server::server_core;
 use crate::server::signals::SignalsManager;
-use crate::server::values_server::{Signal, Value, ValueStatic, ValueTake};
+#[cfg(feature = "python")]
+use crate::server::values_server::ValueTake;
+use crate::server::values_server::{Signal, Value, ValueStatic};
+#[cfg(feature = "python")]
 use crate::server::vec_server::ValueList;
+use crate::transport::ObjectType;
 
 pub(crate) trait SyncTrait: Sync + Send {
     fn sync(&self) -> Result<(), ()>;
+
+    // the current state for a read-only client joining the connection, the messages do not
+    // wait for the acknowledge of the client
+    fn snapshot(&self, snapshot: &mut Snapshot) -> Result<(), ()>;
+}
+
+// messages of the snapshot, batched like the messages of the queue
+#[derive(Default)]
+pub(crate) struct Snapshot {
+    frames: Vec<SenderData>,
+    batched: usize,
+}
+
+impl Snapshot {
+    pub(crate) fn push(&mut self, msg: SenderData, single: bool) {
+        match self.frames.last_mut() {
+            Some(last)
+                if !single
+                    && self.batched > 0
+                    && last.len() <= MSG_SIZE_THRESHOLD
+                    && self.batched <= MAX_MSG_COUNT =>
+            {
+                last.extend_from_data(&msg);
+                self.batched += 1;
+            }
+            _ => {
+                self.frames.push(msg);
+                self.batched = !single as usize;
+            }
+        }
+    }
+
+    pub(crate) fn into_frames(self) -> Vec<SenderData> {
+        self.frames
+    }
 }
 
 pub(crate) trait Acknowledge: Sync + Send {
@@ -38,82 +83,123 @@ pub(crate) trait Acknowledge: Sync + Send {
 #[derive(Clone, Default)]
 pub(crate) struct StatesList {
     pub(crate) values: NoHashMap<u64, Arc<Value>>,
+    #[cfg(feature = "python")]
     pub(crate) values_take: NoHashMap<u64, Arc<ValueTake>>,
     pub(crate) static_values: NoHashMap<u64, Arc<ValueStatic>>,
     pub(crate) signals: NoHashMap<u64, Arc<Signal>>,
     pub(crate) images: NoHashMap<u64, Arc<Image>>,
+    #[cfg(feature = "python")]
     pub(crate) maps: NoHashMap<u64, Arc<ValueMap>>,
+    #[cfg(feature = "python")]
     pub(crate) lists: NoHashMap<u64, Arc<ValueList>>,
     pub(crate) data: NoHashMap<u64, Arc<Data>>,
+    #[cfg(feature = "python")]
     pub(crate) data_take: NoHashMap<u64, Arc<DataTake>>,
+    #[cfg(feature = "python")]
     pub(crate) data_multi: NoHashMap<u64, Arc<DataMulti>>,
+    #[cfg(feature = "python")]
     pub(crate) data_multi_take: NoHashMap<u64, Arc<DataMultiTake>>,
 }
 
 impl StatesList {
+    fn contains_id(&self, id: u64) -> bool {
+        #[cfg(feature = "python")]
+        if self.values_take.contains_key(&id)
+            || self.maps.contains_key(&id)
+            || self.lists.contains_key(&id)
+            || self.data_take.contains_key(&id)
+            || self.data_multi.contains_key(&id)
+            || self.data_multi_take.contains_key(&id)
+        {
+            return true;
+        }
+
+        self.values.contains_key(&id)
+            || self.static_values.contains_key(&id)
+            || self.signals.contains_key(&id)
+            || self.images.contains_key(&id)
+            || self.data.contains_key(&id)
+    }
+
+    // the states are synced on connect in this order: values and statics, then maps and lists,
+    // then images and data, every kind sorted by the id, so the ui has the configuration values
+    // before the content they describe
     fn get_server_list(&self) -> ServerStatesList {
         let mut server_list = ServerStatesList::default();
 
         server_list.values.extend(self.values.clone());
         server_list.signals.extend(self.signals.clone());
 
-        for (id, value_take) in self.values_take.iter() {
-            server_list.sync.push(value_take.clone());
-            server_list.ack.insert(*id, value_take.clone());
-        }
-
-        for (id, value) in self.values.iter() {
+        for (id, value) in by_id(&self.values) {
             server_list.sync.push(value.clone());
-            server_list.ack.insert(*id, value.clone());
+            server_list.ack.insert(id, value.clone());
         }
 
-        for value in self.static_values.values() {
-            server_list.sync.push(value.clone());
+        #[cfg(feature = "python")]
+        for (id, value_take) in by_id(&self.values_take) {
+            server_list.sync.push(value_take.clone());
+            server_list.ack.insert(id, value_take.clone());
         }
 
-        for (id, image) in self.images.iter() {
-            server_list.sync.push(image.clone());
-            server_list.ack.insert(*id, image.clone());
+        for (_, value) in by_id(&self.static_values) {
+            server_list.sync.push(value.clone());
         }
 
-        for map in self.maps.values() {
+        #[cfg(feature = "python")]
+        for (_, map) in by_id(&self.maps) {
             server_list.sync.push(map.clone());
         }
 
-        for list in self.lists.values() {
+        #[cfg(feature = "python")]
+        for (_, list) in by_id(&self.lists) {
             server_list.sync.push(list.clone());
         }
 
-        for (id, data) in self.data.iter() {
+        for (id, image) in by_id(&self.images) {
+            server_list.sync.push(image.clone());
+            server_list.ack.insert(id, image.clone());
+        }
+
+        for (id, data) in by_id(&self.data) {
             server_list.sync.push(data.clone());
-            server_list.ack.insert(*id, data.clone());
+            server_list.ack.insert(id, data.clone());
         }
 
-        for (id, data_multi) in self.data_multi.iter() {
+        #[cfg(feature = "python")]
+        for (id, data_multi) in by_id(&self.data_multi) {
             server_list.sync.push(data_multi.clone());
-            server_list.ack.insert(*id, data_multi.clone());
+            server_list.ack.insert(id, data_multi.clone());
         }
 
-        for (id, data_take) in self.data_take.iter() {
+        #[cfg(feature = "python")]
+        for (id, data_take) in by_id(&self.data_take) {
             server_list.sync.push(data_take.clone());
-            server_list.ack.insert(*id, data_take.clone());
+            server_list.ack.insert(id, data_take.clone());
         }
 
-        for (id, data_multi_take) in self.data_multi_take.iter() {
+        #[cfg(feature = "python")]
+        for (id, data_multi_take) in by_id(&self.data_multi_take) {
             server_list.sync.push(data_multi_take.clone());
-            server_list.ack.insert(*id, data_multi_take.clone());
+            server_list.ack.insert(id, data_multi_take.clone());
         }
 
         server_list
     }
 }
 
+fn by_id<T>(states: &NoHashMap<u64, Arc<T>>) -> Vec<(u64, &Arc<T>)> {
+    let mut states: Vec<(u64, &Arc<T>)> = states.iter().map(|(id, s)| (*id, s)).collect();
+    states.sort_unstable_by_key(|(id, _)| *id);
+    states
+}
+
 #[derive(Clone, Default)]
 pub(crate) struct ServerStatesList {
     pub(crate) values: NoHashMap<u64, Arc<Value>>,
     pub(crate) signals: NoHashMap<u64, Arc<Signal>>,
     pub(crate) ack: NoHashMap<u64, Arc<dyn Acknowledge>>,
     pub(crate) sync: Vec<Arc<dyn SyncTrait>>,
+    pub(crate) types: Bytes,
 }
 
 enum RunnerState {
@@ -133,7 +219,10 @@ impl RunnerState {
 }
 
 pub(crate) struct Server {
+    // some client receives the messages, the states are queued only then
     connected: Arc<AtomicBool>,
+    // the read-write client is connected
+    client_connected: Arc<AtomicBool>,
     stop_event: Event,
     sender: MessageSender,
     addr: SocketAddrV4,
@@ -141,19 +230,34 @@ pub(crate) struct Server {
     states_server: Option<ServerStatesList>,
     signals: SignalsManager,
     handshake: server_core::Handshake,
+    read_only: server_core::ReadOnlyClients,
+    #[cfg(feature = "metrics-http")]
+    metrics_addr: Option<SocketAddrV4>,
 
     runner_state: RunnerState,
 }
 
 impl Server {
-    pub(crate) fn new(addr: SocketAddrV4, version: Option<u64>, token: Option<String>) -> Self {
+    pub(crate) fn new(
+        addr: SocketAddrV4,
+        version: Option<u64>,
+        token: Option<String>,
+        queue_size: Option<usize>,
+    ) -> Self {
         let connected = Arc::new(AtomicBool::new(false));
-        let (sender, rx) = MessageSender::new();
+        let (sender, rx) = MessageSender::new(queue_size);
         let signals = SignalsManager::new();
-        let handshake = server_core::Handshake { version, token };
+        let handshake = server_core::Handshake {
+            version,
+            token,
+            read_only_clients: 0,
+            #[cfg(feature = "tls")]
+            tls: None,
+        };
 
-        let obj = Self {
+        Self {
             connected,
+            client_connected: Arc::new(AtomicBool::new(false)),
             stop_event: Event::new(),
             sender,
             addr,
@@ -161,10 +265,28 @@ impl Server {
             states_server: None,
             signals,
             handshake,
+            read_only: server_core::ReadOnlyClients::default(),
+            #[cfg(feature = "metrics-http")]
+            metrics_addr: None,
             runner_state: RunnerState::Stopped(rx),
-        };
+        }
+    }
+
+    // clients have to connect over TLS with the acceptor, applied on the next start
+    #[cfg(feature = "tls")]
+    pub(crate) fn set_tls(&mut self, acceptor: Option<tokio_rustls::TlsAcceptor>) {
+        self.handshake.tls = acceptor;
+    }
+
+    // maximal count of read-only clients connected besides the client, applied on the next start
+    pub(crate) fn set_read_only_clients(&mut self, count: usize) {
+        self.handshake.read_only_clients = count;
+    }
 
-        obj
+    // metrics endpoint served while the server is running, applied on the next start
+    #[cfg(feature = "metrics-http")]
+    pub(crate) fn set_metrics_addr(&mut self, addr: Option<SocketAddrV4>) {
+        self.metrics_addr = addr;
     }
 
     pub(crate) fn finalize(&mut self) -> Option<StatesList> {
@@ -178,6 +300,25 @@ impl Server {
         }
     }
 
+    pub(crate) fn set_types(
+        &mut self,
+        types: Vec<(u64, u32, ObjectType)>,
+    ) -> Result<(), &'static str> {
+        match self.states_server.as_mut() {
+            Some(states_server) => {
+                let data = postcard::to_stdvec(&types)
+                    .map_err(|_| "Failed to serialize types registry")?;
+                states_server.types = Bytes::from(data);
+                Ok(())
+            }
+            None => Err("Server has not been finalized"),
+        }
+    }
+
+    pub(crate) fn states(&self) -> &StatesList {
+        &self.states
+    }
+
     pub(crate) fn get_signals_manager(&self) -> SignalsManager {
         self.signals.clone()
     }
@@ -192,6 +333,7 @@ impl Server {
                 let runtime = Builder::new_multi_thread()
                     .thread_name("ServerRuntime")
                     .enable_io()
+                    .enable_time()
                     .worker_threads(2)
                     .thread_keep_alive(Duration::from_hours(1))
                     .build()
@@ -199,21 +341,53 @@ impl Server {
 
                 let sender = self.sender.clone();
                 let connected = self.connected.clone();
+                let client_connected = self.client_connected.clone();
                 let stop_event = self.stop_event.clone();
                 let values = states_server.clone();
                 let signals = self.signals.clone();
 
                 let handshake = self.handshake.clone();
+                let read_only = self.read_only.clone();
                 let addr = self.addr;
+                #[cfg(feature = "metrics-http")]
+                let metrics = self.metrics_addr.map(|addr| {
+                    let source = MetricsSource {
+                        sender: sender.clone(),
+                        client_connected: client_connected.clone(),
+                        read_only: read_only.clone(),
+                        values: values.values.clone(),
+                    };
+                    (addr, source)
+                });
 
                 let server_thread = thread::Builder::new().name("StatesServer".to_string());
                 stop_event.clear();
                 let thread_handle_res = server_thread.spawn(move || {
                     runtime.block_on(async move {
-                        server_core::run(
-                            sender, rx, connected, stop_event, values, signals, addr, handshake,
+                        #[cfg(feature = "metrics-http")]
+                        let metrics = metrics.map(|(addr, source)| {
+                            tokio::spawn(metrics::serve(addr, source, signals.clone()))
+                        });
+
+                        let rx = server_core::run(
+                            sender,
+                            rx,
+                            connected,
+                            client_connected,
+                            stop_event,
+                            values,
+                            signals,
+                            addr,
+                            handshake,
+                            read_only,
                         )
-                        .await
+                        .await;
+
+                        #[cfg(feature = "metrics-http")]
+                        if let Some(metrics) = metrics {
+                            metrics.abort();
+                        }
+                        rx
                     })
                 });
 
@@ -243,6 +417,7 @@ impl Server {
             }
             RunnerState::Running(handle) => {
                 self.connected.store(false, Ordering::Release);
+                self.client_connected.store(false, Ordering::Release);
                 self.stop_event.set();
                 self.sender.close();
 
@@ -259,9 +434,12 @@ impl Server {
         }
     }
 
+    // disconnects also the read-only clients
     pub(crate) fn disconnect_client(&mut self) {
         if self.connected.load(Ordering::Acquire) {
             self.connected.store(false, Ordering::Release);
+            self.client_connected.store(false, Ordering::Release);
+            self.read_only.clear();
             self.sender.close();
         }
     }
@@ -273,8 +451,36 @@ impl Server {
         false
     }
 
+    // only the read-write client, the read-only clients are not counted
     pub(crate) fn is_connected(&self) -> bool {
-        self.connected.load(Ordering::Acquire)
+        self.client_connected.load(Ordering::Acquire)
+    }
+
+    pub(crate) fn is_busy(&self) -> bool {
+        self.connected.load(Ordering::Acquire) && self.sender.is_busy()
+    }
+
+    // (messages sent, bytes sent, messages received, bytes received, queue depth) since the start
+    #[cfg(feature = "python")]
+    pub(crate) fn stats(&self) -> (u64, u64, u64, u64, usize) {
+        let (messages_sent, bytes_sent) = self.sender.sent();
+        let (messages_received, bytes_received) = self.sender.received();
+        (
+            messages_sent,
+            bytes_sent,
+            messages_received,
+            bytes_received,
+            self.sender.pending(),
+        )
+    }
+
+    // sender to flush the queue, None if no client is connected, read-only clients included
+    #[cfg(feature = "python")]
+    pub(crate) fn flush_sender(&self) -> Option<MessageSender> {
+        match self.connected.load(Ordering::Acquire) {
+            true => Some(self.sender.clone()),
+            false => None,
+        }
     }
 
     pub(crate) fn update(&self, duration: Option<f32>) -> Result<(), ()> {
@@ -287,18 +493,29 @@ impl Server {
         Ok(())
     }
 
+    // explicit ids keep the wire id stable when the value is renamed
+    fn value_id(&self, name: &str, value_id: Option<u64>) -> Result<u64, String> {
+        match value_id {
+            None => Ok(generate_value_id(name)),
+            Some(id) if id < 10 => Err(format!("Id {} is reserved", id)),
+            Some(id) if self.states.contains_id(id) => Err(format!("Id {} is already used", id)),
+            Some(id) => Ok(id),
+        }
+    }
+
     pub(crate) fn add_value(
         &mut self,
         name: &str,
         type_id: u32,
         value: Bytes,
         queue: bool,
+        value_id: Option<u64>,
     ) -> Result<u64, String> {
         if self.states_server.is_some() {
             return Err("Cannot add new values after server has been finalized".to_string());
         }
 
-        let id = generate_value_id(&name);
+        let id = self.value_id(name, value_id)?;
         if self.states.values.contains_key(&id) {
             return Err(format!("Value with id {} already exists", id));
         }
@@ -322,12 +539,18 @@ impl Server {
         Ok(id)
     }
 
-    pub(crate) fn add_value_take(&mut self, name: &str, type_id: u32) -> Result<u64, String> {
+    #[cfg(feature = "python")]
+    pub(crate) fn add_value_take(
+        &mut self,
+        name: &str,
+        type_id: u32,
+        value_id: Option<u64>,
+    ) -> Result<u64, String> {
         if self.states_server.is_some() {
             return Err("Cannot add new values after server has been finalized".to_string());
         }
 
-        let id = generate_value_id(&name);
+        let id = self.value_id(name, value_id)?;
         if self.states.values_take.contains_key(&id) {
             return Err(format!("ValueTake with id {} already exists", id));
         }
@@ -350,12 +573,13 @@ impl Server {
         name: &str,
         type_id: u32,
         value: Bytes,
+        value_id: Option<u64>,
     ) -> Result<u64, String> {
         if self.states_server.is_some() {
             return Err("Cannot add new values after server has been finalized".to_string());
         }
 
-        let id = generate_value_id(&name);
+        let id = self.value_id(name, value_id)?;
         if self.states.static_values.contains_key(&id) {
             return Err(format!("Static value with id {} already exists", id));
         }
@@ -378,12 +602,13 @@ impl Server {
         name: &str,
         type_id: u32,
         queue: bool,
+        value_id: Option<u64>,
     ) -> Result<u64, String> {
         if self.states_server.is_some() {
             return Err("Cannot add new values after server has been finalized".to_string());
         }
 
-        let id = generate_value_id(&name);
+        let id = self.value_id(name, value_id)?;
         if self.states.signals.contains_key(&id) {
             return Err(format!("Signal with id {} already exists", id));
         }
@@ -399,12 +624,18 @@ impl Server {
         Ok(id)
     }
 
-    pub(crate) fn add_vec(&mut self, name: &str, type_id: u32) -> Result<u64, String> {
+    #[cfg(feature = "python")]
+    pub(crate) fn add_vec(
+        &mut self,
+        name: &str,
+        type_id: u32,
+        value_id: Option<u64>,
+    ) -> Result<u64, String> {
         if self.states_server.is_some() {
             return Err("Cannot add new values after server has been finalized".to_string());
         }
 
-        let id = generate_value_id(&name);
+        let id = self.value_id(name, value_id)?;
         if self.states.lists.contains_key(&id) {
             return Err(format!("Vec with id {} already exists", id));
         }
@@ -421,12 +652,19 @@ impl Server {
         Ok(id)
     }
 
-    pub(crate) fn add_map(&mut self, name: &str, type_id: u32) -> Result<u64, String> {
+    #[cfg(feature = "python")]
+    pub(crate) fn add_map(
+        &mut self,
+        name: &str,
+        type_id: u32,
+        ordered: bool,
+        value_id: Option<u64>,
+    ) -> Result<u64, String> {
         if self.states_server.is_some() {
             return Err("Cannot add new values after server has been finalized".to_string());
         }
 
-        let id = generate_value_id(&name);
+        let id = self.value_id(name, value_id)?;
         if self.states.maps.contains_key(&id) {
             return Err(format!("Map with id {} already exists", id));
         }
@@ -435,6 +673,7 @@ impl Server {
             name.to_string(),
             id,
             type_id,
+            ordered,
             self.sender.clone(),
             self.connected.clone(),
         );
@@ -444,12 +683,17 @@ impl Server {
         Ok(id)
     }
 
-    pub(crate) fn add_image(&mut self, name: &str) -> Result<u64, String> {
+    pub(crate) fn add_image(
+        &mut self,
+        name: &str,
+        premultiply: bool,
+        value_id: Option<u64>,
+    ) -> Result<u64, String> {
         if self.states_server.is_some() {
             return Err("Cannot add new values after server has been finalized".to_string());
         }
 
-        let id = generate_value_id(&name);
+        let id = self.value_id(name, value_id)?;
         if self.states.images.contains_key(&id) {
             return Err(format!("Image with id {} already exists", id));
         }
@@ -459,18 +703,24 @@ impl Server {
             id,
             self.sender.clone(),
             self.connected.clone(),
+            premultiply,
         );
 
         self.states.images.insert(id, val);
         Ok(id)
     }
 
-    pub(crate) fn add_data(&mut self, name: &str, type_id: u8) -> Result<u64, String> {
+    pub(crate) fn add_data(
+        &mut self,
+        name: &str,
+        type_id: u8,
+        value_id: Option<u64>,
+    ) -> Result<u64, String> {
         if self.states_server.is_some() {
             return Err("Cannot add new values after server has been finalized".to_string());
         }
 
-        let id = generate_value_id(&name);
+        let id = self.value_id(name, value_id)?;
         if self.states.data.contains_key(&id) {
             return Err(format!("Data with id {} already exists", id));
         }
@@ -489,12 +739,18 @@ impl Server {
         Ok(id)
     }
 
-    pub(crate) fn add_data_multi(&mut self, name: &str, type_id: u8) -> Result<u64, String> {
+    #[cfg(feature = "python")]
+    pub(crate) fn add_data_multi(
+        &mut self,
+        name: &str,
+        type_id: u8,
+        value_id: Option<u64>,
+    ) -> Result<u64, String> {
         if self.states_server.is_some() {
             return Err("Cannot add new values after server has been finalized".to_string());
         }
 
-        let id = generate_value_id(&name);
+        let id = self.value_id(name, value_id)?;
         if self.states.data_multi.contains_key(&id) {
             return Err(format!("DataMulti with id {} already exists", id));
         }
@@ -513,12 +769,18 @@ impl Server {
         Ok(id)
     }
 
-    pub(crate) fn add_data_take(&mut self, name: &str, type_id: u8) -> Result<u64, String> {
+    #[cfg(feature = "python")]
+    pub(crate) fn add_data_take(
+        &mut self,
+        name: &str,
+        type_id: u8,
+        value_id: Option<u64>,
+    ) -> Result<u64, String> {
         if self.states_server.is_some() {
             return Err("Cannot add new values after server has been finalized".to_string());
         }
 
-        let id = generate_value_id(&name);
+        let id = self.value_id(name, value_id)?;
         if self.states.data_take.contains_key(&id) {
             return Err(format!("DataTake with id {} already exists", id));
         }
@@ -537,12 +799,18 @@ impl Server {
         Ok(id)
     }
 
-    pub(crate) fn add_data_multi_take(&mut self, name: &str, type_id: u8) -> Result<u64, String> {
+    #[cfg(feature = "python")]
+    pub(crate) fn add_data_multi_take(
+        &mut self,
+        name: &str,
+        type_id: u8,
+        value_id: Option<u64>,
+    ) -> Result<u64, String> {
         if self.states_server.is_some() {
             return Err("Cannot add new values after server has been finalized".to_string());
         }
 
-        let id = generate_value_id(&name);
+        let id = self.value_id(name, value_id)?;
         if self.states.data_multi_take.contains_key(&id) {
             return Err(format!("DataMultiTake with id {} already exists", id));
         }
@@ -561,3 +829,27 @@ impl Server {
         Ok(id)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(size: usize) -> SenderData {
+        let mut data = SenderData::new();
+        data.extend_from_slice(&vec![0; size]);
+        data
+    }
+
+    #[test]
+    fn snapshot_batches_messages_like_queue() {
+        let mut snapshot = Snapshot::default();
+        for _ in 0..MAX_MSG_COUNT + 2 {
+            snapshot.push(message(1), false);
+        }
+        snapshot.push(message(2), true);
+        snapshot.push(message(1), false);
+
+        let sizes: Vec<usize> = snapshot.into_frames().iter().map(|f| f.len()).collect();
+        assert_eq!(sizes, vec![MAX_MSG_COUNT + 1, 1, 2, 1]);
+    }
+}
@@ -3,21 +3,47 @@ use std::sync::{
     Arc,
     atomic::{AtomicBool, Ordering},
 };
+use std::time::Duration;
 
+use bytes::Bytes;
 use futures_util::{SinkExt, StreamExt, stream::SplitSink};
-use tokio::net::{TcpListener, TcpStream};
+use parking_lot::Mutex;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
 use tokio::sync::mpsc::error::TryRecvError;
 use tokio::task::JoinHandle;
+use tokio::time::timeout;
 use tokio_tungstenite::WebSocketStream;
+use tokio_tungstenite::tungstenite::handshake::server::{Request, Response};
+use tokio_tungstenite::tungstenite::protocol::CloseFrame;
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
 use tokio_tungstenite::tungstenite::{Message, protocol::WebSocketConfig};
 
-use crate::PROTOCOL_VERSION;
 use crate::event::Event;
-use crate::serialization::{MAX_MSG_COUNT, MSG_SIZE_THRESHOLD, ServerHeader, serialize};
-use crate::server::sender::{MessageReceiver, MessageSender, SenderData};
-use crate::server::server::ServerStatesList;
+#[cfg(feature = "compression")]
+use crate::serialization::COMPRESSION_THRESHOLD;
+use crate::serialization::{
+    HANDSHAKE_CHECKSUM, HANDSHAKE_COMPRESSION, HANDSHAKE_READ_ONLY, MAX_MSG_COUNT,
+    MSG_SIZE_THRESHOLD, ServerHeader, serialize,
+};
+use crate::server::sender::{MessageReceiver, MessageSender, ReadOnlyPosition, SenderData};
+use crate::server::server::{ServerStatesList, Snapshot};
 use crate::server::signals::SignalsManager;
 use crate::server::socket_reader::{ClientMessage, SocketReader};
+use crate::{PROTOCOL_MINOR, PROTOCOL_VERSION};
+
+#[cfg(feature = "tls")]
+pub(crate) use crate::server::tls::ServerStream;
+#[cfg(not(feature = "tls"))]
+pub(crate) type ServerStream = tokio::net::TcpStream;
+
+type SocketSink = SplitSink<WebSocketStream<ServerStream>, Message>;
+
+// frames waiting for a read-only client, a slower client is disconnected
+const READ_ONLY_QUEUE: usize = 1024;
+// connections are accepted one by one, a peer which stalls in the TLS, websocket or states
+// handshake must not block the others
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
 
 enum ChannelHolder {
     Transfer(JoinHandle<MessageReceiver>),
@@ -28,24 +54,137 @@ enum ChannelHolder {
 pub(crate) struct Handshake {
     pub version: Option<u64>,
     pub token: Option<String>,
+    pub read_only_clients: usize,
+    #[cfg(feature = "tls")]
+    pub tls: Option<tokio_rustls::TlsAcceptor>,
+}
+
+// how the frames are sent to the client, negotiated in the handshake
+#[derive(Clone, Copy)]
+struct FrameOptions {
+    compression: bool,
+    checksum: bool,
+}
+
+// the frame with the queue position after it
+type ReadOnlyFrame = (Bytes, u64);
+
+struct ReadOnlyClient {
+    tx: mpsc::Sender<ReadOnlyFrame>,
+    options: FrameOptions,
+}
+
+// clients receiving the same frames as the client, without it they keep the writer running
+#[derive(Clone, Default)]
+pub(crate) struct ReadOnlyClients(Arc<Mutex<Vec<ReadOnlyClient>>>);
+
+impl ReadOnlyClients {
+    pub(crate) fn len(&self) -> usize {
+        self.0.lock().len()
+    }
+
+    // dropping the channels closes the connections
+    pub(crate) fn clear(&self) {
+        self.0.lock().clear();
+    }
+
+    fn add(&self, tx: mpsc::Sender<ReadOnlyFrame>, options: FrameOptions) {
+        self.0.lock().push(ReadOnlyClient { tx, options });
+    }
+
+    // returns false if no read-only client is left
+    fn send(&self, frame: &mut Frame, position: u64) -> bool {
+        let mut clients = self.0.lock();
+        clients.retain(|client| {
+            client
+                .tx
+                .try_send((frame.get(client.options), position))
+                .is_ok()
+        });
+        !clients.is_empty()
+    }
 }
 
+// the read-write client
+struct ClientConnection {
+    websocket: SocketSink,
+    reader: JoinHandle<()>,
+    options: FrameOptions,
+}
+
+impl ClientConnection {
+    async fn close(mut self) {
+        let _ = self.websocket.close().await;
+        self.reader.abort();
+        let _ = self.reader.await;
+    }
+}
+
+// the frame is compressed once for all clients asking for it
+struct Frame {
+    plain: Bytes,
+    #[cfg(feature = "compression")]
+    compressed: Option<Bytes>,
+}
+
+impl Frame {
+    fn new(msg: SenderData) -> Self {
+        Self {
+            plain: msg.to_bytes(),
+            #[cfg(feature = "compression")]
+            compressed: None,
+        }
+    }
+
+    fn get(&mut self, options: FrameOptions) -> Bytes {
+        let data = self.payload(options.compression);
+        // the checksum covers the frame as it is sent, after the compression
+        #[cfg(feature = "checksum")]
+        if options.checksum {
+            return ServerHeader::checksum_frame::<32>(&data).to_bytes();
+        }
+        #[cfg(not(feature = "checksum"))]
+        let _ = options.checksum;
+        data
+    }
+
+    fn payload(&mut self, compression: bool) -> Bytes {
+        #[cfg(feature = "compression")]
+        if compression && self.plain.len() > COMPRESSION_THRESHOLD {
+            let plain = &self.plain;
+            return self
+                .compressed
+                .get_or_insert_with(|| {
+                    ServerHeader::compress_frame::<32>(plain)
+                        .map_or_else(|| plain.clone(), |data| data.to_bytes())
+                })
+                .clone();
+        }
+        #[cfg(not(feature = "compression"))]
+        let _ = compression;
+        self.plain.clone()
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 pub(crate) async fn run(
     sender: MessageSender,
     rx: MessageReceiver,
     connected: Arc<AtomicBool>,
+    client_connected: Arc<AtomicBool>,
     stop_event: Event,
     values: ServerStatesList,
     signals: SignalsManager,
     addr: SocketAddrV4,
     handshake: Handshake,
+    read_only: ReadOnlyClients,
 ) -> MessageReceiver {
     // listen to incoming connections
     let listener = match TcpListener::bind(addr).await {
         Ok(l) => l,
         Err(e) => {
             stop_event.clear();
-            signals.error(&format!("binding failed: {:?}", e));
+            signals.error(format!("binding failed: {:?}", e));
             return rx;
         }
     };
@@ -68,38 +207,83 @@ pub(crate) async fn run(
         let stream = match stream {
             Ok(s) => s.0,
             Err(e) => {
-                signals.error(&format!("accepting connection failed: {:?}", e));
+                signals.error(format!("accepting connection failed: {:?}", e));
                 continue;
             }
         };
 
         if let Err(e) = stream.set_nodelay(true) {
-            signals.error(&format!("failed to set TCP_NODELAY: {:?}", e));
+            signals.error(format!("failed to set TCP_NODELAY: {:?}", e));
             continue;
         }
 
+        #[cfg(feature = "tls")]
+        let stream = match &handshake.tls {
+            Some(acceptor) => match timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                Ok(Ok(stream)) => ServerStream::Tls(Box::new(stream)),
+                Ok(Err(e)) => {
+                    signals.error(format!("TLS handshake failed: {:?}", e));
+                    continue;
+                }
+                Err(_) => {
+                    signals.warning("TLS handshake timed out");
+                    continue;
+                }
+            },
+            None => ServerStream::Plain(stream),
+        };
+
         let mut websocket_config = WebSocketConfig::default();
         websocket_config.max_message_size = Some(536870912); // 512 MB
         websocket_config.max_frame_size = Some(536870912); // 512 MB
-        let websocket =
-            match tokio_tungstenite::accept_async_with_config(stream, Some(websocket_config)).await
+        // token from the url query, used if the handshake has no token
+        let mut query_token = None;
+        // the error type is given by tungstenite
+        #[allow(clippy::result_large_err)]
+        let callback = |request: &Request, mut response: Response| {
+            query_token = request.uri().query().and_then(token_from_query);
+            // browsers close the connection if the requested subprotocol is not accepted
+            if let Some(protocol) = request.headers().get("Sec-WebSocket-Protocol")
+                && let Ok(protocol) = protocol.to_str()
+                && let Some(first) = protocol.split(',').next()
+                && let Ok(value) = first.trim().parse()
             {
-                Ok(ws) => ws,
-                Err(e) => {
-                    signals.error(&format!("websocket handshake failed: {:?}", e));
-                    connected.store(false, Ordering::Release);
-                    continue;
-                }
-            };
+                response
+                    .headers_mut()
+                    .insert("Sec-WebSocket-Protocol", value);
+            }
+            Ok(response)
+        };
+        let accept = tokio_tungstenite::accept_hdr_async_with_config(
+            stream,
+            callback,
+            Some(websocket_config),
+        );
+        let websocket = match timeout(HANDSHAKE_TIMEOUT, accept).await {
+            Ok(Ok(ws)) => ws,
+            Ok(Err(e)) => {
+                signals.error(format!("websocket handshake failed: {:?}", e));
+                connected.store(false, Ordering::Release);
+                continue;
+            }
+            Err(_) => {
+                signals.warning("websocket handshake timed out");
+                continue;
+            }
+        };
 
         let peer_addr = websocket
             .get_ref()
             .peer_addr()
             .map_or("".to_string(), |addr| addr.to_string());
         let (socket_tx, socket_rx) = websocket.split();
-        let mut socket_reader = SocketReader::new(socket_rx);
+        let mut socket_reader = SocketReader::new(socket_rx, sender.clone());
 
-        match socket_reader.next().await {
+        let Ok(message) = timeout(HANDSHAKE_TIMEOUT, socket_reader.next()).await else {
+            signals.warning("client handshake timed out");
+            continue;
+        };
+        match message {
             Err(e) => {
                 if let Some(e) = e {
                     signals.error(e);
@@ -107,9 +291,108 @@ pub(crate) async fn run(
                 connected.store(false, Ordering::Release);
                 continue;
             }
-            Ok(ClientMessage::Handshake(p, v, h)) => {
-                if let Err(e) = check_handshake(&handshake, p, v, h) {
+            Ok(ClientMessage::Handshake(p, minor, v, h, request_types, options)) => {
+                if p != PROTOCOL_VERSION {
+                    signals.warning(format!(
+                        "attempted to connect with wrong protocol version: expected {}, got {}",
+                        PROTOCOL_VERSION, p
+                    ));
+                    reject(socket_tx).await;
+                    continue;
+                }
+                if let Err(e) = check_handshake(&handshake, v, h.or(query_token)) {
                     signals.warning(&e);
+                    refuse(socket_tx, e).await;
+                    continue;
+                }
+                // the lower minor version is used, it gates the optional features
+                let minor = minor.min(PROTOCOL_MINOR);
+                // the client has to ask for compressed frames
+                let compression = cfg!(feature = "compression")
+                    && minor >= 1
+                    && options & HANDSHAKE_COMPRESSION == HANDSHAKE_COMPRESSION;
+                // servers without the checksum support send the frames without it
+                let checksum = cfg!(feature = "checksum")
+                    && minor >= 1
+                    && options & HANDSHAKE_CHECKSUM == HANDSHAKE_CHECKSUM;
+                let frame_options = FrameOptions {
+                    compression,
+                    checksum,
+                };
+
+                if options & HANDSHAKE_READ_ONLY == HANDSHAKE_READ_ONLY {
+                    if read_only.len() >= handshake.read_only_clients {
+                        let e = "read-only clients limit reached".to_string();
+                        signals.warning(&e);
+                        refuse(socket_tx, e).await;
+                        continue;
+                    }
+
+                    // the types registry goes only to the new client
+                    let mut socket_tx = socket_tx;
+                    if request_types {
+                        let sent = match ServerHeader::serialize_types::<32>(&values.types) {
+                            Ok(data) => socket_tx
+                                .send(Message::Binary(data.to_bytes()))
+                                .await
+                                .is_ok(),
+                            Err(_) => {
+                                signals.error("failed to serialize types registry");
+                                false
+                            }
+                        };
+                        if !sent {
+                            let _ = socket_tx.close().await;
+                            continue;
+                        }
+                    }
+
+                    let (tx, frames_rx) = mpsc::channel(READ_ONLY_QUEUE);
+                    read_only.add(tx, frame_options);
+
+                    // without the client the writer has to be started for the read-only clients
+                    let running = matches!(&holder, ChannelHolder::Transfer(handler) if !handler.is_finished());
+                    if !running {
+                        let mut rx = match holder {
+                            ChannelHolder::Transfer(handler) => {
+                                handler.await.expect("joining communication handler failed")
+                            }
+                            ChannelHolder::Rx(rx) => rx,
+                        };
+                        while !rx.is_empty() {
+                            let _ = rx.recv().await;
+                        }
+                        connected.store(true, Ordering::Release);
+                        holder = ChannelHolder::Transfer(tokio::spawn(writer(
+                            rx,
+                            connected.clone(),
+                            client_connected.clone(),
+                            None,
+                            read_only.clone(),
+                            signals.clone(),
+                        )));
+                    }
+
+                    // the states go only to the new client, the changes queued before them are not
+                    // sent to it, waits for the changes in progress
+                    let (snapshot, position) = tokio::task::block_in_place(|| {
+                        sender.read_only_snapshot(|| snapshot(&values, frame_options))
+                    });
+                    let snapshot = match snapshot {
+                        Ok(snapshot) => snapshot,
+                        Err(e) => {
+                            signals.error(e);
+                            continue;
+                        }
+                    };
+                    tokio::spawn(read_only_client(
+                        socket_tx,
+                        socket_reader,
+                        snapshot,
+                        frames_rx,
+                        position,
+                    ));
+                    signals.info(format!("read-only client connected: {}", peer_addr));
                     continue;
                 }
 
@@ -130,46 +413,51 @@ pub(crate) async fn run(
                     let _ = rx.recv().await;
                 }
 
-                // std::thread::sleep(std::time::Duration::from_millis(100));
-                connected.store(true, Ordering::Release);
-                let mut success = true;
-                for v in values.sync.iter() {
-                    if let Err(_) = v.sync() {
-                        success = false;
-                        break;
+                // send types registry before the values, so the client can decode them
+                if request_types {
+                    match ServerHeader::serialize_types(&values.types) {
+                        Ok(data) => sender.send_set(data, true),
+                        Err(_) => {
+                            signals.error("failed to serialize types registry");
+                            holder = ChannelHolder::Rx(rx);
+                            break;
+                        }
                     }
                 }
-                if !success {
-                    signals.error("failed to sync value after handshake");
+
+                // std::thread::sleep(std::time::Duration::from_millis(100));
+                connected.store(true, Ordering::Release);
+                if let Err(e) = sync_all(&values, &sender) {
+                    signals.error(e);
                     connected.store(false, Ordering::Release);
                     holder = ChannelHolder::Rx(rx);
                     break;
                 }
-                match serialize(&ServerHeader::Update(0.0)) {
-                    Ok(data) => sender.send(data),
-                    Err(_) => {
-                        signals.error("failed to serialize update message");
-                        connected.store(false, Ordering::Release);
-                        holder = ChannelHolder::Rx(rx);
-                        break;
-                    }
-                }
 
+                client_connected.store(true, Ordering::Release);
                 signals.on_connect(peer_addr);
 
                 let reader_handler = tokio::spawn(reader(
                     socket_reader,
                     connected.clone(),
+                    client_connected.clone(),
+                    read_only.clone(),
                     signals.clone(),
                     values.clone(),
                     sender.clone(),
                 ));
+                let client = ClientConnection {
+                    websocket: socket_tx,
+                    reader: reader_handler,
+                    options: frame_options,
+                };
                 let handler = tokio::spawn(writer(
                     rx,
                     connected.clone(),
-                    socket_tx,
+                    client_connected.clone(),
+                    Some(client),
+                    read_only.clone(),
                     signals.clone(),
-                    reader_handler,
                 ));
 
                 holder = ChannelHolder::Transfer(handler);
@@ -189,26 +477,135 @@ pub(crate) async fn run(
             signals.debug("terminating previous connection");
             connected.store(false, Ordering::Release);
             sender.close();
+            read_only.clear();
             handler.await.expect("joining communication handler failed")
         }
         ChannelHolder::Rx(rx) => rx,
     }
 }
 
+// sends all states with the update, every connected client receives them
+fn sync_all(values: &ServerStatesList, sender: &MessageSender) -> Result<(), &'static str> {
+    for v in values.sync.iter() {
+        v.sync()
+            .map_err(|_| "failed to sync value after handshake")?;
+    }
+    let data =
+        serialize(&ServerHeader::Update(0.0)).map_err(|_| "failed to serialize update message")?;
+    sender.send(data);
+    Ok(())
+}
+
+// all states for the read-only client, the other clients are not synchronized again
+fn snapshot(values: &ServerStatesList, options: FrameOptions) -> Result<Vec<Bytes>, &'static str> {
+    let mut snapshot = Snapshot::default();
+    for v in values.sync.iter() {
+        v.snapshot(&mut snapshot)
+            .map_err(|_| "failed to create the states snapshot")?;
+    }
+    let data =
+        serialize(&ServerHeader::Update(0.0)).map_err(|_| "failed to serialize update message")?;
+    snapshot.push(data, false);
+
+    let frames = snapshot
+        .into_frames()
+        .into_iter()
+        .map(|msg| Frame::new(stamp(msg)).get(options))
+        .collect();
+    Ok(frames)
+}
+
+// messages of the read-only client are ignored, only the closing is detected
+async fn read_only_client(
+    mut socket_tx: SocketSink,
+    mut socket_rx: SocketReader,
+    snapshot: Vec<Bytes>,
+    mut frames: mpsc::Receiver<ReadOnlyFrame>,
+    position: ReadOnlyPosition,
+) {
+    for frame in snapshot {
+        if socket_tx.send(Message::Binary(frame)).await.is_err() {
+            let _ = socket_tx.close().await;
+            return;
+        }
+    }
+
+    loop {
+        tokio::select! {
+            frame = frames.recv() => match frame {
+                Some((frame, frame_position)) => {
+                    if position.is_sent(frame_position) {
+                        continue;
+                    }
+                    if socket_tx.send(Message::Binary(frame)).await.is_err() {
+                        break;
+                    }
+                    position.record(frame_position);
+                }
+                None => break,
+            },
+            message = socket_rx.next() => {
+                if message.is_err() {
+                    break;
+                }
+            }
+        }
+    }
+    let _ = socket_tx.close().await;
+}
+
+// percent-decoded value of the token parameter
+fn token_from_query(query: &str) -> Option<String> {
+    let value = query
+        .split('&')
+        .find_map(|param| param.strip_prefix("token="))?;
+
+    let mut bytes = Vec::with_capacity(value.len());
+    let mut rest = value.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        match byte {
+            b'%' if tail.len() >= 2 => {
+                let hex = std::str::from_utf8(&tail[..2]).ok()?;
+                bytes.push(u8::from_str_radix(hex, 16).ok()?);
+                rest = &tail[2..];
+            }
+            b'+' => {
+                bytes.push(b' ');
+                rest = tail;
+            }
+            _ => {
+                bytes.push(byte);
+                rest = tail;
+            }
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+// sends the server protocol version before closing, so the client can report the mismatch
+async fn reject(mut socket_tx: SocketSink) {
+    let header = ServerHeader::Rejected(PROTOCOL_VERSION, PROTOCOL_MINOR);
+    if let Ok(data) = serialize::<_, 16>(&header) {
+        let _ = socket_tx.send(Message::Binary(data.to_bytes())).await;
+    }
+    let _ = socket_tx.close().await;
+}
+
+// the client reports the reason of the policy close as the refused handshake
+async fn refuse(mut socket_tx: SocketSink, reason: String) {
+    let frame = CloseFrame {
+        code: CloseCode::Policy,
+        reason: reason.into(),
+    };
+    let _ = socket_tx.send(Message::Close(Some(frame))).await;
+    let _ = socket_tx.close().await;
+}
+
 fn check_handshake(
     server_handshake: &Handshake,
-    client_protocol: u16,
     version: Option<u64>,
     token: Option<String>,
 ) -> Result<(), String> {
-    if client_protocol != PROTOCOL_VERSION {
-        let message = format!(
-            "attempted to connect with wrong protocol version: expected {}, got {}",
-            PROTOCOL_VERSION, client_protocol
-        );
-        return Err(message);
-    }
-
     if let Some(server_version) = server_handshake.version {
         match version {
             Some(client_version) => {
@@ -241,6 +638,8 @@ fn check_handshake(
 async fn reader(
     mut socket_rx: SocketReader,
     connected: Arc<AtomicBool>,
+    client_connected: Arc<AtomicBool>,
+    read_only: ReadOnlyClients,
     signals: SignalsManager,
     values: ServerStatesList,
     sender: MessageSender,
@@ -262,7 +661,6 @@ async fn reader(
                 if let Some(e) = e {
                     signals.error(e);
                 }
-                connected.store(false, Ordering::Release);
                 signals.reset();
                 break;
             }
@@ -273,30 +671,46 @@ async fn reader(
                         val.acknowledge();
                     }
                     None => {
-                        signals.error(&format!("value with id {} not found for Acknowledge", id))
+                        signals.error(format!("value with id {} not found for Acknowledge", id))
                     }
                 }
             }
             Ok(ClientMessage::Value(id, type_id, signal, data)) => match values.values.get(&id) {
                 Some(val) => {
                     if let Err(e) = val.update_value(type_id, signal, data) {
-                        signals.error(&format!("value updating failed: {}", e));
+                        signals.log(
+                            3,
+                            format!("value updating failed: {}", e),
+                            &[("value_id", id.to_string()), ("name", val.name.clone())],
+                        );
                     }
                 }
-                None => signals.error(&format!("value with id {} not found", id)),
+                None => signals.log(
+                    3,
+                    format!("value with id {} not found", id),
+                    &[("value_id", id.to_string())],
+                ),
             },
             Ok(ClientMessage::Signal(id, type_id, data)) => match values.signals.get(&id) {
                 Some(val) => {
                     if let Err(e) = val.update_signal(type_id, data) {
-                        signals.error(&format!("signal updating failed: {}", e));
+                        signals.log(
+                            3,
+                            format!("signal updating failed: {}", e),
+                            &[("value_id", id.to_string()), ("name", val.name.clone())],
+                        );
                     }
                 }
-                None => signals.error(&format!("value with id {} not found", id)),
+                None => signals.log(
+                    3,
+                    format!("value with id {} not found", id),
+                    &[("value_id", id.to_string())],
+                ),
             },
             Ok(ClientMessage::Message(data)) => {
                 signals.client_message(data);
             }
-            Ok(ClientMessage::Handshake(_, _, _)) => {
+            Ok(ClientMessage::Handshake(..)) => {
                 signals.error("unexpected handshake message after connection established");
             }
         }
@@ -309,7 +723,14 @@ async fn reader(
         v.reset();
     }
 
+    // the writer keeps sending to the read-only clients
+    client_connected.store(false, Ordering::Release);
+    if connected.load(Ordering::Acquire) && read_only.len() > 0 {
+        return;
+    }
+
     // send close signal to writing thread if reading fails
+    connected.store(false, Ordering::Release);
     #[cfg(debug_assertions)]
     signals.debug("terminating write thread");
     sender.close();
@@ -318,10 +739,12 @@ async fn reader(
 async fn writer(
     rx: MessageReceiver,
     connected: Arc<AtomicBool>,
-    mut websocket: SplitSink<WebSocketStream<TcpStream>, Message>,
+    client_connected: Arc<AtomicBool>,
+    mut client: Option<ClientConnection>,
+    read_only: ReadOnlyClients,
     signals: SignalsManager,
-    reader_handle: tokio::task::JoinHandle<()>,
 ) -> MessageReceiver {
+    let has_client = client.is_some();
     let mut data_receiver = DataReceiver::new(rx);
     loop {
         // get message from channel
@@ -329,34 +752,68 @@ async fn writer(
             Some(msg) => {
                 // if not connected, stop thread
                 if !connected.load(Ordering::Acquire) {
-                    let _ = websocket.close().await;
-                    reader_handle.abort();
-                    let _ = reader_handle.await;
+                    if let Some(client) = client.take() {
+                        client.close().await;
+                    }
                     break;
                 }
 
+                // the client left, the read-only clients are still connected
+                if let Some(client) = client.take_if(|_| !client_connected.load(Ordering::Acquire))
+                {
+                    client.close().await;
+                }
+
                 // send message
-                let data = Message::Binary(msg.to_bytes());
-                if let Err(e) = websocket.send(data).await {
-                    signals.error(&format!("sending message to client failed: {:?}", e));
-                    reader_handle.abort();
-                    let _ = reader_handle.await;
+                let mut frame = Frame::new(stamp(msg));
+                let mut size = frame.plain.len();
+                if let Some(connection) = client.as_mut() {
+                    let data = frame.get(connection.options);
+                    size = data.len();
+                    if let Err(e) = connection.websocket.send(Message::Binary(data)).await {
+                        signals.error(format!("sending message to client failed: {:?}", e));
+                        connection.reader.abort();
+                        let _ = (&mut connection.reader).await;
+                        break;
+                    }
+                }
+                let read_only_left = read_only.send(&mut frame, data_receiver.position());
+                data_receiver.record_sent(size);
+
+                if client.is_none() && !read_only_left {
+                    connected.store(false, Ordering::Release);
                     break;
                 }
             }
             // check if message is terminate signal
             None => {
                 signals.info("writer is closing connection");
-                let _ = websocket.close().await;
-                reader_handle.abort();
-                let _ = reader_handle.await;
+                if let Some(client) = client.take() {
+                    client.close().await;
+                }
                 break;
             }
         }
     }
+    if has_client {
+        client_connected.store(false, Ordering::Release);
+    }
     data_receiver.finalize()
 }
 
+// stamp the message with the send time, used to measure latency on the client
+fn stamp(msg: SenderData) -> SenderData {
+    #[cfg(feature = "timestamps")]
+    let msg = {
+        let mut msg = msg;
+        if let Ok(stamp) = ServerHeader::serialize_timestamp::<16>() {
+            msg.extend_from_data(&stamp);
+        }
+        msg
+    };
+    msg
+}
+
 // A helper struct to receive from MessageReceiver and create micro-batches
 struct DataReceiver {
     rx: MessageReceiver,
@@ -393,7 +850,7 @@ impl DataReceiver {
                             break Some(msg);
                         }
 
-                        match self.rx.try_recv() {
+                        match self.rx.try_recv_batched() {
                             Ok(Some((next_msg, send_now))) => match send_now {
                                 true => {
                                     self.send_next = Some(next_msg);
@@ -419,6 +876,14 @@ impl DataReceiver {
         }
     }
 
+    fn position(&self) -> u64 {
+        self.rx.position(self.send_next.is_some())
+    }
+
+    fn record_sent(&self, size: usize) {
+        self.rx.record_sent(size, self.send_next.is_some());
+    }
+
     fn finalize(self) -> MessageReceiver {
         self.rx
     }
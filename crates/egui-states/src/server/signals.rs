@@ -1,12 +1,15 @@
 use std::collections::{VecDeque, hash_map::Entry};
-use std::sync::Arc;
+use std::sync::{Arc, Weak};
+use std::thread;
+use std::time::{Duration, Instant};
 
 use bytes::Bytes;
-use parking_lot::Mutex;
+use parking_lot::{Mutex, RwLock};
+use serde::Deserialize;
 
-use crate::hashing::{NoHashMap, NoHashSet};
-use crate::serialization::{FastVec, serialize, serialize_to_data};
 use crate::event::Event;
+use crate::hashing::{NoHashMap, NoHashSet};
+use crate::serialization::{FastVec, deserialize_value, serialize, serialize_to_data};
 
 pub(crate) const LOGGING_ID: u64 = 0;
 pub(crate) const ON_CONNECT_ID: u64 = 1;
@@ -23,6 +26,9 @@ struct ChangedInner {
     indexes: Vec<u64>,              // scheduling order; may contain stale duplicate IDs
     blocked_list: NoHashSet<u64>,   // ids blocked by some thread
     registered: NoHashSet<u64>,     // ids which are registered to be signaled
+    suppressed: NoHashSet<u64>,     // ids with temporarily disabled signals
+    rate_limits: NoHashMap<u64, (Duration, Option<Instant>)>, // min interval, last delivery
+    handled: NoHashSet<u64>,        // ids delivered to the Rust handlers, not to wait_changed_value
 }
 
 impl ChangedInner {
@@ -32,6 +38,9 @@ impl ChangedInner {
             indexes: Vec::new(),
             blocked_list: NoHashSet::default(),
             registered: NoHashSet::default(),
+            suppressed: NoHashSet::default(),
+            rate_limits: NoHashMap::default(),
+            handled: NoHashSet::default(),
         }
     }
 
@@ -41,11 +50,27 @@ impl ChangedInner {
         self.blocked_list.retain(|id| *id <= 9);
     }
 
-    fn set(&mut self, id: u64, value: Bytes, event: &Event) {
-        if !self.registered.contains(&id) {
+    fn set(&mut self, id: u64, value: Bytes, event: &Event, handler_event: &Event) {
+        if !self.registered.contains(&id) || self.suppressed.contains(&id) {
             return;
         }
 
+        // rate limited signals keep only the latest pending value
+        if self.rate_limits.contains_key(&id) {
+            match self.values.get_mut(&id) {
+                Some(Signal::Single(v)) => {
+                    *v = value;
+                    return;
+                }
+                Some(Signal::Queue(v)) if !v.is_empty() => {
+                    v.clear();
+                    v.push_back(value);
+                    return;
+                }
+                _ => {}
+            }
+        }
+
         self.indexes.push(id);
         match self.values.entry(id) {
             Entry::Vacant(e) => {
@@ -58,7 +83,10 @@ impl ChangedInner {
         }
 
         if !self.blocked_list.contains(&id) {
-            event.set_one();
+            match self.handled.contains(&id) {
+                true => handler_event.set_one(),
+                false => event.set_one(),
+            }
         }
     }
 
@@ -77,20 +105,37 @@ impl ChangedInner {
         }
     }
 
-    fn get(&mut self, last_id: Option<u64>) -> Option<(u64, Bytes)> {
+    // deadline is set to the earliest time a rate limited signal can be delivered
+    // handled selects the signals of the Rust handlers or the other ones
+    fn get(
+        &mut self,
+        last_id: Option<u64>,
+        deadline: &mut Option<Instant>,
+        handled: bool,
+    ) -> Option<(u64, Bytes)> {
         if let Some(last_id) = last_id {
             self.blocked_list.remove(&last_id);
         }
 
+        let now = Instant::now();
         let mut pos = 0;
         while pos < self.indexes.len() {
             let id = self.indexes[pos];
 
-            if self.blocked_list.contains(&id) {
+            if self.blocked_list.contains(&id) || self.handled.contains(&id) != handled {
                 pos += 1;
                 continue;
             }
 
+            if let Some((interval, Some(last))) = self.rate_limits.get(&id) {
+                let next = *last + *interval;
+                if next > now {
+                    *deadline = Some(deadline.map_or(next, |d| d.min(next)));
+                    pos += 1;
+                    continue;
+                }
+            }
+
             self.indexes.remove(pos);
 
             if !self.registered.contains(&id) {
@@ -100,6 +145,9 @@ impl ChangedInner {
 
             if let Some(value) = self.get_id(id) {
                 self.blocked_list.insert(id);
+                if let Some((_, last)) = self.rate_limits.get_mut(&id) {
+                    *last = Some(now);
+                }
                 return Some((id, value));
             }
         }
@@ -123,6 +171,7 @@ impl ChangedInner {
         }
     }
 
+    #[cfg(feature = "python")]
     fn set_to_single(&mut self, id: u64) {
         if let Some(signal) = self.values.remove(&id) {
             let res = match signal {
@@ -137,10 +186,27 @@ impl ChangedInner {
     }
 }
 
+type SignalHandler = Arc<dyn Fn(&[u8]) -> Result<(), String> + Send + Sync>;
+
+// Rust side signal handlers, called from a dedicated thread
+struct Handlers {
+    handlers: RwLock<NoHashMap<u64, SignalHandler>>,
+    event: Event,
+    running: Mutex<bool>,
+}
+
+// wakes the handlers thread, so it finds out the manager is gone
+impl Drop for Handlers {
+    fn drop(&mut self) {
+        self.event.set();
+    }
+}
+
 #[derive(Clone)]
 pub(crate) struct SignalsManager {
     event: Event,
     values: Arc<Mutex<ChangedInner>>,
+    handlers: Arc<Handlers>,
 }
 
 impl SignalsManager {
@@ -148,50 +214,108 @@ impl SignalsManager {
         Self {
             event: Event::new(),
             values: Arc::new(Mutex::new(ChangedInner::new())),
+            handlers: Arc::new(Handlers {
+                handlers: RwLock::new(NoHashMap::default()),
+                event: Event::new(),
+                running: Mutex::new(false),
+            }),
         }
     }
 
     pub(crate) fn set(&self, id: u64, value: Bytes) {
-        self.values.lock().set(id, value, &self.event);
+        self.values
+            .lock()
+            .set(id, value, &self.event, &self.handlers.event);
+    }
+
+    // used by the Rust server API, Python uses wait_changed_value; the signal is registered and
+    // delivered only to the handler, with the same suppression and rate limit as for Python
+    pub(crate) fn on_signal<T>(&self, id: u64, callback: impl Fn(T) + Send + Sync + 'static)
+    where
+        T: for<'a> Deserialize<'a> + 'static,
+    {
+        let handler: SignalHandler = Arc::new(move |data: &[u8]| {
+            let (value, _) = deserialize_value::<T>(data)
+                .map_err(|_| format!("Failed to deserialize signal {}", id))?;
+            callback(value);
+            Ok(())
+        });
+        self.handlers.handlers.write().insert(id, handler);
+        {
+            let mut w = self.values.lock();
+            w.registered.insert(id);
+            w.handled.insert(id);
+        }
+
+        let mut running = self.handlers.running.lock();
+        if !*running {
+            let values = Arc::downgrade(&self.values);
+            let handlers = Arc::downgrade(&self.handlers);
+            let (event, handler_event) = (self.event.clone(), self.handlers.event.clone());
+            thread::spawn(move || dispatch_handlers(values, handlers, event, handler_event));
+            *running = true;
+        }
+    }
+
+    pub(crate) fn remove_signal_handler(&self, id: u64) {
+        self.handlers.handlers.write().remove(&id);
+        let mut w = self.values.lock();
+        w.handled.remove(&id);
+        w.registered.remove(&id);
+        w.values.remove(&id);
+        w.indexes.retain(|queued_id| *queued_id != id);
     }
 
     pub(crate) fn reset(&self) {
         self.values.lock().clear();
     }
 
-    fn serialize_message(level: u8, text: impl ToString) -> Result<Bytes, ()> {
+    // (level, message, optional fields), the fields are sent as map of strings
+    fn serialize_message(
+        level: u8,
+        text: impl ToString,
+        fields: Option<&[(&str, String)]>,
+    ) -> Result<Bytes, ()> {
         let data = text.to_string();
         let mut message = FastVec::<64>::new();
         serialize_to_data(&level, &mut message)?;
         serialize_to_data(&data, &mut message)?;
+        serialize_to_data(&fields, &mut message)?;
         Ok(message.to_bytes())
     }
 
-    #[allow(dead_code)]
+    #[inline]
+    pub(crate) fn log(&self, level: u8, message: impl ToString, fields: &[(&str, String)]) {
+        let fields = (!fields.is_empty()).then_some(fields);
+        if let Ok(data) = Self::serialize_message(level, message, fields) {
+            self.set(LOGGING_ID, data);
+        }
+    }
+
     #[inline]
     pub(crate) fn debug(&self, message: impl ToString) {
-        if let Ok(data) = Self::serialize_message(0u8, message) {
+        if let Ok(data) = Self::serialize_message(0u8, message, None) {
             self.set(LOGGING_ID, data);
         }
     }
 
     #[inline]
     pub(crate) fn info(&self, message: impl ToString) {
-        if let Ok(data) = Self::serialize_message(1u8, message) {
+        if let Ok(data) = Self::serialize_message(1u8, message, None) {
             self.set(LOGGING_ID, data);
         }
     }
 
     #[inline]
     pub(crate) fn warning(&self, message: impl ToString) {
-        if let Ok(data) = Self::serialize_message(2u8, message) {
+        if let Ok(data) = Self::serialize_message(2u8, message, None) {
             self.set(LOGGING_ID, data);
         }
     }
 
     #[inline]
     pub(crate) fn error(&self, message: impl ToString) {
-        if let Ok(data) = Self::serialize_message(3u8, message) {
+        if let Ok(data) = Self::serialize_message(3u8, message, None) {
             self.set(LOGGING_ID, data);
         }
     }
@@ -213,15 +337,38 @@ impl SignalsManager {
         self.set(CLIENT_MESSAGE_ID, message);
     }
 
-    pub(crate) fn wait_changed_value(&self, last_id: Option<u64>) -> (u64, Bytes) {
+    // without timeout it blocks until a signal comes, zero timeout only checks the pending signals
+    #[cfg(any(feature = "python", test))]
+    pub(crate) fn wait_changed_value(
+        &self,
+        last_id: Option<u64>,
+        timeout: Option<Duration>,
+    ) -> Option<(u64, Bytes)> {
+        let end = timeout.and_then(|timeout| Instant::now().checked_add(timeout));
         loop {
-            if let Some(val) = self.values.lock().get(last_id) {
-                return val;
+            let mut deadline = None;
+            if let Some(val) = self.values.lock().get(last_id, &mut deadline, false) {
+                return Some(val);
+            }
+
+            if let Some(end) = end {
+                if Instant::now() >= end {
+                    return None;
+                }
+                deadline = Some(deadline.map_or(end, |deadline: Instant| deadline.min(end)));
+            }
+
+            match deadline {
+                Some(deadline) => {
+                    self.event
+                        .wait_clear_timeout(deadline.saturating_duration_since(Instant::now()));
+                }
+                None => self.event.wait_clear(),
             }
-            self.event.wait_clear();
         }
     }
 
+    #[cfg(any(feature = "python", test))]
     pub(crate) fn set_register(&self, id: u64, register: bool) {
         if register {
             self.values.lock().registered.insert(id);
@@ -240,7 +387,180 @@ impl SignalsManager {
         self.values.lock().set_to_queue(id);
     }
 
+    #[cfg(feature = "python")]
     pub(crate) fn set_to_single(&self, id: u64) {
         self.values.lock().set_to_single(id);
     }
+
+    #[cfg(any(feature = "python", test))]
+    pub(crate) fn set_suppressed(&self, id: u64, suppressed: bool) {
+        let mut w = self.values.lock();
+        match suppressed {
+            true => w.suppressed.insert(id),
+            false => w.suppressed.remove(&id),
+        };
+    }
+
+    #[cfg(any(feature = "python", test))]
+    pub(crate) fn set_rate_limit(&self, id: u64, interval: Option<Duration>) {
+        let mut w = self.values.lock();
+        match interval {
+            Some(interval) => {
+                w.rate_limits.insert(id, (interval, None));
+            }
+            None => {
+                w.rate_limits.remove(&id);
+                // pending signal could wait for the rate limit
+                self.event.set_one();
+                self.handlers.event.set_one();
+            }
+        }
+    }
+}
+
+// the thread keeps only weak references, it ends when the last manager is dropped
+fn dispatch_handlers(
+    values: Weak<Mutex<ChangedInner>>,
+    handlers: Weak<Handlers>,
+    event: Event,
+    handler_event: Event,
+) {
+    let mut last_id = None;
+    loop {
+        let (Some(values), Some(handlers)) = (values.upgrade(), handlers.upgrade()) else {
+            return;
+        };
+
+        let mut deadline = None;
+        let next = values.lock().get(last_id.take(), &mut deadline, true);
+        match next {
+            Some((id, value)) => {
+                let handler = handlers.handlers.read().get(&id).cloned();
+                if let Some(handler) = handler
+                    && let Err(e) = handler(&value)
+                {
+                    let signals = SignalsManager {
+                        event: event.clone(),
+                        values,
+                        handlers,
+                    };
+                    signals.error(e);
+                }
+                last_id = Some(id);
+            }
+            None => {
+                drop((values, handlers));
+                match deadline {
+                    Some(deadline) => {
+                        handler_event
+                            .wait_clear_timeout(deadline.saturating_duration_since(Instant::now()));
+                    }
+                    None => handler_event.wait_clear(),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data(value: i32) -> Bytes {
+        serialize::<i32, 16>(&value).unwrap().to_bytes()
+    }
+
+    fn handled(signals: &SignalsManager, id: u64) -> Arc<Mutex<Vec<i32>>> {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let r = received.clone();
+        signals.on_signal(id, move |value: i32| r.lock().push(value));
+        received
+    }
+
+    fn wait_for(received: &Mutex<Vec<i32>>, count: usize) -> Vec<i32> {
+        let end = Instant::now() + Duration::from_secs(2);
+        while received.lock().len() < count && Instant::now() < end {
+            thread::sleep(Duration::from_millis(5));
+        }
+        received.lock().clone()
+    }
+
+    #[test]
+    fn suppressed_signal_does_not_reach_handler() {
+        let signals = SignalsManager::new();
+        let received = handled(&signals, 10);
+
+        signals.set_suppressed(10, true);
+        signals.set(10, data(1));
+        thread::sleep(Duration::from_millis(50));
+        assert!(received.lock().is_empty());
+
+        signals.set_suppressed(10, false);
+        signals.set(10, data(2));
+        assert_eq!(wait_for(&received, 1), vec![2]);
+    }
+
+    #[test]
+    fn unregistered_signal_does_not_reach_handler() {
+        let signals = SignalsManager::new();
+        let received = handled(&signals, 10);
+
+        signals.set_register(10, false);
+        signals.set(10, data(1));
+        thread::sleep(Duration::from_millis(50));
+        assert!(received.lock().is_empty());
+    }
+
+    #[test]
+    fn handler_signals_are_not_returned_to_python() {
+        let signals = SignalsManager::new();
+        let received = handled(&signals, 10);
+        signals.set_register(11, true);
+
+        signals.set(10, data(1));
+        signals.set(11, data(2));
+        let (id, _) = signals
+            .wait_changed_value(None, Some(Duration::from_millis(100)))
+            .unwrap();
+        assert_eq!(id, 11);
+        assert_eq!(wait_for(&received, 1), vec![1]);
+        assert!(
+            signals
+                .wait_changed_value(Some(11), Some(Duration::ZERO))
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn rate_limited_handler_gets_only_latest() {
+        let signals = SignalsManager::new();
+        signals.set_rate_limit(10, Some(Duration::from_millis(200)));
+        let received = handled(&signals, 10);
+
+        signals.set(10, data(1));
+        assert_eq!(wait_for(&received, 1), vec![1]);
+        signals.set(10, data(2));
+        signals.set(10, data(3));
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(received.lock().len(), 1);
+        assert_eq!(wait_for(&received, 2), vec![1, 3]);
+    }
+
+    #[test]
+    fn handlers_are_released_with_manager() {
+        let signals = SignalsManager::new();
+        let token = Arc::new(());
+        let t = token.clone();
+        signals.on_signal(10, move |_: i32| {
+            let _ = &t;
+        });
+        assert_eq!(Arc::strong_count(&token), 2);
+
+        drop(signals);
+        let end = Instant::now() + Duration::from_secs(2);
+        while Arc::strong_count(&token) > 1 && Instant::now() < end {
+            thread::sleep(Duration::from_millis(5));
+        }
+        assert_eq!(Arc::strong_count(&token), 1);
+    }
 }
@@ -1,10 +1,11 @@
 use bytes::Bytes;
 use futures_util::{StreamExt, stream::SplitStream};
-use tokio::net::TcpStream;
 use tokio_tungstenite::WebSocketStream;
 use tokio_tungstenite::tungstenite::Message;
 
 use crate::serialization::ClientHeader;
+use crate::server::sender::MessageSender;
+use crate::server::server_core::ServerStream;
 
 const COPY_SIZE: usize = 1024; // 1 KB
 
@@ -13,19 +14,25 @@ pub(crate) enum ClientMessage {
     Signal(u64, u32, Bytes),
     Ack(u64),
     Message(Bytes),
-    Handshake(u16, Option<u64>, Option<String>),
+    // protocol major and minor version, version, token, request types and options
+    Handshake(u16, u16, Option<u64>, Option<String>, bool, u32),
 }
 
 pub(crate) struct SocketReader {
-    socket: SplitStream<WebSocketStream<TcpStream>>,
+    socket: SplitStream<WebSocketStream<ServerStream>>,
     previous: Option<(Bytes, usize, bool)>,
+    sender: MessageSender,
 }
 
 impl SocketReader {
-    pub(crate) fn new(socket: SplitStream<WebSocketStream<TcpStream>>) -> Self {
+    pub(crate) fn new(
+        socket: SplitStream<WebSocketStream<ServerStream>>,
+        sender: MessageSender,
+    ) -> Self {
         Self {
             socket,
             previous: None,
+            sender,
         }
     }
 
@@ -34,6 +41,7 @@ impl SocketReader {
             Some(prev) => prev,
             None => match self.socket.next().await {
                 Some(Ok(Message::Binary(msg))) => {
+                    self.sender.record_received(msg.len());
                     // Copy data rather than reference it if it's too large
                     let copy = msg.len() > COPY_SIZE;
                     (msg, 0, copy)
@@ -111,14 +119,37 @@ impl SocketReader {
                 }
                 Ok(ClientMessage::Message(message_data))
             }
-            ClientHeader::Handshake(protocol_version, client_version, hash) => {
+            ClientHeader::Handshake(protocol_version, client_version, hash, request_types) => {
                 if pointer + size < data.len() {
                     self.previous = Some((data, pointer + size, copy));
                 }
                 Ok(ClientMessage::Handshake(
                     protocol_version,
+                    0,
                     client_version,
                     hash,
+                    request_types,
+                    0,
+                ))
+            }
+            ClientHeader::HandshakeOptions(
+                protocol_version,
+                minor,
+                client_version,
+                hash,
+                request_types,
+                options,
+            ) => {
+                if pointer + size < data.len() {
+                    self.previous = Some((data, pointer + size, copy));
+                }
+                Ok(ClientMessage::Handshake(
+                    protocol_version,
+                    minor,
+                    client_version,
+                    hash,
+                    request_types,
+                    options,
                 ))
             }
         }
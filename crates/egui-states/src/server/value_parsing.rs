@@ -19,6 +19,11 @@ impl ValueParser {
             self.pointer += size;
         })
     }
+
+    #[inline]
+    pub(crate) fn position(&self) -> usize {
+        self.pointer
+    }
 }
 
 pub(crate) struct ValueCreator {
@@ -1,24 +1,50 @@
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
+use std::collections::VecDeque;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
 
 use bytes::Bytes;
 
+#[cfg(feature = "python")]
 use crate::event::Event;
 use crate::serialization::ServerHeader;
 use crate::server::sender::MessageSender;
-use crate::server::server::{Acknowledge, SyncTrait};
+use crate::server::server::{Acknowledge, Snapshot, SyncTrait};
 use crate::server::signals::SignalsManager;
 
 // Value --------------------------------------------------
+const RATE_WINDOW: Duration = Duration::from_secs(1);
+
+// times of the updates in the last rate window and the optional limit
+struct UpdateRate {
+    times: VecDeque<Instant>,
+    limit: Option<(Duration, bool)>, // min interval between updates, drop excess updates
+    warned: Option<Instant>,         // the excess updates are reported once per rate window
+}
+
+impl UpdateRate {
+    fn prune(&mut self, now: Instant) {
+        while self
+            .times
+            .front()
+            .is_some_and(|time| now.duration_since(*time) > RATE_WINDOW)
+        {
+            self.times.pop_front();
+        }
+    }
+}
+
 pub(crate) struct Value {
     pub(crate) name: String,
     id: u64,
     type_id: u32,
     value: RwLock<(Bytes, usize)>,
+    rate: Mutex<UpdateRate>,
     sender: MessageSender,
     connected: Arc<AtomicBool>,
     signals: SignalsManager,
+    notify_client: AtomicBool,
 }
 
 impl Value {
@@ -36,12 +62,82 @@ impl Value {
             id,
             type_id,
             value: RwLock::new((value, 0)),
+            rate: Mutex::new(UpdateRate {
+                times: VecDeque::new(),
+                limit: None,
+                warned: None,
+            }),
             sender,
             connected,
             signals,
+            notify_client: AtomicBool::new(false),
         })
     }
 
+    // every change from the client emits the signal, even if the client did not ask for it
+    pub(crate) fn set_notify_client(&self, notify: bool) {
+        self.notify_client.store(notify, Ordering::Relaxed);
+    }
+
+    // updates per second over the last second
+    #[cfg(any(feature = "python", feature = "metrics-http"))]
+    pub(crate) fn update_rate(&self) -> f64 {
+        let mut rate = self.rate.lock();
+        rate.prune(Instant::now());
+        rate.times.len() as f64 / RATE_WINDOW.as_secs_f64()
+    }
+
+    #[cfg(feature = "python")]
+    pub(crate) fn set_max_rate(&self, hz: Option<f64>, drop: bool) -> Result<(), String> {
+        let limit = match hz {
+            Some(hz) if hz.is_finite() && hz > 0.0 => {
+                let interval = Duration::try_from_secs_f64(1.0 / hz)
+                    .map_err(|_| "Max rate is too low.".to_string())?;
+                Some((interval, drop))
+            }
+            Some(_) => return Err("Max rate has to be positive.".to_string()),
+            None => None,
+        };
+        let mut rate = self.rate.lock();
+        rate.limit = limit;
+        rate.warned = None;
+        Ok(())
+    }
+
+    // records the update, returns false if it exceeds the max rate and has to be dropped
+    fn register_update(&self) -> bool {
+        let now = Instant::now();
+        let mut rate = self.rate.lock();
+        if let Some((interval, drop)) = rate.limit
+            && rate
+                .times
+                .back()
+                .is_some_and(|last| now.duration_since(*last) < interval)
+        {
+            if rate
+                .warned
+                .is_none_or(|warned| now.duration_since(warned) >= RATE_WINDOW)
+            {
+                rate.warned = Some(now);
+                self.signals.log(
+                    2,
+                    format!("value {} is updated faster than allowed", self.name),
+                    &[
+                        ("value_id", self.id.to_string()),
+                        ("name", self.name.clone()),
+                    ],
+                );
+            }
+            if drop {
+                return false;
+            }
+        }
+
+        rate.times.push_back(now);
+        rate.prune(now);
+        true
+    }
+
     pub(crate) fn update_value(
         &self,
         type_id: u32,
@@ -54,7 +150,18 @@ impl Value {
 
         let mut w = self.value.write();
         if w.1 == 0 {
-            if signal {
+            if !self.register_update() {
+                // send the current value back, so the client does not keep the dropped one
+                if self.connected.load(Ordering::Relaxed) {
+                    let message = ServerHeader::serialize_value(self.id, self.type_id, true, &w.0)
+                        .map_err(|_| format!("Failed to serialize Value: {}", self.name))?;
+                    w.1 += 1;
+                    self.sender.send(message);
+                }
+                return Ok(());
+            }
+
+            if signal || self.notify_client.load(Ordering::Relaxed) {
                 self.signals.set(self.id, value.clone());
             }
 
@@ -69,14 +176,23 @@ impl Value {
         self.value.read().0.clone()
     }
 
-    pub(crate) fn set(&self, value: Bytes, set_signals: bool, update: bool) -> Result<(), ()> {
+    // returns false if the update was dropped by the max rate
+    pub(crate) fn set(&self, value: Bytes, set_signals: bool, update: bool) -> Result<bool, ()> {
+        if !self.register_update() {
+            return Ok(false);
+        }
+
         if self.connected.load(Ordering::Relaxed) {
             let mut w = self.value.write();
-            let message = ServerHeader::serialize_value(self.id, self.type_id, update, &value)?;
+            let replaced = self.sender.send_latest(self.id, update, |update| {
+                ServerHeader::serialize_value(self.id, self.type_id, update, &value)
+            })?;
 
             w.0 = value.clone();
-            w.1 += 1;
-            self.sender.send(message);
+            // the replaced message is never acknowledged
+            if !replaced {
+                w.1 += 1;
+            }
 
             if set_signals {
                 self.signals.set(self.id, value);
@@ -88,7 +204,74 @@ impl Value {
                 self.signals.set(self.id, value);
             }
         }
-        Ok(())
+        Ok(true)
+    }
+
+    // set the value only if the current one is equal to expected, returns whether it was set
+    #[cfg(feature = "python")]
+    pub(crate) fn compare_set(
+        &self,
+        expected: &Bytes,
+        value: Bytes,
+        set_signals: bool,
+        update: bool,
+    ) -> Result<bool, ()> {
+        let mut w = self.value.write();
+        if w.0 != *expected {
+            return Ok(false);
+        }
+        if !self.register_update() {
+            return Ok(false);
+        }
+
+        if self.connected.load(Ordering::Relaxed) {
+            let replaced = self.sender.send_latest(self.id, update, |update| {
+                ServerHeader::serialize_value(self.id, self.type_id, update, &value)
+            })?;
+            if !replaced {
+                w.1 += 1;
+            }
+        }
+
+        w.0 = value.clone();
+        if set_signals {
+            self.signals.set(self.id, value);
+        }
+        Ok(true)
+    }
+
+    // read-modify-write under the value lock, returns the new value or None if the update was
+    // dropped by the max rate
+    #[cfg(feature = "python")]
+    pub(crate) fn modify(
+        &self,
+        f: impl FnOnce(&Bytes) -> Result<Bytes, String>,
+        set_signals: bool,
+        update: bool,
+    ) -> Result<Option<Bytes>, String> {
+        let mut w = self.value.write();
+        if !self.register_update() {
+            return Ok(None);
+        }
+        let value = f(&w.0)?;
+
+        if self.connected.load(Ordering::Relaxed) {
+            let replaced = self
+                .sender
+                .send_latest(self.id, update, |update| {
+                    ServerHeader::serialize_value(self.id, self.type_id, update, &value)
+                })
+                .map_err(|_| format!("Failed to serialize Value: {}", self.name))?;
+            if !replaced {
+                w.1 += 1;
+            }
+        }
+
+        w.0 = value.clone();
+        if set_signals {
+            self.signals.set(self.id, value.clone());
+        }
+        Ok(Some(value))
     }
 }
 
@@ -111,9 +294,19 @@ impl SyncTrait for Value {
         self.sender.send(data);
         Ok(())
     }
+
+    fn snapshot(&self, snapshot: &mut Snapshot) -> Result<(), ()> {
+        let r = self.value.read();
+        snapshot.push(
+            ServerHeader::serialize_value(self.id, self.type_id, false, &r.0)?,
+            false,
+        );
+        Ok(())
+    }
 }
 
 // ValueTake --------------------------------------------------
+#[cfg(feature = "python")]
 pub(crate) struct ValueTake {
     pub(crate) name: String,
     id: u64,
@@ -124,6 +317,7 @@ pub(crate) struct ValueTake {
     connected: Arc<AtomicBool>,
 }
 
+#[cfg(feature = "python")]
 impl ValueTake {
     pub(crate) fn new(
         name: String,
@@ -165,17 +359,24 @@ impl ValueTake {
     }
 }
 
+#[cfg(feature = "python")]
 impl Acknowledge for ValueTake {
     fn acknowledge(&self) {
         self.event.set();
     }
 }
 
+#[cfg(feature = "python")]
 impl SyncTrait for ValueTake {
     fn sync(&self) -> Result<(), ()> {
         self.event.set();
         Ok(())
     }
+
+    // the taken values are only for the client
+    fn snapshot(&self, _snapshot: &mut Snapshot) -> Result<(), ()> {
+        Ok(())
+    }
 }
 
 // ValueStatic --------------------------------------------
@@ -210,10 +411,11 @@ impl ValueStatic {
     pub(crate) fn set(&self, value: Bytes, update: bool) -> Result<(), ()> {
         if self.connected.load(Ordering::Relaxed) {
             let mut w = self.value.write();
-            let message = ServerHeader::serialize_static(self.id, self.type_id, update, &value)?;
+            self.sender.send_latest(self.id, update, |update| {
+                ServerHeader::serialize_static(self.id, self.type_id, update, &value)
+            })?;
 
             *w = value;
-            self.sender.send(message);
         } else {
             let mut w = self.value.write();
             *w = value;
@@ -225,6 +427,36 @@ impl ValueStatic {
     pub(crate) fn get(&self) -> Bytes {
         self.value.read().clone()
     }
+
+    // read-modify-write, f runs without the value lock and it is called again with the new value
+    // if the value was changed meanwhile, returns the new value
+    #[cfg(feature = "python")]
+    pub(crate) fn modify(
+        &self,
+        mut f: impl FnMut(&Bytes) -> Result<Bytes, String>,
+        update: bool,
+    ) -> Result<Bytes, String> {
+        loop {
+            let old = self.get();
+            let value = f(&old)?;
+
+            let mut w = self.value.write();
+            if *w != old {
+                continue;
+            }
+
+            if self.connected.load(Ordering::Relaxed) {
+                self.sender
+                    .send_latest(self.id, update, |update| {
+                        ServerHeader::serialize_static(self.id, self.type_id, update, &value)
+                    })
+                    .map_err(|_| format!("Failed to serialize Static: {}", self.name))?;
+            }
+
+            *w = value.clone();
+            return Ok(value);
+        }
+    }
 }
 
 impl SyncTrait for ValueStatic {
@@ -236,6 +468,15 @@ impl SyncTrait for ValueStatic {
         self.sender.send(data);
         Ok(())
     }
+
+    fn snapshot(&self, snapshot: &mut Snapshot) -> Result<(), ()> {
+        let r = self.value.read();
+        snapshot.push(
+            ServerHeader::serialize_static(self.id, self.type_id, false, &r)?,
+            false,
+        );
+        Ok(())
+    }
 }
 
 // Signals --------------------------------------------
@@ -256,6 +497,7 @@ impl Signal {
         })
     }
 
+    #[cfg(feature = "python")]
     pub(crate) fn set(&self, value: Bytes) {
         self.signals.set(self.id, value);
     }
@@ -269,3 +511,39 @@ impl Signal {
         Ok(())
     }
 }
+
+#[cfg(all(test, feature = "python"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn static_modify_runs_without_lock_and_retries() {
+        let (sender, _receiver) = MessageSender::new(None);
+        let value = ValueStatic::new(
+            "static".to_string(),
+            1,
+            0,
+            Bytes::from_static(&[1]),
+            sender,
+            Arc::new(AtomicBool::new(false)),
+        );
+
+        // the first call changes the value, so the callback runs again with the new one
+        let mut calls = Vec::new();
+        let result = value
+            .modify(
+                |old| {
+                    calls.push(old[0]);
+                    if calls.len() == 1 {
+                        value.set(Bytes::from_static(&[5]), false).unwrap();
+                    }
+                    Ok(Bytes::copy_from_slice(&[old[0] + 1]))
+                },
+                false,
+            )
+            .unwrap();
+        assert_eq!(calls, vec![1, 5]);
+        assert_eq!(result, Bytes::from_static(&[6]));
+        assert_eq!(value.get(), result);
+    }
+}
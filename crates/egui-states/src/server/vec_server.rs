@@ -5,9 +5,9 @@ use std::sync::atomic::{AtomicBool, Ordering};
 use tokio_tungstenite::tungstenite::Bytes;
 
 use crate::collections::VecHeader;
-use crate::serialization::{ServerHeader, serialize};
+use crate::serialization::{FastVec, ServerHeader, serialize};
 use crate::server::sender::{MessageSender, SenderData};
-use crate::server::server::SyncTrait;
+use crate::server::server::{Snapshot, SyncTrait};
 
 pub(crate) struct ValueList {
     pub(crate) name: String,
@@ -36,7 +36,7 @@ impl ValueList {
         })
     }
 
-    fn serialize_all(&self, vec: &Vec<Bytes>, update: bool) -> Result<SenderData, ()> {
+    fn serialize_all(&self, vec: &[Bytes], update: bool) -> Result<SenderData, ()> {
         let len = vec.len() as u64;
         let mut size = 0;
         vec.iter().for_each(|b| {
@@ -52,13 +52,14 @@ impl ValueList {
 
         let mut data = serialize(&header)?;
         vec.iter().for_each(|b| {
-            data.extend_from_slice(&b);
+            data.extend_from_slice(b);
         });
 
         Ok(data)
     }
 
     pub(crate) fn set(&self, list: Vec<Bytes>, update: bool) -> Result<(), ()> {
+        let _change = self.sender.change();
         let mut w = self.list.write();
 
         if self.connected.load(Ordering::Relaxed) {
@@ -74,12 +75,29 @@ impl ValueList {
         self.list.read().clone()
     }
 
+    // items of the same size packed in one buffer, the items are slices of it without copying
+    pub(crate) fn set_packed(&self, data: Bytes, item_size: usize, update: bool) -> Result<(), ()> {
+        let _change = self.sender.change();
+        if item_size == 0 {
+            return Err(());
+        }
+        let list = (0..data.len() / item_size)
+            .map(|i| data.slice(i * item_size..(i + 1) * item_size))
+            .collect();
+        self.set(list, update)
+    }
+
+    pub(crate) fn read<R>(&self, f: impl FnOnce(&[Bytes]) -> R) -> R {
+        f(&self.list.read())
+    }
+
     pub(crate) fn set_item_py(
         &self,
         idx: usize,
         value: Bytes,
         update: bool,
     ) -> Result<(), &'static str> {
+        let _change = self.sender.change();
         let mut w = self.list.write();
         if idx >= w.len() {
             return Err("Index out of bounds");
@@ -110,11 +128,22 @@ impl ValueList {
         Ok(r[idx].clone())
     }
 
+    // stop is clamped to the list length, start past the end gives an empty range
+    pub(crate) fn get_range(&self, start: usize, stop: usize) -> Vec<Bytes> {
+        let r = self.list.read();
+        let stop = stop.min(r.len());
+        if start >= stop {
+            return Vec::new();
+        }
+        r[start..stop].to_vec()
+    }
+
     pub(crate) fn len(&self) -> usize {
         self.list.read().len()
     }
 
     pub(crate) fn remove_item(&self, idx: usize, update: bool) -> Result<Bytes, &'static str> {
+        let _change = self.sender.change();
         let mut w = self.list.write();
         if idx >= w.len() {
             return Err("Index out of bounds");
@@ -137,6 +166,7 @@ impl ValueList {
     }
 
     pub(crate) fn append_item(&self, value: Bytes, update: bool) -> Result<(), ()> {
+        let _change = self.sender.change();
         let mut w = self.list.write();
         if self.connected.load(Ordering::Relaxed) {
             let header = ServerHeader::ValueVec(
@@ -153,6 +183,70 @@ impl ValueList {
         w.push(value);
         Ok(())
     }
+
+    // f computes the permutation without the list lock, the new item i is the old item at index
+    // i, f is called again if the list was changed meanwhile
+    pub(crate) fn reorder(
+        &self,
+        mut f: impl FnMut(&[Bytes]) -> Result<Vec<u32>, String>,
+        update: bool,
+    ) -> Result<(), String> {
+        let _change = self.sender.change();
+        let (mut w, permutation) = loop {
+            let items = self.get();
+            let permutation = f(&items)?;
+
+            // the items are the same if they share the buffers, the contents are not compared
+            let w = self.list.write();
+            let unchanged = w.len() == items.len()
+                && w.iter()
+                    .zip(items.iter())
+                    .all(|(a, b)| a.as_ptr() == b.as_ptr() && a.len() == b.len());
+            if unchanged {
+                break (w, permutation);
+            }
+        };
+        if permutation.len() != w.len() {
+            return Err("Permutation length does not match the list length".to_string());
+        }
+
+        // the permutation is checked before any item is moved, so an invalid one keeps the list
+        let mut seen = vec![false; w.len()];
+        for idx in permutation.iter() {
+            match seen.get_mut(*idx as usize) {
+                Some(seen) if !*seen => *seen = true,
+                _ => return Err("Invalid permutation".to_string()),
+            }
+        }
+        let reordered = permutation
+            .iter()
+            .map(|idx| w[*idx as usize].clone())
+            .collect();
+        *w = reordered;
+
+        if self.connected.load(Ordering::Relaxed) {
+            let order: FastVec<64> = serialize(&permutation)
+                .map_err(|_| format!("Failed to serialize reorder of list {}", self.name))?;
+            let header = ServerHeader::ValueVec(
+                self.id,
+                self.type_id,
+                update,
+                VecHeader::Reorder,
+                order.len() as u32,
+            );
+            let mut message: SenderData = serialize(&header)
+                .map_err(|_| format!("Failed to serialize reorder of list {}", self.name))?;
+            message.extend_from_data(&order);
+            self.sender.send(message);
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn reverse(&self, update: bool) -> Result<(), String> {
+        let _change = self.sender.change();
+        self.reorder(|items| Ok((0..items.len() as u32).rev().collect()), update)
+    }
 }
 
 impl SyncTrait for ValueList {
@@ -162,4 +256,134 @@ impl SyncTrait for ValueList {
         self.sender.send(data);
         Ok(())
     }
+
+    fn snapshot(&self, snapshot: &mut Snapshot) -> Result<(), ()> {
+        let r = self.list.read();
+        snapshot.push(self.serialize_all(&r, false)?, false);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::Transportable;
+
+    fn item(value: i32) -> Bytes {
+        serialize::<i32, 16>(&value).unwrap().to_bytes()
+    }
+
+    fn list(connected: bool) -> (Arc<ValueList>, crate::server::sender::MessageReceiver) {
+        let (sender, receiver) = MessageSender::new(None);
+        let list = ValueList::new(
+            "list".to_string(),
+            1,
+            i32::get_type().get_hash(),
+            sender,
+            Arc::new(AtomicBool::new(connected)),
+        );
+        list.set((0..4).map(item).collect(), false).unwrap();
+        (list, receiver)
+    }
+
+    #[test]
+    fn packed_items_need_size() {
+        let (list, _receiver) = list(false);
+        let original = list.get();
+        assert!(
+            list.set_packed(Bytes::from_static(&[1, 2]), 0, false)
+                .is_err()
+        );
+        assert_eq!(list.get(), original);
+
+        list.set_packed(Bytes::from_static(&[1, 2, 3, 4]), 2, false)
+            .unwrap();
+        assert_eq!(
+            list.get(),
+            [[1u8, 2], [3, 4]].map(|item| Bytes::copy_from_slice(&item))
+        );
+    }
+
+    #[test]
+    fn reorder_runs_without_lock_and_retries() {
+        let (list, _receiver) = list(false);
+
+        // the first call appends an item, so the order is computed again for the new list
+        let mut calls = Vec::new();
+        list.reorder(
+            |items| {
+                calls.push(items.len());
+                if calls.len() == 1 {
+                    list.append_item(item(4), false).unwrap();
+                }
+                Ok((0..items.len() as u32).rev().collect())
+            },
+            false,
+        )
+        .unwrap();
+        assert_eq!(calls, vec![4, 5]);
+        assert_eq!(list.get(), [4, 3, 2, 1, 0].map(item));
+    }
+
+    #[test]
+    fn invalid_permutation_keeps_list() {
+        let (list, mut receiver) = list(false);
+        let original = list.get();
+
+        for permutation in [vec![0, 1, 1, 2], vec![3, 2, 1, 4], vec![0, 1, 2]] {
+            assert!(list.reorder(|_| Ok(permutation.clone()), false).is_err());
+            assert_eq!(list.get(), original);
+        }
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[cfg(feature = "client")]
+    #[test]
+    fn reorder_applied_by_client() {
+        use crate::client::messages::{MessagesParser, ServerMessage};
+        use crate::client::value_vec::{UpdateList, VecState};
+
+        let (list, mut receiver) = list(true);
+        let state = VecState::<i32>::new("list".to_string(), i32::get_type().get_hash());
+        let mut apply = || {
+            let (message, _) = receiver.try_recv().unwrap().unwrap();
+            match MessagesParser::from_bytes(message.to_bytes()).unwrap().1 {
+                ServerMessage::ValueVec(_, type_id, _, header, data) => {
+                    state.update_list(type_id, header, &data)
+                }
+                _ => panic!("Unexpected message"),
+            }
+        };
+
+        apply().unwrap();
+        list.reorder(|_| Ok(vec![2, 0, 3, 1]), false).unwrap();
+        apply().unwrap();
+        list.reverse(false).unwrap();
+        apply().unwrap();
+        assert_eq!(state.get(), vec![1, 3, 0, 2]);
+        assert_eq!(list.get(), [1, 3, 0, 2].map(item));
+    }
+
+    #[cfg(feature = "client")]
+    #[test]
+    fn invalid_order_keeps_client_list() {
+        use crate::client::value_vec::{UpdateList, VecState};
+
+        let type_id = i32::get_type().get_hash();
+        let state = VecState::<i32>::new("list".to_string(), type_id);
+        let all: FastVec<64> = serialize(&(1i32, 2i32, 3i32)).unwrap();
+        state
+            .update_list(type_id, VecHeader::All(3), all.as_slice())
+            .unwrap();
+
+        for order in [vec![0u32, 0, 1], vec![0, 1, 3], vec![1, 0]] {
+            let data: FastVec<64> = serialize(&order).unwrap();
+            assert!(
+                state
+                    .update_list(type_id, VecHeader::Reorder, data.as_slice())
+                    .is_err()
+            );
+            assert_eq!(state.get(), vec![1, 2, 3]);
+        }
+    }
 }
@@ -1,6 +1,8 @@
 use std::collections::HashMap;
 use std::hash::{Hash, Hasher};
 
+use serde::{Deserialize, Serialize};
+
 use crate::hashing::StableHasher;
 
 #[derive(Clone)]
@@ -24,9 +26,10 @@ pub enum InitValue {
     List(Vec<InitValue>),
     Vec(Vec<InitValue>),
     Map(Vec<(InitValue, InitValue)>),
+    Union(String, Vec<InitValue>),
 }
 
-#[derive(Clone, PartialEq)]
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
 pub enum ObjectType {
     U8,
     U16,
@@ -48,6 +51,8 @@ pub enum ObjectType {
     Map(Box<ObjectType>, Box<ObjectType>),
     Option(Box<ObjectType>),
     Empty,
+    // enum with data, variants with the types of their fields
+    Union(String, Vec<(String, Vec<ObjectType>)>),
 }
 
 impl Hash for ObjectType {
@@ -109,6 +114,18 @@ impl Hash for ObjectType {
                 inner.hash(state);
             }
             ObjectType::Empty => 19u8.hash(state),
+            ObjectType::Union(name, variants) => {
+                20u8.hash(state);
+                name.hash(state);
+                (variants.len() as u64).hash(state);
+                for (variant_name, fields) in variants {
+                    variant_name.hash(state);
+                    (fields.len() as u64).hash(state);
+                    for field_type in fields {
+                        field_type.hash(state);
+                    }
+                }
+            }
         }
     }
 }
@@ -128,6 +145,33 @@ impl ObjectType {
     }
 }
 
+/// Integer which is not a discriminant of the enum, the error of `TryFrom<i32>` generated by the
+/// `EnumConvert` derive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidEnumValue {
+    pub enum_name: &'static str,
+    pub value: i32,
+}
+
+impl std::fmt::Display for InvalidEnumValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} is not a value of the enum {}",
+            self.value, self.enum_name
+        )
+    }
+}
+
+impl std::error::Error for InvalidEnumValue {}
+
+/// Type which can be sent between the server and the client, usually implemented by the derive
+/// macros.
+///
+/// # Safety
+///
+/// `get_type` has to describe exactly the serialized form of the type, the server reads and patches
+/// the serialized values by this description without deserializing them.
 pub unsafe trait Transportable {
     fn init_value(&self) -> InitValue;
     fn get_type() -> ObjectType;
@@ -3,6 +3,7 @@
 from egui_states import version
 from egui_states.logging import LogLevel
 from egui_states.structures import (
+    Bitset,
     Data,
     DataMulti,
     DataMultiTake,
@@ -23,6 +24,7 @@ __all__ = [
     "Signal",
     "SignalEmpty",
     "Value",
+    "Bitset",
     "Map",
     "Image",
     "Vec",
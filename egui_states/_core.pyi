@@ -1,12 +1,20 @@
-from collections.abc import Buffer
+from collections.abc import Buffer, Callable, Iterable
 from enum import IntEnum
-from typing import Any
+from types import TracebackType
+from typing import Any, Self, overload
 
-from egui_states.structures import _CustomStruct
+from egui_states.structures import _CustomStruct, _CustomUnion
 
 class PyObjectType:
     """A class representing a type of object in the state server."""
 
+class ImageArray(Buffer):
+    """A read-only RGBA image with shape (height, width, 4) and the buffer protocol."""
+
+    @property
+    def shape(self) -> tuple[int, int, int]: ...
+    def __buffer__(self, flags: int, /) -> memoryview: ...
+
 u8: PyObjectType
 u16: PyObjectType
 u32: PyObjectType
@@ -20,6 +28,12 @@ f64: PyObjectType
 bo: PyObjectType
 st: PyObjectType
 emp: PyObjectType
+TIMESTAMPS: bool
+METRICS_HTTP: bool
+IMAGE_COMPRESSION: bool
+CHECKSUM: bool
+TLS: bool
+COMPRESSION: bool
 
 def opt(pytype: PyObjectType) -> PyObjectType: ...
 def tu(elements: list[PyObjectType]) -> PyObjectType: ...
@@ -28,6 +42,9 @@ def li(element_type: PyObjectType, size: int) -> PyObjectType: ...
 def vec(element_type: PyObjectType) -> PyObjectType: ...
 def map(key_type: PyObjectType, value_type: PyObjectType) -> PyObjectType: ...
 def enu(enum_obj: type[IntEnum]) -> PyObjectType: ...
+def uni(
+    union_type: type[_CustomUnion], variants: list[tuple[str, type[_CustomUnion], list[PyObjectType]]]
+) -> PyObjectType: ...
 
 class StateServerCore:
     """A class representing the core functionality of the state server."""
@@ -38,18 +55,44 @@ class StateServerCore:
         ip_addr: tuple[int, int, int, int] | None = None,
         version: int | None = None,
         token: str | None = None,
+        queue_size: int | None = None,
     ) -> None: ...
     def start(self) -> None: ...
     def stop(self) -> None: ...
+    def __enter__(self) -> Self: ...
+    def __exit__(
+        self,
+        exc_type: type[BaseException] | None = None,
+        exc_value: BaseException | None = None,
+        traceback: TracebackType | None = None,
+    ) -> bool: ...
+    def set_read_only_clients(self, count: int) -> None: ...
+    def set_metrics_addr(self, port: int | None, ip_addr: tuple[int, int, int, int] | None = None) -> None: ...
+    def set_tls(self, cert_path: str | None, key_path: str | None) -> None: ...
     def is_running(self) -> bool: ...
     def is_connected(self) -> bool: ...
     def disconnect_client(self) -> None: ...
     def update(self, duration: float | None = None) -> None: ...
+    def begin_batch(self) -> None: ...
+    def end_batch(self) -> None: ...
+    def flush(self, timeout: float) -> bool: ...
+    def stats(self) -> dict[str, int]: ...
     def id_to_name(self, value_id: int) -> str: ...
+    def snapshot_dict(self) -> dict[str, Any]: ...
 
     # values ----------------------------------------------------------------------
-    def value_set(self, value_id: int, value: object, set_signal: bool, update: bool) -> None: ...
+    def value_set(self, value_id: int, value: object, set_signal: bool, update: bool) -> bool: ...
+    def value_set_many(self, items: dict[int, Any], set_signal: bool, update: bool) -> int: ...
     def value_get(self, value_id: int) -> Any: ...
+    def value_compare_set(self, value_id: int, expected: Any, value: Any, set_signal: bool, update: bool) -> bool: ...
+    def value_increment(self, value_id: int, delta: float, set_signal: bool, update: bool) -> Any | None: ...
+    def value_update_bits(
+        self, value_id: int, set_mask: int, clear_mask: int, toggle_mask: int, set_signal: bool, update: bool
+    ) -> int | None: ...
+    def value_set_field(self, value_id: int, path: str, value: object, set_signal: bool, update: bool) -> bool: ...
+    def value_update_rate(self, value_id: int) -> float: ...
+    def value_set_max_rate(self, value_id: int, hz: float | None, drop: bool) -> None: ...
+    def value_set_notify_client(self, value_id: int, notify: bool) -> None: ...
 
     # values take -----------------------------------------------------------------
     def value_take_set(self, value_id: int, value: object, blocking: bool, update: bool) -> None: ...
@@ -57,29 +100,46 @@ class StateServerCore:
     # static ----------------------------------------------------------------------
     def static_set(self, value_id: int, value: object, update: bool) -> None: ...
     def static_get(self, value_id: int) -> Any: ...
+    # the callback runs without the lock and again with the new value if the value changed meanwhile, so it can be
+    # called several times
+    def static_update(self, value_id: int, callback: Callable[[Any], Any], update: bool) -> Any: ...
 
     # signals ---------------------------------------------------------------------
     def signal_set(self, value_id: int, value: object) -> None: ...
     def signal_register(self, value_id: int, register: bool) -> None: ...
+    @overload
     def signal_get(self, last_id: int | None) -> tuple[int, Any]: ...
+    @overload
+    def signal_get(self, last_id: int | None, timeout: float | None) -> tuple[int, Any] | None: ...
+    def log(self, level: int, message: str, fields: dict[str, str] | None = None) -> None: ...
     def signal_set_to_queue(self, value_id: int) -> None: ...
     def signal_set_to_single(self, value_id: int) -> None: ...
+    def signal_set_suppressed(self, value_id: int, suppressed: bool) -> None: ...
+    def signal_set_rate_limit(self, value_id: int, hz: float | None) -> None: ...
 
     # lists -----------------------------------------------------------------------
     def list_set(self, value_id: int, value: list[Any], update: bool) -> None: ...
     def list_get(self, value_id: int) -> list[Any]: ...
+    def list_get_range(self, value_id: int, start: int, stop: int) -> list[Any]: ...
+    def list_item_size(self, value_id: int) -> int | None: ...
+    def list_set_buffer(self, value_id: int, data: Buffer, update: bool) -> None: ...
+    def list_get_buffer(self, value_id: int) -> bytearray: ...
     def list_set_item(self, value_id: int, idx: int, value: object, update: bool) -> None: ...
     def list_get_item(self, value_id: int, idx: int) -> Any: ...
     def list_del_item(self, value_id: int, idx: int, update: bool) -> None: ...
     def list_append_item(self, value_id: int, value: object, update: bool) -> None: ...
     def list_len(self, value_id: int) -> int: ...
+    def list_sort(self, value_id: int, key: Callable[[Any], Any] | None, reverse: bool, update: bool) -> None: ...
+    def list_reverse(self, value_id: int, update: bool) -> None: ...
 
     # map ------------------------------------------------------------------------
     def map_set(self, value_id: int, value: dict[Any, Any], update: bool) -> None: ...
     def map_get(self, value_id: int) -> dict[Any, Any]: ...
     def map_set_item(self, value_id: int, key: object, value: object, update: bool) -> None: ...
+    def map_update_item(self, value_id: int, key: object, callback: Callable[[Any], Any], update: bool) -> Any: ...
     def map_get_item(self, value_id: int, key: object) -> Any: ...
     def map_del_item(self, value_id: int, key: object, update: bool) -> None: ...
+    def map_del_items(self, value_id: int, keys: Iterable[Any], update: bool) -> int: ...
     def map_len(self, value_id: int) -> int: ...
 
     # image -----------------------------------------------------------------------
@@ -88,6 +148,15 @@ class StateServerCore:
         value_id: int,
         image: Buffer,
         update: bool,
+        window: tuple[float, float] | None = None,
+    ) -> None: ...
+    def image_set_scaled(
+        self,
+        value_id: int,
+        image: Buffer,
+        max_dim: int,
+        update: bool,
+        window: tuple[float, float] | None = None,
     ) -> None: ...
     def image_update(
         self,
@@ -96,17 +165,34 @@ class StateServerCore:
         origin: list[int] | tuple[int, int],
         update: bool,
         force: bool = False,
+        window: tuple[float, float] | None = None,
     ) -> None: ...
+    def image_scroll_append(
+        self,
+        value_id: int,
+        rows: Buffer,
+        update: bool,
+        window: tuple[float, float] | None = None,
+    ) -> None: ...
+    def image_set_compression(self, value_id: int, compression: str | None, quality: int = 90) -> None: ...
     def image_get(self, value_id: int) -> tuple[bytearray, tuple[int, int]]: ...
+    def image_get_array(self, value_id: int) -> ImageArray: ...
     def image_size(self, value_id: int) -> tuple[int, int]: ...
 
     # data ------------------------------------------------------------------------
     def data_get(self, value_id: int) -> bytearray: ...
-    def data_set(self, value_id: int, data: Buffer, update: bool) -> None: ...
-    def data_add(self, value_id: int, data: Buffer, update: bool) -> None: ...
+    def data_set(
+        self, value_id: int, data: Buffer, update: bool, max_points: int | None = None, xy: bool = False
+    ) -> None: ...
+    def data_add(self, value_id: int, data: Buffer, update: bool) -> int: ...
     def data_replace(self, value_id: int, data: Buffer, index: int, update: bool) -> None: ...
+    def data_splice(self, value_id: int, data: Buffer, index: int, removed: int, update: bool) -> None: ...
     def data_remove(self, value_id: int, index: int, count: int, update: bool) -> None: ...
     def data_clear(self, value_id: int, update: bool) -> None: ...
+    def data_set_group(self, group: int, value_ids: list[int], x_range: tuple[float, float], update: bool) -> None: ...
+    def data_clear_group(self, value_ids: list[int], update: bool) -> None: ...
+    def data_set_max_len(self, value_id: int, max_len: int | None, update: bool) -> None: ...
+    def data_get_group(self, value_id: int) -> tuple[int, tuple[float, float]] | None: ...
 
     # data take -------------------------------------------------------------------
     def data_take_set(self, value_id: int, data: Buffer, blocking: bool, update: bool, cache: bool) -> None: ...
@@ -116,8 +202,12 @@ class StateServerCore:
     def data_multi_set(self, value_id: int, index: int, data: Buffer, update: bool) -> None: ...
     def data_multi_add(self, value_id: int, index: int, data: Buffer, update: bool) -> None: ...
     def data_multi_replace(self, value_id: int, index: int, data: Buffer, data_index: int, update: bool) -> None: ...
+    def data_multi_splice(
+        self, value_id: int, index: int, data: Buffer, data_index: int, removed: int, update: bool
+    ) -> None: ...
     def data_multi_remove(self, value_id: int, index: int, data_index: int, count: int, update: bool) -> None: ...
     def data_multi_clear(self, value_id: int, index: int, update: bool) -> None: ...
+    def data_multi_set_max_len(self, value_id: int, index: int, max_len: int | None, update: bool) -> None: ...
     def data_multi_remove_index(self, value_id: int, index: int, update: bool) -> None: ...
     def data_multi_reset(self, value_id: int, update: bool) -> None: ...
 
@@ -135,17 +225,28 @@ class StateServerCore:
     def data_multi_take_reset(self, value_id: int, update: bool) -> None: ...
 
     # add states ------------------------------------------------------------------
-    def add_value(self, name: str, object_type: PyObjectType, initial_value: object, queue: bool) -> int: ...
-    def add_value_take(self, name: str, object_type: PyObjectType) -> int: ...
-    def add_static(self, name: str, object_type: PyObjectType, initial_value: object) -> int: ...
-    def add_signal(self, name: str, object_type: PyObjectType, queue: bool) -> int: ...
-    def add_vec(self, name: str, object_type: PyObjectType) -> int: ...
-    def add_map(self, name: str, key_type: PyObjectType, value_type: PyObjectType) -> int: ...
-    def add_image(self, name: str) -> int: ...
-    def add_data(self, name: str, data_type: int) -> int: ...
-    def add_data_take(self, name: str, data_type: int) -> int: ...
-    def add_data_multi(self, name: str, data_type: int) -> int: ...
-    def add_data_multi_take(self, name: str, data_type: int) -> int: ...
+    def add_value(
+        self, name: str, object_type: PyObjectType, initial_value: object, queue: bool, value_id: int | None = None
+    ) -> int: ...
+    def add_value_take(self, name: str, object_type: PyObjectType, value_id: int | None = None) -> int: ...
+    def add_static(
+        self, name: str, object_type: PyObjectType, initial_value: object, value_id: int | None = None
+    ) -> int: ...
+    def add_signal(self, name: str, object_type: PyObjectType, queue: bool, value_id: int | None = None) -> int: ...
+    def add_vec(self, name: str, object_type: PyObjectType, value_id: int | None = None) -> int: ...
+    def add_map(
+        self,
+        name: str,
+        key_type: PyObjectType,
+        value_type: PyObjectType,
+        ordered: bool = False,
+        value_id: int | None = None,
+    ) -> int: ...
+    def add_image(self, name: str, premultiply_alpha: bool = False, value_id: int | None = None) -> int: ...
+    def add_data(self, name: str, data_type: int, value_id: int | None = None) -> int: ...
+    def add_data_take(self, name: str, data_type: int, value_id: int | None = None) -> int: ...
+    def add_data_multi(self, name: str, data_type: int, value_id: int | None = None) -> int: ...
+    def add_data_multi_take(self, name: str, data_type: int, value_id: int | None = None) -> int: ...
     def finalize(self) -> None: ...
 
 __all__ = [
@@ -169,4 +270,5 @@ __all__ = [
     "vec",
     "map",
     "enu",
+    "uni",
 ]
@@ -22,23 +22,50 @@ class LoggingSignal:
     def __init__(self, signals_manager: SignalsManager, server: StateServerCore) -> None:
         """Initialize the LoggingSignal."""
         self._loggers: dict[int, list[Callable[[str], None]]] = {0: [], 1: [], 2: [], 3: []}
+        self._structured: dict[int, list[Callable[[str, dict[str, str]], None]]] = {0: [], 1: [], 2: [], 3: []}
+        self._server = server
         signals_manager.add_callback(_LOGGING_ID, self._callback)
         server.signal_set_to_queue(_LOGGING_ID)
 
-    def _callback(self, message: tuple[int, str]) -> None:
-        level = message[0]
-        if level == LogLevel.Debug.value:
-            for logger in self._loggers[0]:
-                logger(message[1])
-        elif level == LogLevel.Info.value:
-            for logger in self._loggers[1]:
-                logger(message[1])
-        elif level == LogLevel.Warning.value:
-            for logger in self._loggers[2]:
-                logger(message[1])
-        elif level == LogLevel.Error.value:
-            for logger in self._loggers[3]:
-                logger(message[1])
+    def _callback(self, message: tuple[int, str, dict[str, str] | None]) -> None:
+        level, text, fields = message
+        if level not in self._loggers:
+            return
+        for logger in self._loggers[level]:
+            logger(text)
+        for structured_logger in self._structured[level]:
+            structured_logger(text, fields or {})
+
+    def log(self, level: LogLevel, message: str, fields: dict[str, str] | None = None) -> None:
+        """Send a log record to the loggers.
+
+        Args:
+            level(LogLevel): The logging level.
+            message(str): The log message.
+            fields(dict[str, str] | None, optional): Key-value fields of the record. Defaults to None.
+        """
+        self._server.log(level.value, message, fields)
+
+    def add_structured_logger(self, level: LogLevel, logger: Callable[[str, dict[str, str]], None]):
+        """Add logger for a specific level, which receives also the fields of the record.
+
+        Records without fields are passed with empty dict.
+
+        Args:
+            level(Level): The logging level.
+            logger(Callable[[str, dict[str, str]], None]): The logger to add.
+        """
+        self._structured[level.value].append(logger)
+
+    def remove_structured_logger(self, level: LogLevel, logger: Callable[[str, dict[str, str]], None]):
+        """Remove structured logger for a specific level.
+
+        Args:
+            level(Level): The logging level.
+            logger(Callable[[str, dict[str, str]], None]): The logger to remove.
+        """
+        if logger in self._structured[level.value]:
+            self._structured[level.value].remove(logger)
 
     def add_logger(self, level: LogLevel, logger: Callable[[str], None]):
         """Add logger for a specific level.
@@ -66,3 +93,4 @@ class LoggingSignal:
             level(Level): The logging level.
         """
         self._loggers[level.value].clear()
+        self._structured[level.value].clear()
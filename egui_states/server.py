@@ -1,12 +1,14 @@
 # ruff: noqa: D107
 from abc import ABC, abstractmethod
-from collections.abc import Callable
-from typing import Any
+from collections.abc import Callable, Iterator
+from contextlib import contextmanager
+from types import TracebackType
+from typing import Any, Self
 
-from egui_states._core import PyObjectType, StateServerCore
+from egui_states._core import METRICS_HTTP, TLS, PyObjectType, StateServerCore
 from egui_states.logging import LoggingSignal
 from egui_states.signals import SignalsManager
-from egui_states.structures import ISubStates, _SignalBase, _StaticBase
+from egui_states.structures import Data, ISubStates, Value, _SignalBase, _StaticBase
 
 _ON_CONNECT_ID = 1
 _ON_DISCONNECT_ID = 2
@@ -67,6 +69,7 @@ class StateServerBase[T: StatesBase]:
         ip_addr: tuple[int, int, int, int] | None = None,
         version: int | None = None,
         token: str | None = None,
+        queue_size: int | None = None,
     ) -> None:
         """Initialize the SteteServer.
 
@@ -78,8 +81,12 @@ class StateServerBase[T: StatesBase]:
             ip_addr (tuple[int, int, int, int] | None): The IP address to bind the server to.
             version (int | None): The optional version number for client connection.
             token (str | None): The optional token string for client connection.
+            queue_size (int | None): The maximal number of pending messages to the client, 65536 if not given. If the
+                queue is full, set methods raise BlockingIOError instead of queueing more messages. A new set of Value
+                or Static replaces its message still waiting in the queue, so only the latest value is sent, after all
+                messages queued before it.
         """
-        self._server = StateServerCore(port, ip_addr, version, token)
+        self._server = StateServerCore(port, ip_addr, version, token, queue_size)
         self._signals_manager = SignalsManager(self._server, signals_workers, error_handler)
         self._states: T = state_class(self)
 
@@ -116,8 +123,66 @@ class StateServerBase[T: StatesBase]:
         """Stop the state server."""
         self._server.stop()
 
+    def __enter__(self) -> Self:
+        """Use the server as a context manager, it is stopped when the block exits."""
+        return self
+
+    def __exit__(
+        self,
+        exc_type: type[BaseException] | None,
+        exc_value: BaseException | None,
+        traceback: TracebackType | None,
+    ) -> None:
+        """Stop the server, also when the block exits with an exception."""
+        self.stop()
+
+    def enable_metrics(self, port: int | None, ip_addr: tuple[int, int, int, int] | None = None) -> None:
+        """Expose the server metrics in Prometheus text format on http://ip_addr:port/metrics.
+
+        The endpoint runs while the server is running, the change is applied on the next start.
+
+        Args:
+            port (int | None): The port of the endpoint, None disables the endpoint.
+            ip_addr (tuple[int, int, int, int] | None): The IP address to bind the endpoint to. Defaults to all.
+
+        Raises:
+            RuntimeError: If the library is built without the metrics-http feature.
+        """
+        if not METRICS_HTTP:
+            raise RuntimeError("Metrics need the library built with the metrics-http feature.")
+        self._server.set_metrics_addr(port, ip_addr)
+
+    def allow_read_only_clients(self, count: int) -> None:
+        """Allow read-only clients to connect besides the client, e.g. several dashboards.
+
+        The read-only clients receive all states like the client, but the server ignores their changes. They stay
+        connected also without the client, but they are not counted by is_connected. The change is applied on the
+        next start.
+
+        Args:
+            count (int): The maximal number of connected read-only clients, 0 disallows them.
+        """
+        self._server.set_read_only_clients(count)
+
+    def enable_tls(self, cert_path: str | None, key_path: str | None = None) -> None:
+        """Accept only TLS connections, the client has to connect with TLS enabled.
+
+        The change is applied on the next start.
+
+        Args:
+            cert_path (str | None): The PEM file with the certificate chain, None disables TLS.
+            key_path (str | None): The PEM file with the private key. Required with the certificate.
+
+        Raises:
+            RuntimeError: If the library is built without the tls feature.
+            ValueError: If the certificate or the private key cannot be loaded.
+        """
+        if not TLS:
+            raise RuntimeError("TLS needs the library built with the tls feature.")
+        self._server.set_tls(cert_path, key_path)
+
     def disconnect_client(self) -> None:
-        """Disconnect actual client."""
+        """Disconnect actual client and all read-only clients."""
         self._server.disconnect_client()
 
     def is_running(self) -> bool:
@@ -125,9 +190,105 @@ class StateServerBase[T: StatesBase]:
         return self._server.is_running()
 
     def is_connected(self) -> bool:
-        """If client is connected to the state server."""
+        """If the client is connected to the state server, the read-only clients are not counted."""
         return self._server.is_connected()
 
+    def flush(self, timeout: float) -> bool:
+        """Block until all messages queued so far are written to the connected client and read-only clients.
+
+        Args:
+            timeout(float): Maximum time to wait in seconds, float("inf") waits until the messages are written.
+
+        Returns:
+            bool: True if the messages were written, False if no client or read-only client is connected or the
+                timeout elapsed.
+        """
+        return self._server.flush(timeout)
+
+    def stats(self) -> dict[str, int]:
+        """Get the transport counters since the server was created.
+
+        Messages are the websocket messages, every one can carry several value updates.
+
+        Returns:
+            dict[str, int]: The messages_sent, bytes_sent, messages_received, bytes_received and queue_depth, which
+                is the count of messages waiting to be sent to the client.
+        """
+        return self._server.stats()
+
+    def snapshot(self) -> dict[str, Any]:
+        """Get current values of all values, static values, lists and maps as nested dict following the state tree.
+
+        Returns:
+            dict[str, Any]: The nested dict with substate and value names as keys.
+        """
+        return self._server.snapshot_dict()
+
+    @contextmanager
+    def batch(self) -> Iterator[None]:
+        """Defer the UI update of all changes inside the with block to a single update at its end.
+
+        The value messages are still sent one by one, only the update requested by the individual changes is
+        replaced by one update after the block. The batch is shared by all threads and it can be nested.
+        """
+        self._server.begin_batch()
+        try:
+            yield
+        finally:
+            self._server.end_batch()
+
+    def set_values(self, values: dict[Value, Any], set_signal: bool = False, update: bool = False) -> int:
+        """Set many values at once with a single UI update at the end.
+
+        All values are checked before any of them is set, so a wrong value does not leave the values partially set.
+
+        Args:
+            values(dict[Value, Any]): The values with the new values to set.
+            set_signal(bool, optional): Whether to set the signals. Defaults to False.
+            update(bool, optional): Whether to update the UI. Defaults to False.
+
+        Returns:
+            int: The count of set values, the values dropped by the max rate are not counted.
+        """
+        return self._server.value_set_many({v._value_id: val for v, val in values.items()}, set_signal, update)
+
+    @contextmanager
+    def signals_suppressed(self, *values: _SignalBase) -> Iterator[None]:
+        """Disable signals of the values inside the with block, e.g. when batch setting values.
+
+        Args:
+            *values(_SignalBase): The values with signals to suppress.
+        """
+        for value in values:
+            value.suppress_signals(True)
+        try:
+            yield
+        finally:
+            for value in values:
+                value.suppress_signals(False)
+
+    def set_data_group(
+        self, group: int, data: list[Data], x_range: tuple[float, float], update: bool = False
+    ) -> None:
+        """Group several data with shared x range, so the UI can pan and zoom them together.
+
+        Args:
+            group(int): The group id.
+            data(list[Data]): The data to group.
+            x_range(tuple[float, float]): The shared x range of the group.
+            update(bool, optional): Whether to update the UI. Defaults to False.
+        """
+        self._server.data_set_group(group, [d._value_id for d in data], x_range, update)
+
+    def clear_data_group(self, data: list[Data], update: bool = False) -> None:
+        """Remove data from their groups.
+
+        Args:
+            data(list[Data]): The data to remove from groups.
+            update(bool, optional): Whether to update the UI. Defaults to False.
+        """
+        self._server.data_clear_group([d._value_id for d in data], update)
+
     def set_error_handler(self, error_handler: Callable[[Exception], None] | None) -> None:
         """Set the error handler.
 
@@ -7,7 +7,13 @@ from egui_states._core import StateServerCore
 
 
 class SignalsManager:
-    """The class for managing signals."""
+    """The class for managing signals.
+
+    The callbacks run on the worker threads of the manager, never on the thread that started the server. The workers
+    wait for the signals with the GIL released, so they do not block other python threads or an asyncio event loop
+    while idle. A callback holds the GIL while it runs, so long work should be moved elsewhere. To hand a signal over
+    to an asyncio event loop, use `loop.call_soon_threadsafe` inside the callback.
+    """
 
     def __init__(
         self,
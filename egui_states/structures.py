@@ -2,8 +2,9 @@
 from __future__ import annotations
 
 from abc import ABC, abstractmethod
-from collections.abc import Buffer, Callable
-from typing import Any
+from collections.abc import Buffer, Callable, Iterable
+from enum import IntEnum
+from typing import Any, Literal, Self
 
 import numpy as np
 import numpy.typing as npt
@@ -31,6 +32,7 @@ from egui_states._core import (
     u16,
     u32,
     u64,
+    uni,
     vec,
 )
 from egui_states.signals import SignalsManager
@@ -40,6 +42,35 @@ class _CustomStruct:
     __getitem__ = object.__getattribute__
 
 
+class _CustomEnum(IntEnum):
+    """The base class for enums without data, the variants can be created from their names."""
+
+    def __str__(self) -> str:
+        return self.name
+
+    @classmethod
+    def from_str(cls, name: str) -> Self:
+        """Get the variant by its name, for example loaded from a config file.
+
+        Args:
+            name(str): The name of the variant.
+
+        Raises:
+            ValueError: If the enum has no variant with the name.
+        """
+        try:
+            return cls[name]
+        except KeyError:
+            variants = ", ".join(cls.__members__)
+            raise ValueError(f"{name!r} is not a variant of {cls.__name__}, valid variants: {variants}") from None
+
+
+class _CustomUnion:
+    """The base class for enums with data, each variant is a subclass with the variant fields."""
+
+    __getitem__ = object.__getattribute__
+
+
 class ISubStates(ABC):
     """The base class for substates in the UI states."""
 
@@ -51,6 +82,19 @@ class ISubStates(ABC):
 class _StaticBase(ABC):
     _server: StateServerCore
     _value_id: int
+    _explicit_id: int | None = None
+
+    def with_id(self, value_id: int) -> Self:
+        """Use an explicit id instead of the id generated from the name.
+
+        The explicit id does not change when the value is renamed, so already deployed clients stay compatible. It
+        has to match the id used by the UI.
+
+        Args:
+            value_id(int): The id of the value, ids 0-9 are reserved.
+        """
+        self._explicit_id = value_id
+        return self
 
     def _initialize_base(self, server: StateServerCore) -> None:
         self._server = server
@@ -76,10 +120,34 @@ class _SignalBase(_StaticBase):
     def signal_set_to_single(self) -> None:
         """Set the value to single mode. It is the default mode.
 
-        In single mode, only the last change of the value is processed.
+        In single mode, only the last change of the value is processed. Changes arriving before the previous one is
+        processed replace it, so a slow callback of a high-frequency value gets only the latest value. Switching from
+        queue mode keeps only the newest queued change.
         """
         self._server.signal_set_to_single(self._value_id)
 
+    def suppress_signals(self, suppressed: bool = True) -> None:
+        """Temporarily disable signals of the value.
+
+        While suppressed, changes of the value from the server or the UI do not emit signals, even if set_signal is
+        True. Useful when loading many values at once.
+
+        Args:
+            suppressed(bool, optional): Whether to suppress the signals. Defaults to True.
+        """
+        self._server.signal_set_suppressed(self._value_id, suppressed)
+
+    def set_rate_limit(self, hz: float | None) -> None:
+        """Limit how often the signals of the value are delivered.
+
+        At most hz signals per second are processed, the values in between are dropped but the latest value is always
+        delivered. Unlike debouncing, the signals are delivered also during continuous changes.
+
+        Args:
+            hz(float | None): The maximum number of signals per second. None removes the limit.
+        """
+        self._server.signal_set_rate_limit(self._value_id, hz)
+
 
 class Value[T](_SignalBase):
     """General UI value of type T."""
@@ -90,20 +158,25 @@ class Value[T](_SignalBase):
         self._queue = queue
 
     def _initialize(self, name: str, types: list[PyObjectType]) -> None:
-        self._value_id = self._server.add_value(name, types[self._obj_id], self._initial_value, self._queue)
+        self._value_id = self._server.add_value(
+            name, types[self._obj_id], self._initial_value, self._queue, self._explicit_id
+        )
         del self._initial_value
         del self._obj_id
         del self._queue
 
-    def set(self, value: T, set_signal: bool = False, update: bool = False) -> None:
+    def set(self, value: T, set_signal: bool = False, update: bool = False) -> bool:
         """Set the value of the UI element.
 
         Args:
             value(T): The value to set.
             set_signal(bool, optional): Whether to set the signal. Defaults to True.
             update(bool, optional): Whether to update the UI. Defaults to False.
+
+        Returns:
+            bool: Whether the value was set, False if it was dropped by the max rate.
         """
-        self._server.value_set(self._value_id, value, set_signal, update)
+        return self._server.value_set(self._value_id, value, set_signal, update)
 
     def get(self) -> T:
         """Get the value of the UI element.
@@ -113,6 +186,81 @@ class Value[T](_SignalBase):
         """
         return self._server.value_get(self._value_id)
 
+    def compare_set(self, expected: T, value: T, set_signal: bool = False, update: bool = False) -> bool:
+        """Set the value only if the current value is equal to expected.
+
+        The comparison and the set are done atomically, so concurrent changes of the value are not overwritten.
+
+        Args:
+            expected(T): The value expected to be the current value.
+            value(T): The new value.
+            set_signal(bool, optional): Whether to set the signal. Defaults to False.
+            update(bool, optional): Whether to update the UI. Defaults to False.
+
+        Returns:
+            bool: Whether the value was set.
+        """
+        return self._server.value_compare_set(self._value_id, expected, value, set_signal, update)
+
+    def increment(self, delta: int | float, set_signal: bool = False, update: bool = False) -> T | None:
+        """Atomically add delta to the numeric value and return the new value.
+
+        Args:
+            delta(int | float): The amount to add, integer values can be incremented only by an integer.
+            set_signal(bool, optional): Whether to set the signal. Defaults to False.
+            update(bool, optional): Whether to update the UI. Defaults to False.
+
+        Returns:
+            T | None: The new value, None if the change was dropped by the max rate.
+        """
+        return self._server.value_increment(self._value_id, delta, set_signal, update)
+
+    def set_field(self, path: str, value: Any, set_signal: bool = False, update: bool = False) -> bool:
+        """Set a single field of the struct value.
+
+        Args:
+            path(str): The path to the field, nested fields are separated by dots (e.g. "field.subfield").
+            value(Any): The value of the field.
+            set_signal(bool, optional): Whether to set the signal. Defaults to False.
+            update(bool, optional): Whether to update the UI. Defaults to False.
+
+        Returns:
+            bool: Whether the value was set, False if it was dropped by the max rate.
+        """
+        return self._server.value_set_field(self._value_id, path, value, set_signal, update)
+
+    def update_rate(self) -> float:
+        """Get the number of updates of the value in the last second.
+
+        Returns:
+            float: The update rate in updates per second.
+        """
+        return self._server.value_update_rate(self._value_id)
+
+    def set_max_rate(self, hz: float | None, drop: bool = False) -> None:
+        """Limit how often the value can be updated, from the server or from the UI.
+
+        Updates coming faster than the limit are logged as a warning once per second and with drop also discarded. The
+        dropped updates are reported by the return value of the set methods.
+
+        Args:
+            hz(float | None): The maximum update rate in updates per second, None removes the limit.
+            drop(bool, optional): Whether to drop the excess updates. Defaults to False.
+        """
+        self._server.value_set_max_rate(self._value_id, hz, drop)
+
+    def notify_client_changes(self, notify: bool = True) -> None:
+        """Emit the signal of the value for every change from the UI.
+
+        By default the UI decides whether its change emits the signal. With notify, every value written by the UI
+        reaches the connected callbacks, e.g. each edit of a text field. Only values and bitsets can be changed by the
+        UI, static values, lists, maps and data are set only from the server.
+
+        Args:
+            notify(bool, optional): Whether to emit the signal for every change. Defaults to True.
+        """
+        self._server.value_set_notify_client(self._value_id, notify)
+
     def connect(self, callback: Callable[[T], Any]) -> None:
         """Connect a callback to the value.
 
@@ -134,6 +282,64 @@ class Value[T](_SignalBase):
         self._signals_manager.clear_callbacks(self._value_id)
 
 
+class Bitset[E: IntEnum](Value[int]):
+    """Up to 64 flags stored in a single integer value, the bits are named by the enum E."""
+
+    def is_set(self, flag: E) -> bool:
+        """Check if the flag is set.
+
+        Args:
+            flag(E): The flag to check.
+
+        Returns:
+            bool: True if the flag is set.
+        """
+        return bool(self.get() & (1 << flag))
+
+    def set_flag(self, flag: E, on: bool = True, set_signal: bool = False, update: bool = False) -> int | None:
+        """Set or clear the flag.
+
+        Args:
+            flag(E): The flag to change.
+            on(bool, optional): Whether to set or clear the flag. Defaults to True.
+            set_signal(bool, optional): Whether to set the signal. Defaults to False.
+            update(bool, optional): Whether to update the UI. Defaults to False.
+
+        Returns:
+            int | None: The new bits, None if the change was dropped by the max rate.
+        """
+        mask = 1 << flag
+        if on:
+            return self._server.value_update_bits(self._value_id, mask, 0, 0, set_signal, update)
+        return self._server.value_update_bits(self._value_id, 0, mask, 0, set_signal, update)
+
+    def clear_flag(self, flag: E, set_signal: bool = False, update: bool = False) -> int | None:
+        """Clear the flag.
+
+        Args:
+            flag(E): The flag to clear.
+            set_signal(bool, optional): Whether to set the signal. Defaults to False.
+            update(bool, optional): Whether to update the UI. Defaults to False.
+
+        Returns:
+            int | None: The new bits, None if the change was dropped by the max rate.
+        """
+        return self._server.value_update_bits(self._value_id, 0, 1 << flag, 0, set_signal, update)
+
+    def toggle_flag(self, flag: E, set_signal: bool = False, update: bool = False) -> int | None:
+        """Toggle the flag.
+
+        Args:
+            flag(E): The flag to toggle.
+            set_signal(bool, optional): Whether to set the signal. Defaults to False.
+            update(bool, optional): Whether to update the UI. Defaults to False.
+
+        Returns:
+            int | None: The new bits, None if the change was dropped by the max rate.
+        """
+        return self._server.value_update_bits(self._value_id, 0, 0, 1 << flag, set_signal, update)
+
+
 class ValueTake[T](_StaticBase):
     """ValueTake is a value which can be taken in the UI only once.
 
@@ -145,7 +351,7 @@ class ValueTake[T](_StaticBase):
         self._obj_id = obj_id
 
     def _initialize(self, name: str, types: list[PyObjectType]) -> None:
-        self._value_id = self._server.add_value_take(name, types[self._obj_id])
+        self._value_id = self._server.add_value_take(name, types[self._obj_id], self._explicit_id)
         del self._obj_id
 
     def set(self, value: T, blocking: bool = False, update: bool = False) -> None:
@@ -167,7 +373,7 @@ class ValueTakeEmpty(_StaticBase):
     """
 
     def _initialize(self, name: str, types: list[PyObjectType]) -> None:
-        self._value_id = self._server.add_value_take(name, emp)
+        self._value_id = self._server.add_value_take(name, emp, self._explicit_id)
 
     def set(self, blocking: bool = False, update: bool = False) -> None:
         """Set the value of the UI element.
@@ -188,7 +394,7 @@ class Static[T](_StaticBase):
         self._obj_id = obj_id
 
     def _initialize(self, name: str, types: list[PyObjectType]) -> None:
-        self._value_id = self._server.add_static(name, types[self._obj_id], self._initial_value)
+        self._value_id = self._server.add_static(name, types[self._obj_id], self._initial_value, self._explicit_id)
         del self._initial_value
         del self._obj_id
 
@@ -209,6 +415,22 @@ class Static[T](_StaticBase):
         """
         return self._server.static_get(self._value_id)
 
+    def modify(self, callback: Callable[[T], T | None], update: bool = False) -> T:
+        """Modify the static value by the callback.
+
+        The callback gets the current value, it can change it in place and return None or return the new value. The
+        callback runs without the value lock, if the value is changed meanwhile, it is called again with the new value.
+        So the callback can run several times for one modification and it should not have other side effects.
+
+        Args:
+            callback(Callable[[T], T | None]): The function modifying the value.
+            update(bool, optional): Whether to update the UI. Defaults to False.
+
+        Returns:
+            T: The new static value.
+        """
+        return self._server.static_update(self._value_id, callback, update)
+
 
 class Signal[T](_SignalBase):
     """Signal from UI."""
@@ -218,7 +440,7 @@ class Signal[T](_SignalBase):
         self._queue = queue
 
     def _initialize(self, name: str, types: list[PyObjectType]) -> None:
-        self._value_id = self._server.add_signal(name, types[self._obj_id], self._queue)
+        self._value_id = self._server.add_signal(name, types[self._obj_id], self._queue, self._explicit_id)
         del self._obj_id
         del self._queue
 
@@ -260,7 +482,7 @@ class SignalEmpty(_SignalBase):
         self._queue = queue
 
     def _initialize(self, name: str, types: list[PyObjectType]) -> None:
-        self._value_id = self._server.add_signal(name, _core.emp, self._queue)
+        self._value_id = self._server.add_signal(name, _core.emp, self._queue, self._explicit_id)
         del self._queue
 
     def set(self) -> None:
@@ -294,21 +516,58 @@ class SignalEmpty(_SignalBase):
 class Image(_StaticBase):
     """Image UI element."""
 
+    def __init__(self, premultiply_alpha: bool = False) -> None:
+        """Initialize the image.
+
+        Args:
+            premultiply_alpha(bool, optional): Whether the UI premultiplies alpha of the images with alpha channel. Use
+                it for images with straight alpha to avoid dark edges when blending. Defaults to False.
+        """
+        self._premultiply_alpha = premultiply_alpha
+
     def _initialize(self, name: str, types: list[PyObjectType]) -> None:
-        self._value_id = self._server.add_image(name)
+        self._value_id = self._server.add_image(name, self._premultiply_alpha, self._explicit_id)
+        del self._premultiply_alpha
 
     def set(
         self,
         image: Buffer,
         update: bool = False,
+        window: tuple[float, float] | None = None,
     ) -> None:
         """Set the image in the UI image.
 
+        The image can be uint8, uint16 or float32. The uint16 and float32 images are tone mapped to uint8 before
+        sending, the alpha channel is mapped from the full uint16 range or from 0.0-1.0 for float32.
+
         Args:
             image(Buffer): The image to set.
             update(bool, optional): Whether to update the UI. Defaults to False.
+            window(tuple[float, float] | None, optional): The (min, max) range mapped to 0-255 for uint16 and float32
+                images. None uses the minimum and maximum of the image. Defaults to None.
         """
-        self._server.image_set(self._value_id, image, update)
+        self._server.image_set(self._value_id, image, update, window)
+
+    def set_scaled(
+        self,
+        image: Buffer,
+        max_dim: int,
+        update: bool = False,
+        window: tuple[float, float] | None = None,
+    ) -> None:
+        """Set the image and send it to the UI downscaled, the full resolution image is kept on the server.
+
+        The image is downscaled preserving the aspect ratio, so its larger side is at most max_dim.
+        Parts of the downscaled image cannot be updated by update method.
+
+        Args:
+            image(Buffer): The image to set.
+            max_dim(int): The maximum size of the larger side of the transmitted image.
+            update(bool, optional): Whether to update the UI. Defaults to False.
+            window(tuple[float, float] | None, optional): The (min, max) range mapped to 0-255 for uint16 and float32
+                images. None uses the minimum and maximum of the image. Defaults to None.
+        """
+        self._server.image_set_scaled(self._value_id, image, max_dim, update, window)
 
     def update(
         self,
@@ -316,6 +575,7 @@ class Image(_StaticBase):
         origin: list[int] | tuple[int, int],
         update: bool = False,
         force: bool = False,
+        window: tuple[float, float] | None = None,
     ) -> None:
         """Update a rectangular part of the image.
 
@@ -324,8 +584,43 @@ class Image(_StaticBase):
             origin(list[int] | tuple[int, int]): Top-left origin as (height, width) or (y, x).
             update(bool, optional): Whether to update the UI. Defaults to False.
             force(bool, optional): Whether to replace a pending update for the same rectangle. Defaults to False.
+            window(tuple[float, float] | None, optional): The (min, max) range mapped to 0-255 for uint16 and float32
+                images, kept for the next partial updates. None uses the window of the last set or updated uint16 or
+                float32 image, so the whole image keeps the same brightness. Defaults to None.
         """
-        self._server.image_update(self._value_id, image, origin, update, force)
+        self._server.image_update(self._value_id, image, origin, update, force, window)
+
+    def scroll_append(self, rows: Buffer, update: bool = False, window: tuple[float, float] | None = None) -> None:
+        """Scroll the image down and write the new rows at the top.
+
+        Only the new rows are sent to the UI, which scrolls its image the same way. Useful for waterfall displays.
+
+        Args:
+            rows(Buffer): The new rows, shape (rows, width) or (rows, width, channels) with the image width.
+            update(bool, optional): Whether to update the UI. Defaults to False.
+            window(tuple[float, float] | None, optional): The (min, max) range mapped to 0-255 for uint16 and float32
+                images, kept for the next partial updates. None uses the window of the last set or updated uint16 or
+                float32 image, so the whole image keeps the same brightness. Defaults to None.
+        """
+        self._server.image_scroll_append(self._value_id, rows, update, window)
+
+    def set_compression(self, compression: Literal["png", "jpeg"] | None, quality: int = 90) -> None:
+        """Set the compression of the image sent to the UI.
+
+        Only the whole image sent by set and set_scaled is compressed, the update and scroll_append are always sent
+        raw. JPEG does not support images with alpha channel.
+
+        Args:
+            compression(Literal["png", "jpeg"] | None): The compression, PNG is lossless, JPEG is lossy. None sends
+                the raw image.
+            quality(int, optional): The JPEG quality from 1 to 100. Defaults to 90.
+
+        Raises:
+            RuntimeError: If the library is built without the image-compression feature.
+        """
+        if compression is not None and not _core.IMAGE_COMPRESSION:
+            raise RuntimeError("Image compression needs the library built with the image-compression feature.")
+        self._server.image_set_compression(self._value_id, compression, quality)
 
     def get(self) -> npt.NDArray[np.uint8]:
         """Get the image in the UI image.
@@ -338,6 +633,14 @@ class Image(_StaticBase):
 
         return np.frombuffer(data, dtype=np.uint8).reshape(shape)
 
+    def get_array(self) -> npt.NDArray[np.uint8]:
+        """Get the image in the UI image as a read-only array, without the copy to a writable buffer.
+
+        Returns:
+            npt.NDArray[np.uint8]: The read-only image with shape (height, width, 4). 4 is for RGBA.
+        """
+        return np.asarray(self._server.image_get_array(self._value_id))
+
     def shape(self) -> tuple[int, int]:
         """Get the shape of the image.
 
@@ -350,14 +653,26 @@ class Image(_StaticBase):
 class Map[K, V](_StaticBase):
     """Dict UI element."""
 
-    def __init__(self, key_id: int, value_id: int) -> None:
+    def __init__(self, key_id: int, value_id: int, ordered: bool = False) -> None:
+        """Initialize the dict.
+
+        Args:
+            key_id(int): The type index of the keys.
+            value_id(int): The type index of the values.
+            ordered(bool, optional): Whether the dict keeps the insertion order of the keys for the UI and get. Setting
+                an existing key keeps its position and removing a key keeps the order of the rest, so the remove shifts
+                all the next keys and it is slower for large dicts. Defaults to False.
+        """
         self._key_id = key_id
         self._value_type_id = value_id
+        self._ordered = ordered
 
     def _initialize(self, name: str, types: list[PyObjectType]) -> None:
-        self._value_id = self._server.add_map(name, types[self._key_id], types[self._value_type_id])
+        key_type, value_type = types[self._key_id], types[self._value_type_id]
+        self._value_id = self._server.add_map(name, key_type, value_type, self._ordered, self._explicit_id)
         del self._key_id
         del self._value_type_id
+        del self._ordered
 
     def set(self, value: dict[K, V], update: bool = False) -> None:
         """Set the dict in the UI dict.
@@ -397,6 +712,23 @@ class Map[K, V](_StaticBase):
         """
         return self._server.map_get_item(self._value_id, key)
 
+    def update_item(self, key: K, callback: Callable[[V | None], V | None], update: bool = False) -> V | None:
+        """Update the item in the UI dict by the callback.
+
+        The callback gets the current value or None if the key is missing. An existing value can be changed in place
+        and None returned, or the new value returned. For a missing key, returning None leaves the dict unchanged. The
+        callback runs without the dict lock, if the item is changed meanwhile, it is called again with the new value.
+
+        Args:
+            key(K): The key of the item.
+            callback(Callable[[V | None], V | None]): The function updating the value.
+            update(bool, optional): Whether to update the UI. Defaults to False.
+
+        Returns:
+            V | None: The new value of the item or None if nothing was inserted.
+        """
+        return self._server.map_update_item(self._value_id, key, callback, update)
+
     def remove_item(self, key: K, update: bool = False) -> None:
         """Remove the item from the UI dict.
 
@@ -406,6 +738,18 @@ class Map[K, V](_StaticBase):
         """
         self._server.map_del_item(self._value_id, key, update)
 
+    def remove_items(self, keys: Iterable[K], update: bool = False) -> int:
+        """Remove the items from the UI dict, all removed keys are sent to the UI at once.
+
+        Args:
+            keys(Iterable[K]): The keys of the items, missing keys are skipped.
+            update(bool, optional): Whether to update the UI. Defaults to False.
+
+        Returns:
+            int: The number of removed items.
+        """
+        return self._server.map_del_items(self._value_id, keys, update)
+
     def __getitem__(self, key: K) -> V:
         """Get the item in the UI dict."""
         return self.get_item(key)
@@ -426,7 +770,7 @@ class Vec[T](_StaticBase):
         self._obj_id = obj_id
 
     def _initialize(self, name: str, types: list[PyObjectType]) -> None:
-        self._value_id = self._server.add_vec(name, types[self._obj_id])
+        self._value_id = self._server.add_vec(name, types[self._obj_id], self._explicit_id)
         del self._obj_id
 
     def set(self, value: list[T], update: bool = False) -> None:
@@ -446,6 +790,52 @@ class Vec[T](_StaticBase):
         """
         return self._server.list_get(self._value_id)
 
+    def get_range(self, start: int, stop: int) -> list[T]:
+        """Get a slice of the UI list, only the items in the slice are converted.
+
+        Args:
+            start(int): The index of the first item.
+            stop(int): The index after the last item, it is clamped to the list length.
+
+        Returns:
+            list[T]: The items from start to stop, empty if start is past the end.
+        """
+        return self._server.list_get_range(self._value_id, start, stop)
+
+    def set_array(self, array: npt.NDArray[Any], update: bool = False) -> None:
+        """Set the list from a numpy array with a single copy instead of converting every item.
+
+        Works only for items of fixed size (u8, i8, float and tuples, classes and lists of them). The array dtype has to
+        match the item layout without padding, e.g. np.dtype([("x", np.float32), ("y", np.float32)]) for a class with
+        two f32 fields.
+
+        Args:
+            array(npt.NDArray[Any]): The array with one element per item.
+            update(bool, optional): Whether to update the UI. Defaults to False.
+        """
+        self._check_dtype(array.dtype)
+        self._server.list_set_buffer(self._value_id, np.ascontiguousarray(array), update)
+
+    def get_array(self, dtype: npt.DTypeLike) -> npt.NDArray[Any]:
+        """Get the list as a numpy array, the counterpart of set_array.
+
+        Args:
+            dtype(npt.DTypeLike): The dtype matching the item layout.
+
+        Returns:
+            npt.NDArray[Any]: The array with one element per item.
+        """
+        dtype = np.dtype(dtype)
+        self._check_dtype(dtype)
+        return np.frombuffer(self._server.list_get_buffer(self._value_id), dtype=dtype)
+
+    def _check_dtype(self, dtype: np.dtype) -> None:
+        item_size = self._server.list_item_size(self._value_id)
+        if item_size is None:
+            raise ValueError("List items do not have a fixed size.")
+        if dtype.itemsize != item_size:
+            raise ValueError(f"Dtype size {dtype.itemsize} does not match the item size {item_size}.")
+
     def set_item(self, idx: int, value: T, update: bool = False) -> None:
         """Set the item in the UI list.
 
@@ -485,6 +875,29 @@ class Vec[T](_StaticBase):
         """
         self._server.list_append_item(self._value_id, value, update)
 
+    def sort(self, key: Callable[[T], Any] | None = None, reverse: bool = False, update: bool = False) -> None:
+        """Sort the UI list.
+
+        Only the new order is sent to the UI, not the items. The sort is stable. The key function runs without the list
+        lock, if the list is changed meanwhile, the keys are computed again for the new items.
+
+        Args:
+            key(Callable[[T], Any] | None, optional): Function computing the sort key of the item. Defaults to None.
+            reverse(bool, optional): Whether to sort in descending order. Defaults to False.
+            update(bool, optional): Whether to update the UI. Defaults to False.
+        """
+        self._server.list_sort(self._value_id, key, reverse, update)
+
+    def reverse(self, update: bool = False) -> None:
+        """Reverse the UI list.
+
+        Only the new order is sent to the UI, not the items.
+
+        Args:
+            update(bool, optional): Whether to update the UI. Defaults to False.
+        """
+        self._server.list_reverse(self._value_id, update)
+
     def __getitem__(self, idx: int) -> T:
         """Get the item in the UI list."""
         return self.get_item(idx)
@@ -513,7 +926,7 @@ class Data[T: np.generic](_StaticBase):
         self._dtype = dtype
 
     def _initialize(self, name: str, types: list[PyObjectType]) -> None:
-        self._value_id = self._server.add_data(name, _DTYPE_TO_ID[np.dtype(self._dtype).type])
+        self._value_id = self._server.add_data(name, _DTYPE_TO_ID[np.dtype(self._dtype).type], self._explicit_id)
 
     def get(self) -> npt.NDArray[T]:
         """Get the data from the UI data.
@@ -524,23 +937,35 @@ class Data[T: np.generic](_StaticBase):
         data = self._server.data_get(self._value_id)
         return np.frombuffer(data, dtype=self._dtype)
 
-    def set(self, data: Buffer, update: bool = False) -> None:
+    def set(self, data: Buffer, update: bool = False, max_points: int | None = None, xy: bool = False) -> None:
         """Set the data in the UI data.
 
+        With max_points, the full data is kept on the server, but the UI receives at most max_points points, the
+        minimum and maximum of each bucket. The decimation is applied to all next changes until the data is set without
+        it. With xy, the data are interleaved x and y pairs, the pairs are kept together and compared by y.
+
         Args:
             data(Buffer): The data to set. Has to implement the buffer protocol (numpy array).
             update(bool, optional): Whether to update the UI. Defaults to False.
+            max_points(int | None, optional): The maximum number of points sent to the UI. Defaults to None.
+            xy(bool, optional): Whether the data are x and y pairs. Defaults to False.
         """
-        self._server.data_set(self._value_id, data, update)
+        self._server.data_set(self._value_id, data, update, max_points, xy)
 
-    def add(self, data: Buffer, update: bool = False) -> None:
+    def add(self, data: Buffer, update: bool = False) -> int:
         """Add the data to the UI data.
 
+        Large series can be streamed in chunks together with set_max_len, so the full series is never held in memory.
+        BlockingIOError is raised while the queue of the server is full, so the producer can wait and retry.
+
         Args:
             data(Buffer): The data to add. Has to implement the buffer protocol (numpy array).
             update(bool, optional): Whether to update the UI. Defaults to False.
+
+        Returns:
+            int: The number of items in the data after adding.
         """
-        self._server.data_add(self._value_id, data, update)
+        return self._server.data_add(self._value_id, data, update)
 
     def replace(self, data: Buffer, index: int, update: bool = False) -> None:
         """Replace the data in the UI data.
@@ -552,6 +977,17 @@ class Data[T: np.generic](_StaticBase):
         """
         self._server.data_replace(self._value_id, data, index, update)
 
+    def splice(self, data: Buffer, index: int, removed: int, update: bool = False) -> None:
+        """Replace a segment of the UI data with new data, the new data can have a different length.
+
+        Args:
+            data(Buffer): The new data. Has to implement the buffer protocol (numpy array).
+            index(int): The start index of the segment.
+            removed(int): The number of data items in the segment which are replaced.
+            update(bool, optional): Whether to update the UI. Defaults to False.
+        """
+        self._server.data_splice(self._value_id, data, index, removed, update)
+
     def remove(self, index: int, count: int, update: bool = False) -> None:
         """Remove the data from the UI data.
 
@@ -570,13 +1006,32 @@ class Data[T: np.generic](_StaticBase):
         """
         self._server.data_clear(self._value_id, update)
 
+    def set_max_len(self, max_len: int | None, update: bool = False) -> None:
+        """Limit the number of items in the data.
+
+        When new items are added over the limit, the oldest items are dropped, so the data works as a ring buffer.
+
+        Args:
+            max_len(int | None): The maximum number of items. None removes the limit.
+            update(bool, optional): Whether to update the UI. Defaults to False.
+        """
+        self._server.data_set_max_len(self._value_id, max_len, update)
+
+    def group(self) -> tuple[int, tuple[float, float]] | None:
+        """Get the group of the data.
+
+        Returns:
+            tuple[int, tuple[float, float]] | None: The group id and shared x range or None if not grouped.
+        """
+        return self._server.data_get_group(self._value_id)
+
 
 class DataTake[T: np.generic](_StaticBase):
     def __init__(self, dtype: type[T]) -> None:
         self._dtype = dtype
 
     def _initialize(self, name: str, types: list[PyObjectType]) -> None:
-        self._value_id = self._server.add_data_take(name, _DTYPE_TO_ID[np.dtype(self._dtype).type])
+        self._value_id = self._server.add_data_take(name, _DTYPE_TO_ID[np.dtype(self._dtype).type], self._explicit_id)
 
     def set(
         self,
@@ -645,6 +1100,17 @@ class SingleData[T: np.generic]:
         """
         self._server.data_multi_replace(self._value_id, self._index, data, index, update)
 
+    def splice(self, data: Buffer, index: int, removed: int, update: bool = False) -> None:
+        """Replace a segment of the UI data at this index with new data of any length.
+
+        Args:
+            data(Buffer): The new data. Has to implement the buffer protocol (numpy array).
+            index(int): The start index of the segment within this single data.
+            removed(int): The number of data items in the segment which are replaced.
+            update(bool, optional): Whether to update the UI. Defaults to False.
+        """
+        self._server.data_multi_splice(self._value_id, self._index, data, index, removed, update)
+
     def remove(self, index: int, count: int, update: bool = False) -> None:
         """Remove the data from the UI data at this index.
 
@@ -663,13 +1129,24 @@ class SingleData[T: np.generic]:
         """
         self._server.data_multi_clear(self._value_id, self._index, update)
 
+    def set_max_len(self, max_len: int | None, update: bool = False) -> None:
+        """Limit the number of items in the data at this index.
+
+        When new items are added over the limit, the oldest items are dropped, so the data works as a ring buffer.
+
+        Args:
+            max_len(int | None): The maximum number of items. None removes the limit.
+            update(bool, optional): Whether to update the UI. Defaults to False.
+        """
+        self._server.data_multi_set_max_len(self._value_id, self._index, max_len, update)
+
 
 class DataMulti[T: np.generic](_StaticBase):
     def __init__(self, dtype: type[T]) -> None:
         self._dtype = dtype
 
     def _initialize(self, name: str, types: list[PyObjectType]) -> None:
-        self._value_id = self._server.add_data_multi(name, _DTYPE_TO_ID[np.dtype(self._dtype).type])
+        self._value_id = self._server.add_data_multi(name, _DTYPE_TO_ID[np.dtype(self._dtype).type], self._explicit_id)
 
     def get(self, index: int) -> SingleData[T]:
         """Get the SingleData object for the given index.
@@ -740,6 +1217,7 @@ class DataMultiTake[T: np.generic](_StaticBase):
         self._value_id = self._server.add_data_multi_take(
             name,
             _DTYPE_TO_ID[np.dtype(self._dtype).type],
+            self._explicit_id,
         )
 
     def get(self, index: int) -> SingleDataTake[T]:
@@ -790,6 +1268,7 @@ __all__ = [
     "bo",
     "emp",
     "enu",
+    "uni",
     "cl",
     "st",
     "vec",
@@ -798,4 +1277,5 @@ __all__ = [
     "tu",
     "map",
     "_CustomStruct",
+    "_CustomUnion",
 ]
@@ -75,6 +75,7 @@ pub struct MainApp {
 }
 
 impl MainApp {
+    #[allow(clippy::new_ret_no_self)]
     pub fn new(
         cc: &CreationContext,
         port: u16,
@@ -102,13 +103,17 @@ impl eframe::App for MainApp {
                         ConnectionState::Connected => {
                             egui::Button::new("Connected").fill(egui::Color32::LIGHT_GREEN)
                         }
-                        ConnectionState::Disconnected => {
+                        ConnectionState::Disconnected(_) => {
                             egui::Button::new("Reconnect").fill(egui::Color32::LIGHT_RED)
                         }
+                        ConnectionState::Reconnecting => egui::Button::new("Reconnecting..."),
                     };
                     if ui.add(button).clicked() {
                         self.client.connect();
                     }
+                    if let ConnectionState::Disconnected(reason) = self.client.get_state() {
+                        ui.label(reason.to_string());
+                    }
                 });
             });
 
@@ -9,9 +9,16 @@ pub(super) struct ValueVecActionStates {
     pub reset_demo: Signal<()>,
 }
 
+#[derive(Clone, serde::Serialize, serde::Deserialize, egui_states::Transportable)]
+pub(super) struct VecPoint {
+    pub x: f32,
+    pub y: f32,
+}
+
 #[derive(egui_states::State)]
 pub(super) struct ValueVecStates {
     pub items: VecState<i32>,
+    pub points: VecState<VecPoint>,
     pub actions: ValueVecActionStates,
 }
 
@@ -45,6 +52,11 @@ pub(super) fn show_value_vec(ui: &mut egui::Ui, state: &mut State) {
             }
         });
 
+        let points = state.value_vec.points.read(|points| points.len());
+        ui.label(format!(
+            "ValueVec<VecPoint>: root.value_vec.points, {points} points"
+        ));
+
         ui.horizontal(|ui| {
             if ui.button("append item").clicked() {
                 state.value_vec.actions.append_item.set(());
@@ -1,5 +1,7 @@
 use std::fmt::Display;
 
+use egui_states::ToggleGroup;
+
 use super::sections::{TestEnum, TestEnum2, TestStruct2};
 
 pub(super) fn preview_slice<T: Display>(values: &[T]) -> String {
@@ -35,13 +37,8 @@ pub(super) fn show_multi_data_preview(ui: &mut egui::Ui, items: &[(u32, usize, S
 }
 
 pub(super) fn show_test_enum_selector(ui: &mut egui::Ui, value: &mut TestEnum) -> bool {
-    let mut changed = false;
-    ui.horizontal(|ui| {
-        changed |= ui.selectable_value(value, TestEnum::A, "A").changed();
-        changed |= ui.selectable_value(value, TestEnum::B, "B").changed();
-        changed |= ui.selectable_value(value, TestEnum::C, "C").changed();
-    });
-    changed
+    let options = [(TestEnum::A, "A"), (TestEnum::B, "B"), (TestEnum::C, "C")];
+    ui.add(ToggleGroup::new(value, &options)).changed()
 }
 
 pub(super) fn show_test_enum2_selector(ui: &mut egui::Ui, value: &mut TestEnum2) -> bool {
@@ -54,14 +51,6 @@ pub(super) fn show_test_enum2_selector(ui: &mut egui::Ui, value: &mut TestEnum2)
     changed
 }
 
-pub(super) fn test_enum_label(value: TestEnum) -> &'static str {
-    match value {
-        TestEnum::A => "A",
-        TestEnum::B => "B",
-        TestEnum::C => "C",
-    }
-}
-
 pub(super) fn format_test_struct2(value: &TestStruct2) -> String {
     format!(
         "enabled = {}, level = {}, name = {}",
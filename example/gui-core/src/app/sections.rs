@@ -1,10 +1,10 @@
-use egui_states::{Queue, Signal, Static, StaticAtomic, Value, ValueAtomic};
+use egui_states::{
+    Queue, Signal, Static, StaticAtomic, Value, ValueArray, ValueAtomic, ValueBitset, state_text,
+};
 
 use super::{
     State,
-    helpers::{
-        format_test_struct2, show_test_enum_selector, show_test_enum2_selector, test_enum_label,
-    },
+    helpers::{format_test_struct2, show_test_enum_selector, show_test_enum2_selector},
 };
 
 #[derive(
@@ -16,6 +16,7 @@ use super::{
     serde::Serialize,
     serde::Deserialize,
     egui_states::Transportable,
+    egui_states::EnumConvert,
 )]
 pub(super) enum TestEnum {
     #[default]
@@ -59,6 +60,24 @@ pub(super) struct TestStruct2 {
     pub name: String,
 }
 
+#[derive(
+    Clone, Default, PartialEq, serde::Serialize, serde::Deserialize, egui_states::Transportable,
+)]
+pub(super) struct TestStruct3 {
+    pub point: TestStruct,
+    pub scale: f32,
+}
+
+#[derive(
+    Clone, Default, PartialEq, serde::Serialize, serde::Deserialize, egui_states::Transportable,
+)]
+pub(super) enum TestUnion {
+    #[default]
+    Off,
+    Level(u32),
+    Point(TestStruct, bool),
+}
+
 #[derive(egui_states::State)]
 pub(super) struct NestedValueStates {
     pub secondary_choice: Value<TestEnum2>,
@@ -71,10 +90,13 @@ pub(super) struct ValueStates {
     pub count: Value<i32>,
     pub ratio: ValueAtomic<f64>,
     pub queued_progress: Value<f32, Queue>,
+    #[id(1000)]
     pub title: Value<String>,
     pub optional_value: Value<Option<i32>>,
-    pub fixed_numbers: Value<[u16; 3]>,
+    pub fixed_numbers: ValueArray<u16, 3>,
     pub test_enum: Value<TestEnum>,
+    pub test_union: Value<TestUnion>,
+    pub flags: ValueBitset<TestEnum>,
     pub nested: NestedValueStates,
 }
 
@@ -103,6 +125,7 @@ pub(super) struct SignalStates {
 pub(super) struct CustomValueStates {
     pub point: Value<TestStruct>,
     pub optional_struct: Value<Option<TestStruct2>>,
+    pub nested_point: Value<TestStruct3>,
 }
 
 pub(super) fn show_values(ui: &mut egui::Ui, state: &mut State) {
@@ -148,11 +171,7 @@ pub(super) fn show_values(ui: &mut egui::Ui, state: &mut State) {
 
         ui.separator();
 
-        ui.label("Value<String>: root.values.title");
-        let mut title = state.values.title.get();
-        if ui.text_edit_singleline(&mut title).changed() {
-            state.values.title.set_signal(title);
-        }
+        state_text(ui, &state.values.title, "Value<String>: root.values.title");
 
         ui.separator();
 
@@ -178,17 +197,18 @@ pub(super) fn show_values(ui: &mut egui::Ui, state: &mut State) {
 
         ui.separator();
 
-        ui.label("Value<[u16; 3]>: root.values.fixed_numbers");
-        let mut fixed_numbers = state.values.fixed_numbers.get();
-        let mut fixed_changed = false;
+        ui.label("ValueArray<u16, 3>: root.values.fixed_numbers");
         ui.horizontal(|ui| {
-            for value in &mut fixed_numbers {
-                fixed_changed |= ui.add(egui::DragValue::new(value)).changed();
+            for (index, mut value) in state.values.fixed_numbers.get().into_iter().enumerate() {
+                if ui.add(egui::DragValue::new(&mut value)).changed() {
+                    state
+                        .values
+                        .fixed_numbers
+                        .set_index_signal(index, value)
+                        .unwrap();
+                }
             }
         });
-        if fixed_changed {
-            state.values.fixed_numbers.set_signal(fixed_numbers);
-        }
 
         ui.separator();
 
@@ -200,6 +220,30 @@ pub(super) fn show_values(ui: &mut egui::Ui, state: &mut State) {
 
         ui.separator();
 
+        ui.label("Value<TestUnion>: root.values.test_union");
+        match state.values.test_union.get() {
+            TestUnion::Off => ui.label("Off"),
+            TestUnion::Level(level) => ui.label(format!("Level({})", level)),
+            TestUnion::Point(point, visible) => ui.label(format!(
+                "Point(x = {}, y = {}, visible = {})",
+                point.x, point.y, visible
+            )),
+        };
+
+        ui.separator();
+
+        ui.label("ValueBitset<TestEnum>: root.values.flags");
+        ui.horizontal(|ui| {
+            for (flag, label) in [(TestEnum::A, "A"), (TestEnum::B, "B"), (TestEnum::C, "C")] {
+                let mut on = state.values.flags.get(flag);
+                if ui.checkbox(&mut on, label).changed() {
+                    state.values.flags.set_signal(flag, on);
+                }
+            }
+        });
+
+        ui.separator();
+
         ui.label("Nested values: root.values.nested.*");
         let mut secondary_choice = state.values.nested.secondary_choice.get();
         if show_test_enum2_selector(ui, &mut secondary_choice) {
@@ -257,7 +301,7 @@ pub(super) fn show_signals(ui: &mut egui::Ui, state: &mut State) {
         ui.label("Signal<TestEnum, Queue>: root.signals.enum_signal");
         let mut enum_changed = false;
         egui::ComboBox::from_label("variant")
-            .selected_text(test_enum_label(state.enum_signal_value))
+            .selected_text(state.enum_signal_value.to_string())
             .show_ui(ui, |ui| {
                 enum_changed |= ui
                     .selectable_value(&mut state.enum_signal_value, TestEnum::A, "A")
@@ -298,7 +342,7 @@ pub(super) fn show_statics(ui: &mut egui::Ui, state: &mut State) {
         ui.label(state.statics.nested.label.get());
         ui.label(format!(
             "Enum hint: {}",
-            test_enum_label(state.statics.nested.enum_hint.get())
+            state.statics.nested.enum_hint.get()
         ));
     });
 }
@@ -347,5 +391,17 @@ pub(super) fn show_custom_values(ui: &mut egui::Ui, state: &mut State) {
                 .optional_struct
                 .set_signal(optional_struct);
         }
+
+        ui.separator();
+
+        ui.label("Value<TestStruct3>: root.custom_values.nested_point");
+        let nested_point = state.custom_values.nested_point.get();
+        ui.label(format!(
+            "point: ({:.2}, {:.2}) {}, scale: {:.2}",
+            nested_point.point.x,
+            nested_point.point.y,
+            nested_point.point.label,
+            nested_point.scale
+        ));
     });
 }
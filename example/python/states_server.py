@@ -1,8 +1,7 @@
 # Generated by build.rs, do not edit
-# ruff: noqa: D101 D107
+# ruff: noqa: D101 D107 E501
 from collections.abc import Callable
 from dataclasses import dataclass
-from enum import IntEnum
 
 import numpy as np
 
@@ -11,18 +10,25 @@ from egui_states.server import StatesBase, StateServerBase
 from egui_states.structures import ISubStates
 
 
-class TestEnum(IntEnum):
+class TestEnum(s._CustomEnum):
     A = 0
     B = 1
     C = 2
 
 
-class TestEnum2(IntEnum):
+class TestEnum2(s._CustomEnum):
     X = 0
     Y = 1
     Z = 2
 
 
+@dataclass
+class TestStruct(s._CustomStruct):
+    x: float
+    y: float
+    label: str
+
+
 @dataclass
 class TestStruct2(s._CustomStruct):
     enabled: bool
@@ -31,16 +37,41 @@ class TestStruct2(s._CustomStruct):
 
 
 @dataclass
-class TestStruct(s._CustomStruct):
+class TestStruct3(s._CustomStruct):
+    point: TestStruct
+    scale: float
+
+
+@dataclass
+class VecPoint(s._CustomStruct):
     x: float
     y: float
-    label: str
+
+
+class TestUnion(s._CustomUnion):
+    pass
+
+
+@dataclass
+class TestUnionOff(TestUnion):
+    pass
+
+
+@dataclass
+class TestUnionLevel(TestUnion):
+    value_0: int
+
+
+@dataclass
+class TestUnionPoint(TestUnion):
+    value_0: TestStruct
+    value_1: bool
 
 
 class NestedValueStates(ISubStates):
     def __init__(self, parent: str):
-        self.secondary_choice: s.Value[TestEnum2] = s.Value[TestEnum2](8, TestEnum2.Y)
-        self.selected_enum: s.Value[TestEnum | None] = s.Value[TestEnum | None](9, None)
+        self.secondary_choice: s.Value[TestEnum2] = s.Value[TestEnum2](10, TestEnum2.Y)
+        self.selected_enum: s.Value[TestEnum | None] = s.Value[TestEnum | None](11, None)
 
 
 class ValueStates(ISubStates):
@@ -49,10 +80,12 @@ class ValueStates(ISubStates):
         self.count: s.Value[int] = s.Value[int](1, 0)
         self.ratio: s.Value[float] = s.Value[float](2, 0)
         self.queued_progress: s.Value[float] = s.Value[float](3, 0, True)
-        self.title: s.Value[str] = s.Value[str](4, "")
+        self.title: s.Value[str] = s.Value[str](4, "").with_id(1000)
         self.optional_value: s.Value[int | None] = s.Value[int | None](5, None)
         self.fixed_numbers: s.Value[list[int]] = s.Value[list[int]](6, [0, 0, 0])
         self.test_enum: s.Value[TestEnum] = s.Value[TestEnum](7, TestEnum.A)
+        self.test_union: s.Value[TestUnion] = s.Value[TestUnion](8, TestUnionOff())
+        self.flags: s.Bitset[TestEnum] = s.Bitset[TestEnum](9, 0)
         self.nested: NestedValueStates = NestedValueStates(parent + ".nested")
 
 
@@ -72,8 +105,8 @@ class NestedStaticStates(ISubStates):
 class StaticStates(ISubStates):
     def __init__(self, parent: str):
         self.status_text: s.Static[str] = s.Static[str](4, "")
-        self.summary: s.Static[TestStruct2] = s.Static[TestStruct2](11, TestStruct2(False, 0, ""))
-        self.pair: s.Static[list[float]] = s.Static[list[float]](12, [0, 0])
+        self.summary: s.Static[TestStruct2] = s.Static[TestStruct2](13, TestStruct2(False, 0, ""))
+        self.pair: s.Static[list[float]] = s.Static[list[float]](14, [0, 0])
         self.nested: NestedStaticStates = NestedStaticStates(parent + ".nested")
 
 
@@ -85,8 +118,9 @@ class ValueTakeStates(ISubStates):
 
 class CustomValueStates(ISubStates):
     def __init__(self, parent: str):
-        self.point: s.Value[TestStruct] = s.Value[TestStruct](13, TestStruct(0, 0, ""))
-        self.optional_struct: s.Value[TestStruct2 | None] = s.Value[TestStruct2 | None](14, None)
+        self.point: s.Value[TestStruct] = s.Value[TestStruct](15, TestStruct(0, 0, ""))
+        self.optional_struct: s.Value[TestStruct2 | None] = s.Value[TestStruct2 | None](16, None)
+        self.nested_point: s.Value[TestStruct3] = s.Value[TestStruct3](17, TestStruct3(TestStruct(0, 0, ""), 0))
 
 
 class ValueVecActionStates(ISubStates):
@@ -99,6 +133,7 @@ class ValueVecActionStates(ISubStates):
 class ValueVecStates(ISubStates):
     def __init__(self, parent: str):
         self.items: s.Vec[int] = s.Vec[int](1)
+        self.points: s.Vec[VecPoint] = s.Vec[VecPoint](18)
         self.actions: ValueVecActionStates = ValueVecActionStates(parent + ".actions")
 
 
@@ -111,7 +146,7 @@ class ValueMapActionStates(ISubStates):
 
 class ValueMapStates(ISubStates):
     def __init__(self, parent: str):
-        self.items: s.Map[int, int] = s.Map[int, int](15, 16)
+        self.items: s.Map[int, int] = s.Map[int, int](19, 20)
         self.actions: ValueMapActionStates = ValueMapActionStates(parent + ".actions")
 
 
@@ -174,6 +209,8 @@ class State(StatesBase):
             s.opt(s.i32),
             s.li(s.u16, 3),
             s.enu(TestEnum),
+            s.uni(TestUnion, [("Off", TestUnionOff, []), ("Level", TestUnionLevel, [s.u32]), ("Point", TestUnionPoint, [s.cl([s.f32, s.f32, s.st], TestStruct), s.bo])]),
+            s.u64,
             s.enu(TestEnum2),
             s.opt(s.enu(TestEnum)),
             s.emp,
@@ -181,6 +218,8 @@ class State(StatesBase):
             s.li(s.f32, 2),
             s.cl([s.f32, s.f32, s.st], TestStruct),
             s.opt(s.cl([s.bo, s.u16, s.st], TestStruct2)),
+            s.cl([s.cl([s.f32, s.f32, s.st], TestStruct), s.f32], TestStruct3),
+            s.cl([s.f32, s.f32], VecPoint),
             s.u16,
             s.u32,
         ]
@@ -205,7 +244,7 @@ class State(StatesBase):
 class StatesServer(StateServerBase):
     """The main class for the StateServer for UI."""
 
-    VERSION_HASH: int = 5850504962483486421
+    VERSION_HASH: int = 18445711673778110824
     states: State
 
     def __init__(
@@ -216,6 +255,7 @@ class StatesServer(StateServerBase):
         ip_addr: tuple[int, int, int, int] | None = None,
         version: int | None = None,
         token: str | None = None,
+        queue_size: int | None = None,
     ) -> None:
         """Initialize the StateServer.
 
@@ -226,5 +266,6 @@ class StatesServer(StateServerBase):
             ip_addr (tuple[int, int, int, int] | None, optional): IP address to bind to. Defaults to None.
             version (int, optional): The optional version number for client connection.
             token (str, optional): The optional token string for client connection.
+            queue_size (int, optional): The maximal number of pending messages to the client. Defaults to 65536.
         """
-        super().__init__(State, port, signals_workers, error_handler, ip_addr, version, token)
+        super().__init__(State, port, signals_workers, error_handler, ip_addr, version, token, queue_size)
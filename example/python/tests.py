@@ -1,17 +1,30 @@
 # ruff: noqa: D103, E402, PLR0915
+import base64
+import contextlib
+import os
+import shutil
 import socket
+import ssl
+import struct
+import subprocess
+import tempfile
 import sys
 import threading
 import time
+import urllib.request
+import zlib
+from collections.abc import Callable, Iterator
 from pathlib import Path
 
 import numpy as np
+import numpy.typing as npt
 import pytest
 
 THIS_DIR = Path(__file__).resolve().parent
 if str(THIS_DIR) not in sys.path:
     sys.path.insert(0, str(THIS_DIR))
 
+from egui_states import LogLevel, _core
 from states_server import (
     State,
     StatesServer,
@@ -28,6 +41,24 @@ from states_server import (
 from states_server import (
     TestStruct2 as ExampleTestStruct2,
 )
+from states_server import (
+    TestStruct3 as ExampleTestStruct3,
+)
+from states_server import (
+    TestUnion as ExampleTestUnion,
+)
+from states_server import (
+    VecPoint as ExampleVecPoint,
+)
+from states_server import (
+    TestUnionLevel as ExampleTestUnionLevel,
+)
+from states_server import (
+    TestUnionOff as ExampleTestUnionOff,
+)
+from states_server import (
+    TestUnionPoint as ExampleTestUnionPoint,
+)
 
 DEFAULT_VEC = [10, -3, 27]
 DEFAULT_MAP = {1: 100, 2: 200, 5: 500}
@@ -53,6 +84,186 @@ def _wait_event(event: threading.Event, timeout: float = 1.0) -> None:
     assert event.wait(timeout), "timed out waiting for callback"
 
 
+def _open_websocket(
+    port: int, tls: ssl.SSLContext | None = None, path: str = "/", headers: str = ""
+) -> tuple[socket.socket, bytes]:
+    # websocket upgrade, returns the socket and the response head
+    sock = socket.socket(socket.AF_INET, socket.SOCK_STREAM)
+    sock.setsockopt(socket.SOL_SOCKET, socket.SO_RCVBUF, 4096)
+    _wait_until(lambda: sock.connect_ex(("127.0.0.1", port)) == 0)
+    if tls is not None:
+        sock = tls.wrap_socket(sock, server_hostname="localhost")
+    key = base64.b64encode(os.urandom(16)).decode()
+    request = (
+        f"GET {path} HTTP/1.1\r\nHost: 127.0.0.1:{port}\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n"
+        f"Sec-WebSocket-Key: {key}\r\nSec-WebSocket-Version: 13\r\n{headers}\r\n"
+    )
+    sock.sendall(request.encode())
+    response = b""
+    while b"\r\n\r\n" not in response:
+        response += sock.recv(1)
+    return sock, response
+
+
+def _connect_raw_client(
+    port: int,
+    request_types: bool = False,
+    tls: ssl.SSLContext | None = None,
+    compression: bool = False,
+    protocol: int = 8,
+    read_only: bool = False,
+    checksum: bool = False,
+) -> socket.socket:
+    # minimal websocket client which sends the handshake
+    sock, _ = _open_websocket(port, tls)
+
+    # ClientHeader::Handshake(protocol, None, None, request_types) serialized with postcard, with options
+    # ClientHeader::HandshakeOptions(protocol, minor, None, None, request_types, COMPRESSION | READ_ONLY | CHECKSUM)
+    options = int(compression) | int(read_only) << 1 | int(checksum) << 2
+    if options:
+        _send_frame(sock, bytes([5, protocol, 1, 0, 0, int(request_types), options]))
+    else:
+        _send_frame(sock, bytes([4, protocol, 0, 0, int(request_types)]))
+    return sock
+
+
+def _send_frame(sock: socket.socket, payload: bytes) -> None:
+    # client frames are masked, payload is short
+    mask = os.urandom(4)
+    masked = bytes(b ^ mask[i % 4] for i, b in enumerate(payload))
+    sock.sendall(bytes([0x82, 0x80 | len(payload)]) + mask + masked)
+
+
+def _send_ack(sock: socket.socket, value_id: int) -> None:
+    # ClientHeader::Ack(id)
+    payload = bytearray([2])
+    while value_id >= 0x80:
+        payload.append((value_id & 0x7F) | 0x80)
+        value_id >>= 7
+    payload.append(value_id)
+    _send_frame(sock, bytes(payload))
+
+
+def _read_frame(sock: socket.socket) -> bytes:
+    def read_exact(size: int) -> bytes:
+        data = b""
+        while len(data) < size:
+            chunk = sock.recv(size - len(data))
+            assert chunk, "connection closed"
+            data += chunk
+        return data
+
+    size = read_exact(2)[1] & 0x7F
+    if size == 126:
+        size = int.from_bytes(read_exact(2), "big")
+    elif size == 127:
+        size = int.from_bytes(read_exact(8), "big")
+    return read_exact(size)
+
+
+def _drain_initial_sync(sock: socket.socket, timeout: float = 1.0) -> None:
+    # skip initial synchronization, reads until the server is quiet and sets the timeout for the test
+    sock.settimeout(0.2)
+    with contextlib.suppress(TimeoutError):
+        while True:
+            _read_frame(sock)
+    sock.settimeout(timeout)
+
+
+class _PostcardReader:
+    def __init__(self, data: bytes) -> None:
+        self.data = data
+        self.pos = 0
+
+    def take(self, size: int) -> bytes:
+        data = self.data[self.pos : self.pos + size]
+        self.pos += size
+        return data
+
+    def varint(self) -> int:
+        value = shift = 0
+        while True:
+            byte = self.take(1)[0]
+            value |= (byte & 0x7F) << shift
+            shift += 7
+            if byte < 0x80:
+                return value
+
+    def zigzag(self) -> int:
+        value = self.varint()
+        return (value >> 1) ^ -(value & 1)
+
+    def string(self) -> str:
+        return self.take(self.varint()).decode()
+
+    def object_type(self) -> tuple:
+        # ObjectType variants in declaration order
+        names = ["u8", "u16", "u32", "u64", "i8", "i16", "i32", "i64", "f64", "f32", "str", "bool"]
+        variant = self.varint()
+        if variant < len(names):
+            return (names[variant],)
+        if variant == 12:
+            name = self.string()
+            return ("enum", name, [(self.string(), self.zigzag()) for _ in range(self.varint())])
+        if variant == 13:
+            name = self.string()
+            return ("struct", name, [(self.string(), self.object_type()) for _ in range(self.varint())])
+        if variant == 14:
+            return ("tuple", [self.object_type() for _ in range(self.varint())])
+        if variant == 15:
+            return ("list", self.varint(), self.object_type())
+        if variant == 16:
+            return ("vec", self.object_type())
+        if variant == 17:
+            return ("map", self.object_type(), self.object_type())
+        if variant == 18:
+            return ("option", self.object_type())
+        if variant == 20:
+            name = self.string()
+            variants = []
+            for _ in range(self.varint()):
+                variant_name = self.string()
+                variants.append((variant_name, [self.object_type() for _ in range(self.varint())]))
+            return ("union", name, variants)
+        return ("empty",)
+
+    def value(self, object_type: tuple):
+        kind = object_type[0]
+        if kind == "u8":
+            return self.take(1)[0]
+        if kind in ("u16", "u32", "u64"):
+            return self.varint()
+        if kind == "i8":
+            return int.from_bytes(self.take(1), "little", signed=True)
+        if kind in ("i16", "i32", "i64"):
+            return self.zigzag()
+        if kind in ("f32", "f64"):
+            fmt, size = ("<f", 4) if kind == "f32" else ("<d", 8)
+            return struct.unpack(fmt, self.take(size))[0]
+        if kind == "str":
+            return self.string()
+        if kind == "bool":
+            return self.take(1)[0] == 1
+        if kind == "enum":
+            return object_type[2][self.varint()][0]
+        if kind == "struct":
+            return {name: self.value(field_type) for name, field_type in object_type[2]}
+        if kind == "tuple":
+            return tuple(self.value(item_type) for item_type in object_type[1])
+        if kind == "list":
+            return [self.value(object_type[2]) for _ in range(object_type[1])]
+        if kind == "vec":
+            return [self.value(object_type[1]) for _ in range(self.varint())]
+        if kind == "map":
+            return {self.value(object_type[1]): self.value(object_type[2]) for _ in range(self.varint())}
+        if kind == "option":
+            return self.value(object_type[1]) if self.take(1)[0] == 1 else None
+        if kind == "union":
+            variant_name, fields = object_type[2][self.varint()]
+            return (variant_name, *(self.value(field_type) for field_type in fields))
+        return None
+
+
 def _wire_collection_actions(states) -> None:
     def reset_vec() -> None:
         states.value_vec.items.set(list(DEFAULT_VEC), update=True)
@@ -101,6 +312,38 @@ def server_bundle():
             server.stop()
 
 
+class _RawServer:
+    # not started server with its port, tracks the raw clients connected by the test
+    def __init__(self, **options) -> None:
+        self.port = _free_port()
+        self.server = StatesServer(port=self.port, **options)
+        self.sockets: list[socket.socket] = []
+
+    def connect(self, **options) -> socket.socket:
+        sock = _connect_raw_client(self.port, **options)
+        self.sockets.append(sock)
+        return sock
+
+
+@pytest.fixture
+def raw_server():
+    # creates the servers for the tests with raw clients, the clients are closed and the servers stopped after the test
+    servers: list[_RawServer] = []
+
+    def create(**options) -> _RawServer:
+        servers.append(_RawServer(**options))
+        return servers[-1]
+
+    try:
+        yield create
+    finally:
+        for raw in servers:
+            for sock in raw.sockets:
+                sock.close()
+            if raw.server.is_running():
+                raw.server.stop()
+
+
 def test_server_lifecycle_and_value_roundtrips(server_bundle: tuple[StatesServer, State, list[Exception]]) -> None:
     server, states, _errors = server_bundle
 
@@ -138,6 +381,279 @@ def test_server_lifecycle_and_value_roundtrips(server_bundle: tuple[StatesServer
     assert states.custom_values.optional_struct.get() == optional_struct
 
 
+def test_server_context_manager_stops_on_exit() -> None:
+    with StatesServer(port=_free_port()) as server:
+        server.start()
+        assert server.is_running()
+    assert not server.is_running()
+
+    with pytest.raises(RuntimeError, match="inside the block"), StatesServer(port=_free_port()) as server:
+        server.start()
+        raise RuntimeError("inside the block")
+    assert not server.is_running()
+
+    core = _core.StateServerCore(_free_port())
+    core.finalize()
+    with core as entered:
+        assert entered is core
+        core.start()
+        assert core.is_running()
+    assert not core.is_running()
+    # exiting an already stopped server does nothing
+    assert core.__exit__(None, None, None) is False
+
+
+def test_union_value_roundtrips_each_variant(server_bundle: tuple[StatesServer, State, list[Exception]]) -> None:
+    _server, states, _errors = server_bundle
+
+    assert states.values.test_union.get() == ExampleTestUnionOff()
+
+    variants = [
+        ExampleTestUnionLevel(7),
+        ExampleTestUnionPoint(ExampleTestStruct(1.5, -2.0, "union"), True),
+        ExampleTestUnionOff(),
+    ]
+    for variant in variants:
+        states.values.test_union.set(variant)
+        value = states.values.test_union.get()
+        assert isinstance(value, ExampleTestUnion)
+        assert type(value) is type(variant)
+        assert value == variant
+
+    with pytest.raises(ValueError, match="not a variant"):
+        states.values.test_union.set(ExampleTestStruct(0.0, 0.0, "not a variant"))
+
+
+def test_enum_roundtrips_through_variant_name(server_bundle: tuple[StatesServer, State, list[Exception]]) -> None:
+    _server, states, _errors = server_bundle
+
+    assert str(ExampleTestEnum.B) == "B"
+    assert ExampleTestEnum.from_str(str(ExampleTestEnum.C)) is ExampleTestEnum.C
+
+    states.values.test_enum.set(ExampleTestEnum.from_str("B"))
+    assert states.values.test_enum.get() == ExampleTestEnum.B
+
+    with pytest.raises(ValueError, match="valid variants: A, B, C"):
+        ExampleTestEnum.from_str("D")
+
+
+def test_optional_struct_value_roundtrips_none(server_bundle: tuple[StatesServer, State, list[Exception]]) -> None:
+    _server, states, _errors = server_bundle
+
+    states.custom_values.optional_struct.set(None)
+    assert states.custom_values.optional_struct.get() is None
+
+    value = ExampleTestStruct2(False, 3, "back")
+    states.custom_values.optional_struct.set(value)
+    assert states.custom_values.optional_struct.get() == value
+
+    with pytest.raises(TypeError):
+        states.custom_values.optional_struct.set(ExampleTestStruct(0.0, 0.0, "other struct"))
+
+
+def test_set_values_sets_all_or_nothing(server_bundle: tuple[StatesServer, State, list[Exception]]) -> None:
+    server, states, _errors = server_bundle
+
+    count = server.set_values({states.values.count: 5, states.values.title: "many", states.values.ratio: 0.25})
+    assert count == 3
+    assert states.values.count.get() == 5
+    assert states.values.title.get() == "many"
+    assert states.values.ratio.get() == 0.25
+
+    # the wrong value is reported with its id and nothing is set
+    with pytest.raises(TypeError, match=f"ID {states.values.title._value_id}"):
+        server.set_values({states.values.count: 6, states.values.title: 7})
+    assert states.values.count.get() == 5
+
+    with pytest.raises(ValueError, match="ID 123456789"):
+        server._server.value_set_many({123456789: 1}, False, False)
+
+
+def test_value_set_field_by_path(server_bundle: tuple[StatesServer, State, list[Exception]]) -> None:
+    _server, states, _errors = server_bundle
+
+    states.custom_values.point.set(ExampleTestStruct(1.0, 2.0, "point"))
+    states.custom_values.point.set_field("label", "renamed")
+    states.custom_values.point.set_field("x", 3.5)
+    assert states.custom_values.point.get() == ExampleTestStruct(3.5, 2.0, "renamed")
+
+    nested = ExampleTestStruct3(ExampleTestStruct(1.0, 2.0, "inner"), 0.5)
+    states.custom_values.nested_point.set(nested)
+    states.custom_values.nested_point.set_field("point.label", "a much longer inner label")
+    states.custom_values.nested_point.set_field("point.y", -7.0)
+    states.custom_values.nested_point.set_field("scale", 2.0)
+    assert states.custom_values.nested_point.get() == ExampleTestStruct3(
+        ExampleTestStruct(1.0, -7.0, "a much longer inner label"), 2.0
+    )
+
+    states.custom_values.nested_point.set_field("point", ExampleTestStruct(4.0, 5.0, "whole"))
+    assert states.custom_values.nested_point.get() == ExampleTestStruct3(ExampleTestStruct(4.0, 5.0, "whole"), 2.0)
+
+    with pytest.raises(ValueError):
+        states.custom_values.nested_point.set_field("point.missing", 1.0)
+    with pytest.raises(ValueError):
+        states.custom_values.nested_point.set_field("scale.value", 1.0)
+    with pytest.raises(ValueError):
+        states.values.count.set_field("value", 1)
+
+
+def test_value_set_field_keeps_concurrent_fields(server_bundle: tuple[StatesServer, State, list[Exception]]) -> None:
+    _server, states, _errors = server_bundle
+
+    states.custom_values.point.set(ExampleTestStruct(0.0, 0.0, "start"))
+    count = 500
+
+    def set_x() -> None:
+        for i in range(count):
+            states.custom_values.point.set_field("x", float(i))
+
+    def set_label() -> None:
+        for i in range(count):
+            states.custom_values.point.set_field("label", f"label {i}")
+
+    threads = [threading.Thread(target=set_x), threading.Thread(target=set_label)]
+    for thread in threads:
+        thread.start()
+    for thread in threads:
+        thread.join()
+
+    # every field is patched in the current value, the other field is never reverted
+    assert states.custom_values.point.get() == ExampleTestStruct(count - 1.0, 0.0, f"label {count - 1}")
+
+
+def test_value_increment_is_atomic(server_bundle: tuple[StatesServer, State, list[Exception]]) -> None:
+    _server, states, _errors = server_bundle
+
+    states.values.count.set(0)
+    threads_count = 8
+    increments = 250
+
+    def worker() -> None:
+        for _ in range(increments):
+            states.values.count.increment(1)
+
+    threads = [threading.Thread(target=worker) for _ in range(threads_count)]
+    for thread in threads:
+        thread.start()
+    for thread in threads:
+        thread.join()
+
+    assert states.values.count.get() == threads_count * increments
+    assert states.values.count.increment(-5) == threads_count * increments - 5
+
+    with pytest.raises(ValueError):
+        states.values.title.increment(1)
+
+    # the arithmetic keeps the type of the value
+    states.values.ratio.set(0.5)
+    assert states.values.ratio.increment(1) == 1.5
+    assert states.values.ratio.increment(-0.25) == 1.25
+    with pytest.raises(ValueError):
+        states.values.count.increment(0.5)
+    states.values.count.set(2**31 - 1)
+    with pytest.raises(ValueError):
+        states.values.count.increment(1)
+    assert states.values.count.get() == 2**31 - 1
+
+
+def test_value_compare_set_keeps_concurrent_updates(
+    server_bundle: tuple[StatesServer, State, list[Exception]],
+) -> None:
+    _server, states, _errors = server_bundle
+
+    states.values.count.set(0)
+    threads_count = 8
+    increments = 100
+
+    def worker() -> None:
+        done = 0
+        while done < increments:
+            current = states.values.count.get()
+            if states.values.count.compare_set(current, current + 1):
+                done += 1
+
+    threads = [threading.Thread(target=worker) for _ in range(threads_count)]
+    for thread in threads:
+        thread.start()
+    for thread in threads:
+        thread.join()
+
+    assert states.values.count.get() == threads_count * increments
+    assert not states.values.title.compare_set("other", "new")
+    assert states.values.title.get() != "new"
+
+
+def test_bitset_flags_set_clear_toggle(server_bundle: tuple[StatesServer, State, list[Exception]]) -> None:
+    _server, states, _errors = server_bundle
+    flags = states.values.flags
+
+    assert flags.get() == 0
+    assert flags.set_flag(ExampleTestEnum.A) == 0b001
+    assert flags.set_flag(ExampleTestEnum.C) == 0b101
+    assert flags.is_set(ExampleTestEnum.A)
+    assert not flags.is_set(ExampleTestEnum.B)
+
+    assert flags.clear_flag(ExampleTestEnum.A) == 0b100
+    assert flags.set_flag(ExampleTestEnum.C, False) == 0
+    assert flags.toggle_flag(ExampleTestEnum.B) == 0b010
+    assert flags.toggle_flag(ExampleTestEnum.B) == 0
+
+    # the whole word roundtrips, including the highest bit
+    word = (1 << 63) | 0b110
+    flags.set(word)
+    assert flags.get() == word
+    assert flags.is_set(ExampleTestEnum.B)
+    assert flags.toggle_flag(ExampleTestEnum.A) == word | 0b001
+
+
+def test_snapshot_dict_follows_state_tree(server_bundle: tuple[StatesServer, State, list[Exception]]) -> None:
+    server, states, _errors = server_bundle
+
+    states.values.count.set(42)
+    states.values.title.set("snapshot")
+    states.statics.nested.label.set("nested static")
+    states.value_vec.items.set([1, 2, 3])
+    states.value_map.items.set({7: 70})
+
+    snapshot = server.snapshot()
+    assert "root" not in snapshot
+    assert snapshot["values"]["count"] == 42
+    assert snapshot["values"]["title"] == "snapshot"
+    assert snapshot["statics"]["nested"]["label"] == "nested static"
+    assert snapshot["value_vec"]["items"] == [1, 2, 3]
+    assert snapshot["value_map"]["items"] == {7: 70}
+    assert snapshot["custom_values"]["point"] == states.custom_values.point.get()
+
+    # signals, images and data have no snapshot value
+    assert "actions" not in snapshot["value_vec"]
+    assert "image" not in snapshot
+
+
+def test_explicit_value_id_is_honored(server_bundle: tuple[StatesServer, State, list[Exception]]) -> None:
+    _server, states, _errors = server_bundle
+
+    # title has #[id(1000)] in the UI state, the generated server uses the same id
+    assert states.values.title._value_id == 1000
+    assert states.values.count._value_id != 1000
+
+
+def test_explicit_value_id_survives_rename() -> None:
+    ids = []
+    hashed_ids = []
+    for name in ("root.values.title", "root.values.renamed_title"):
+        server = _core.StateServerCore(_free_port(), None, None, None, None)
+        ids.append(server.add_value(name, _core.st, "", False, 1000))
+        hashed_ids.append(server.add_value(name + "_hashed", _core.st, "", False))
+
+        with pytest.raises(ValueError, match="already used"):
+            server.add_signal("root.values.other", _core.st, False, 1000)
+        with pytest.raises(ValueError, match="reserved"):
+            server.add_value("root.values.reserved", _core.st, "", False, 3)
+
+    assert ids == [1000, 1000]
+    assert hashed_ids[0] != hashed_ids[1]
+
+
 def test_static_value_roundtrips(server_bundle: tuple[StatesServer, State, list[Exception]]) -> None:
     _server, states, _errors = server_bundle
 
@@ -155,6 +671,62 @@ def test_static_value_roundtrips(server_bundle: tuple[StatesServer, State, list[
     assert states.statics.nested.enum_hint.get() == ExampleTestEnum.B
 
 
+def test_static_modify_changes_value_in_place(server_bundle: tuple[StatesServer, State, list[Exception]]) -> None:
+    _server, states, _errors = server_bundle
+
+    states.statics.summary.set(ExampleTestStruct2(True, 9, "summary"))
+
+    def raise_level(summary: ExampleTestStruct2) -> None:
+        summary.level += 1
+
+    assert states.statics.summary.modify(raise_level) == ExampleTestStruct2(True, 10, "summary")
+    assert states.statics.summary.get() == ExampleTestStruct2(True, 10, "summary")
+    assert states.statics.status_text.modify(lambda text: text + "!") == states.statics.status_text.get()
+
+    def fail(_summary: ExampleTestStruct2) -> None:
+        raise KeyError("failed")
+
+    # failed modification keeps the value
+    with pytest.raises(KeyError):
+        states.statics.summary.modify(fail)
+    assert states.statics.summary.get() == ExampleTestStruct2(True, 10, "summary")
+
+    # the callback runs without the lock, a change of the value runs it again with the new value
+    calls: list[int] = []
+
+    def change_first(summary: ExampleTestStruct2) -> None:
+        calls.append(summary.level)
+        if len(calls) == 1:
+            states.statics.summary.set(ExampleTestStruct2(True, 20, "summary"))
+        summary.level += 1
+
+    assert states.statics.summary.modify(change_first) == ExampleTestStruct2(True, 21, "summary")
+    assert calls == [10, 20]
+
+    # set from another thread while the callback runs
+    started = threading.Event()
+    changed = threading.Event()
+    calls.clear()
+
+    def wait_for_set(summary: ExampleTestStruct2) -> None:
+        calls.append(summary.level)
+        if len(calls) == 1:
+            started.set()
+            _wait_event(changed)
+        summary.level += 1
+
+    def set_concurrently() -> None:
+        _wait_event(started)
+        states.statics.summary.set(ExampleTestStruct2(True, 30, "summary"))
+        changed.set()
+
+    thread = threading.Thread(target=set_concurrently)
+    thread.start()
+    assert states.statics.summary.modify(wait_for_set) == ExampleTestStruct2(True, 31, "summary")
+    thread.join()
+    assert calls == [21, 30]
+
+
 def test_image_value_roundtrip(server_bundle: tuple[StatesServer, State, list[Exception]]) -> None:
     _server, states, _errors = server_bundle
 
@@ -180,6 +752,300 @@ def test_image_value_roundtrip(server_bundle: tuple[StatesServer, State, list[Ex
     assert image_result[5, 5, 0] == 10
     assert states.image.image.shape() == (8, 8)
 
+    # rectangle reaching outside of the image is rejected and the image is kept
+    with pytest.raises(ValueError, match="exceeds the image size"):
+        states.image.image.update(patch, origin=(7, 6))
+    np.testing.assert_array_equal(states.image.image.get(), image_result)
+
+
+def test_image_get_array_is_shaped_read_only_buffer(
+    server_bundle: tuple[StatesServer, State, list[Exception]],
+) -> None:
+    _server, states, _errors = server_bundle
+
+    image = np.arange(3 * 5 * 4, dtype=np.uint8).reshape((3, 5, 4))
+    states.image.image.set(image)
+
+    buffer = states.image.image._server.image_get_array(states.image.image._value_id)
+    assert buffer.shape == (3, 5, 4)
+    view = memoryview(buffer)
+    assert view.shape == (3, 5, 4)
+    assert view.format == "B"
+    assert view.readonly
+
+    array = states.image.image.get_array()
+    assert array.dtype == np.uint8
+    np.testing.assert_array_equal(array, image)
+    assert not array.flags.writeable
+    # numpy uses the buffer of the object, nothing is copied
+    assert np.shares_memory(array, np.asarray(array.base))
+
+
+def test_image_tone_maps_uint16_and_float32(server_bundle: tuple[StatesServer, State, list[Exception]]) -> None:
+    _server, states, _errors = server_bundle
+    image = states.image.image
+
+    frame = np.array([[0, 1000], [2000, 4000]], dtype=np.uint16)
+    image.set(frame, window=(0, 4000))
+    np.testing.assert_array_equal(image.get()[..., 0], np.array([[0, 63], [127, 255]], dtype=np.uint8))
+    image.set(frame)
+    np.testing.assert_array_equal(image.get()[..., 0], np.array([[0, 63], [127, 255]], dtype=np.uint8))
+
+    # values outside the window are clipped, alpha is mapped from 0.0-1.0
+    frame = np.array([[[-1.0, 1.0], [0.0, 0.5]], [[1.0, 0.0], [3.0, 1.0]]], dtype=np.float32)
+    image.set(frame, window=(-1.0, 1.0))
+    np.testing.assert_array_equal(image.get()[..., 0], np.array([[0, 127], [255, 255]], dtype=np.uint8))
+    np.testing.assert_array_equal(image.get()[..., 3], np.array([[255, 127], [0, 255]], dtype=np.uint8))
+
+    with pytest.raises(ValueError):
+        image.set(np.zeros((2, 2), dtype=np.uint8), window=(0, 1))
+    with pytest.raises(ValueError):
+        image.set(frame, window=(1.0, 0.0))
+
+
+def test_image_partial_updates_keep_the_window(server_bundle: tuple[StatesServer, State, list[Exception]]) -> None:
+    _server, states, _errors = server_bundle
+    image = states.image.image
+
+    image.set(np.zeros((4, 2), dtype=np.uint8))
+    # no window of the image yet, the part would be mapped by its own range
+    with pytest.raises(ValueError):
+        image.update(np.full((1, 2), 100, dtype=np.uint16), (0, 0))
+
+    image.set(np.array([[0, 4000], [0, 0], [0, 0], [0, 0]], dtype=np.uint16))
+    image.update(np.full((1, 2), 2000, dtype=np.uint16), (1, 0))
+    np.testing.assert_array_equal(image.get()[1, :, 0], np.array([127, 127], dtype=np.uint8))
+    image.scroll_append(np.full((1, 2), 1000, dtype=np.uint16))
+    np.testing.assert_array_equal(image.get()[0, :, 0], np.array([63, 63], dtype=np.uint8))
+
+    # the given window is used also for the next updates
+    image.update(np.full((1, 2), 100, dtype=np.uint16), (3, 0), window=(0, 200))
+    image.update(np.full((1, 2), 50, dtype=np.uint16), (2, 0))
+    np.testing.assert_array_equal(image.get()[2:, :, 0], np.array([[63, 63], [127, 127]], dtype=np.uint8))
+
+
+def test_batch_defers_update_to_the_end(raw_server: Callable[..., _RawServer]) -> None:
+    raw = raw_server()
+    server = raw.server
+    server.start()
+    client = raw.connect()
+    _wait_until(server.is_connected)
+    _drain_initial_sync(client)
+
+    values = server.states.values
+    with server.batch():
+        values.count.set(1, update=True)
+        with server.batch():
+            values.bool_value.set(True, update=True)
+        values.title.set("batch", update=True)
+
+    # ServerHeader::Value(id, type_id, update, size) messages without update, then ServerHeader::Update
+    updates = []
+    while True:
+        reader = _PostcardReader(_read_frame(client))
+        header = None
+        while reader.pos < len(reader.data):
+            header = reader.varint()
+            if header != 0:
+                break
+            value_id = reader.varint()
+            reader.varint()
+            updates.append((value_id, reader.take(1)))
+            reader.take(reader.varint())
+        if header == 10:
+            break
+
+    ids = [values.count._value_id, values.bool_value._value_id, values.title._value_id]
+    assert updates == [(value_id, b"\x00") for value_id in ids]
+
+
+def test_image_set_scaled_limits_transmitted_size(raw_server: Callable[..., _RawServer]) -> None:
+    raw = raw_server()
+    server = raw.server
+    server.start()
+    client = raw.connect()
+    _wait_until(server.is_connected)
+    _drain_initial_sync(client)
+
+    image = np.zeros((200, 300, 4), dtype=np.uint8)
+    image[..., 0] = 40
+    image[..., 3] = 255
+    server.states.image.image.set_scaled(image, max_dim=64)
+
+    # full resolution image is kept on the server
+    assert server.states.image.image.shape() == (200, 300)
+    assert server.states.image.image.get()[199, 299, 0] == 40
+
+    # ServerHeader::Image(id, ImageHeader::Set(ImageSetHeader::All([x, y], update), type), size)
+    reader = _PostcardReader(_read_frame(client))
+    assert reader.varint() == 3
+    assert reader.varint() == server.states.image.image._value_id
+    assert reader.varint() == 0
+    assert reader.varint() == 0
+    width, height = reader.varint(), reader.varint()
+    assert max(width, height) <= 64
+    assert (width, height) == (64, 42)
+    assert width / height == pytest.approx(300 / 200, rel=0.05)
+
+    with pytest.raises(ValueError):
+        server.states.image.image.update(np.zeros((2, 2, 4), dtype=np.uint8), origin=(0, 0))
+
+
+@pytest.mark.skipif(not _core.IMAGE_COMPRESSION, reason="built without image-compression feature")
+def test_image_compression_sends_encoded_image(raw_server: Callable[..., _RawServer]) -> None:
+    raw = raw_server()
+    server = raw.server
+    server.start()
+    client = raw.connect()
+    _wait_until(server.is_connected)
+    _drain_initial_sync(client)
+
+    image = server.states.image.image
+    image.set_compression("png")
+    frame = np.zeros((40, 60, 3), dtype=np.uint8)
+    frame[..., 1] = 200
+    image.set(frame)
+
+    # ServerHeader::Image(id, ImageHeader::Encoded([x, y], encoding, update), size)
+    reader = _PostcardReader(_read_frame(client))
+    assert reader.varint() == 3
+    assert reader.varint() == image._value_id
+    assert reader.varint() == 4
+    assert (reader.varint(), reader.varint()) == (60, 40)
+    assert reader.varint() == 0
+    assert reader.take(1) == b"\x00"
+    size = reader.varint()
+    assert size < frame.size
+    assert reader.take(size).startswith(b"\x89PNG")
+
+    # the server keeps the raw image
+    assert image.get()[39, 59, 1] == 200
+
+    with pytest.raises(ValueError):
+        image.set_compression("jpeg", quality=0)
+    with pytest.raises(ValueError):
+        image.set_compression("webp")
+
+
+def test_image_scroll_append_sends_only_new_rows(raw_server: Callable[..., _RawServer]) -> None:
+    raw = raw_server()
+    server = raw.server
+    server.start()
+    client = raw.connect()
+    _wait_until(server.is_connected)
+    _drain_initial_sync(client)
+
+    image_value = server.states.image.image
+    image = np.zeros((6, 4, 4), dtype=np.uint8)
+    image[..., 0] = np.arange(6, dtype=np.uint8)[:, None]
+    image[..., 3] = 255
+    image_value.set(image)
+
+    # ServerHeader::Image(id, ImageHeader::Set(ImageSetHeader::All([x, y], update), type), size)
+    reader = _PostcardReader(_read_frame(client))
+    assert [reader.varint(), reader.varint(), reader.varint(), reader.varint()] == [3, image_value._value_id, 0, 0]
+    assert (reader.varint(), reader.varint()) == (4, 6)
+    reader.take(1)
+    reader.varint()
+    client_image = np.frombuffer(reader.take(reader.varint()), dtype=np.uint8).reshape((6, 4, 4))
+    _send_ack(client, image_value._value_id)
+
+    for i in range(3):
+        rows = np.full((i + 1, 4, 4), 100 + i, dtype=np.uint8)
+        image_value.scroll_append(rows)
+
+        # ServerHeader::Image(id, ImageHeader::Scroll(rows, type, update), size)
+        reader = _PostcardReader(_read_frame(client))
+        assert [reader.varint(), reader.varint(), reader.varint()] == [3, image_value._value_id, 2]
+        assert reader.varint() == i + 1
+        reader.varint()
+        reader.take(1)
+        payload = reader.take(reader.varint())
+        assert len(payload) == rows.nbytes
+
+        new_rows = np.frombuffer(payload, dtype=np.uint8).reshape(rows.shape)
+        client_image = np.concatenate([new_rows, client_image[: -(i + 1)]])
+        _send_ack(client, image_value._value_id)
+
+    np.testing.assert_array_equal(client_image, image_value.get())
+    assert image_value.get()[0, 0, 0] == 102
+    assert image_value.get()[5, 0, 0] == 100
+
+    with pytest.raises(ValueError):
+        image_value.scroll_append(np.zeros((1, 3, 4), dtype=np.uint8))
+
+
+def test_image_set_supersedes_pending_parts(raw_server: Callable[..., _RawServer]) -> None:
+    raw = raw_server()
+    server = raw.server
+    server.start()
+    client = raw.connect()
+    _wait_until(server.is_connected)
+    _drain_initial_sync(client, 5.0)
+
+    def read_part() -> tuple[int, int]:
+        # ServerHeader::Image(id, ImageHeader::Set(set_header, type), size), returns set variant and first byte
+        reader = _PostcardReader(_read_frame(client))
+        assert [reader.varint(), reader.varint(), reader.varint()] == [3, image_value._value_id, 0]
+        variant = reader.varint()
+        if variant == 1:  # Start([x, y], pixels)
+            reader.varint(), reader.varint(), reader.varint()
+        else:  # Batch(pixels) or End(pixels, update)
+            reader.varint()
+            if variant == 3:
+                reader.take(1)
+        reader.varint()
+        reader.varint()
+        return variant, reader.take(1)[0]
+
+    # image larger than a single message is sent in parts
+    image_value = server.states.image.image
+    stale = np.full((2000, 1500, 4), 1, dtype=np.uint8)
+    image_value.set(stale)
+    assert read_part() == (1, 1)
+
+    # new image replaces the not yet sent parts of the stale one
+    fresh = np.full((2000, 1500, 4), 2, dtype=np.uint8)
+    image_value.set(fresh)
+    _send_ack(client, image_value._value_id)
+    assert read_part() == (1, 2)
+    _send_ack(client, image_value._value_id)
+    assert read_part() == (3, 2)
+    _send_ack(client, image_value._value_id)
+
+    client.settimeout(0.3)
+    with pytest.raises(TimeoutError):
+        _read_frame(client)
+    np.testing.assert_array_equal(image_value.get(), fresh)
+
+
+def test_image_premultiply_alpha_option_is_sent_to_client() -> None:
+    port = _free_port()
+    server = _core.StateServerCore(port, None, None, None, None)
+    image_id = server.add_image("root.image", premultiply_alpha=True)
+    plain_id = server.add_image("root.plain")
+    server.finalize()
+    server.start()
+    client = None
+    try:
+        client = _connect_raw_client(port)
+        _wait_until(server.is_connected)
+
+        # ServerHeader::Image(id, ImageHeader::Premultiply(true), 0) is sent only for premultiplied images
+        reader = _PostcardReader(_read_frame(client))
+        assert [reader.varint(), reader.varint(), reader.varint()] == [3, image_id, 3]
+        assert reader.take(1) == b"\x01"
+        assert reader.varint() == 0
+        # followed only by ServerHeader::Update after synchronization
+        assert reader.varint() == 10
+        assert reader.pos + 4 == len(reader.data)
+        assert image_id != plain_id
+    finally:
+        if client is not None:
+            client.close()
+        if server.is_running():
+            server.stop()
+
 
 def test_data_array_methods(server_bundle: tuple[StatesServer, State, list[Exception]]) -> None:
     _server, states, _errors = server_bundle
@@ -209,6 +1075,97 @@ def test_data_array_methods(server_bundle: tuple[StatesServer, State, list[Excep
     )
 
 
+def test_data_streams_chunks_into_capped_buffer(server_bundle: tuple[StatesServer, State, list[Exception]]) -> None:
+    _server, states, _errors = server_bundle
+    samples = states.data.samples
+    total = 1_000_000
+    chunk = 10_000
+    max_len = 50_000
+
+    def points() -> Iterator[npt.NDArray[np.float32]]:
+        for start in range(0, total, chunk):
+            yield np.arange(start, start + chunk, dtype=np.float32)
+
+    samples.set_max_len(max_len)
+    lengths = [samples.add(points_chunk) for points_chunk in points()]
+
+    assert max(lengths) == max_len
+    assert lengths[: max_len // chunk] == list(range(chunk, max_len + chunk, chunk))
+    np.testing.assert_array_equal(samples.get(), np.arange(total - max_len, total, dtype=np.float32))
+
+    # a chunk larger than the buffer keeps only its newest points
+    assert samples.add(np.arange(2 * max_len, dtype=np.float32)) == max_len
+    np.testing.assert_array_equal(samples.get(), np.arange(max_len, 2 * max_len, dtype=np.float32))
+
+    samples.set_max_len(10)
+    np.testing.assert_array_equal(samples.get(), np.arange(2 * max_len - 10, 2 * max_len, dtype=np.float32))
+    samples.set_max_len(None)
+    assert samples.add(np.zeros(5, dtype=np.float32)) == 15
+
+
+def test_data_splice_replaces_interior_segment(server_bundle: tuple[StatesServer, State, list[Exception]]) -> None:
+    _server, states, _errors = server_bundle
+    samples = states.data.samples
+    samples.set_max_len(None)
+    samples.set(np.arange(10, dtype=np.float32))
+
+    # segment of the same length
+    samples.splice(np.array([40, 50, 60], dtype=np.float32), 4, 3)
+    np.testing.assert_array_equal(samples.get(), np.array([0, 1, 2, 3, 40, 50, 60, 7, 8, 9], dtype=np.float32))
+
+    # shorter and longer segments change the length
+    samples.splice(np.array([45], dtype=np.float32), 4, 3)
+    np.testing.assert_array_equal(samples.get(), np.array([0, 1, 2, 3, 45, 7, 8, 9], dtype=np.float32))
+    samples.splice(np.array([10, 20, 30, 40], dtype=np.float32), 1, 2)
+    np.testing.assert_array_equal(samples.get(), np.array([0, 10, 20, 30, 40, 3, 45, 7, 8, 9], dtype=np.float32))
+
+    with pytest.raises(ValueError):
+        samples.splice(np.array([1], dtype=np.float32), 8, 3)
+
+    bytes_zero = states.multi_data.bytes[0]
+    bytes_zero.set(np.array([0, 1, 2, 3, 4], dtype=np.uint8))
+    bytes_zero.splice(np.array([10, 11, 12], dtype=np.uint8), 1, 2)
+    np.testing.assert_array_equal(bytes_zero.get(), np.array([0, 10, 11, 12, 3, 4], dtype=np.uint8))
+    bytes_zero.splice(np.array([], dtype=np.uint8), 1, 3)
+    np.testing.assert_array_equal(bytes_zero.get(), np.array([0, 3, 4], dtype=np.uint8))
+
+
+def test_data_set_decimated_sends_bucket_extremes(raw_server: Callable[..., _RawServer]) -> None:
+    raw = raw_server()
+    server = raw.server
+    server.start()
+    client = raw.connect()
+    _wait_until(server.is_connected)
+    _drain_initial_sync(client)
+
+    samples = server.states.data.samples
+    data = np.zeros(1_000_000, dtype=np.float32)
+    data[500_123] = 1e6
+    data[700_001] = -1e6
+    samples.set(data, max_points=1000)
+
+    # full data is kept on the server, the client gets only the extremes of every bucket
+    np.testing.assert_array_equal(samples.get(), data)
+    frame = _read_frame(client)
+    assert len(frame) < 1000 * 4 + 64
+    assert struct.pack("<f", 1e6) in frame
+    assert struct.pack("<f", -1e6) in frame
+    _send_ack(client, samples._value_id)
+
+    # next changes are decimated too
+    samples.add(np.full(10, 5e5, dtype=np.float32))
+    assert len(samples.get()) == 1_000_010
+    frame = _read_frame(client)
+    assert len(frame) < 1000 * 4 + 64
+    assert struct.pack("<f", 5e5) in frame
+
+    with pytest.raises(ValueError):
+        samples.set(data, max_points=1)
+    # x and y pairs are kept together, an odd count cannot be split to pairs
+    with pytest.raises(ValueError):
+        samples.set(data[:-1], max_points=1000, xy=True)
+
+
 def test_multi_data_methods(server_bundle: tuple[StatesServer, State, list[Exception]]) -> None:
     _server, states, _errors = server_bundle
 
@@ -259,6 +1216,32 @@ def test_multi_data_methods(server_bundle: tuple[StatesServer, State, list[Excep
         states.multi_data.bytes[5].get()
 
 
+def test_multi_data_max_len_keeps_newest_items(server_bundle: tuple[StatesServer, State, list[Exception]]) -> None:
+    _server, states, _errors = server_bundle
+    item = states.multi_data.bytes[3]
+    item.set(np.arange(4, dtype=np.uint8))
+    item.set_max_len(6)
+
+    for start in range(4, 20, 3):
+        item.add(np.arange(start, start + 3, dtype=np.uint8))
+        np.testing.assert_array_equal(item.get(), np.arange(max(start + 3 - 6, 0), start + 3, dtype=np.uint8))
+
+    # a chunk larger than the buffer keeps only its newest items
+    item.add(np.arange(30, 40, dtype=np.uint8))
+    np.testing.assert_array_equal(item.get(), np.arange(34, 40, dtype=np.uint8))
+    item.splice(np.array([1, 2, 3], dtype=np.uint8), 2, 1)
+    np.testing.assert_array_equal(item.get(), np.array([1, 2, 3, 37, 38, 39], dtype=np.uint8))
+
+    item.set_max_len(2)
+    np.testing.assert_array_equal(item.get(), np.array([38, 39], dtype=np.uint8))
+    item.set_max_len(None)
+    item.add(np.array([40], dtype=np.uint8))
+    np.testing.assert_array_equal(item.get(), np.array([38, 39, 40], dtype=np.uint8))
+
+    with pytest.raises(ValueError):
+        item.set_max_len(0)
+
+
 def test_value_take_methods(server_bundle: tuple[StatesServer, State, list[Exception]]) -> None:
     _server, states, _errors = server_bundle
 
@@ -291,6 +1274,188 @@ def test_value_vec_and_value_map_follow_action_signals(
     _wait_until(lambda: states.value_map.items.get() == {2: 200, 5: 500, 6: 600})
 
 
+def test_value_vec_get_range_clamps_to_length(server_bundle: tuple[StatesServer, State, list[Exception]]) -> None:
+    _server, states, _errors = server_bundle
+    items = states.value_vec.items
+    items.set(list(range(10)))
+
+    assert items.get_range(2, 5) == [2, 3, 4]
+    assert items.get_range(7, 100) == [7, 8, 9]
+    assert items.get_range(10, 12) == []
+    assert items.get_range(5, 3) == []
+
+
+def test_value_map_update_item(server_bundle: tuple[StatesServer, State, list[Exception]]) -> None:
+    _server, states, _errors = server_bundle
+    items = states.value_map.items
+    items.set({1: 100, 2: 200})
+
+    assert items.update_item(1, lambda value: value + 1) == 101
+    # missing key is inserted only when the callback returns a value
+    assert items.update_item(3, lambda value: None) is None
+    assert items.update_item(4, lambda value: 400 if value is None else value) == 400
+    assert items.get() == {1: 101, 2: 200, 4: 400}
+
+    def fail(value: int | None) -> int:
+        raise KeyError("failed")
+
+    with pytest.raises(KeyError):
+        items.update_item(2, fail)
+    assert items.get_item(2) == 200
+
+    # the callback can access the dict, a change of the item runs it again with the new value
+    calls: list[int | None] = []
+
+    def change_first(value: int | None) -> int:
+        calls.append(value)
+        if len(calls) == 1:
+            items.set_item(2, items.get_item(2) + 50)
+        return (value or 0) + 1
+
+    assert items.update_item(2, change_first) == 251
+    assert calls == [200, 250]
+
+
+def test_value_map_remove_items_sends_one_message(raw_server: Callable[..., _RawServer]) -> None:
+    raw = raw_server()
+    server = raw.server
+    server.start()
+    client = raw.connect()
+    _wait_until(server.is_connected)
+    items = server.states.value_map.items
+    items.set({1: 100, 2: 200, 3: 300, 4: 400})
+
+    _drain_initial_sync(client)
+
+    assert items.remove_items([1, 3, 7, 3]) == 2
+    assert items.get() == {2: 200, 4: 400}
+
+    # ServerHeader::ValueMap(id, type_id, update, MapHeader::RemoveMany(count), size)
+    reader = _PostcardReader(_read_frame(client))
+    assert reader.varint() == 9
+    assert reader.varint() == items._value_id
+    reader.varint()
+    assert reader.take(1) == b"\x00"
+    assert (reader.varint(), reader.varint()) == (3, 2)
+    size = reader.varint()
+    keys = _PostcardReader(reader.take(size))
+    assert [keys.varint(), keys.varint()] == [1, 3]
+
+    assert items.remove_items([1, 3]) == 0
+
+
+def test_ordered_map_keeps_insertion_order() -> None:
+    server = _core.StateServerCore(_free_port(), None, None, None, None)
+    ordered = server.add_map("root.ordered", _core.st, _core.i32, True)
+    server.finalize()
+
+    server.map_set(ordered, {"c": 1, "a": 2, "b": 3}, False)
+    server.map_set_item(ordered, "d", 4, False)
+    # existing key keeps its position, removing keeps the order of the rest
+    server.map_set_item(ordered, "c", 5, False)
+    server.map_del_item(ordered, "a", False)
+    assert list(server.map_get(ordered).items()) == [("c", 5), ("b", 3), ("d", 4)]
+
+
+POINT_DTYPE = np.dtype([("x", np.float32), ("y", np.float32)])
+
+
+def test_value_vec_packed_array_roundtrip(server_bundle: tuple[StatesServer, State, list[Exception]]) -> None:
+    _server, states, _errors = server_bundle
+    points = states.value_vec.points
+
+    array = np.zeros(3, dtype=POINT_DTYPE)
+    array["x"] = [0.5, 1.5, 2.5]
+    array["y"] = [-1.0, -2.0, -3.0]
+    points.set_array(array)
+    assert points.get() == [
+        ExampleVecPoint(0.5, -1.0),
+        ExampleVecPoint(1.5, -2.0),
+        ExampleVecPoint(2.5, -3.0),
+    ]
+
+    # the per-item path and the packed path share the same storage
+    points.add_item(ExampleVecPoint(4.5, -4.0))
+    np.testing.assert_array_equal(points.get_array(POINT_DTYPE)["x"], [0.5, 1.5, 2.5, 4.5])
+
+    with pytest.raises(ValueError):
+        points.set_array(np.zeros(3, dtype=np.float64))
+    # integers are varint encoded, so they do not have a fixed size
+    with pytest.raises(ValueError):
+        states.value_vec.items.get_array(np.int32)
+
+
+def test_value_vec_sort_sends_only_the_order(raw_server: Callable[..., _RawServer]) -> None:
+    raw = raw_server()
+    server = raw.server
+    server.start()
+    client = raw.connect()
+    _wait_until(server.is_connected)
+    items = server.states.value_vec.items
+    items.set([10, -3, 27, 5])
+
+    _drain_initial_sync(client)
+
+    def read_order() -> list[int]:
+        # ServerHeader::ValueVec(id, type_id, update, VecHeader::Reorder, size)
+        reader = _PostcardReader(_read_frame(client))
+        assert reader.varint() == 8
+        assert reader.varint() == items._value_id
+        reader.varint()
+        assert reader.take(1) == b"\x00"
+        assert reader.varint() == 4
+        size = reader.varint()
+        order = _PostcardReader(reader.take(size))
+        return [order.varint() for _ in range(order.varint())]
+
+    client_items = items.get()
+    items.sort()
+    client_items = [client_items[i] for i in read_order()]
+    assert client_items == items.get() == [-3, 5, 10, 27]
+
+    items.sort(key=lambda item: item % 4, reverse=True)
+    client_items = [client_items[i] for i in read_order()]
+    assert client_items == items.get() == [27, 10, -3, 5]
+
+    items.reverse()
+    client_items = [client_items[i] for i in read_order()]
+    assert client_items == items.get() == [5, -3, 10, 27]
+
+    # exception of the key function is kept and the list is not changed
+    with pytest.raises(ZeroDivisionError):
+        items.sort(key=lambda item: item / 0)
+    assert items.get() == [5, -3, 10, 27]
+
+    # the key function runs without the list lock, so it can read the list
+    items.sort(key=lambda item: -items.get().index(item))
+    client_items = [client_items[i] for i in read_order()]
+    assert client_items == items.get() == [27, 10, -3, 5]
+
+
+def test_value_vec_packed_array_is_faster_than_items(
+    server_bundle: tuple[StatesServer, State, list[Exception]],
+) -> None:
+    _server, states, _errors = server_bundle
+    points = states.value_vec.points
+    count = 50_000
+    array = np.zeros(count, dtype=POINT_DTYPE)
+    array["x"] = np.arange(count)
+    items = [ExampleVecPoint(float(i), 0.0) for i in range(count)]
+
+    start = time.perf_counter()
+    points.set(items)
+    per_item = points.get()
+    per_item_time = time.perf_counter() - start
+
+    start = time.perf_counter()
+    points.set_array(array)
+    packed = points.get_array(POINT_DTYPE)
+    packed_time = time.perf_counter() - start
+
+    assert [point.x for point in per_item] == packed["x"].tolist()
+    assert packed_time < per_item_time
+
+
 def test_value_callbacks_disconnect_and_signal_mode(
     server_bundle: tuple[StatesServer, State, list[Exception]],
 ) -> None:
@@ -335,6 +1500,111 @@ def test_value_callbacks_disconnect_and_signal_mode(
     assert not ratio_event.wait(0.2)
 
 
+def test_signal_get_timeout_releases_gil() -> None:
+    server = StatesServer(port=_free_port())
+    core = server._server
+    title = server.states.values.title
+
+    assert core.signal_get(None, 0.0) is None
+
+    # the waiting thread does not hold the GIL, so this thread keeps running
+    ticks = 0
+    waiter = threading.Thread(target=core.signal_get, args=(None, 0.3))
+    start = time.monotonic()
+    waiter.start()
+    while waiter.is_alive():
+        ticks += 1
+        time.sleep(0.01)
+    assert time.monotonic() - start >= 0.3
+    assert ticks > 5
+
+    core.signal_register(title._value_id, True)
+    title.set("signal", set_signal=True)
+    assert core.signal_get(None, 1.0) == (title._value_id, "signal")
+
+    # infinite or too long timeouts wait without the limit
+    title.set("infinite", set_signal=True)
+    assert core.signal_get(title._value_id, float("inf")) == (title._value_id, "infinite")
+    title.set("long", set_signal=True)
+    assert core.signal_get(title._value_id, 1e300) == (title._value_id, "long")
+    with pytest.raises(ValueError):
+        core.signal_get(None, float("nan"))
+
+
+def test_signals_suppressed_while_batch_setting(
+    server_bundle: tuple[StatesServer, State, list[Exception]],
+) -> None:
+    server, states, _errors = server_bundle
+
+    received: list[object] = []
+    event = threading.Event()
+
+    def on_change(value: object) -> None:
+        received.append(value)
+        event.set()
+
+    states.values.title.connect(on_change)
+    states.values.count.connect(on_change)
+    states.values.title.signal_set_to_queue()
+
+    with server.signals_suppressed(states.values.title, states.values.count):
+        states.values.title.set("loaded", set_signal=True)
+        states.values.count.set(12, set_signal=True)
+        states.values.title.set("loaded again", set_signal=True)
+
+    assert not event.wait(0.2)
+    assert received == []
+    assert states.values.title.get() == "loaded again"
+    assert states.values.count.get() == 12
+
+    states.values.count.set(13, set_signal=True)
+    _wait_event(event)
+    assert received == [13]
+
+
+def test_signal_rate_limit_keeps_latest_value(
+    server_bundle: tuple[StatesServer, State, list[Exception]],
+) -> None:
+    _server, states, _errors = server_bundle
+
+    received: list[tuple[float, int]] = []
+    last_value = 999
+    done = threading.Event()
+
+    def on_change(value: int) -> None:
+        received.append((time.monotonic(), value))
+        if value == last_value:
+            done.set()
+
+    states.values.count.connect(on_change)
+    states.values.count.set_rate_limit(60)
+
+    start = time.monotonic()
+    for i in range(last_value + 1):
+        states.values.count.set(i, set_signal=True)
+        time.sleep(0.001)
+    duration = time.monotonic() - start
+
+    _wait_event(done)
+    assert received[-1][1] == last_value
+    assert len(received) <= 60 * duration + 3
+    intervals = [b[0] - a[0] for a, b in zip(received, received[1:])]
+    assert min(intervals) >= 1 / 60 * 0.9
+
+    with pytest.raises(ValueError):
+        states.values.count.set_rate_limit(0)
+
+    received.clear()
+    done.clear()
+    states.values.count.set_rate_limit(None)
+    for i in range(5):
+        states.values.count.set(i, set_signal=True)
+        time.sleep(0.01)
+    states.values.count.set(last_value, set_signal=True)
+    _wait_event(done)
+    assert len(received) == 6
+
+
 def test_signal_callbacks_and_disconnect_all(
     server_bundle: tuple[StatesServer, State, list[Exception]],
 ) -> None:
@@ -405,6 +1675,729 @@ def test_error_handler_receives_callback_failures() -> None:
             server.stop()
 
 
+def test_structured_log_records_keep_fields(raw_server: Callable[..., _RawServer]) -> None:
+    raw = raw_server()
+    server = raw.server
+    records: list[tuple[str, dict[str, str]]] = []
+    messages: list[str] = []
+    server.logging.add_structured_logger(LogLevel.Info, lambda text, fields: records.append((text, fields)))
+    server.logging.add_structured_logger(LogLevel.Error, lambda text, fields: records.append((text, fields)))
+    server.logging.add_logger(LogLevel.Info, messages.append)
+    server.start()
+    server.logging.log(LogLevel.Info, "file loaded", {"file": "data.csv", "rows": "120"})
+    server.logging.log(LogLevel.Info, "plain message")
+    _wait_until(lambda: len(records) == 2)
+    assert records == [("file loaded", {"file": "data.csv", "rows": "120"}), ("plain message", {})]
+    # simple loggers still receive only the message
+    assert messages == ["file loaded", "plain message"]
+
+    # ClientHeader::Value(77, 0, false, 0) for a value which does not exist
+    client = raw.connect()
+    _wait_until(server.is_connected)
+    _send_frame(client, bytes([0, 77, 0, 0, 0]))
+    _wait_until(lambda: len(records) == 3)
+    assert records[2] == ("value with id 77 not found", {"value_id": "77"})
+
+
+def test_value_update_rate_and_max_rate() -> None:
+    server = StatesServer(port=_free_port())
+    warnings: list[dict[str, str]] = []
+    server.logging.add_structured_logger(LogLevel.Warning, lambda _, fields: warnings.append(fields))
+    server.start()
+    try:
+        count = server.states.values.count
+        for i in range(5):
+            count.set(i)
+        assert count.update_rate() == 5
+
+        # excess updates are only reported, once per rate window
+        count.set_max_rate(1.0)
+        assert count.set(10)
+        assert count.set(11)
+        assert count.get() == 11
+        _wait_until(lambda: len(warnings) == 1)
+        assert warnings[0]["name"] == "root.values.count"
+
+        # excess updates are dropped and reported by the return value
+        count.set_max_rate(1.0, drop=True)
+        assert not any(count.set(i) for i in range(20, 30))
+        assert count.increment(1) is None
+        assert server.set_values({count: 50}) == 0
+        assert count.get() == 11
+        assert count.update_rate() == 7
+        _wait_until(lambda: len(warnings) == 2)
+        time.sleep(0.1)
+        assert len(warnings) == 2
+
+        count.set_max_rate(None)
+        assert count.set(42)
+        assert count.increment(1) == 43
+
+        with pytest.raises(ValueError):
+            count.set_max_rate(0.0)
+    finally:
+        if server.is_running():
+            server.stop()
+
+
+def test_wrong_protocol_version_is_rejected_with_server_version(raw_server: Callable[..., _RawServer]) -> None:
+    raw = raw_server()
+    server = raw.server
+    server.start()
+    client = raw.connect(protocol=6)
+    client.settimeout(1.0)
+
+    # ServerHeader::Rejected(major, minor)
+    reader = _PostcardReader(_read_frame(client))
+    assert reader.varint() == 14
+    assert reader.varint() == 8
+    assert reader.varint() == 1
+    assert not server.is_connected()
+
+
+def test_token_in_query_and_subprotocol_are_accepted(raw_server: Callable[..., _RawServer]) -> None:
+    raw = raw_server(token="a b&c")
+    server = raw.server
+    server.start()
+    headers = "Sec-WebSocket-Protocol: egui-states, other\r\n"
+    client, response = _open_websocket(raw.port, path="/ws?mode=1&token=a%20b%26c", headers=headers)
+    raw.sockets.append(client)
+    assert b"sec-websocket-protocol: egui-states\r\n" in response.lower()
+
+    # ClientHeader::Handshake(protocol, None, None, false), the token is taken from the query
+    _send_frame(client, bytes([4, 8, 0, 0, 0]))
+    _wait_until(server.is_connected)
+
+
+def test_set_raises_busy_when_queue_is_full(raw_server: Callable[..., _RawServer]) -> None:
+    raw = raw_server(queue_size=2)
+    server = raw.server
+    server.start()
+    raw.connect()
+    _wait_until(server.is_connected)
+
+    # list sets are never coalesced, so they fill the queue
+    large_list = list(range(1024 * 1024))
+    with pytest.raises(BlockingIOError):
+        for _ in range(100):
+            server.states.value_vec.items.set(large_list, update=True)
+
+
+def test_busy_set_in_batch_does_not_request_update(raw_server: Callable[..., _RawServer]) -> None:
+    raw = raw_server(queue_size=2)
+    server = raw.server
+    server.start()
+    client = raw.connect()
+    _wait_until(server.is_connected)
+    _drain_initial_sync(client)
+
+    items = server.states.value_vec.items
+    large_list = list(range(1024 * 1024))
+    with pytest.raises(BlockingIOError):
+        for _ in range(100):
+            items.set(large_list)
+
+    # the refused set does not leave the update for the end of the batch
+    with server.batch(), pytest.raises(BlockingIOError):
+        items.set(large_list, update=True)
+
+    # ServerHeader::ValueVec(id, type_id, update, VecHeader::All(count), size) messages, no ServerHeader::Update
+    headers = []
+    with contextlib.suppress(TimeoutError):
+        while True:
+            reader = _PostcardReader(_read_frame(client))
+            while reader.pos < len(reader.data):
+                header = reader.varint()
+                headers.append(header)
+                if header != 8:
+                    break
+                reader.varint()
+                reader.varint()
+                assert reader.take(1) == b"\x00"
+                assert reader.varint() == 0
+                reader.varint()
+                reader.take(reader.varint())
+    assert headers
+    assert set(headers) == {8}
+
+
+def test_value_sets_coalesce_when_client_stalls(raw_server: Callable[..., _RawServer]) -> None:
+    raw = raw_server()
+    server = raw.server
+    server.start()
+    client = raw.connect()
+    _wait_until(server.is_connected)
+    _drain_initial_sync(client)
+
+    # the client does not read, so the queue is drained slower than it is filled
+    title = server.states.values.title
+    count = 2000
+    for i in range(count):
+        title.set(f"{i:04}" * 2048, update=i == 0)
+        assert server.stats()["queue_depth"] <= 1
+
+    # ServerHeader::Value(id, type_id, update, size)
+    client.settimeout(1.0)
+    values = []
+    with contextlib.suppress(TimeoutError):
+        while True:
+            reader = _PostcardReader(_read_frame(client))
+            while reader.pos < len(reader.data):
+                header = reader.varint()
+                if header != 0:
+                    break
+                value_id = reader.varint()
+                reader.varint()
+                update = reader.take(1) == b"\x01"
+                reader.varint()
+                text = reader.string()
+                if value_id == title._value_id and len(text) == 4 * 2048:
+                    values.append((text[:4], update))
+    assert len(values) < count
+    # the last set is never dropped and the replacing message keeps the update request of the first set
+    assert values[-1][0] == f"{count - 1:04}"
+    assert values[0][1]
+
+
+def test_value_notifies_every_client_change(raw_server: Callable[..., _RawServer]) -> None:
+    raw = raw_server()
+    server = raw.server
+    title = server.states.values.title
+    received: list[str] = []
+    title.connect(received.append)
+    server.start()
+
+    def varint(value: int) -> bytes:
+        data = bytearray()
+        while value >= 0x80:
+            data.append((value & 0x7F) | 0x80)
+            value >>= 7
+        data.append(value)
+        return bytes(data)
+
+    client = raw.connect()
+    _wait_until(server.is_connected)
+
+    # the type id of the title from the initial ServerHeader::Value(id, type_id, update, size)
+    type_id = None
+    while type_id is None:
+        reader = _PostcardReader(_read_frame(client))
+        while reader.pos < len(reader.data) and type_id is None:
+            if reader.varint() != 0:
+                break
+            value_id, value_type_id = reader.varint(), reader.varint()
+            reader.take(1)
+            size = reader.varint()
+            if value_id == title._value_id:
+                type_id = value_type_id
+            reader.take(size)
+    _send_ack(client, title._value_id)
+
+    def send_title(text: str) -> None:
+        # ClientHeader::Value(id, type_id, false, size) without the signal requested by the client
+        data = varint(len(text)) + text.encode()
+        _send_frame(client, b"\x00" + varint(title._value_id) + varint(type_id) + b"\x00" + varint(len(data)) + data)
+
+    send_title("quiet")
+    _wait_until(lambda: title.get() == "quiet")
+    time.sleep(0.1)
+    assert received == []
+
+    title.notify_client_changes()
+    send_title("edited")
+    _wait_until(lambda: received == ["edited"])
+
+    title.notify_client_changes(False)
+    send_title("quiet again")
+    _wait_until(lambda: title.get() == "quiet again")
+    time.sleep(0.1)
+    assert received == ["edited"]
+
+
+def test_read_only_clients_receive_broadcast(raw_server: Callable[..., _RawServer]) -> None:
+    raw = raw_server()
+    server = raw.server
+    server.allow_read_only_clients(2)
+    title = server.states.values.title
+    server.start()
+
+    def wait_title(sock: socket.socket, text: str) -> None:
+        # ServerHeader::Value(id, type_id, update, size) with the postcard string
+        expected = bytes([len(text)]) + text.encode()
+        while True:
+            reader = _PostcardReader(_read_frame(sock))
+            while reader.pos < len(reader.data):
+                if reader.varint() != 0:
+                    break
+                value_id = reader.varint()
+                reader.varint()
+                reader.take(1)
+                data = reader.take(reader.varint())
+                if value_id == title._value_id and data == expected:
+                    return
+
+    # the read-only client gets the sync and the updates also without the client
+    title.set("first")
+    viewer = raw.connect(read_only=True)
+    viewer.settimeout(2.0)
+    wait_title(viewer, "first")
+    assert not server.is_connected()
+    title.set("alone", update=True)
+    assert server.flush(5.0)
+    wait_title(viewer, "alone")
+
+    client = raw.connect()
+    client.settimeout(2.0)
+    wait_title(client, "alone")
+    _wait_until(server.is_connected)
+    _drain_initial_sync(client, 0.3)
+
+    # the states go only to the new read-only client, the others are not synchronized again
+    second_viewer = raw.connect(read_only=True)
+    second_viewer.settimeout(2.0)
+    wait_title(second_viewer, "alone")
+    with pytest.raises(TimeoutError):
+        _read_frame(client)
+    client.settimeout(2.0)
+
+    title.set("everyone", update=True)
+    for sock in (viewer, client, second_viewer):
+        wait_title(sock, "everyone")
+
+    # above the limit the client is refused with the reason in the close frame
+    refused = raw.connect(read_only=True)
+    refused.settimeout(2.0)
+    assert b"read-only clients limit reached" in _read_frame(refused)
+
+    # the read-only clients stay connected without the client
+    client.close()
+    _wait_until(lambda: not server.is_connected())
+    title.set("after client", update=True)
+    wait_title(viewer, "after client")
+
+    server.disconnect_client()
+    with pytest.raises((AssertionError, OSError)):
+        while True:
+            _read_frame(viewer)
+
+
+def test_initial_sync_sends_values_before_collections_and_images(raw_server: Callable[..., _RawServer]) -> None:
+    raw = raw_server()
+    server = raw.server
+    server.start()
+    client = raw.connect()
+    client.settimeout(0.5)
+
+    # ServerHeader index: Value, ValueTake and Static first, then ValueVec and ValueMap, then images and data
+    ranks = {0: 0, 1: 0, 2: 0, 8: 1, 9: 1, 3: 2, 4: 2, 5: 2, 6: 2, 7: 2}
+    seen: list[int] = []
+    scalars: list[tuple[int, int]] = []
+    with contextlib.suppress(TimeoutError):
+        while True:
+            reader = _PostcardReader(_read_frame(client))
+            while reader.pos < len(reader.data):
+                header = reader.varint()
+                if header not in ranks:
+                    break
+                seen.append(ranks[header])
+                # images and data are not parsed, the rest of the frame is skipped
+                if ranks[header] == 2:
+                    break
+                value_id = reader.varint()
+                reader.varint()
+                if header == 1:
+                    reader.take(1)
+                reader.take(1)
+                if ranks[header] == 0:
+                    scalars.append((header, value_id))
+                # VecHeader::All(count) or MapHeader::All(count, ordered)
+                elif reader.varint() == 0:
+                    reader.varint()
+                    if header == 9:
+                        reader.take(1)
+                else:
+                    break
+                reader.take(reader.varint())
+
+    assert seen == sorted(seen)
+    assert set(seen) == {0, 1, 2}
+    # values, value takes and statics, every kind sorted by the id
+    assert scalars == sorted(scalars)
+
+
+def test_value_larger_than_64kb_reaches_client(raw_server: Callable[..., _RawServer]) -> None:
+    raw = raw_server()
+    server = raw.server
+    title = server.states.values.title
+    server.start()
+    client = raw.connect()
+    _wait_until(server.is_connected)
+    _drain_initial_sync(client, 2.0)
+
+    # the sizes are u32 in the headers and varints in postcard, nothing is cut at 65535 bytes
+    text = "json" * 20000
+    title.set(text, update=True)
+    reader = _PostcardReader(_read_frame(client))
+    assert reader.varint() == 0
+    assert reader.varint() == title._value_id
+    reader.varint()
+    reader.take(1)
+    assert reader.varint() == len(text) + 3
+    assert reader.string() == text
+
+
+def test_flush_waits_until_messages_are_written(raw_server: Callable[..., _RawServer]) -> None:
+    raw = raw_server()
+    server = raw.server
+    server.start()
+    server.states.values.title.set("offline", update=True)
+    assert not server.flush(0.1)
+
+    client = raw.connect()
+    _wait_until(server.is_connected)
+    assert server.flush(1.0)
+
+    # the client does not read, so the large message can not be written
+    large_text = "x" * (16 * 1024 * 1024)
+    server.states.values.title.set(large_text, update=True)
+    assert not server.flush(0.2)
+
+    received = threading.Event()
+
+    def read_all(sock: socket.socket) -> None:
+        buffer = bytearray(1024 * 1024)
+        size = 0
+        while size < len(large_text):
+            count = sock.recv_into(buffer)
+            if count == 0:
+                return
+            size += count
+        received.set()
+
+    reader = threading.Thread(target=read_all, args=(client,), daemon=True)
+    reader.start()
+    assert server.flush(10.0)
+    _wait_event(received, timeout=10.0)
+
+    # infinite or too long timeouts wait without the limit
+    server.states.values.title.set("online", update=True)
+    assert server.flush(float("inf"))
+    assert server.flush(1e300)
+    with pytest.raises(ValueError):
+        server.flush(float("nan"))
+
+
+def test_client_fetches_types_registry(raw_server: Callable[..., _RawServer]) -> None:
+    raw = raw_server()
+    server = raw.server
+    server.start()
+    point = ExampleTestStruct(1.5, -2.25, "registry")
+    server.states.custom_values.point.set(point)
+
+    client = raw.connect(request_types=True)
+    client.settimeout(1.0)
+
+    # ServerHeader::Types(size) is sent as the first message after the handshake
+    reader = _PostcardReader(_read_frame(client))
+    assert reader.varint() == 11
+    assert reader.varint() == len(reader.data) - reader.pos
+    registry = {}
+    for _ in range(reader.varint()):
+        value_id, type_id = reader.varint(), reader.varint()
+        registry[value_id] = (type_id, reader.object_type())
+
+    value_id = server.states.custom_values.point._value_id
+    assert registry[value_id][1] == (
+        "struct",
+        "TestStruct",
+        [("x", ("f32",)), ("y", ("f32",)), ("label", ("str",))],
+    )
+
+    # wait for a single value message after the initial synchronization
+    _drain_initial_sync(client)
+
+    server.states.custom_values.point.set(point)
+    reader = _PostcardReader(_read_frame(client))
+    assert reader.varint() == 0  # ServerHeader::Value
+    assert reader.varint() == value_id
+    type_id, object_type = registry[value_id]
+    assert reader.varint() == type_id
+    reader.take(1)  # update flag
+    reader.varint()  # data size
+    assert reader.value(object_type) == {"x": 1.5, "y": -2.25, "label": "registry"}
+
+    # enum with data is sent as the variant index followed by the variant fields
+    union_id = server.states.values.test_union._value_id
+    assert registry[union_id][1][:2] == ("union", "TestUnion")
+    for variant, expected in [
+        (ExampleTestUnionLevel(300), ("Level", 300)),
+        (ExampleTestUnionPoint(point, True), ("Point", {"x": 1.5, "y": -2.25, "label": "registry"}, True)),
+        (ExampleTestUnionOff(), ("Off",)),
+    ]:
+        server.states.values.test_union.set(variant)
+        reader = _PostcardReader(_read_frame(client))
+        assert [reader.varint(), reader.varint()] == [0, union_id]
+        type_id, object_type = registry[union_id]
+        assert reader.varint() == type_id
+        reader.take(1)  # update flag
+        reader.varint()  # data size
+        assert reader.value(object_type) == expected
+
+
+def _create_certificate(directory: str) -> tuple[str, str]:
+    # self-signed certificate for localhost, returns the certificate and the key paths
+    cert, key = os.path.join(directory, "cert.pem"), os.path.join(directory, "key.pem")
+    subprocess.run(
+        ["openssl", "req", "-x509", "-newkey", "rsa:2048", "-nodes", "-days", "1", "-subj", "/CN=localhost"]
+        + ["-addext", "subjectAltName=DNS:localhost", "-keyout", key, "-out", cert],
+        check=True,
+        capture_output=True,
+    )
+    return cert, key
+
+
+def _check_stalled_peer_does_not_block(server: StatesServer, port: int, tls: ssl.SSLContext | None = None) -> None:
+    # the stalled peer connects but never sends its handshake
+    stalled = socket.socket(socket.AF_INET, socket.SOCK_STREAM)
+    _wait_until(lambda: stalled.connect_ex(("127.0.0.1", port)) == 0)
+    clients: list[socket.socket] = []
+    thread = threading.Thread(target=lambda: clients.append(_connect_raw_client(port, tls=tls)), daemon=True)
+    try:
+        thread.start()
+        _wait_until(server.is_connected, timeout=10.0)
+    finally:
+        stalled.close()
+        thread.join(timeout=1.0)
+        for client in clients:
+            client.close()
+
+
+def test_stalled_handshake_does_not_block_clients(raw_server: Callable[..., _RawServer]) -> None:
+    raw = raw_server()
+    raw.server.start()
+    _check_stalled_peer_does_not_block(raw.server, raw.port)
+
+
+@pytest.mark.skipif(not _core.TLS or shutil.which("openssl") is None, reason="built without tls feature")
+def test_stalled_tls_handshake_does_not_block_clients(raw_server: Callable[..., _RawServer]) -> None:
+    with tempfile.TemporaryDirectory() as directory:
+        cert, key = _create_certificate(directory)
+        raw = raw_server()
+        raw.server.enable_tls(cert, key)
+        raw.server.start()
+        _check_stalled_peer_does_not_block(raw.server, raw.port, ssl.create_default_context(cafile=cert))
+
+
+@pytest.mark.skipif(not _core.TLS or shutil.which("openssl") is None, reason="built without tls feature")
+def test_tls_connection_keeps_message_framing(raw_server: Callable[..., _RawServer]) -> None:
+    with tempfile.TemporaryDirectory() as directory:
+        cert, key = _create_certificate(directory)
+
+        raw = raw_server()
+        server = raw.server
+        with pytest.raises(ValueError):
+            server.enable_tls(cert, os.path.join(directory, "missing.pem"))
+        server.enable_tls(cert, key)
+        server.start()
+
+        client = raw.connect(tls=ssl.create_default_context(cafile=cert))
+        _wait_until(server.is_connected)
+        _drain_initial_sync(client)
+
+        title = server.states.values.title
+        title.set("secure", update=True)
+
+        # ServerHeader::Value(id, type_id, update, size)
+        reader = _PostcardReader(_read_frame(client))
+        assert reader.varint() == 0
+        assert reader.varint() == title._value_id
+        reader.varint()
+        assert reader.take(1) == b"\x01"
+        reader.varint()
+        assert reader.string() == "secure"
+
+
+@pytest.mark.skipif(not _core.COMPRESSION, reason="built without compression feature")
+def test_large_frames_are_compressed(raw_server: Callable[..., _RawServer]) -> None:
+    raw = raw_server()
+    server = raw.server
+    server.start()
+    client = raw.connect(compression=True)
+    _wait_until(server.is_connected)
+    _drain_initial_sync(client)
+
+    title = server.states.values.title
+    title.set("small", update=True)
+    assert _PostcardReader(_read_frame(client)).varint() == 0
+
+    # ServerHeader::Compressed(size) followed by the LZ4 block
+    title.set("large" * 2000, update=True)
+    frame = _read_frame(client)
+    reader = _PostcardReader(frame)
+    assert reader.varint() == 13
+    assert reader.varint() > 10000 > len(frame)
+
+
+@pytest.mark.skipif(not _core.CHECKSUM, reason="built without checksum feature")
+def test_frames_carry_checksum(raw_server: Callable[..., _RawServer]) -> None:
+    raw = raw_server()
+    server = raw.server
+    server.start()
+    client = raw.connect(checksum=True)
+    _wait_until(server.is_connected)
+    _drain_initial_sync(client)
+
+    # ServerHeader::Checksum(crc) with the CRC32 of the rest of the frame
+    server.states.values.title.set("checked", update=True)
+    reader = _PostcardReader(_read_frame(client))
+    assert reader.varint() == 15
+    crc = reader.varint()
+    rest = reader.data[reader.pos :]
+    assert crc == zlib.crc32(rest)
+    assert _PostcardReader(rest).varint() == 0
+
+
+def test_server_stats_count_transport(raw_server: Callable[..., _RawServer]) -> None:
+    raw = raw_server()
+    server = raw.server
+    assert server.stats() == {
+        "messages_sent": 0,
+        "bytes_sent": 0,
+        "messages_received": 0,
+        "bytes_received": 0,
+        "queue_depth": 0,
+    }
+    server.start()
+    client = raw.connect()
+    _wait_until(server.is_connected)
+    _drain_initial_sync(client)
+
+    # only the handshake was received
+    stats = server.stats()
+    assert stats["messages_received"] == 1
+    assert stats["bytes_received"] == 5
+    assert stats["messages_sent"] > 0
+
+    title = server.states.values.title
+    title.set("counted", update=True)
+    frame = _read_frame(client)
+    _wait_until(lambda: server.stats()["bytes_sent"] >= stats["bytes_sent"] + len(frame))
+    assert server.stats()["messages_sent"] == stats["messages_sent"] + 1
+
+    _send_ack(client, title._value_id)
+    _wait_until(lambda: server.stats()["messages_received"] == 2)
+    assert server.stats()["queue_depth"] == 0
+
+
+def _scrape_metrics(port: int) -> dict[str, float]:
+    # empty until the endpoint is listening
+    try:
+        with urllib.request.urlopen(f"http://127.0.0.1:{port}/metrics", timeout=2) as response:
+            text = response.read().decode()
+    except OSError:
+        return {}
+    metrics = {}
+    for line in text.splitlines():
+        if line and not line.startswith("#"):
+            name, value = line.rsplit(" ", 1)
+            metrics[name] = float(value)
+    return metrics
+
+
+@pytest.mark.skipif(not _core.METRICS_HTTP, reason="built without metrics-http feature")
+def test_metrics_endpoint_reports_activity(raw_server: Callable[..., _RawServer]) -> None:
+    raw = raw_server()
+    server = raw.server
+    metrics_port = _free_port()
+    server.enable_metrics(metrics_port, (127, 0, 0, 1))
+    server.allow_read_only_clients(1)
+    server.start()
+    _wait_until(lambda: "egui_states_connected" in _scrape_metrics(metrics_port))
+    metrics = _scrape_metrics(metrics_port)
+    assert metrics["egui_states_connected"] == 0
+    assert metrics["egui_states_read_only_clients"] == 0
+    assert metrics["egui_states_messages_sent_total"] == 0
+    assert "egui_states_queue_depth" in metrics
+
+    # the read-only client is counted apart from the client
+    viewer = raw.connect(read_only=True)
+    _wait_until(lambda: _scrape_metrics(metrics_port).get("egui_states_read_only_clients") == 1)
+    assert _scrape_metrics(metrics_port)["egui_states_connected"] == 0
+
+    client = raw.connect()
+    _wait_until(server.is_connected)
+    for i in range(3):
+        server.states.values.count.set(i, update=True)
+
+    _wait_until(lambda: _scrape_metrics(metrics_port).get("egui_states_queue_depth") == 0)
+    metrics = _scrape_metrics(metrics_port)
+    assert metrics["egui_states_connected"] == 1
+    assert metrics["egui_states_messages_sent_total"] > 0
+    assert metrics["egui_states_bytes_sent_total"] > 0
+    assert metrics['egui_states_value_update_rate{name="root.values.count"}'] == 3
+
+
+@pytest.mark.skipif(not _core.TIMESTAMPS, reason="built without timestamps feature")
+def test_messages_are_stamped_with_send_time(raw_server: Callable[..., _RawServer]) -> None:
+    raw = raw_server()
+    server = raw.server
+    server.start()
+    client = raw.connect()
+    _wait_until(server.is_connected)
+    _drain_initial_sync(client)
+
+    for delay in (0.0, 0.3):
+        server.states.values.count.set(5)
+        time.sleep(delay)
+        frame = _read_frame(client)
+        received = time.time()
+
+        # ServerHeader::Value(id, type_id, update, size) followed by ServerHeader::Timestamp(micros)
+        reader = _PostcardReader(frame)
+        assert reader.varint() == 0
+        assert reader.varint() == server.states.values.count._value_id
+        reader.varint()
+        reader.take(1)
+        reader.take(reader.varint())
+        assert reader.varint() == 12
+        latency = received - reader.varint() / 1e6
+        assert delay - 0.01 <= latency < delay + 0.1
+
+
+def test_data_group_propagates_to_client(raw_server: Callable[..., _RawServer]) -> None:
+    raw = raw_server()
+    server = raw.server
+    server.start()
+    client = raw.connect()
+    _wait_until(server.is_connected)
+    _drain_initial_sync(client)
+
+    grouped = [server.states.data.bytes, server.states.data.samples]
+    server.set_data_group(7, grouped, (-1.5, 10.0), update=True)
+    assert server.states.data.bytes.group() == (7, (-1.5, 10.0))
+    assert server.states.data.samples.group() == (7, (-1.5, 10.0))
+    assert server.states.data.nested.buffer.group() is None
+
+    # ServerHeader::Data(id, DataHeader::Group(Some((group, [start, end])), update))
+    received = {}
+    while len(received) < len(grouped):
+        reader = _PostcardReader(_read_frame(client))
+        while reader.pos < len(reader.data):
+            assert reader.varint() == 4
+            value_id = reader.varint()
+            assert reader.varint() == 6
+            assert reader.take(1) == b"\x01"
+            group = reader.varint()
+            x_range = struct.unpack("<dd", reader.take(16))
+            assert reader.take(1) == b"\x01"
+            received[value_id] = (group, x_range)
+
+    assert received == {d._value_id: (7, (-1.5, 10.0)) for d in grouped}
+
+    server.clear_data_group(grouped)
+    assert server.states.data.bytes.group() is None
+
+
 def test_data_take_methods(server_bundle: tuple[StatesServer, State, list[Exception]]) -> None:
     _server, states, _errors = server_bundle
 